@@ -15,7 +15,7 @@ QNetworkAccessManager* NetworkManager::manager() {
 }
 
 void NetworkManager::registerUser(User &user) {
-    QUrl url("http://localhost:3000/api/users/create");
+    QUrl url("http://localhost:3000/api/v1/users/create");
 
     QNetworkRequest request(url);
 
@@ -0,0 +1,78 @@
+//! Building READY for users in more and more servers, next to loading the
+//! same channels with a query per server. `ready` should stay close to flat
+//! while `query_per_server` grows with the server count.
+//!
+//! Runs on a temporary SQLite file, so it needs no database server:
+//!
+//! ```bash
+//! cargo bench --bench ready
+//! ```
+
+#[path = "../tests/common/fixtures.rs"]
+mod fixtures;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use songbird_server::config::DatabaseConfig;
+use songbird_server::database::connect;
+use fixtures::{create_joined_server, create_user, ready_service};
+use songbird_server::models::models::User;
+use songbird_server::repositories::Repositories;
+use tokio::runtime::Runtime;
+
+const SERVER_COUNTS: [usize; 4] = [1, 10, 50, 200];
+const CHANNELS_PER_SERVER: usize = 5;
+
+/// A user in `servers` servers of `CHANNELS_PER_SERVER` channels each.
+async fn seed(repositories: &Repositories, servers: usize) -> User {
+    let user = create_user(repositories, &format!("user{}", servers)).await;
+
+    let channel_names: Vec<String> = (0..CHANNELS_PER_SERVER).map(|channel| format!("channel{}", channel)).collect();
+    let channel_names: Vec<&str> = channel_names.iter().map(String::as_str).collect();
+    for i in 0..servers {
+        create_joined_server(repositories, &format!("server{}-{}", servers, i), user.user_id, &channel_names).await;
+    }
+
+    user
+}
+
+/// What building READY would take with `find_by_server` for each server.
+async fn query_per_server(repositories: &Repositories, user_id: i64) -> usize {
+    let servers = repositories.server_repository.find_servers_for_user(user_id).await.unwrap();
+    let mut channels = 0;
+    for server in servers {
+        channels += repositories.channel_repository.find_by_server(server.server_id).await.unwrap().len();
+    }
+    channels
+}
+
+fn bench_ready(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let path = std::env::temp_dir().join(format!("songbird_bench_ready_{}.db", std::process::id()));
+    let config = DatabaseConfig {
+        url: format!("sqlite://{}", path.display()),
+        ..DatabaseConfig::default()
+    };
+
+    let repositories = runtime.block_on(async { connect(&config).await.unwrap().repositories() });
+    let ready = ready_service(&repositories);
+
+    let mut group = c.benchmark_group("ready");
+    for servers in SERVER_COUNTS {
+        let user = runtime.block_on(seed(&repositories, servers));
+
+        group.bench_with_input(BenchmarkId::new("ready", servers), &user, |b, user| {
+            b.to_async(&runtime).iter(|| ready.build(1, user.clone()))
+        });
+        group.bench_with_input(BenchmarkId::new("query_per_server", servers), &user.user_id, |b, &user_id| {
+            b.to_async(&runtime).iter(|| query_per_server(&repositories, user_id))
+        });
+    }
+    group.finish();
+
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+}
+
+criterion_group!(benches, bench_ready);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+CREATE TABLE users (
+    user_id SERIAL PRIMARY KEY,
+    username VARCHAR(32) NOT NULL UNIQUE,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    avatar_url TEXT,
+    status VARCHAR(32) NOT NULL DEFAULT 'online',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP
+);
+CREATE TABLE servers (
+    server_id SERIAL PRIMARY KEY,
+    server_name VARCHAR(100) NOT NULL UNIQUE,
+    owner_user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    icon_url TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP
+);
+CREATE TABLE server_members (
+    server_id INTEGER NOT NULL REFERENCES servers(server_id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    nickname VARCHAR(32),
+    joined_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (server_id, user_id)
+);
+CREATE TABLE channels (
+    channel_id SERIAL PRIMARY KEY,
+    server_id INTEGER REFERENCES servers(server_id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    type VARCHAR(16) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP
+);
+CREATE TABLE messages (
+    message_id SERIAL PRIMARY KEY,
+    channel_id INTEGER NOT NULL REFERENCES channels(channel_id) ON DELETE CASCADE,
+    author_user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP,
+    edited_at TIMESTAMP
+);
+CREATE TABLE direct_message_members (
+    channel_id INTEGER NOT NULL REFERENCES channels(channel_id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    PRIMARY KEY (channel_id, user_id)
+);
@@ -0,0 +1,11 @@
+-- `users.status` now holds the presence other users see (online, idle, dnd or
+-- offline). The status a user picked for themselves lives in `preferred_status`.
+ALTER TABLE users
+    ADD COLUMN preferred_status VARCHAR(16) NOT NULL DEFAULT 'online',
+    ADD COLUMN custom_status_text VARCHAR(128),
+    ADD COLUMN custom_status_emoji VARCHAR(64),
+    ADD COLUMN custom_status_expires_at TIMESTAMP;
+
+UPDATE users SET status = 'offline';
+
+ALTER TABLE users ALTER COLUMN status SET DEFAULT 'offline';
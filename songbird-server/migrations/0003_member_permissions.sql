@@ -0,0 +1,3 @@
+-- Bit set of `Permissions` granted to a member. Server owners implicitly have all of them.
+ALTER TABLE server_members
+    ADD COLUMN permissions BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+ALTER TABLE messages
+    ADD COLUMN message_type VARCHAR(32) NOT NULL DEFAULT 'default',
+    ADD COLUMN referenced_message_id INTEGER REFERENCES messages(message_id) ON DELETE SET NULL;
+
+CREATE TABLE pinned_messages (
+    message_id INTEGER PRIMARY KEY REFERENCES messages(message_id) ON DELETE CASCADE,
+    channel_id INTEGER NOT NULL REFERENCES channels(channel_id) ON DELETE CASCADE,
+    pinned_by_user_id INTEGER REFERENCES users(user_id) ON DELETE SET NULL,
+    pinned_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX pinned_messages_channel_id_idx ON pinned_messages (channel_id, pinned_at DESC);
@@ -0,0 +1,5 @@
+ALTER TABLE messages
+    ADD CONSTRAINT messages_message_type_check CHECK (message_type IN (
+        'default', 'reply', 'member_join', 'member_leave', 'pin',
+        'channel_rename', 'call', 'thread_created'
+    ));
@@ -0,0 +1,4 @@
+-- Per-channel slowmode: seconds a member must wait between messages.
+ALTER TABLE channels
+    ADD COLUMN rate_limit_per_user INTEGER NOT NULL DEFAULT 0
+        CONSTRAINT channels_rate_limit_per_user_check CHECK (rate_limit_per_user BETWEEN 0 AND 21600);
@@ -0,0 +1,22 @@
+-- TOTP two-factor authentication. A row exists once enrollment has started;
+-- it only counts once `enabled` is set by a confirmed code.
+CREATE TABLE user_totp (
+    user_id INTEGER PRIMARY KEY REFERENCES users(user_id) ON DELETE CASCADE,
+    secret VARCHAR(64) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Highest time step accepted so far, so a code cannot be replayed.
+    last_used_step BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Single-use recovery codes, stored as SHA-256 hex digests.
+CREATE TABLE recovery_codes (
+    recovery_code_id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMP,
+    UNIQUE (user_id, code_hash)
+);
+
+-- Members with moderation permissions must have 2FA enabled to use them.
+ALTER TABLE servers ADD COLUMN mfa_required BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,29 @@
+-- Indexes behind the lookups in the repositories. Primary keys and unique
+-- constraints already cover lookups by id, username, email and server name,
+-- and (server_id, user_id) / (channel_id, user_id) member lookups.
+
+-- ServerRepository::find_by_owner
+CREATE INDEX servers_owner_user_id_idx ON servers (owner_user_id);
+
+-- Servers a user is in, and members sharing a server with a user
+CREATE INDEX server_members_user_id_idx ON server_members (user_id);
+
+-- Channels of a server, oldest first for system messages
+CREATE INDEX channels_server_id_idx ON channels (server_id, created_at);
+
+-- DM channels a user is in
+CREATE INDEX direct_message_members_user_id_idx ON direct_message_members (user_id);
+
+-- Channel history, newest first
+CREATE INDEX messages_channel_id_created_at_idx ON messages (channel_id, created_at DESC);
+
+-- MessageRepository::count_by_user, and the cascade when a user is deleted
+CREATE INDEX messages_author_user_id_idx ON messages (author_user_id);
+
+-- ON DELETE SET NULL of replies when the original message is deleted
+CREATE INDEX messages_referenced_message_id_idx ON messages (referenced_message_id)
+    WHERE referenced_message_id IS NOT NULL;
+
+-- The custom status expiry sweeper
+CREATE INDEX users_custom_status_expires_at_idx ON users (custom_status_expires_at)
+    WHERE custom_status_expires_at IS NOT NULL;
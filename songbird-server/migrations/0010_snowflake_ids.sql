@@ -0,0 +1,129 @@
+-- User, server, channel and message IDs become 64-bit Snowflakes handed out
+-- by the app (see src/snowflake.rs) instead of SERIAL sequences: the
+-- milliseconds since 2020-01-01 UTC in the top bits, then a 10 bit worker ID
+-- and a 12 bit sequence.
+--
+-- Existing rows are renumbered from their created_at, keeping the low 22 bits
+-- of the old ID where new IDs have worker and sequence, so old and new IDs
+-- sort and paginate together and never collide. Every referencing column is
+-- renumbered with them. This rewrites the affected tables, so expect it to
+-- take a while on a large database. Session tokens name the old user IDs, so
+-- everyone has to log in again afterwards.
+
+CREATE FUNCTION pg_temp.legacy_snowflake(created_at TIMESTAMP, old_id BIGINT) RETURNS BIGINT
+    LANGUAGE SQL IMMUTABLE
+    AS $$
+        SELECT (GREATEST(0, (EXTRACT(EPOCH FROM created_at) * 1000)::BIGINT - 1577836800000) << 22)
+            | (old_id & 4194303)
+    $$;
+
+-- Foreign keys come back once both sides are renumbered
+ALTER TABLE servers DROP CONSTRAINT servers_owner_user_id_fkey;
+ALTER TABLE server_members DROP CONSTRAINT server_members_server_id_fkey;
+ALTER TABLE server_members DROP CONSTRAINT server_members_user_id_fkey;
+ALTER TABLE channels DROP CONSTRAINT channels_server_id_fkey;
+ALTER TABLE messages DROP CONSTRAINT messages_channel_id_fkey;
+ALTER TABLE messages DROP CONSTRAINT messages_author_user_id_fkey;
+ALTER TABLE messages DROP CONSTRAINT messages_referenced_message_id_fkey;
+ALTER TABLE direct_message_members DROP CONSTRAINT direct_message_members_channel_id_fkey;
+ALTER TABLE direct_message_members DROP CONSTRAINT direct_message_members_user_id_fkey;
+ALTER TABLE pinned_messages DROP CONSTRAINT pinned_messages_message_id_fkey;
+ALTER TABLE pinned_messages DROP CONSTRAINT pinned_messages_channel_id_fkey;
+ALTER TABLE pinned_messages DROP CONSTRAINT pinned_messages_pinned_by_user_id_fkey;
+ALTER TABLE user_totp DROP CONSTRAINT user_totp_user_id_fkey;
+ALTER TABLE recovery_codes DROP CONSTRAINT recovery_codes_user_id_fkey;
+ALTER TABLE email_tokens DROP CONSTRAINT email_tokens_user_id_fkey;
+
+ALTER TABLE users ALTER COLUMN user_id DROP DEFAULT, ALTER COLUMN user_id TYPE BIGINT;
+ALTER TABLE servers
+    ALTER COLUMN server_id DROP DEFAULT,
+    ALTER COLUMN server_id TYPE BIGINT,
+    ALTER COLUMN owner_user_id TYPE BIGINT;
+ALTER TABLE server_members ALTER COLUMN server_id TYPE BIGINT, ALTER COLUMN user_id TYPE BIGINT;
+ALTER TABLE channels
+    ALTER COLUMN channel_id DROP DEFAULT,
+    ALTER COLUMN channel_id TYPE BIGINT,
+    ALTER COLUMN server_id TYPE BIGINT;
+ALTER TABLE messages
+    ALTER COLUMN message_id DROP DEFAULT,
+    ALTER COLUMN message_id TYPE BIGINT,
+    ALTER COLUMN channel_id TYPE BIGINT,
+    ALTER COLUMN author_user_id TYPE BIGINT,
+    ALTER COLUMN referenced_message_id TYPE BIGINT;
+ALTER TABLE direct_message_members ALTER COLUMN channel_id TYPE BIGINT, ALTER COLUMN user_id TYPE BIGINT;
+ALTER TABLE pinned_messages
+    ALTER COLUMN message_id TYPE BIGINT,
+    ALTER COLUMN channel_id TYPE BIGINT,
+    ALTER COLUMN pinned_by_user_id TYPE BIGINT;
+ALTER TABLE user_totp ALTER COLUMN user_id TYPE BIGINT;
+ALTER TABLE recovery_codes ALTER COLUMN user_id TYPE BIGINT;
+ALTER TABLE email_tokens ALTER COLUMN user_id TYPE BIGINT;
+
+DROP SEQUENCE users_user_id_seq;
+DROP SEQUENCE servers_server_id_seq;
+DROP SEQUENCE channels_channel_id_seq;
+DROP SEQUENCE messages_message_id_seq;
+
+CREATE TEMPORARY TABLE user_ids ON COMMIT DROP AS
+    SELECT user_id AS old_id, pg_temp.legacy_snowflake(created_at, user_id) AS new_id FROM users;
+CREATE TEMPORARY TABLE server_ids ON COMMIT DROP AS
+    SELECT server_id AS old_id, pg_temp.legacy_snowflake(created_at, server_id) AS new_id FROM servers;
+CREATE TEMPORARY TABLE channel_ids ON COMMIT DROP AS
+    SELECT channel_id AS old_id, pg_temp.legacy_snowflake(created_at, channel_id) AS new_id FROM channels;
+CREATE TEMPORARY TABLE message_ids ON COMMIT DROP AS
+    SELECT message_id AS old_id, pg_temp.legacy_snowflake(created_at, message_id) AS new_id FROM messages;
+
+UPDATE users SET user_id = ids.new_id FROM user_ids ids WHERE users.user_id = ids.old_id;
+UPDATE servers SET server_id = ids.new_id FROM server_ids ids WHERE servers.server_id = ids.old_id;
+UPDATE servers SET owner_user_id = ids.new_id FROM user_ids ids WHERE servers.owner_user_id = ids.old_id;
+UPDATE server_members SET server_id = ids.new_id FROM server_ids ids WHERE server_members.server_id = ids.old_id;
+UPDATE server_members SET user_id = ids.new_id FROM user_ids ids WHERE server_members.user_id = ids.old_id;
+UPDATE channels SET channel_id = ids.new_id FROM channel_ids ids WHERE channels.channel_id = ids.old_id;
+UPDATE channels SET server_id = ids.new_id FROM server_ids ids WHERE channels.server_id = ids.old_id;
+UPDATE messages SET message_id = ids.new_id FROM message_ids ids WHERE messages.message_id = ids.old_id;
+UPDATE messages SET channel_id = ids.new_id FROM channel_ids ids WHERE messages.channel_id = ids.old_id;
+UPDATE messages SET author_user_id = ids.new_id FROM user_ids ids WHERE messages.author_user_id = ids.old_id;
+UPDATE messages SET referenced_message_id = ids.new_id FROM message_ids ids WHERE messages.referenced_message_id = ids.old_id;
+UPDATE direct_message_members SET channel_id = ids.new_id FROM channel_ids ids WHERE direct_message_members.channel_id = ids.old_id;
+UPDATE direct_message_members SET user_id = ids.new_id FROM user_ids ids WHERE direct_message_members.user_id = ids.old_id;
+UPDATE pinned_messages SET message_id = ids.new_id FROM message_ids ids WHERE pinned_messages.message_id = ids.old_id;
+UPDATE pinned_messages SET channel_id = ids.new_id FROM channel_ids ids WHERE pinned_messages.channel_id = ids.old_id;
+UPDATE pinned_messages SET pinned_by_user_id = ids.new_id FROM user_ids ids WHERE pinned_messages.pinned_by_user_id = ids.old_id;
+UPDATE user_totp SET user_id = ids.new_id FROM user_ids ids WHERE user_totp.user_id = ids.old_id;
+UPDATE recovery_codes SET user_id = ids.new_id FROM user_ids ids WHERE recovery_codes.user_id = ids.old_id;
+UPDATE email_tokens SET user_id = ids.new_id FROM user_ids ids WHERE email_tokens.user_id = ids.old_id;
+
+ALTER TABLE servers ADD CONSTRAINT servers_owner_user_id_fkey
+    FOREIGN KEY (owner_user_id) REFERENCES users(user_id) ON DELETE CASCADE;
+ALTER TABLE server_members ADD CONSTRAINT server_members_server_id_fkey
+    FOREIGN KEY (server_id) REFERENCES servers(server_id) ON DELETE CASCADE;
+ALTER TABLE server_members ADD CONSTRAINT server_members_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE;
+ALTER TABLE channels ADD CONSTRAINT channels_server_id_fkey
+    FOREIGN KEY (server_id) REFERENCES servers(server_id) ON DELETE CASCADE;
+ALTER TABLE messages ADD CONSTRAINT messages_channel_id_fkey
+    FOREIGN KEY (channel_id) REFERENCES channels(channel_id) ON DELETE CASCADE;
+ALTER TABLE messages ADD CONSTRAINT messages_author_user_id_fkey
+    FOREIGN KEY (author_user_id) REFERENCES users(user_id) ON DELETE CASCADE;
+ALTER TABLE messages ADD CONSTRAINT messages_referenced_message_id_fkey
+    FOREIGN KEY (referenced_message_id) REFERENCES messages(message_id) ON DELETE SET NULL;
+ALTER TABLE direct_message_members ADD CONSTRAINT direct_message_members_channel_id_fkey
+    FOREIGN KEY (channel_id) REFERENCES channels(channel_id) ON DELETE CASCADE;
+ALTER TABLE direct_message_members ADD CONSTRAINT direct_message_members_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE;
+ALTER TABLE pinned_messages ADD CONSTRAINT pinned_messages_message_id_fkey
+    FOREIGN KEY (message_id) REFERENCES messages(message_id) ON DELETE CASCADE;
+ALTER TABLE pinned_messages ADD CONSTRAINT pinned_messages_channel_id_fkey
+    FOREIGN KEY (channel_id) REFERENCES channels(channel_id) ON DELETE CASCADE;
+ALTER TABLE pinned_messages ADD CONSTRAINT pinned_messages_pinned_by_user_id_fkey
+    FOREIGN KEY (pinned_by_user_id) REFERENCES users(user_id) ON DELETE SET NULL;
+ALTER TABLE user_totp ADD CONSTRAINT user_totp_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE;
+ALTER TABLE recovery_codes ADD CONSTRAINT recovery_codes_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE;
+ALTER TABLE email_tokens ADD CONSTRAINT email_tokens_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE;
+
+-- Channel history is now paged by ID, which sorts the same as created_at
+DROP INDEX messages_channel_id_created_at_idx;
+CREATE INDEX messages_channel_id_message_id_idx ON messages (channel_id, message_id DESC);
@@ -0,0 +1,12 @@
+-- Operator controls from songbird-admin. A disabled account cannot log in
+-- and its sessions stop working; `songbird-admin purge` deletes accounts
+-- that have stayed disabled long enough. Session tokens issued at or before
+-- `sessions_revoked_at` are rejected, which is how every session of a user
+-- is logged out without a session table.
+ALTER TABLE users
+    ADD COLUMN disabled_at TIMESTAMP,
+    ADD COLUMN sessions_revoked_at TIMESTAMP;
+
+-- songbird-admin purge
+CREATE INDEX users_disabled_at_idx ON users (disabled_at) WHERE disabled_at IS NOT NULL;
+CREATE INDEX email_tokens_expires_at_idx ON email_tokens (expires_at);
@@ -0,0 +1,7 @@
+-- Word search over message content. Punctuation becomes whitespace first,
+-- so addresses, URLs and numbers like 2.5 are split into words the way
+-- `search_terms` splits queries instead of being kept whole by the text
+-- search parser. The 'simple' configuration then only lowercases, without
+-- stemming or stop words, which is what the SQLite full-text index does too.
+CREATE INDEX messages_content_search_idx ON messages
+    USING GIN (to_tsvector('simple', regexp_replace(content, '[[:punct:][:space:]]+', ' ', 'g')));
@@ -0,0 +1,143 @@
+-- SQLite support starts from version 9 of the Postgres schema, so this one
+-- file is the SQLite equivalent of migrations/0001 through 0009. Every later
+-- Postgres migration gets a counterpart here under the same version.
+--
+-- Differences from Postgres:
+--   * SERIAL columns are INTEGER PRIMARY KEY AUTOINCREMENT, which likewise
+--     never hands out an id twice.
+--   * Timestamps are RFC 3339 text in UTC. The repositories always bind them,
+--     truncated to the microsecond, so they sort the same as in Postgres.
+--   * Constraints carry the names Postgres gives them. SQLite reports unique
+--     violations by column instead, which the repositories map back.
+
+CREATE TABLE users (
+    user_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username VARCHAR(32) NOT NULL CONSTRAINT users_username_key UNIQUE,
+    email VARCHAR(255) NOT NULL CONSTRAINT users_email_key UNIQUE,
+    password_hash TEXT NOT NULL,
+    avatar_url TEXT,
+    -- The presence other users see (online, idle, dnd or offline)
+    status VARCHAR(32) NOT NULL DEFAULT 'offline',
+    -- The status the user picked for themselves
+    preferred_status VARCHAR(16) NOT NULL DEFAULT 'online',
+    custom_status_text VARCHAR(128),
+    custom_status_emoji VARCHAR(64),
+    custom_status_expires_at TEXT,
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT
+);
+
+CREATE TABLE servers (
+    server_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_name VARCHAR(100) NOT NULL CONSTRAINT servers_server_name_key UNIQUE,
+    owner_user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    icon_url TEXT,
+    -- Members with moderation permissions must have 2FA enabled to use them
+    mfa_required BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT
+);
+
+CREATE TABLE server_members (
+    server_id INTEGER NOT NULL REFERENCES servers(server_id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    nickname VARCHAR(32),
+    -- Bit set of `Permissions` granted to a member. Server owners implicitly have all of them.
+    permissions BIGINT NOT NULL DEFAULT 0,
+    joined_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT server_members_pkey PRIMARY KEY (server_id, user_id)
+);
+
+CREATE TABLE channels (
+    channel_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    server_id INTEGER REFERENCES servers(server_id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    type VARCHAR(16) NOT NULL,
+    -- Per-channel slowmode: seconds a member must wait between messages
+    rate_limit_per_user INTEGER NOT NULL DEFAULT 0
+        CONSTRAINT channels_rate_limit_per_user_check CHECK (rate_limit_per_user BETWEEN 0 AND 21600),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT
+);
+
+CREATE TABLE messages (
+    message_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    channel_id INTEGER NOT NULL REFERENCES channels(channel_id) ON DELETE CASCADE,
+    author_user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    message_type VARCHAR(32) NOT NULL DEFAULT 'default'
+        CONSTRAINT messages_message_type_check CHECK (message_type IN (
+            'default', 'reply', 'member_join', 'member_leave', 'pin',
+            'channel_rename', 'call', 'thread_created'
+        )),
+    referenced_message_id INTEGER REFERENCES messages(message_id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT,
+    edited_at TEXT
+);
+
+CREATE TABLE direct_message_members (
+    channel_id INTEGER NOT NULL REFERENCES channels(channel_id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    CONSTRAINT direct_message_members_pkey PRIMARY KEY (channel_id, user_id)
+);
+
+CREATE TABLE pinned_messages (
+    message_id INTEGER NOT NULL REFERENCES messages(message_id) ON DELETE CASCADE,
+    channel_id INTEGER NOT NULL REFERENCES channels(channel_id) ON DELETE CASCADE,
+    pinned_by_user_id INTEGER REFERENCES users(user_id) ON DELETE SET NULL,
+    pinned_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT pinned_messages_pkey PRIMARY KEY (message_id)
+);
+
+CREATE INDEX pinned_messages_channel_id_idx ON pinned_messages (channel_id, pinned_at DESC);
+
+-- TOTP two-factor authentication. A row exists once enrollment has started;
+-- it only counts once `enabled` is set by a confirmed code.
+CREATE TABLE user_totp (
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    secret VARCHAR(64) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Highest time step accepted so far, so a code cannot be replayed.
+    last_used_step BIGINT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT user_totp_pkey PRIMARY KEY (user_id)
+);
+
+-- Single-use recovery codes, stored as SHA-256 hex digests.
+CREATE TABLE recovery_codes (
+    recovery_code_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TEXT,
+    CONSTRAINT recovery_codes_user_id_code_hash_key UNIQUE (user_id, code_hash)
+);
+
+-- Every emailed token that has been issued, so each one works only once.
+-- The token itself is a signed JWT; this table tracks its `jti`.
+CREATE TABLE email_tokens (
+    token_id VARCHAR(64) NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    purpose VARCHAR(32) NOT NULL
+        CONSTRAINT email_tokens_purpose_check CHECK (purpose IN ('verify_email', 'reset_password')),
+    email VARCHAR(255) NOT NULL,
+    expires_at TEXT NOT NULL,
+    used_at TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT email_tokens_pkey PRIMARY KEY (token_id)
+);
+
+CREATE INDEX email_tokens_user_purpose_idx ON email_tokens (user_id, purpose) WHERE used_at IS NULL;
+
+-- Indexes behind the lookups in the repositories, as in 0009_query_indexes.sql
+CREATE INDEX servers_owner_user_id_idx ON servers (owner_user_id);
+CREATE INDEX server_members_user_id_idx ON server_members (user_id);
+CREATE INDEX channels_server_id_idx ON channels (server_id, created_at);
+CREATE INDEX direct_message_members_user_id_idx ON direct_message_members (user_id);
+CREATE INDEX messages_channel_id_created_at_idx ON messages (channel_id, created_at DESC);
+CREATE INDEX messages_author_user_id_idx ON messages (author_user_id);
+CREATE INDEX messages_referenced_message_id_idx ON messages (referenced_message_id)
+    WHERE referenced_message_id IS NOT NULL;
+CREATE INDEX users_custom_status_expires_at_idx ON users (custom_status_expires_at)
+    WHERE custom_status_expires_at IS NOT NULL;
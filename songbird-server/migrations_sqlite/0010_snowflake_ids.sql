@@ -0,0 +1,57 @@
+-- The SQLite counterpart of migrations/0010_snowflake_ids.sql. INTEGER is
+-- already 64-bit here, so only the existing IDs need renumbering, the same
+-- way: milliseconds since 2020-01-01 UTC from created_at, above the low 22
+-- bits of the old ID. The app supplies IDs from now on; AUTOINCREMENT is
+-- left in place but no longer used.
+
+-- Foreign keys are checked at COMMIT, once every column is renumbered
+PRAGMA defer_foreign_keys = ON;
+
+CREATE TEMPORARY TABLE user_ids AS
+    SELECT user_id AS old_id,
+           (MAX(0, CAST(ROUND((julianday(created_at) - 2440587.5) * 86400000) AS INTEGER) - 1577836800000) << 22)
+               | (user_id & 4194303) AS new_id
+    FROM users;
+CREATE TEMPORARY TABLE server_ids AS
+    SELECT server_id AS old_id,
+           (MAX(0, CAST(ROUND((julianday(created_at) - 2440587.5) * 86400000) AS INTEGER) - 1577836800000) << 22)
+               | (server_id & 4194303) AS new_id
+    FROM servers;
+CREATE TEMPORARY TABLE channel_ids AS
+    SELECT channel_id AS old_id,
+           (MAX(0, CAST(ROUND((julianday(created_at) - 2440587.5) * 86400000) AS INTEGER) - 1577836800000) << 22)
+               | (channel_id & 4194303) AS new_id
+    FROM channels;
+CREATE TEMPORARY TABLE message_ids AS
+    SELECT message_id AS old_id,
+           (MAX(0, CAST(ROUND((julianday(created_at) - 2440587.5) * 86400000) AS INTEGER) - 1577836800000) << 22)
+               | (message_id & 4194303) AS new_id
+    FROM messages;
+
+UPDATE users SET user_id = ids.new_id FROM user_ids ids WHERE users.user_id = ids.old_id;
+UPDATE servers SET server_id = ids.new_id FROM server_ids ids WHERE servers.server_id = ids.old_id;
+UPDATE servers SET owner_user_id = ids.new_id FROM user_ids ids WHERE servers.owner_user_id = ids.old_id;
+UPDATE server_members SET server_id = ids.new_id FROM server_ids ids WHERE server_members.server_id = ids.old_id;
+UPDATE server_members SET user_id = ids.new_id FROM user_ids ids WHERE server_members.user_id = ids.old_id;
+UPDATE channels SET channel_id = ids.new_id FROM channel_ids ids WHERE channels.channel_id = ids.old_id;
+UPDATE channels SET server_id = ids.new_id FROM server_ids ids WHERE channels.server_id = ids.old_id;
+UPDATE messages SET message_id = ids.new_id FROM message_ids ids WHERE messages.message_id = ids.old_id;
+UPDATE messages SET channel_id = ids.new_id FROM channel_ids ids WHERE messages.channel_id = ids.old_id;
+UPDATE messages SET author_user_id = ids.new_id FROM user_ids ids WHERE messages.author_user_id = ids.old_id;
+UPDATE messages SET referenced_message_id = ids.new_id FROM message_ids ids WHERE messages.referenced_message_id = ids.old_id;
+UPDATE direct_message_members SET channel_id = ids.new_id FROM channel_ids ids WHERE direct_message_members.channel_id = ids.old_id;
+UPDATE direct_message_members SET user_id = ids.new_id FROM user_ids ids WHERE direct_message_members.user_id = ids.old_id;
+UPDATE pinned_messages SET message_id = ids.new_id FROM message_ids ids WHERE pinned_messages.message_id = ids.old_id;
+UPDATE pinned_messages SET channel_id = ids.new_id FROM channel_ids ids WHERE pinned_messages.channel_id = ids.old_id;
+UPDATE pinned_messages SET pinned_by_user_id = ids.new_id FROM user_ids ids WHERE pinned_messages.pinned_by_user_id = ids.old_id;
+UPDATE user_totp SET user_id = ids.new_id FROM user_ids ids WHERE user_totp.user_id = ids.old_id;
+UPDATE recovery_codes SET user_id = ids.new_id FROM user_ids ids WHERE recovery_codes.user_id = ids.old_id;
+UPDATE email_tokens SET user_id = ids.new_id FROM user_ids ids WHERE email_tokens.user_id = ids.old_id;
+
+DROP TABLE user_ids;
+DROP TABLE server_ids;
+DROP TABLE channel_ids;
+DROP TABLE message_ids;
+
+DROP INDEX messages_channel_id_created_at_idx;
+CREATE INDEX messages_channel_id_message_id_idx ON messages (channel_id, message_id DESC);
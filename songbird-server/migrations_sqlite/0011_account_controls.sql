@@ -0,0 +1,6 @@
+-- The SQLite counterpart of migrations/0011_account_controls.sql.
+ALTER TABLE users ADD COLUMN disabled_at TEXT;
+ALTER TABLE users ADD COLUMN sessions_revoked_at TEXT;
+
+CREATE INDEX users_disabled_at_idx ON users (disabled_at) WHERE disabled_at IS NOT NULL;
+CREATE INDEX email_tokens_expires_at_idx ON email_tokens (expires_at);
@@ -0,0 +1,27 @@
+-- The SQLite counterpart of migrations/0012_message_search.sql: an FTS5
+-- index over message content in place of the GIN index. It stores no copy
+-- of the text, reading it from messages instead, so the triggers keep it
+-- in step with every insert, edit and delete, cascades included. Diacritics
+-- are kept, as Postgres and `search_terms` keep them.
+CREATE VIRTUAL TABLE messages_fts USING fts5(
+    content,
+    content = 'messages',
+    content_rowid = 'message_id',
+    tokenize = 'unicode61 remove_diacritics 0'
+);
+
+CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+    INSERT INTO messages_fts (rowid, content) VALUES (new.message_id, new.content);
+END;
+
+CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+    INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.message_id, old.content);
+END;
+
+CREATE TRIGGER messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+    INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.message_id, old.content);
+    INSERT INTO messages_fts (rowid, content) VALUES (new.message_id, new.content);
+END;
+
+-- Messages from before this migration
+INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');
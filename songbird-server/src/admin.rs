@@ -0,0 +1,528 @@
+// src/admin.rs
+//! `songbird-admin`: the operator tasks that used to take hand-written SQL
+//! against production. Every command goes through the same repositories as
+//! the server, so the same rules apply: deleting a server takes its
+//! channels and messages with it, a disabled user's sessions stop working.
+//!
+//! Running servers check sessions against the database on every request,
+//! and open gateway connections on every heartbeat, so disabling a user or
+//! ending their sessions takes effect at once. Other changes, such as
+//! transferring a server, reach them once their cached entries expire, up
+//! to `cache.ttl_secs` later.
+
+use crate::auth::hash_password;
+use crate::config::Cli;
+use crate::database::Database;
+use crate::handlers::user_handlers::CreateUserRequest;
+use crate::models::models::{NewUser, Server, User};
+use crate::repositories::Repositories;
+use crate::validation::validate_password;
+use chrono::{Duration, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use rand::distr::{Alphanumeric, SampleString};
+use serde::Serialize;
+use sqlx::migrate::MigrateError;
+use std::fmt;
+use std::path::PathBuf;
+use validator::Validate;
+
+const GENERATED_PASSWORD_LEN: usize = 20;
+
+#[derive(Debug, Parser)]
+#[command(name = "songbird-admin", version, about = "Songbird operator tools")]
+pub struct AdminCli {
+    /// Config file to read, the same one the server uses. Defaults to
+    /// songbird.toml in the working directory, if there is one.
+    #[arg(long, env = "SONGBIRD_CONFIG", value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Postgres or SQLite connection string
+    #[arg(long, value_name = "URL", global = true)]
+    pub database_url: Option<String>,
+
+    /// Override any setting, e.g. --set server.worker_id=1023
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    pub overrides: Vec<String>,
+
+    /// How to print the result
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human, global = true)]
+    pub format: OutputFormat,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl AdminCli {
+    /// The settings given here, in the form `Config::load` takes.
+    pub fn server_cli(&self) -> Cli {
+        Cli {
+            config: self.config.clone(),
+            database_url: self.database_url.clone(),
+            overrides: self.overrides.clone(),
+            ..Cli::default()
+        }
+    }
+}
+
+/// Users are given by username or by ID.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create a user. Without --password, one is generated and printed
+    CreateUser {
+        username: String,
+        email: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Stop a user from logging in and end their sessions
+    DisableUser { user: String },
+    /// Let a disabled user log in again. Their old sessions stay ended
+    EnableUser { user: String },
+    /// Set a new password and end the user's sessions. Without --password,
+    /// one is generated and printed
+    ResetPassword {
+        user: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// End every session of a user
+    Logout { user: String },
+    /// Make another member the owner of a server
+    TransferServer { server_id: i64, new_owner: String },
+    /// Delete a server with its channels, messages and members
+    DeleteServer { server_id: i64 },
+    /// Apply pending database migrations
+    Migrate,
+    /// Delete users disabled for longer than --older-than-days, and the
+    /// records of expired email tokens
+    Purge {
+        #[arg(long, value_name = "DAYS", default_value_t = 30)]
+        older_than_days: u32,
+    },
+    /// Member and message counts
+    Stats {
+        #[command(subcommand)]
+        target: StatsTarget,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StatsTarget {
+    /// Members of the server and messages in each of its channels
+    Server { server_id: i64 },
+    /// Messages in the channel
+    Channel { channel_id: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// What a command did. Printed as text or, with `--format json`, as an
+/// object whose `result` field names the variant.
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Output {
+    UserCreated {
+        user: User,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        generated_password: Option<String>,
+    },
+    UserDisabled {
+        user: User,
+    },
+    UserEnabled {
+        user: User,
+    },
+    PasswordReset {
+        user: User,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        generated_password: Option<String>,
+    },
+    LoggedOut {
+        user: User,
+    },
+    ServerTransferred {
+        server: Server,
+        #[serde(with = "crate::snowflake::string")]
+        previous_owner_user_id: i64,
+    },
+    ServerDeleted {
+        server: Server,
+    },
+    Migrated {
+        applied: Vec<AppliedMigration>,
+    },
+    Purged {
+        users: u64,
+        email_tokens: u64,
+    },
+    ServerStats(ServerStats),
+    ChannelStats(ChannelStats),
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerStats {
+    #[serde(with = "crate::snowflake::string")]
+    pub server_id: i64,
+    pub server_name: String,
+    pub members: i64,
+    pub channels: Vec<ChannelStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChannelStats {
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    pub name: String,
+    pub messages: i64,
+}
+
+impl Output {
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Human => self.to_string(),
+            OutputFormat::Json => serde_json::to_string_pretty(self).expect("output serializes to JSON"),
+        }
+    }
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Output::UserCreated { user, generated_password } => {
+                write!(f, "Created user {} ({})", user.username, user.user_id)?;
+                if let Some(password) = generated_password {
+                    write!(f, "\nPassword: {}", password)?;
+                }
+                Ok(())
+            }
+            Output::UserDisabled { user } => {
+                write!(f, "Disabled user {} ({}) and ended their sessions", user.username, user.user_id)
+            }
+            Output::UserEnabled { user } => write!(f, "Enabled user {} ({})", user.username, user.user_id),
+            Output::PasswordReset { user, generated_password } => {
+                write!(f, "Reset the password of {} ({}) and ended their sessions", user.username, user.user_id)?;
+                if let Some(password) = generated_password {
+                    write!(f, "\nPassword: {}", password)?;
+                }
+                Ok(())
+            }
+            Output::LoggedOut { user } => {
+                write!(f, "Ended every session of {} ({})", user.username, user.user_id)
+            }
+            Output::ServerTransferred { server, previous_owner_user_id } => write!(
+                f,
+                "Transferred {} ({}) from user {} to user {}",
+                server.server_name, server.server_id, previous_owner_user_id, server.owner_user_id
+            ),
+            Output::ServerDeleted { server } => {
+                write!(f, "Deleted server {} ({})", server.server_name, server.server_id)
+            }
+            Output::Migrated { applied } if applied.is_empty() => write!(f, "The schema is already up to date"),
+            Output::Migrated { applied } => {
+                write!(f, "Applied {} migration(s):", applied.len())?;
+                for migration in applied {
+                    write!(f, "\n  {} {}", migration.version, migration.description)?;
+                }
+                Ok(())
+            }
+            Output::Purged { users, email_tokens } => {
+                write!(f, "Deleted {} disabled user(s) and {} expired email token(s)", users, email_tokens)
+            }
+            Output::ServerStats(stats) => {
+                write!(f, "{} ({})\n  members: {}", stats.server_name, stats.server_id, stats.members)?;
+                for channel in &stats.channels {
+                    write!(f, "\n  #{} ({}): {} message(s)", channel.name, channel.channel_id, channel.messages)?;
+                }
+                Ok(())
+            }
+            Output::ChannelStats(stats) => {
+                write!(f, "#{} ({}): {} message(s)", stats.name, stats.channel_id, stats.messages)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AdminError {
+    /// The arguments do not make sense for the data, e.g. a username that
+    /// is taken.
+    Invalid(String),
+    NotFound(String),
+    Database(sqlx::Error),
+    Migrate(MigrateError),
+    Password(argon2::password_hash::Error),
+}
+
+impl fmt::Display for AdminError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminError::Invalid(message) | AdminError::NotFound(message) => write!(f, "{}", message),
+            AdminError::Database(e) => write!(f, "database error: {}", e),
+            AdminError::Migrate(e) => write!(f, "migration failed: {}", e),
+            AdminError::Password(e) => write!(f, "cannot hash password: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AdminError {}
+
+impl From<sqlx::Error> for AdminError {
+    fn from(e: sqlx::Error) -> Self {
+        AdminError::Database(e)
+    }
+}
+
+impl From<MigrateError> for AdminError {
+    fn from(e: MigrateError) -> Self {
+        AdminError::Migrate(e)
+    }
+}
+
+impl From<argon2::password_hash::Error> for AdminError {
+    fn from(e: argon2::password_hash::Error) -> Self {
+        AdminError::Password(e)
+    }
+}
+
+/// Runs `command` against `database`. Only `migrate` changes the schema.
+pub async fn execute(command: Command, database: &Database) -> Result<Output, AdminError> {
+    let repositories = database.repositories();
+
+    match command {
+        Command::CreateUser { username, email, password } => {
+            create_user(&repositories, username, email, password).await
+        }
+        Command::DisableUser { user } => {
+            let user = find_user(&repositories, &user).await?;
+            repositories.user_repository.set_disabled(user.user_id, true).await?;
+            Ok(Output::UserDisabled {
+                user: reload_user(&repositories, user.user_id).await?,
+            })
+        }
+        Command::EnableUser { user } => {
+            let user = find_user(&repositories, &user).await?;
+            repositories.user_repository.set_disabled(user.user_id, false).await?;
+            Ok(Output::UserEnabled {
+                user: reload_user(&repositories, user.user_id).await?,
+            })
+        }
+        Command::ResetPassword { user, password } => {
+            let user = find_user(&repositories, &user).await?;
+            let (password, generated_password) = password_or_generated(password);
+            validate_password(&password).map_err(|e| AdminError::Invalid(validation_message(&e)))?;
+
+            repositories
+                .user_repository
+                .update_password(user.user_id, &hash_password(&password)?)
+                .await?;
+            repositories.user_repository.revoke_sessions(user.user_id).await?;
+            Ok(Output::PasswordReset {
+                user: reload_user(&repositories, user.user_id).await?,
+                generated_password,
+            })
+        }
+        Command::Logout { user } => {
+            let user = find_user(&repositories, &user).await?;
+            repositories.user_repository.revoke_sessions(user.user_id).await?;
+            Ok(Output::LoggedOut {
+                user: reload_user(&repositories, user.user_id).await?,
+            })
+        }
+        Command::TransferServer { server_id, new_owner } => {
+            transfer_server(&repositories, server_id, &new_owner).await
+        }
+        Command::DeleteServer { server_id } => {
+            let server = find_server(&repositories, server_id).await?;
+            if !repositories.server_repository.delete(server_id).await? {
+                return Err(server_not_found(server_id));
+            }
+            Ok(Output::ServerDeleted { server })
+        }
+        Command::Migrate => {
+            let applied = database.migrate().await?;
+            Ok(Output::Migrated {
+                applied: applied
+                    .into_iter()
+                    .map(|migration| AppliedMigration {
+                        version: migration.version,
+                        description: migration.description.to_string(),
+                    })
+                    .collect(),
+            })
+        }
+        Command::Purge { older_than_days } => {
+            let disabled_before = Utc::now() - Duration::days(i64::from(older_than_days));
+            let users = repositories.user_repository.purge_disabled(disabled_before).await?;
+            let email_tokens = repositories.email_token_repository.purge_expired().await?;
+            Ok(Output::Purged { users, email_tokens })
+        }
+        Command::Stats { target: StatsTarget::Server { server_id } } => server_stats(&repositories, server_id).await,
+        Command::Stats { target: StatsTarget::Channel { channel_id } } => {
+            let channel = repositories
+                .channel_repository
+                .find_by_id(channel_id)
+                .await?
+                .ok_or_else(|| AdminError::NotFound(format!("no channel with ID {}", channel_id)))?;
+            Ok(Output::ChannelStats(ChannelStats {
+                messages: repositories.message_repository.count_by_channel(channel_id).await?,
+                channel_id,
+                name: channel.name,
+            }))
+        }
+    }
+}
+
+async fn create_user(
+    repositories: &Repositories,
+    username: String,
+    email: String,
+    password: Option<String>,
+) -> Result<Output, AdminError> {
+    let (password, generated_password) = password_or_generated(password);
+
+    // The same rules as signing up through the API
+    let request = CreateUserRequest {
+        username,
+        email,
+        password,
+        avatar_url: None,
+    };
+    request
+        .validate()
+        .map_err(|e| AdminError::Invalid(e.to_string().replace('\n', "; ")))?;
+
+    if repositories.user_repository.find_by_username(&request.username).await?.is_some() {
+        return Err(AdminError::Invalid(format!("username {} is taken", request.username)));
+    }
+    if repositories.user_repository.find_by_email(&request.email).await?.is_some() {
+        return Err(AdminError::Invalid(format!("email {} is taken", request.email)));
+    }
+
+    let user = repositories
+        .user_repository
+        .create(NewUser {
+            password_hash: hash_password(&request.password)?,
+            username: request.username,
+            email: request.email,
+            avatar_url: None,
+            status: "offline".to_string(),
+        })
+        .await?;
+
+    Ok(Output::UserCreated { user, generated_password })
+}
+
+async fn transfer_server(repositories: &Repositories, server_id: i64, new_owner: &str) -> Result<Output, AdminError> {
+    let mut server = find_server(repositories, server_id).await?;
+    let new_owner = find_user(repositories, new_owner).await?;
+
+    let previous_owner_user_id = server.owner_user_id;
+    if previous_owner_user_id == new_owner.user_id {
+        return Err(AdminError::Invalid(format!(
+            "{} already owns {}",
+            new_owner.username, server.server_name
+        )));
+    }
+    if !repositories.server_member_repository.is_member(server_id, new_owner.user_id).await? {
+        return Err(AdminError::Invalid(format!(
+            "{} is not a member of {}",
+            new_owner.username, server.server_name
+        )));
+    }
+
+    server.owner_user_id = new_owner.user_id;
+    let server = repositories.server_repository.update(server_id, server).await?;
+
+    Ok(Output::ServerTransferred {
+        server,
+        previous_owner_user_id,
+    })
+}
+
+async fn server_stats(repositories: &Repositories, server_id: i64) -> Result<Output, AdminError> {
+    let server = find_server(repositories, server_id).await?;
+    let members = repositories.server_member_repository.count_members(server_id).await?;
+
+    let mut channels = Vec::new();
+    for channel in repositories.channel_repository.find_by_server(server_id).await? {
+        channels.push(ChannelStats {
+            messages: repositories.message_repository.count_by_channel(channel.channel_id).await?,
+            channel_id: channel.channel_id,
+            name: channel.name,
+        });
+    }
+
+    Ok(Output::ServerStats(ServerStats {
+        server_id,
+        server_name: server.server_name,
+        members,
+        channels,
+    }))
+}
+
+/// By username, or else by ID: usernames may be all digits too.
+async fn find_user(repositories: &Repositories, user: &str) -> Result<User, AdminError> {
+    if let Some(found) = repositories.user_repository.find_by_username(user).await? {
+        return Ok(found);
+    }
+    if let Ok(user_id) = user.parse::<i64>() {
+        if let Some(found) = repositories.user_repository.find_by_id(user_id).await? {
+            return Ok(found);
+        }
+    }
+    Err(AdminError::NotFound(format!("no user named or with ID {}", user)))
+}
+
+async fn reload_user(repositories: &Repositories, user_id: i64) -> Result<User, AdminError> {
+    repositories
+        .user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or_else(|| AdminError::NotFound(format!("user {} was deleted meanwhile", user_id)))
+}
+
+async fn find_server(repositories: &Repositories, server_id: i64) -> Result<Server, AdminError> {
+    repositories
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(|| server_not_found(server_id))
+}
+
+fn server_not_found(server_id: i64) -> AdminError {
+    AdminError::NotFound(format!("no server with ID {}", server_id))
+}
+
+/// The password given, or a generated one that is also returned for
+/// printing.
+fn password_or_generated(password: Option<String>) -> (String, Option<String>) {
+    match password {
+        Some(password) => (password, None),
+        None => {
+            let password = loop {
+                let candidate = Alphanumeric.sample_string(&mut rand::rng(), GENERATED_PASSWORD_LEN);
+                if validate_password(&candidate).is_ok() {
+                    break candidate;
+                }
+            };
+            (password.clone(), Some(password))
+        }
+    }
+}
+
+fn validation_message(error: &validator::ValidationError) -> String {
+    error
+        .message
+        .as_ref()
+        .map_or_else(|| error.code.to_string(), |message| message.to_string())
+}
@@ -0,0 +1,248 @@
+// src/app.rs
+use crate::{
+    auth::SessionKeys,
+    config::{Config, MailBackend},
+    database::Database,
+    gateway::GatewayHub,
+    mailer::{LogMailer, Mailer, MailerError, SmtpMailer},
+    metrics::Metrics,
+    rate_limit::{RateLimiter, TrustedProxies},
+    repositories::{Repositories, RepositoryCache},
+    router::{create_router, AppState},
+    services::{
+        ChannelAccess, ChannelService, EmailService, LoginGuard, MemberService, MessageService, PresenceService,
+        ReadyService, SlowmodeService, TypingService,
+    },
+    shutdown::{wait_for_signal, Shutdown},
+    snowflake,
+    tls::TlsListener,
+};
+use axum::serve::ListenerExt;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time::Instant;
+
+/// Below this the secret is easy to brute force from a captured token.
+const JWT_SECRET_MIN_LEN: usize = 32;
+
+/// Wires the services on top of `repositories`. Nothing is spawned and
+/// nothing touches the database, so tests can build the app around a lazy
+/// pool or the in-memory backend.
+pub fn build_state(config: &Config, repositories: Repositories, shutdown: Shutdown) -> Result<AppState, MailerError> {
+    let metrics = Metrics::new();
+    let cache = config
+        .cache
+        .enabled
+        .then(|| RepositoryCache::new(&config.cache, metrics.clone()));
+    let session_user_repository = repositories.user_repository.clone();
+    let repositories = match &cache {
+        Some(cache) => repositories.cached(cache.clone()),
+        None => repositories,
+    };
+
+    let Repositories {
+        user_repository,
+        server_repository,
+        server_member_repository,
+        message_repository,
+        channel_repository,
+        mfa_repository,
+        email_token_repository,
+        health_repository,
+        ..
+    } = repositories;
+
+    let session_keys = SessionKeys::new(config.auth.jwt_secret.as_bytes());
+
+    // The config makes sure smtp_url is set for the smtp backend
+    let mailer: Arc<dyn Mailer> = match (config.mail.backend, &config.mail.smtp_url) {
+        (MailBackend::Smtp, Some(url)) => Arc::new(SmtpMailer::from_url(url, &config.mail.from)?),
+        _ => Arc::new(LogMailer::new(config.mail.dir.clone())),
+    };
+    let email = EmailService::new(
+        mailer,
+        session_keys.clone(),
+        email_token_repository,
+        config.server.public_url.clone(),
+        shutdown.clone(),
+    );
+
+    let gateway = GatewayHub::new(metrics.clone());
+    let presence = PresenceService::new(user_repository.clone(), gateway.clone());
+
+    let channel_access = ChannelAccess::new(
+        channel_repository.clone(),
+        server_repository.clone(),
+        server_member_repository.clone(),
+        mfa_repository.clone(),
+    );
+    let typing = TypingService::new(channel_access.clone(), gateway.clone());
+    let messages = MessageService::new(
+        message_repository.clone(),
+        user_repository.clone(),
+        channel_repository.clone(),
+        channel_access.clone(),
+        typing.clone(),
+        gateway.clone(),
+        metrics.clone(),
+    );
+    let members = MemberService::new(server_member_repository.clone(), messages.clone(), gateway.clone());
+    let channels = ChannelService::new(
+        channel_repository.clone(),
+        channel_access.clone(),
+        messages.clone(),
+        gateway.clone(),
+    );
+    let slowmode = SlowmodeService::new(channel_access.clone());
+    let ready = ReadyService::new(
+        user_repository.clone(),
+        server_repository.clone(),
+        server_member_repository.clone(),
+        channel_repository.clone(),
+    );
+
+    let rate_limiter = RateLimiter::new(config.rate_limit.per_user, config.rate_limit.per_ip);
+
+    Ok(AppState {
+        user_repository,
+        session_user_repository,
+        server_repository,
+        server_member_repository,
+        message_repository,
+        channel_repository,
+        mfa_repository,
+        health_repository,
+        session_keys,
+        gateway,
+        cache,
+        presence,
+        ready,
+        channel_access,
+        typing,
+        messages,
+        members,
+        channels,
+        slowmode,
+        rate_limiter,
+        trusted_proxies: TrustedProxies::new(&config.rate_limit.trusted_proxies),
+        login_guard: LoginGuard::new(),
+        email,
+        shutdown,
+        metrics,
+    })
+}
+
+/// Serves the API on an already migrated database until SIGTERM or Ctrl+C,
+/// then drains connections and background work and closes the database.
+pub async fn run(config: Config, database: Database) -> Result<(), Box<dyn std::error::Error>> {
+    if config.auth.jwt_secret.len() < JWT_SECRET_MIN_LEN {
+        tracing::warn!(
+            "auth.jwt_secret is shorter than {} bytes; use a long random value in production",
+            JWT_SECRET_MIN_LEN
+        );
+    }
+
+    snowflake::set_worker_id(config.server.worker_id);
+
+    let shutdown = Shutdown::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            wait_for_signal().await;
+            shutdown.trigger();
+        }
+    });
+
+    let app_state = build_state(&config, database.repositories(), shutdown.clone())?;
+
+    // Nobody can be connected to the gateway before we start listening
+    app_state.user_repository.reset_all_statuses().await?;
+
+    let status_sweeper = app_state.presence.spawn_custom_status_sweeper();
+    let rate_limit_sweeper = app_state.rate_limiter.spawn_sweeper();
+    let cache_follower = app_state
+        .cache
+        .as_ref()
+        .map(|cache| cache.follow(&app_state.gateway));
+
+    // Build the router
+    let app = create_router(app_state, &config);
+
+    // The rate limiter keys anonymous requests by peer address
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+
+    // Start the server. Once shutdown is triggered it stops accepting
+    // connections and lets in-flight requests finish.
+    let addr = config.server.bind;
+    let timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+    let stopped = {
+        let shutdown = shutdown.clone();
+        async move { shutdown.triggered().await }
+    };
+    let deadline = match &config.server.tls {
+        Some(tls) => {
+            let listener = TlsListener::bind(addr, tls).await?;
+            tracing::info!("listening on {} (https)", addr);
+            // tap_io gives the listener axum's own `Connected` impl, so
+            // ConnectInfo<SocketAddr> works the same as over plain TCP
+            let serve = axum::serve(listener.tap_io(|_| {}), app).with_graceful_shutdown(stopped);
+            serve_until_drained(serve, &shutdown, timeout).await?
+        }
+        None => {
+            let listener = TcpListener::bind(addr).await?;
+            tracing::info!("listening on {}", addr);
+            let serve = axum::serve(listener, app).with_graceful_shutdown(stopped);
+            serve_until_drained(serve, &shutdown, timeout).await?
+        }
+    };
+
+    // Gateway sessions have been sent RECONNECT by now; wait for them and
+    // for queued emails, which still need the pool
+    status_sweeper.abort();
+    rate_limit_sweeper.abort();
+    if let Some(cache_follower) = cache_follower {
+        cache_follower.abort();
+    }
+    if !shutdown.drain(deadline.saturating_duration_since(Instant::now())).await {
+        tracing::warn!(
+            "{} background tasks did not finish within {}s, dropping them",
+            shutdown.pending_tasks(),
+            timeout.as_secs()
+        );
+    }
+
+    database.close().await;
+    tracing::info!("Shutdown complete");
+
+    Ok(())
+}
+
+/// Runs the server until shutdown is triggered and then gives open requests
+/// until `timeout` to finish. Returns the deadline, which the rest of the
+/// shutdown shares.
+async fn serve_until_drained<F>(serve: F, shutdown: &Shutdown, timeout: Duration) -> std::io::Result<Instant>
+where
+    F: IntoFuture<Output = std::io::Result<()>>,
+{
+    let serve = serve.into_future();
+    tokio::pin!(serve);
+
+    tokio::select! {
+        result = &mut serve => return result.map(|()| Instant::now() + timeout),
+        _ = shutdown.triggered() => {}
+    }
+
+    let deadline = Instant::now() + timeout;
+    match tokio::time::timeout_at(deadline, serve).await {
+        Ok(result) => result?,
+        Err(_) => tracing::warn!(
+            "Requests still in flight after {}s, dropping them",
+            timeout.as_secs()
+        ),
+    }
+
+    Ok(deadline)
+}
@@ -0,0 +1,220 @@
+// src/auth.rs
+use crate::error::AppError;
+use crate::models::models::User;
+use crate::router::AppState;
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use chrono::{DateTime, Duration, Utc};
+use data_encoding::HEXLOWER;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const SESSION_LIFETIME_DAYS: i64 = 7;
+const MFA_TICKET_LIFETIME_MINUTES: i64 = 5;
+const EMAIL_VERIFICATION_LIFETIME_HOURS: i64 = 24;
+const PASSWORD_RESET_LIFETIME_HOURS: i64 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: i64,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailTokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+}
+
+impl EmailTokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailTokenPurpose::VerifyEmail => "verify_email",
+            EmailTokenPurpose::ResetPassword => "reset_password",
+        }
+    }
+
+    fn lifetime(&self) -> Duration {
+        match self {
+            EmailTokenPurpose::VerifyEmail => Duration::hours(EMAIL_VERIFICATION_LIFETIME_HOURS),
+            EmailTokenPurpose::ResetPassword => Duration::hours(PASSWORD_RESET_LIFETIME_HOURS),
+        }
+    }
+}
+
+/// Claims of the tokens sent by email. `jti` is recorded when the token is
+/// issued and crossed off when it is used, which makes it single-use.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailTokenClaims {
+    pub sub: i64,
+    pub jti: String,
+    pub purpose: EmailTokenPurpose,
+    pub email: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// Signs and verifies the session tokens handed out by `login_attempt`, the
+/// short-lived tickets exchanged at `/api/v1/login/mfa` when the account has
+/// 2FA, and the tokens in verification and password reset emails. Each kind
+/// uses a key derived from the same secret so none can stand in for another.
+#[derive(Clone)]
+pub struct SessionKeys {
+    encoding: Arc<EncodingKey>,
+    decoding: Arc<DecodingKey>,
+    mfa_encoding: Arc<EncodingKey>,
+    mfa_decoding: Arc<DecodingKey>,
+    email_encoding: Arc<EncodingKey>,
+    email_decoding: Arc<DecodingKey>,
+}
+
+impl SessionKeys {
+    pub fn new(secret: &[u8]) -> Self {
+        let mfa_secret = [secret, b":mfa-ticket"].concat();
+        let email_secret = [secret, b":email-token"].concat();
+        Self {
+            encoding: Arc::new(EncodingKey::from_secret(secret)),
+            decoding: Arc::new(DecodingKey::from_secret(secret)),
+            mfa_encoding: Arc::new(EncodingKey::from_secret(&mfa_secret)),
+            mfa_decoding: Arc::new(DecodingKey::from_secret(&mfa_secret)),
+            email_encoding: Arc::new(EncodingKey::from_secret(&email_secret)),
+            email_decoding: Arc::new(DecodingKey::from_secret(&email_secret)),
+        }
+    }
+
+    pub fn issue(&self, user_id: i64) -> Result<String, jsonwebtoken::errors::Error> {
+        sign(&self.encoding, user_id, Duration::days(SESSION_LIFETIME_DAYS))
+    }
+
+    /// A session that survives revoking the ones before it at `revoked_at`.
+    /// `iat` has whole seconds only, so it is moved past that second if the
+    /// two fall in the same one, see `session_is_current`.
+    pub fn issue_after(&self, user_id: i64, revoked_at: DateTime<Utc>) -> Result<String, jsonwebtoken::errors::Error> {
+        let issued_at = Utc::now().max(revoked_at + Duration::seconds(1));
+        sign_at(&self.encoding, user_id, issued_at, Duration::days(SESSION_LIFETIME_DAYS))
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        decode::<Claims>(token, &self.decoding, &Validation::default()).map(|data| data.claims)
+    }
+
+    pub fn issue_mfa_ticket(&self, user_id: i64) -> Result<String, jsonwebtoken::errors::Error> {
+        sign(&self.mfa_encoding, user_id, Duration::minutes(MFA_TICKET_LIFETIME_MINUTES))
+    }
+
+    pub fn verify_mfa_ticket(&self, ticket: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+        decode::<Claims>(ticket, &self.mfa_decoding, &Validation::default()).map(|data| data.claims)
+    }
+
+    pub fn issue_email_token(
+        &self,
+        user_id: i64,
+        purpose: EmailTokenPurpose,
+        email: &str,
+    ) -> Result<(String, EmailTokenClaims), jsonwebtoken::errors::Error> {
+        let mut jti = [0u8; 16];
+        rand::rng().fill(&mut jti);
+
+        let now = Utc::now();
+        let claims = EmailTokenClaims {
+            sub: user_id,
+            jti: HEXLOWER.encode(&jti),
+            purpose,
+            email: email.to_string(),
+            iat: now.timestamp(),
+            exp: (now + purpose.lifetime()).timestamp(),
+        };
+
+        let token = encode(&Header::default(), &claims, &self.email_encoding)?;
+        Ok((token, claims))
+    }
+
+    pub fn verify_email_token(&self, token: &str) -> Result<EmailTokenClaims, jsonwebtoken::errors::Error> {
+        decode::<EmailTokenClaims>(token, &self.email_decoding, &Validation::default()).map(|data| data.claims)
+    }
+}
+
+fn sign(key: &EncodingKey, user_id: i64, lifetime: Duration) -> Result<String, jsonwebtoken::errors::Error> {
+    sign_at(key, user_id, Utc::now(), lifetime)
+}
+
+fn sign_at(
+    key: &EncodingKey,
+    user_id: i64,
+    issued_at: DateTime<Utc>,
+    lifetime: Duration,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = Claims {
+        sub: user_id,
+        iat: issued_at.timestamp(),
+        exp: (issued_at + lifetime).timestamp(),
+    };
+
+    encode(&Header::default(), &claims, key)
+}
+
+/// Hashes a new password for storage, with a fresh salt.
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
+
+/// Whether a session token with these claims still lets `user` in: the
+/// account is not disabled and its sessions were not revoked after the
+/// token was issued. `iat` only has whole seconds, so a token issued in the
+/// same second as a revocation counts as revoked.
+pub fn session_is_current(claims: &Claims, user: &User) -> bool {
+    user.disabled_at.is_none()
+        && user
+            .sessions_revoked_at
+            .is_none_or(|revoked_at| claims.iat > revoked_at.timestamp())
+}
+
+/// The user making the request, taken from an `Authorization: Bearer` header.
+/// The account is looked up too, so disabled users and revoked sessions are
+/// turned away.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: i64,
+}
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| AppError::unauthorized("Missing authorization header"))?;
+
+        let token = header
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| AppError::unauthorized("Malformed authorization header"))?;
+
+        let claims = state
+            .session_keys
+            .verify(token)
+            .map_err(|_| AppError::unauthorized("Invalid or expired session"))?;
+
+        let user = state.session_user_repository.find_by_id(claims.sub).await?;
+        if !user.is_some_and(|user| session_is_current(&claims, &user)) {
+            return Err(AppError::unauthorized("Invalid or expired session"));
+        }
+
+        tracing::Span::current().record("user_id", claims.sub);
+        Ok(AuthUser { user_id: claims.sub })
+    }
+}
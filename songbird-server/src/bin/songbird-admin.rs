@@ -0,0 +1,47 @@
+// src/bin/songbird-admin.rs
+use clap::Parser;
+use songbird_server::admin::{self, AdminCli};
+use songbird_server::config::Config;
+use songbird_server::{database, snowflake};
+
+#[tokio::main]
+async fn main() {
+    // Load environment variables
+    dotenv::dotenv().ok();
+
+    let cli = AdminCli::parse();
+    let config = match Config::load(&cli.server_cli()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("songbird-admin: {}", e);
+            std::process::exit(2);
+        }
+    };
+    for var in &config.ignored_env {
+        eprintln!("songbird-admin: ignoring {}: there is no such setting", var);
+    }
+
+    // Users created here get IDs like any instance's; pick a worker ID no
+    // running instance uses with --set server.worker_id=N
+    snowflake::set_worker_id(config.server.worker_id);
+
+    // Unlike the server, leaves migrations to `songbird-admin migrate`
+    let database = match database::open(&config.database).await {
+        Ok(database) => database,
+        Err(e) => {
+            eprintln!("songbird-admin: cannot connect to the database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let result = admin::execute(cli.command, &database).await;
+    database.close().await;
+
+    match result {
+        Ok(output) => println!("{}", output.render(cli.format)),
+        Err(e) => {
+            eprintln!("songbird-admin: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
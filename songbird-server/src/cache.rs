@@ -0,0 +1,116 @@
+// src/cache.rs
+//! A bounded, expiring in-process map for rows that nearly every request
+//! reads. See `repositories::cached` for what is cached and when entries
+//! are dropped.
+
+use crate::metrics::Metrics;
+use hashlink::LruCache;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Entries<K, V> {
+    lru: LruCache<K, (V, Instant)>,
+    /// Bumped by every invalidation, so a load that raced with one does not
+    /// put back what was just invalidated.
+    generation: u64,
+}
+
+/// Keeps up to `capacity` values for `ttl` each, evicting the least
+/// recently used when full. Lookups are counted in the `cache_lookups`
+/// metric under `name`.
+pub struct TtlCache<K, V> {
+    name: &'static str,
+    ttl: Duration,
+    entries: Mutex<Entries<K, V>>,
+    metrics: Metrics,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> TtlCache<K, V> {
+    pub fn new(name: &'static str, ttl: Duration, capacity: usize, metrics: Metrics) -> Self {
+        Self {
+            name,
+            ttl,
+            entries: Mutex::new(Entries {
+                lru: LruCache::new(capacity),
+                generation: 0,
+            }),
+            metrics,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+
+        let value = match entries.lru.get(key).map(|(value, expires_at)| (*expires_at > now).then(|| value.clone())) {
+            Some(Some(value)) => Some(value),
+            Some(None) => {
+                entries.lru.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        self.metrics.cache_lookup(self.name, value.is_some());
+        value
+    }
+
+    /// The cached value for `key`, or else the one `load` comes back with,
+    /// which is cached for next time. Errors are not cached.
+    pub async fn get_or_load<F, Fut, E>(&self, key: K, load: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        let generation = self.entries.lock().unwrap().generation;
+        let value = load().await?;
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation == generation {
+            entries.lru.insert(key, (value.clone(), Instant::now() + self.ttl));
+        }
+        Ok(value)
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        entries.lru.remove(key);
+    }
+
+    /// Drops every entry `predicate` returns true for.
+    pub fn invalidate_where(&self, mut predicate: impl FnMut(&K, &V) -> bool) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+
+        let stale: Vec<K> = entries
+            .lru
+            .iter()
+            .filter(|(key, (value, _))| predicate(key, value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &stale {
+            entries.lru.remove(key);
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        entries.lru.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().lru.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,595 @@
+// src/config.rs
+use crate::rate_limit::{parse_trusted_proxy, BucketConfig, PER_IP, PER_USER};
+use crate::snowflake;
+use crate::validation::validate_http_url;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Environment variables starting with this override settings, with `__`
+/// between the levels: `SONGBIRD_DATABASE__MAX_CONNECTIONS=20`. Ones that
+/// name no setting are skipped rather than refused, since the environment
+/// is shared with everything else on the host.
+const ENV_PREFIX: &str = "SONGBIRD_";
+const DEFAULT_CONFIG_FILE: &str = "songbird.toml";
+
+/// Variables the server read before it had a config file. They still work,
+/// but lose to their `SONGBIRD_` spelling.
+const LEGACY_ENV: &[(&str, &str)] = &[
+    ("DATABASE_URL", "database.url"),
+    ("JWT_SECRET", "auth.jwt_secret"),
+    ("PUBLIC_URL", "server.public_url"),
+    ("MAIL_BACKEND", "mail.backend"),
+    ("SMTP_URL", "mail.smtp_url"),
+    ("MAIL_FROM", "mail.from"),
+    ("MAIL_DIR", "mail.dir"),
+    ("RUST_LOG", "log.filter"),
+];
+
+#[derive(Debug, Default, Parser)]
+#[command(name = "songbird-server", version, about = "Songbird chat server")]
+pub struct Cli {
+    /// Config file to read. Defaults to songbird.toml in the working
+    /// directory, if there is one.
+    #[arg(long, env = "SONGBIRD_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on, e.g. 0.0.0.0:3000
+    #[arg(long, value_name = "ADDR")]
+    pub bind: Option<String>,
+
+    /// Postgres connection string
+    #[arg(long, value_name = "URL")]
+    pub database_url: Option<String>,
+
+    /// pretty or json
+    #[arg(long, value_name = "FORMAT")]
+    pub log_format: Option<String>,
+
+    /// Override any setting, e.g. --set database.max_connections=20
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+
+    /// Apply pending database migrations and exit
+    #[arg(long)]
+    pub migrate_only: bool,
+}
+
+/// Everything the server can be configured with. Settings are layered:
+/// built-in defaults, then the TOML file, then the environment, then command
+/// line flags. See `songbird.example.toml` for every key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub limits: LimitsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub cache: CacheConfig,
+    pub cors: CorsConfig,
+    pub log: LogConfig,
+    pub mail: MailConfig,
+    /// `SONGBIRD_` variables that name no setting and were skipped, for
+    /// the caller to warn about once logging is set up.
+    #[serde(skip)]
+    pub ignored_env: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: SocketAddr,
+    /// Where clients reach the server; used for links in emails.
+    pub public_url: String,
+    /// Serve HTTPS directly instead of behind a terminating proxy.
+    pub tls: Option<TlsConfig>,
+    /// After SIGTERM, how long in-flight requests, gateway sessions and
+    /// background jobs get to finish before they are dropped.
+    pub shutdown_timeout_secs: u64,
+    /// Stamped into every ID this instance generates; instances sharing a
+    /// database need different ones, from 0 to 1023.
+    pub worker_id: u16,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], 3000)),
+            public_url: "http://localhost:3000".to_string(),
+            tls: None,
+            shutdown_timeout_secs: 30,
+            worker_id: 0,
+        }
+    }
+}
+
+/// PEM files; the key has to be PKCS#8 (`BEGIN PRIVATE KEY`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    /// Close connections idle for this long; 0 keeps them.
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 5,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Signs sessions, 2FA tickets and email links.
+    pub jwt_secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Largest request body accepted, in bytes.
+    pub max_body_bytes: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub per_user: BucketConfig,
+    pub per_ip: BucketConfig,
+    /// Addresses or CIDR ranges of the reverse proxies in front of the
+    /// server. Only requests from these have the client address taken from
+    /// `Forwarded` or `X-Forwarded-For`, for rate limiting and login backoff.
+    pub trusted_proxies: Vec<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            per_user: PER_USER,
+            per_ip: PER_IP,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+/// The in-process cache of users, servers, channels and memberships.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// How long an entry is trusted. Writes through this instance take
+    /// effect immediately; writes through another one can take this long.
+    pub ttl_secs: u64,
+    /// The most entries each of the four caches holds before evicting the
+    /// least recently used.
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl_secs: 10,
+            max_entries: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins such as `https://app.example.com`, or `*` for any. Empty
+    /// sends no CORS headers at all.
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// A `tracing` filter such as `info` or `songbird_server=debug,info`.
+    pub filter: String,
+    /// OTLP/HTTP collector to send traces to, e.g. `http://localhost:4318`.
+    /// Needs a build with the `otlp` feature.
+    pub otlp_endpoint: Option<String>,
+    /// How this instance is named in traces.
+    pub service_name: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Pretty,
+            filter: "info".to_string(),
+            otlp_endpoint: None,
+            service_name: "songbird-server".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    #[default]
+    Smtp,
+    /// Only logs mail, and writes it to `mail.dir` if set. For development:
+    /// the emails carry live password reset and verification links.
+    Log,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    pub backend: MailBackend,
+    /// Required by the `smtp` backend.
+    pub smtp_url: Option<String>,
+    pub from: String,
+    /// Where the `log` backend writes each email.
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            backend: MailBackend::Smtp,
+            smtp_url: None,
+            from: "Songbird <no-reply@localhost>".to_string(),
+            dir: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, source: std::io::Error },
+    Parse { source: String, message: String },
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => write!(f, "cannot read {}: {}", path.display(), source),
+            ConfigError::Parse { source, message } => write!(f, "invalid configuration in {}: {}", source, message),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Reads every layer from the process environment and validates the
+    /// result.
+    pub fn load(cli: &Cli) -> Result<Self, ConfigError> {
+        Self::load_from(cli, std::env::vars())
+    }
+
+    /// Like `load`, with the environment passed in.
+    pub fn load_from(cli: &Cli, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self, ConfigError> {
+        let mut merged = match Value::try_from(Config::default()) {
+            Ok(Value::Table(table)) => table,
+            _ => unreachable!("the default config serializes to a table"),
+        };
+
+        let path = cli.config.clone().or_else(|| {
+            let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+            default.is_file().then_some(default)
+        });
+        if let Some(path) = path {
+            merge(&mut merged, read_file(&path)?);
+        }
+
+        let vars: Vec<(String, String)> = vars.into_iter().collect();
+        let mut ignored_env = Vec::new();
+        for (name, key) in LEGACY_ENV {
+            if let Some((_, value)) = vars.iter().find(|(var, _)| var == name) {
+                set(&mut merged, key, value);
+            }
+        }
+        for (var, value) in &vars {
+            let Some(name) = var.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            // Picks the file, handled above
+            if name == "CONFIG" {
+                continue;
+            }
+            let key = name.to_lowercase().replace("__", ".");
+            if !is_known_key(&key, value) {
+                ignored_env.push(var.clone());
+                continue;
+            }
+            set(&mut merged, &key, value);
+        }
+
+        if let Some(bind) = &cli.bind {
+            set(&mut merged, "server.bind", bind);
+        }
+        if let Some(url) = &cli.database_url {
+            set(&mut merged, "database.url", url);
+        }
+        if let Some(format) = &cli.log_format {
+            set(&mut merged, "log.format", format);
+        }
+        for entry in &cli.overrides {
+            let Some((key, value)) = entry.split_once('=') else {
+                return Err(ConfigError::Parse {
+                    source: "--set".to_string(),
+                    message: format!("expected KEY=VALUE, got `{}`", entry),
+                });
+            };
+            set(&mut merged, key.trim(), value.trim());
+        }
+
+        let mut config: Config = serde_path_to_error::deserialize(Value::Table(merged)).map_err(|e| {
+            let path = e.path().to_string();
+            ConfigError::Parse {
+                source: if path == "." { "settings".to_string() } else { path },
+                message: e.into_inner().message().to_string(),
+            }
+        })?;
+
+        config.validate()?;
+        config.ignored_env = ignored_env;
+        Ok(config)
+    }
+
+    /// Checks everything that would otherwise fail later, and reports all
+    /// of it at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.database.url.is_empty() {
+            problems.push("database.url is required (or set DATABASE_URL)".to_string());
+        } else if !["postgres://", "postgresql://", "sqlite:"]
+            .iter()
+            .any(|scheme| self.database.url.starts_with(scheme))
+        {
+            problems.push("database.url must be a postgres:// or sqlite: URL".to_string());
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            problems.push("database.min_connections cannot exceed database.max_connections".to_string());
+        }
+        if self.database.acquire_timeout_secs == 0 {
+            problems.push("database.acquire_timeout_secs must be at least 1".to_string());
+        }
+
+        if self.auth.jwt_secret.is_empty() {
+            problems.push("auth.jwt_secret is required (or set JWT_SECRET)".to_string());
+        }
+
+        if validate_http_url(&self.server.public_url).is_err() {
+            problems.push("server.public_url must be an http or https URL".to_string());
+        }
+        if self.server.worker_id > snowflake::MAX_WORKER_ID {
+            problems.push(format!("server.worker_id must be between 0 and {}", snowflake::MAX_WORKER_ID));
+        }
+        if let Some(tls) = &self.server.tls {
+            for (key, path) in [("cert_path", &tls.cert_path), ("key_path", &tls.key_path)] {
+                if !path.is_file() {
+                    problems.push(format!("server.tls.{}: {} is not a file", key, path.display()));
+                }
+            }
+        }
+
+        if self.limits.max_body_bytes == 0 {
+            problems.push("limits.max_body_bytes must be at least 1".to_string());
+        }
+
+        for (key, bucket) in [("per_user", self.rate_limit.per_user), ("per_ip", self.rate_limit.per_ip)] {
+            if bucket.capacity == 0 {
+                problems.push(format!("rate_limit.{}.capacity must be at least 1", key));
+            }
+            if !(bucket.refill_per_second.is_finite() && bucket.refill_per_second > 0.0) {
+                problems.push(format!("rate_limit.{}.refill_per_second must be positive", key));
+            }
+        }
+
+        for proxy in &self.rate_limit.trusted_proxies {
+            if parse_trusted_proxy(proxy).is_none() {
+                problems.push(format!(
+                    "rate_limit.trusted_proxies: `{}` is not an address or a range like 10.0.0.0/8",
+                    proxy
+                ));
+            }
+        }
+
+        if self.cache.enabled {
+            if self.cache.ttl_secs == 0 {
+                problems.push("cache.ttl_secs must be at least 1".to_string());
+            }
+            if self.cache.max_entries == 0 {
+                problems.push("cache.max_entries must be at least 1".to_string());
+            }
+        }
+
+        for origin in &self.cors.allowed_origins {
+            if !is_valid_origin(origin) {
+                problems.push(format!(
+                    "cors.allowed_origins: `{}` is not an origin like https://app.example.com",
+                    origin
+                ));
+            }
+        }
+
+        if tracing_subscriber::EnvFilter::try_new(&self.log.filter).is_err() {
+            problems.push(format!("log.filter: `{}` is not a valid filter", self.log.filter));
+        }
+
+        if let Some(endpoint) = &self.log.otlp_endpoint {
+            if !cfg!(feature = "otlp") {
+                problems.push("log.otlp_endpoint needs a build with the `otlp` feature".to_string());
+            } else if validate_http_url(endpoint).is_err() {
+                problems.push("log.otlp_endpoint must be an http or https URL".to_string());
+            }
+        }
+
+        match (self.mail.backend, &self.mail.smtp_url) {
+            (MailBackend::Smtp, None) => problems.push(
+                "mail.smtp_url is required (or set SMTP_URL, or mail.backend = \"log\" for development)".to_string(),
+            ),
+            (MailBackend::Log, Some(_)) => {
+                problems.push("mail.smtp_url is set but mail.backend is \"log\"".to_string())
+            }
+            _ => {}
+        }
+        if self.mail.from.parse::<lettre::message::Mailbox>().is_err() {
+            problems.push(format!("mail.from: `{}` is not a valid address", self.mail.from));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<Table, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    text.parse::<Table>().map_err(|e| ConfigError::Parse {
+        source: path.display().to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Whether `key` names a setting, found by setting it on the defaults and
+/// seeing whether that is refused as an unknown field. Any other problem
+/// with the value is left for the real load to report.
+fn is_known_key(key: &str, raw: &str) -> bool {
+    let mut probe = match Value::try_from(Config::default()) {
+        Ok(Value::Table(table)) => table,
+        _ => unreachable!("the default config serializes to a table"),
+    };
+    set(&mut probe, key, raw);
+
+    match serde_path_to_error::deserialize::<_, Config>(Value::Table(probe)) {
+        Ok(_) => true,
+        Err(e) => !e.inner().message().starts_with("unknown field"),
+    }
+}
+
+/// Copies `overlay` onto `base`, descending into tables present in both.
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Sets a dotted key from a string, as found in the environment or on the
+/// command line.
+fn set(table: &mut Table, key: &str, raw: &str) {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let leaf = parts.pop().unwrap_or_default();
+
+    let mut table = table;
+    for part in parts {
+        let entry = table
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        table = entry.as_table_mut().expect("just made sure it is a table");
+    }
+
+    let value = parse_override(table.get(leaf), raw);
+    table.insert(leaf.to_string(), value);
+}
+
+/// Strings stay strings unless the setting they replace is a number, bool
+/// or list, so a JWT secret of `12345` is not turned into an integer.
+fn parse_override(existing: Option<&Value>, raw: &str) -> Value {
+    let as_toml = || {
+        format!("value = {}", raw)
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+    };
+
+    match existing {
+        Some(Value::Integer(_) | Value::Float(_) | Value::Boolean(_)) => {
+            as_toml().unwrap_or_else(|| Value::String(raw.to_string()))
+        }
+        Some(Value::Array(_)) if raw.trim_start().starts_with('[') => {
+            as_toml().unwrap_or_else(|| Value::String(raw.to_string()))
+        }
+        Some(Value::Array(_)) => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        ),
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+fn is_valid_origin(origin: &str) -> bool {
+    if origin == "*" {
+        return true;
+    }
+
+    let Some((_, rest)) = origin.split_once("://") else {
+        return false;
+    };
+    validate_http_url(origin).is_ok() && !rest.contains(['/', '?', '#'])
+}
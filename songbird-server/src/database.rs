@@ -1,18 +1,133 @@
-use dotenv::dotenv;
-use sqlx::pool::Pool;
-use sqlx::postgres::PgPoolOptions;
+use crate::config::DatabaseConfig;
+use crate::repositories::{Repositories, SqliteDatabase};
+use sqlx::migrate::{Migrate, MigrateError, Migration, Migrator};
+use sqlx::pool::{Pool, PoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
 use sqlx::Error;
-use sqlx::Postgres;
-use std::env;
+use sqlx::{Postgres, Sqlite};
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::time::Duration;
 
-pub async fn establish_connection() -> Result<Pool<Postgres>, Error> {
-    dotenv().ok();
-    let connection_string = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+/// The migrations in `migrations/`, compiled into the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
-    let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&connection_string)
-        .await?;
+/// The SQLite equivalents, in `migrations_sqlite/`.
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations_sqlite");
+
+/// The storage backend picked by the scheme of `database.url`.
+#[derive(Clone)]
+pub enum Database {
+    Postgres(Pool<Postgres>),
+    Sqlite(Pool<Sqlite>),
+}
+
+impl Database {
+    pub fn repositories(&self) -> Repositories {
+        match self {
+            Database::Postgres(pool) => Repositories::postgres(pool.clone()),
+            Database::Sqlite(pool) => Repositories::sqlite(SqliteDatabase::new(pool.clone())),
+        }
+    }
+
+    /// Applies the migrations this database has not had yet and returns
+    /// them, oldest first.
+    pub async fn migrate(&self) -> Result<Vec<&'static Migration>, MigrateError> {
+        match self {
+            Database::Postgres(pool) => {
+                let applied = applied_versions(&mut *pool.acquire().await?).await?;
+                MIGRATOR.run(pool).await?;
+                Ok(newly_applied(&MIGRATOR, &applied))
+            }
+            Database::Sqlite(pool) => {
+                let applied = applied_versions(&mut *pool.acquire().await?).await?;
+                SQLITE_MIGRATOR.run(pool).await?;
+                Ok(newly_applied(&SQLITE_MIGRATOR, &applied))
+            }
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Database::Postgres(pool) => pool.close().await,
+            Database::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
+
+/// Connects to Postgres for `postgres://` URLs and SQLite for `sqlite:`
+/// ones, migrating the schema either way.
+pub async fn connect(config: &DatabaseConfig) -> Result<Database, Error> {
+    if config.url.starts_with("sqlite:") {
+        establish_sqlite_connection(config).await.map(Database::Sqlite)
+    } else {
+        establish_connection(config).await.map(Database::Postgres)
+    }
+}
+
+/// Like `connect`, but leaves the schema alone. For tools that should not
+/// migrate a database out from under the servers using it.
+pub async fn open(config: &DatabaseConfig) -> Result<Database, Error> {
+    if config.url.starts_with("sqlite:") {
+        let options = sqlite_options(config)?;
+        pool_options::<Sqlite>(config).connect_with(options).await.map(Database::Sqlite)
+    } else {
+        pool_options::<Postgres>(config).connect(&config.url).await.map(Database::Postgres)
+    }
+}
+
+/// Connects to `database.url` and brings the schema up to date before
+/// anything else touches it. Migrations that were already applied are
+/// skipped, and concurrent starts are serialized by sqlx's advisory lock.
+pub async fn establish_connection(config: &DatabaseConfig) -> Result<Pool<Postgres>, Error> {
+    let pool = pool_options::<Postgres>(config).connect(&config.url).await?;
+
+    MIGRATOR.run(&pool).await?;
+    tracing::info!("Database schema is up to date");
+
+    Ok(pool)
+}
+
+/// Opens the SQLite database in `database.url`, creating the file if it
+/// does not exist, and brings its schema up to date. SQLite only enforces
+/// foreign keys when asked to, and WAL lets readers carry on while a write
+/// is in progress; writers queue up for the busy timeout rather than fail.
+pub async fn establish_sqlite_connection(config: &DatabaseConfig) -> Result<Pool<Sqlite>, Error> {
+    let pool = pool_options::<Sqlite>(config).connect_with(sqlite_options(config)?).await?;
+
+    SQLITE_MIGRATOR.run(&pool).await?;
+    tracing::info!("Database schema is up to date");
 
     Ok(pool)
 }
+
+fn sqlite_options(config: &DatabaseConfig) -> Result<SqliteConnectOptions, Error> {
+    Ok(SqliteConnectOptions::from_str(&config.url)?
+        .create_if_missing(true)
+        .foreign_keys(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(Duration::from_secs(5)))
+}
+
+async fn applied_versions(conn: &mut impl Migrate) -> Result<HashSet<i64>, MigrateError> {
+    conn.ensure_migrations_table().await?;
+    let applied = conn.list_applied_migrations().await?;
+    Ok(applied.into_iter().map(|migration| migration.version).collect())
+}
+
+fn newly_applied(migrator: &'static Migrator, before: &HashSet<i64>) -> Vec<&'static Migration> {
+    migrator
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration() && !before.contains(&migration.version))
+        .collect()
+}
+
+fn pool_options<DB: sqlx::Database>(config: &DatabaseConfig) -> PoolOptions<DB> {
+    let idle_timeout = (config.idle_timeout_secs > 0).then(|| Duration::from_secs(config.idle_timeout_secs));
+
+    PoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+        .idle_timeout(idle_timeout)
+}
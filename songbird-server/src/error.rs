@@ -0,0 +1,312 @@
+// src/error.rs
+use crate::handlers::ApiResponse;
+use crate::rate_limit::RateLimitedResponse;
+use crate::services::email::EmailError;
+use crate::services::{ChannelAccessError, SlowmodeError};
+use crate::validation::ValidationErrorResponse;
+use axum::{
+    extract::rejection::JsonRejection,
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::time::Duration;
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+
+/// Stable, machine-readable error codes sent as `code` in the response
+/// envelope. Clients should branch on these rather than on `error`, whose
+/// wording may change. Each code always comes with the same HTTP status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // 400
+    InvalidBody,
+    InvalidRequest,
+    InvalidReference,
+    InvalidToken,
+    PinLimitReached,
+    // 401
+    Unauthorized,
+    InvalidCredentials,
+    InvalidMfaCode,
+    InvalidMfaTicket,
+    // 403
+    Forbidden,
+    MissingPermissions,
+    MfaSetupRequired,
+    AccountDisabled,
+    // 404
+    NotFound,
+    UnknownUser,
+    UnknownServer,
+    UnknownChannel,
+    UnknownMessage,
+    UnknownMember,
+    // 409
+    Conflict,
+    UsernameTaken,
+    EmailTaken,
+    ServerNameTaken,
+    MfaAlreadyEnabled,
+    EmailAlreadyVerified,
+    // 415
+    UnsupportedMediaType,
+    // 422
+    ValidationFailed,
+    // 429
+    RateLimited,
+    // 500
+    InternalError,
+    // 503
+    ServiceUnavailable,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        use ErrorCode::*;
+        match self {
+            InvalidBody | InvalidRequest | InvalidReference | InvalidToken | PinLimitReached => {
+                StatusCode::BAD_REQUEST
+            }
+            Unauthorized | InvalidCredentials | InvalidMfaCode | InvalidMfaTicket => StatusCode::UNAUTHORIZED,
+            Forbidden | MissingPermissions | MfaSetupRequired | AccountDisabled => StatusCode::FORBIDDEN,
+            NotFound | UnknownUser | UnknownServer | UnknownChannel | UnknownMessage | UnknownMember => {
+                StatusCode::NOT_FOUND
+            }
+            Conflict | UsernameTaken | EmailTaken | ServerNameTaken | MfaAlreadyEnabled | EmailAlreadyVerified => {
+                StatusCode::CONFLICT
+            }
+            UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ValidationFailed => StatusCode::UNPROCESSABLE_ENTITY,
+            RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// Everything a handler can fail with. Handlers return `Result<_, AppError>`
+/// and use `?`; the conversion to a response happens in one place.
+#[derive(Debug)]
+pub enum AppError {
+    /// A failure the client can act on, with a message for humans.
+    Api {
+        code: ErrorCode,
+        message: Cow<'static, str>,
+    },
+    /// The body parsed but broke one of the DTO's rules.
+    Validation(ValidationErrors),
+    /// The body could not be read as the expected JSON.
+    Json(JsonRejection),
+    RateLimited {
+        retry_after: Duration,
+        message: Cow<'static, str>,
+    },
+    /// Classified by constraint name and SQLSTATE; anything unexpected
+    /// becomes a 500 and is logged.
+    Database(sqlx::Error),
+    /// Something that is never the client's fault. The detail is logged,
+    /// never sent.
+    Internal(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::Api {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCode::InvalidRequest, message)
+    }
+
+    pub fn unauthorized(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCode::Unauthorized, message)
+    }
+
+    pub fn forbidden(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCode::Forbidden, message)
+    }
+
+    pub fn missing_permissions(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCode::MissingPermissions, message)
+    }
+
+    pub fn rate_limited(retry_after: Duration, message: impl Into<Cow<'static, str>>) -> Self {
+        AppError::RateLimited {
+            retry_after,
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(detail: impl std::fmt::Display) -> Self {
+        AppError::Internal(detail.to_string())
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Api { code, .. } => *code,
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::Json(JsonRejection::MissingJsonContentType(_)) => ErrorCode::UnsupportedMediaType,
+            AppError::Json(_) => ErrorCode::InvalidBody,
+            AppError::RateLimited { .. } => ErrorCode::RateLimited,
+            AppError::Database(e) => database_error_code(e),
+            AppError::Internal(_) => ErrorCode::InternalError,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        match self {
+            AppError::Api { message, .. } | AppError::RateLimited { message, .. } => message.clone(),
+            AppError::Validation(_) => "Validation failed".into(),
+            AppError::Json(rejection) => rejection.body_text().into(),
+            AppError::Database(_) | AppError::Internal(_) => match self.code() {
+                ErrorCode::UsernameTaken => "Username already taken".into(),
+                ErrorCode::EmailTaken => "Email already registered".into(),
+                ErrorCode::ServerNameTaken => "Server name already exists".into(),
+                ErrorCode::Conflict => "Already exists".into(),
+                ErrorCode::InvalidReference => "Referenced resource does not exist".into(),
+                ErrorCode::InvalidRequest => "Value is out of range".into(),
+                _ => "Internal server error".into(),
+            },
+        }
+    }
+}
+
+/// Maps well-known constraint violations to their codes. Unique violations
+/// are matched by constraint name so a new unique column cannot silently
+/// turn into the wrong code.
+fn database_error_code(e: &sqlx::Error) -> ErrorCode {
+    let Some(db_error) = e.as_database_error() else {
+        return ErrorCode::InternalError;
+    };
+
+    match (db_error.code().as_deref(), db_error.constraint()) {
+        (Some("23505"), Some("users_username_key")) => ErrorCode::UsernameTaken,
+        (Some("23505"), Some("users_email_key")) => ErrorCode::EmailTaken,
+        (Some("23505"), Some("servers_server_name_key")) => ErrorCode::ServerNameTaken,
+        (Some("23505"), _) => ErrorCode::Conflict,
+        // foreign_key_violation
+        (Some("23503"), _) => ErrorCode::InvalidReference,
+        // check_violation, string_data_right_truncation, numeric_value_out_of_range
+        (Some("23514" | "22001" | "22003"), _) => ErrorCode::InvalidRequest,
+        _ => ErrorCode::InternalError,
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Database(e) => write!(f, "database error: {}", e),
+            AppError::Internal(detail) => write!(f, "internal error: {}", detail),
+            _ => write!(f, "{:?}: {}", self.code(), self.message()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        AppError::Database(e)
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::Validation(errors)
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::Json(rejection)
+    }
+}
+
+impl From<ChannelAccessError> for AppError {
+    fn from(e: ChannelAccessError) -> Self {
+        match e {
+            ChannelAccessError::NotFound => AppError::new(ErrorCode::UnknownChannel, "Channel not found"),
+            ChannelAccessError::Forbidden => AppError::forbidden("You cannot access this channel"),
+            ChannelAccessError::Database(e) => AppError::Database(e),
+        }
+    }
+}
+
+impl From<SlowmodeError> for AppError {
+    fn from(e: SlowmodeError) -> Self {
+        match e {
+            SlowmodeError::RateLimited { retry_after } => {
+                AppError::rate_limited(retry_after, "Slowmode is enabled in this channel")
+            }
+            SlowmodeError::Database(e) => AppError::Database(e),
+        }
+    }
+}
+
+impl From<EmailError> for AppError {
+    fn from(e: EmailError) -> Self {
+        match e {
+            EmailError::Database(e) => AppError::Database(e),
+            e => AppError::internal(e),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let code = self.code();
+        if code == ErrorCode::InternalError {
+            tracing::error!("{}", self);
+        }
+
+        let status = match &self {
+            AppError::Json(rejection) => rejection.status(),
+            _ => code.status(),
+        };
+        let message = self.message().into_owned();
+
+        match self {
+            AppError::Validation(errors) => {
+                envelope(status, code, message, Some(ValidationErrorResponse::from(&errors)))
+            }
+            AppError::RateLimited { retry_after, .. } => {
+                let mut response = envelope(
+                    status,
+                    code,
+                    message,
+                    Some(RateLimitedResponse {
+                        retry_after: retry_after.as_secs_f64(),
+                    }),
+                );
+
+                // Retry-After only takes whole seconds, so round up.
+                let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                response
+                    .headers_mut()
+                    .insert("retry-after", HeaderValue::from(seconds));
+                response
+            }
+            _ => envelope(status, code, message, None::<()>),
+        }
+    }
+}
+
+fn envelope<T: Serialize>(status: StatusCode, code: ErrorCode, message: String, data: Option<T>) -> Response {
+    (
+        status,
+        Json(ApiResponse {
+            success: false,
+            data,
+            error: Some(message),
+            code: Some(code),
+        }),
+    )
+        .into_response()
+}
@@ -0,0 +1,201 @@
+// src/gateway/events.rs
+use crate::models::models::{
+    Channel, CustomStatus, MessageWithAuthorResponse, PresenceStatus, Server, ServerMember, UserResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Gateway opcodes. Every frame on the socket is `{ "op": .., "d": .. }`, and
+/// dispatches additionally carry the event name in `t`.
+pub mod opcode {
+    pub const DISPATCH: u8 = 0;
+    pub const HEARTBEAT: u8 = 1;
+    pub const IDENTIFY: u8 = 2;
+    pub const PRESENCE_UPDATE: u8 = 3;
+    pub const TYPING_START: u8 = 4;
+    /// The server is going away; connect again, to another instance.
+    pub const RECONNECT: u8 = 7;
+    pub const INVALID_SESSION: u8 = 9;
+    pub const HELLO: u8 = 10;
+    pub const HEARTBEAT_ACK: u8 = 11;
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncomingFrame {
+    pub op: u8,
+    #[serde(default)]
+    pub d: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct OutgoingFrame<'a, T: Serialize> {
+    op: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    t: Option<&'a str>,
+    d: T,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdentifyPayload {
+    pub token: String,
+}
+
+/// Sent by a client to change the user's chosen status and whether this
+/// particular session is away from keyboard.
+#[derive(Debug, Deserialize)]
+pub struct PresenceUpdatePayload {
+    pub status: Option<PresenceStatus>,
+    #[serde(default)]
+    pub afk: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TypingStartPayload {
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HelloPayload {
+    pub heartbeat_interval: u64,
+}
+
+/// Everything a client needs to draw its first screen, so it does not have
+/// to follow up with a request per server. Built by `services::ReadyService`.
+#[derive(Debug, Serialize)]
+pub struct ReadyPayload {
+    pub session_id: u64,
+    pub user: UserResponse,
+    pub preferred_status: PresenceStatus,
+    pub servers: Vec<ReadyServer>,
+    /// The user's DM channels.
+    pub private_channels: Vec<Channel>,
+}
+
+/// A server the user is in, with their membership and its channels.
+#[derive(Debug, Serialize)]
+pub struct ReadyServer {
+    pub server: Server,
+    pub member: ServerMember,
+    pub channels: Vec<Channel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PresenceUpdateEvent {
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
+    pub status: PresenceStatus,
+    pub custom_status: Option<CustomStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TypingStartEvent {
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TypingStopEvent {
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelPinsUpdateEvent {
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub message_id: i64,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerMemberRemoveEvent {
+    #[serde(with = "crate::snowflake::string")]
+    pub server_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerDeleteEvent {
+    #[serde(with = "crate::snowflake::string")]
+    pub server_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelDeleteEvent {
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::option_string")]
+    pub server_id: Option<i64>,
+}
+
+#[derive(Debug)]
+pub enum DispatchEvent {
+    Ready(ReadyPayload),
+    PresenceUpdate(PresenceUpdateEvent),
+    TypingStart(TypingStartEvent),
+    TypingStop(TypingStopEvent),
+    MessageCreate(MessageWithAuthorResponse),
+    ChannelPinsUpdate(ChannelPinsUpdateEvent),
+    ServerMemberAdd(ServerMember),
+    ServerMemberRemove(ServerMemberRemoveEvent),
+    ServerUpdate(Server),
+    ServerDelete(ServerDeleteEvent),
+    ChannelUpdate(Channel),
+    ChannelDelete(ChannelDeleteEvent),
+    UserUpdate(UserResponse),
+}
+
+impl DispatchEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DispatchEvent::Ready(_) => "READY",
+            DispatchEvent::PresenceUpdate(_) => "PRESENCE_UPDATE",
+            DispatchEvent::TypingStart(_) => "TYPING_START",
+            DispatchEvent::TypingStop(_) => "TYPING_STOP",
+            DispatchEvent::MessageCreate(_) => "MESSAGE_CREATE",
+            DispatchEvent::ChannelPinsUpdate(_) => "CHANNEL_PINS_UPDATE",
+            DispatchEvent::ServerMemberAdd(_) => "SERVER_MEMBER_ADD",
+            DispatchEvent::ServerMemberRemove(_) => "SERVER_MEMBER_REMOVE",
+            DispatchEvent::ServerUpdate(_) => "SERVER_UPDATE",
+            DispatchEvent::ServerDelete(_) => "SERVER_DELETE",
+            DispatchEvent::ChannelUpdate(_) => "CHANNEL_UPDATE",
+            DispatchEvent::ChannelDelete(_) => "CHANNEL_DELETE",
+            DispatchEvent::UserUpdate(_) => "USER_UPDATE",
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let d = match self {
+            DispatchEvent::Ready(payload) => serde_json::to_value(payload),
+            DispatchEvent::PresenceUpdate(payload) => serde_json::to_value(payload),
+            DispatchEvent::TypingStart(payload) => serde_json::to_value(payload),
+            DispatchEvent::TypingStop(payload) => serde_json::to_value(payload),
+            DispatchEvent::MessageCreate(payload) => serde_json::to_value(payload),
+            DispatchEvent::ChannelPinsUpdate(payload) => serde_json::to_value(payload),
+            DispatchEvent::ServerMemberAdd(payload) => serde_json::to_value(payload),
+            DispatchEvent::ServerMemberRemove(payload) => serde_json::to_value(payload),
+            DispatchEvent::ServerUpdate(payload) => serde_json::to_value(payload),
+            DispatchEvent::ServerDelete(payload) => serde_json::to_value(payload),
+            DispatchEvent::ChannelUpdate(payload) => serde_json::to_value(payload),
+            DispatchEvent::ChannelDelete(payload) => serde_json::to_value(payload),
+            DispatchEvent::UserUpdate(payload) => serde_json::to_value(payload),
+        }
+        .unwrap_or(serde_json::Value::Null);
+
+        encode_frame(opcode::DISPATCH, Some(self.name()), d)
+    }
+}
+
+pub fn encode_frame<T: Serialize>(op: u8, t: Option<&str>, d: T) -> String {
+    serde_json::to_string(&OutgoingFrame { op, t, d })
+        .expect("gateway frames are always serializable")
+}
@@ -0,0 +1,108 @@
+// src/gateway/hub.rs
+use crate::gateway::events::DispatchEvent;
+use crate::metrics::Metrics;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+
+/// How many published events a slow subscriber can fall behind by before
+/// it misses some.
+const PUBLISHED_BACKLOG: usize = 1024;
+
+#[derive(Default)]
+struct Registry {
+    sessions: HashMap<u64, (i64, mpsc::UnboundedSender<Arc<str>>)>,
+    by_user: HashMap<i64, HashSet<u64>>,
+}
+
+/// In-process registry of connected gateway sessions, used to fan dispatch
+/// events out to the sockets of specific users. Events about changed
+/// servers, channels, members and users also go to whoever called
+/// `subscribe`, such as the repository cache.
+#[derive(Clone)]
+pub struct GatewayHub {
+    registry: Arc<Mutex<Registry>>,
+    next_session_id: Arc<AtomicU64>,
+    published: broadcast::Sender<Arc<DispatchEvent>>,
+    metrics: Metrics,
+}
+
+impl GatewayHub {
+    pub fn new(metrics: Metrics) -> Self {
+        Self {
+            registry: Arc::default(),
+            next_session_id: Arc::default(),
+            published: broadcast::channel(PUBLISHED_BACKLOG).0,
+            metrics,
+        }
+    }
+
+    pub fn register(&self, user_id: i64) -> (u64, mpsc::UnboundedReceiver<Arc<str>>) {
+        let session_id = self.next_session_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let mut registry = self.registry.lock().unwrap();
+        registry.sessions.insert(session_id, (user_id, tx));
+        registry.by_user.entry(user_id).or_default().insert(session_id);
+        self.metrics.gateway_connected();
+
+        (session_id, rx)
+    }
+
+    pub fn unregister(&self, session_id: u64) {
+        let mut registry = self.registry.lock().unwrap();
+        if let Some((user_id, _)) = registry.sessions.remove(&session_id) {
+            self.metrics.gateway_disconnected();
+            if let Some(sessions) = registry.by_user.get_mut(&user_id) {
+                sessions.remove(&session_id);
+                if sessions.is_empty() {
+                    registry.by_user.remove(&user_id);
+                }
+            }
+        }
+    }
+
+    /// Sends `event` to every session of every listed user. The event is
+    /// encoded once no matter how many sockets receive it.
+    pub fn dispatch_to_users<I>(&self, user_ids: I, event: &DispatchEvent)
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        let frame: Arc<str> = event.encode().into();
+        let registry = self.registry.lock().unwrap();
+        let mut delivered = 0;
+
+        for user_id in user_ids {
+            let Some(session_ids) = registry.by_user.get(&user_id) else {
+                continue;
+            };
+            for session_id in session_ids {
+                if let Some((_, tx)) = registry.sessions.get(session_id) {
+                    if tx.send(frame.clone()).is_ok() {
+                        delivered += 1;
+                    }
+                }
+            }
+        }
+
+        self.metrics.events_dispatched(event.name(), delivered);
+    }
+
+    /// Dispatches `event` like `dispatch_to_users` and then hands it to
+    /// every subscriber. For changes to stored state, which more than the
+    /// connected clients need to hear about.
+    pub fn publish<I>(&self, user_ids: I, event: DispatchEvent)
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        self.dispatch_to_users(user_ids, &event);
+        // Fails only when nobody is subscribed
+        let _ = self.published.send(Arc::new(event));
+    }
+
+    /// Everything `publish`ed from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<DispatchEvent>> {
+        self.published.subscribe()
+    }
+}
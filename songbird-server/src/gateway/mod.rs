@@ -0,0 +1,27 @@
+// src/gateway/mod.rs
+pub mod events;
+pub mod hub;
+mod session;
+
+pub use hub::GatewayHub;
+
+use crate::router::AppState;
+use axum::{
+    extract::{State, WebSocketUpgrade},
+    response::IntoResponse,
+};
+
+/// Clients identify with their session token in the first frame, not in a
+/// header, so the upgrade itself is unauthenticated.
+#[utoipa::path(
+    get,
+    path = "/gateway",
+    tag = "gateway",
+    responses((status = 101, description = "Switched to the WebSocket protocol"))
+)]
+pub async fn gateway_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
+    // Upgraded sockets outlive the HTTP connection, so graceful shutdown
+    // only waits for them if they are tracked
+    let shutdown = state.shutdown.clone();
+    ws.on_upgrade(move |socket| shutdown.track(session::run(socket, state)))
+}
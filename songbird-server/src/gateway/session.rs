@@ -0,0 +1,217 @@
+// src/gateway/session.rs
+use crate::auth::{session_is_current, Claims};
+use crate::gateway::events::{
+    encode_frame, opcode, DispatchEvent, HelloPayload, IdentifyPayload, IncomingFrame,
+    PresenceUpdatePayload, TypingStartPayload,
+};
+use crate::models::models::{PresenceStatus, User};
+use crate::router::AppState;
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use std::time::Duration;
+use tokio::time::Instant;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(41_250);
+const IDENTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn run(mut socket: WebSocket, state: AppState) {
+    let hello = HelloPayload {
+        heartbeat_interval: HEARTBEAT_INTERVAL.as_millis() as u64,
+    };
+    if send(&mut socket, encode_frame(opcode::HELLO, None, hello)).await.is_err() {
+        return;
+    }
+
+    if state.shutdown.is_triggered() {
+        reconnect(&mut socket).await;
+        return;
+    }
+
+    let Some((user, claims)) = identify(&mut socket, &state).await else {
+        invalid_session(&mut socket).await;
+        return;
+    };
+
+    let user_id = user.user_id;
+    let (session_id, mut outbound) = state.gateway.register(user_id);
+    tracing::info!("Gateway session {} identified as user {}", session_id, user_id);
+
+    // Loaded after registering, so nothing that changes in the meantime
+    // is missed; the client applies those events on top of READY
+    let ready = match state.ready.build(session_id, user).await {
+        Ok(payload) => DispatchEvent::Ready(payload),
+        Err(e) => {
+            tracing::warn!("Failed to load READY for user {}: {}", user_id, e);
+            state.gateway.unregister(session_id);
+            let _ = socket
+                .send(Message::Close(Some(CloseFrame {
+                    code: close_code::ERROR,
+                    reason: "Failed to load session".into(),
+                })))
+                .await;
+            return;
+        }
+    };
+
+    if send(&mut socket, ready.encode()).await.is_ok() {
+        if let Err(e) = state.presence.connect(user_id, session_id).await {
+            tracing::warn!("Failed to mark user {} online: {}", user_id, e);
+        }
+        event_loop(&mut socket, &state, &claims, session_id, &mut outbound).await;
+    }
+
+    state.gateway.unregister(session_id);
+    if let Err(e) = state.presence.disconnect(user_id, session_id).await {
+        tracing::warn!("Failed to update presence for user {}: {}", user_id, e);
+    }
+    tracing::info!("Gateway session {} closed", session_id);
+}
+
+async fn event_loop(
+    socket: &mut WebSocket,
+    state: &AppState,
+    claims: &Claims,
+    session_id: u64,
+    outbound: &mut tokio::sync::mpsc::UnboundedReceiver<std::sync::Arc<str>>,
+) {
+    let user_id = claims.sub;
+    let mut last_heartbeat = Instant::now();
+    let mut zombie_check = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    Some(Ok(_)) => continue,
+                };
+
+                let Ok(frame) = serde_json::from_str::<IncomingFrame>(text.as_str()) else {
+                    tracing::debug!("Ignoring malformed gateway frame from session {}", session_id);
+                    continue;
+                };
+
+                match frame.op {
+                    opcode::HEARTBEAT => {
+                        last_heartbeat = Instant::now();
+                        // The token was only checked at IDENTIFY; a password
+                        // change, a disabled account or a revocation since
+                        // then ends the session here
+                        if !still_current(state, claims).await {
+                            tracing::info!("Gateway session {} is no longer valid", session_id);
+                            invalid_session(socket).await;
+                            return;
+                        }
+                        if send(socket, encode_frame(opcode::HEARTBEAT_ACK, None, ())).await.is_err() {
+                            return;
+                        }
+                    }
+                    opcode::PRESENCE_UPDATE => {
+                        if let Ok(payload) = serde_json::from_value::<PresenceUpdatePayload>(frame.d) {
+                            update_presence(state, user_id, session_id, payload).await;
+                        }
+                    }
+                    opcode::TYPING_START => {
+                        if let Ok(payload) = serde_json::from_value::<TypingStartPayload>(frame.d) {
+                            if let Err(e) = state.typing.start(payload.channel_id, user_id).await {
+                                tracing::debug!("Dropped typing signal from user {}: {:?}", user_id, e);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            frame = outbound.recv() => {
+                let Some(frame) = frame else {
+                    return;
+                };
+                if send(socket, frame.as_ref()).await.is_err() {
+                    return;
+                }
+            }
+            _ = state.shutdown.triggered() => {
+                tracing::debug!("Asking gateway session {} to reconnect", session_id);
+                reconnect(socket).await;
+                return;
+            }
+            _ = zombie_check.tick() => {
+                if last_heartbeat.elapsed() > HEARTBEAT_INTERVAL + HEARTBEAT_INTERVAL / 2 {
+                    tracing::info!("Gateway session {} missed its heartbeat", session_id);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn identify(socket: &mut WebSocket, state: &AppState) -> Option<(User, Claims)> {
+    let deadline = Instant::now() + IDENTIFY_TIMEOUT;
+
+    loop {
+        let message = tokio::time::timeout_at(deadline, socket.recv()).await.ok()??.ok()?;
+        let Message::Text(text) = message else {
+            continue;
+        };
+
+        let frame: IncomingFrame = serde_json::from_str(text.as_str()).ok()?;
+        if frame.op != opcode::IDENTIFY {
+            continue;
+        }
+
+        let payload: IdentifyPayload = serde_json::from_value(frame.d).ok()?;
+        let claims = state.session_keys.verify(&payload.token).ok()?;
+        let user = state.session_user_repository.find_by_id(claims.sub).await.ok()??;
+        return session_is_current(&claims, &user).then_some((user, claims));
+    }
+}
+
+/// Whether the account behind the session still accepts its token. A
+/// failed lookup keeps the session; the next heartbeat tries again.
+async fn still_current(state: &AppState, claims: &Claims) -> bool {
+    match state.session_user_repository.find_by_id(claims.sub).await {
+        Ok(Some(user)) => session_is_current(claims, &user),
+        Ok(None) => false,
+        Err(e) => {
+            tracing::warn!("Failed to recheck the session of user {}: {}", claims.sub, e);
+            true
+        }
+    }
+}
+
+async fn update_presence(state: &AppState, user_id: i64, session_id: u64, payload: PresenceUpdatePayload) {
+    if let Some(status) = payload.status {
+        // "offline" is not something a user can pick; the closest thing is invisible.
+        let status = match status {
+            PresenceStatus::Offline => PresenceStatus::Invisible,
+            other => other,
+        };
+        if let Err(e) = state.presence.set_preferred_status(user_id, status).await {
+            tracing::warn!("Failed to set status for user {}: {}", user_id, e);
+        }
+    }
+
+    if let Err(e) = state.presence.set_afk(user_id, session_id, payload.afk).await {
+        tracing::warn!("Failed to set afk for user {}: {}", user_id, e);
+    }
+}
+
+/// Tells the client its session is gone and it must identify again.
+async fn invalid_session(socket: &mut WebSocket) {
+    let _ = send(socket, encode_frame(opcode::INVALID_SESSION, None, false)).await;
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Tells the client to resume on another instance and closes the socket.
+async fn reconnect(socket: &mut WebSocket) {
+    let _ = send(socket, encode_frame(opcode::RECONNECT, None, ())).await;
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::RESTART,
+            reason: "Server restarting".into(),
+        })))
+        .await;
+}
+
+async fn send(socket: &mut WebSocket, frame: impl Into<String>) -> Result<(), axum::Error> {
+    socket.send(Message::Text(frame.into().into())).await
+}
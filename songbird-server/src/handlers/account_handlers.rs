@@ -0,0 +1,207 @@
+// src/handlers/account_handlers.rs
+use crate::auth::{hash_password, AuthUser, EmailTokenPurpose};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::handlers::user_handlers::user_not_found;
+use crate::handlers::ApiResponse;
+use crate::openapi::ErrorResponse;
+use crate::router::AppState;
+use crate::validation::{validate_password, ValidatedJson, ValidationErrorResponse};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1, max = 2048, message = "Token must be between 1 and 2048 characters"))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Must be a valid email address"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, max = 2048, message = "Token must be between 1 and 2048 characters"))]
+    pub token: String,
+    #[validate(custom(function = "validate_password"))]
+    pub new_password: String,
+}
+
+/// Redeems the link from a verification email and marks the address as
+/// verified.
+#[utoipa::path(
+    post,
+    path = "/email/verify",
+    tag = "auth",
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 204, description = "The address is verified"),
+        (status = 400, description = "Invalid or expired link", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    )
+)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<VerifyEmailRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Verifying email...");
+
+    let invalid_link = || AppError::new(ErrorCode::InvalidToken, "Invalid or expired verification link");
+
+    let redeemed = state
+        .email
+        .redeem(&payload.token, EmailTokenPurpose::VerifyEmail)
+        .await?
+        .ok_or_else(invalid_link)?;
+
+    // A token for an address the user has since changed away from is useless
+    if !state
+        .user_repository
+        .mark_email_verified(redeemed.user_id, &redeemed.email)
+        .await?
+    {
+        return Err(invalid_link());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/email/verification",
+    tag = "users",
+    params(("user_id" = i64, Path)),
+    responses(
+        (status = 202, description = "A new verification email was sent"),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Not your own account", body = ErrorResponse),
+        (status = 404, description = "No such user", body = ErrorResponse),
+        (status = 409, description = "Already verified", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Resending verification email...");
+
+    if auth.user_id != user_id {
+        return Err(AppError::forbidden("Cannot verify another user's email"));
+    }
+
+    let user = state
+        .user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    if user.email_verified {
+        return Err(AppError::new(ErrorCode::EmailAlreadyVerified, "Email is already verified"));
+    }
+
+    state.email.send_verification(&user).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Always answers 202 so the response does not reveal whether an account
+/// uses the address; the email goes out in the background.
+#[utoipa::path(
+    post,
+    path = "/password/forgot",
+    tag = "auth",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 202, description = "Sent whether or not an account uses the address", body = ApiResponse<String>),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    )
+)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ForgotPasswordRequest>,
+) -> impl IntoResponse {
+    tracing::info!("Password reset requested");
+
+    let email = state.email.clone();
+    let user_repository = state.user_repository.clone();
+    state.shutdown.spawn(async move {
+        match user_repository.find_by_email(&payload.email).await {
+            Ok(Some(user)) => {
+                if let Err(e) = email.send_password_reset(&user).await {
+                    tracing::warn!("Failed to send password reset email to user {}: {}", user.user_id, e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to look up user for password reset: {}", e),
+        }
+    });
+
+    (
+        StatusCode::ACCEPTED,
+        ApiResponse::ok("If an account uses that address, a reset link is on its way".to_string()),
+    )
+}
+
+/// Sets a new password from a reset link and ends every session. Following
+/// the link proves control of the mailbox, so it also verifies the address
+/// and lifts any login lockout on the account.
+#[utoipa::path(
+    post,
+    path = "/password/reset",
+    tag = "auth",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 204, description = "The password is changed"),
+        (status = 400, description = "Invalid or expired link", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    )
+)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<ResetPasswordRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Resetting password...");
+
+    let invalid_link = || AppError::new(ErrorCode::InvalidToken, "Invalid or expired reset link");
+
+    let redeemed = state
+        .email
+        .redeem(&payload.token, EmailTokenPurpose::ResetPassword)
+        .await?
+        .ok_or_else(invalid_link)?;
+
+    let user = match state.user_repository.find_by_id(redeemed.user_id).await? {
+        Some(user) if user.email == redeemed.email => user,
+        _ => return Err(invalid_link()),
+    };
+
+    let password_hash = hash_password(&payload.new_password).map_err(AppError::internal)?;
+
+    state
+        .user_repository
+        .update_password(user.user_id, &password_hash)
+        .await?;
+    // Whoever knew the old password may still be logged in
+    state.user_repository.revoke_sessions(user.user_id).await?;
+
+    if let Err(e) = state
+        .user_repository
+        .mark_email_verified(user.user_id, &user.email)
+        .await
+    {
+        tracing::warn!("Failed to mark email verified for user {}: {}", user.user_id, e);
+    }
+    state.login_guard.clear_account(&user.username);
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,176 @@
+// src/handlers/channel_handlers.rs
+use crate::auth::AuthUser;
+use crate::error::{AppError, AppResult};
+use crate::handlers::ApiResponse;
+use crate::models::models::{Channel, Permissions};
+use crate::openapi::ErrorResponse;
+use crate::router::AppState;
+use crate::services::slowmode::MAX_RATE_LIMIT_PER_USER;
+use crate::services::ChannelAccessError;
+use crate::validation::{validate_not_blank, ValidatedJson, ValidationErrorResponse};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct UpdateSlowmodeRequest {
+    /// Seconds between messages per member, 0 turns slowmode off.
+    #[validate(range(min = 0, max = "MAX_RATE_LIMIT_PER_USER", message = "Slowmode must be between 0 and 21600 seconds"))]
+    pub rate_limit_per_user: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct UpdateChannelRequest {
+    #[validate(length(min = 1, max = 100, message = "Channel name must be between 1 and 100 characters"), custom(function = "validate_not_blank"))]
+    pub name: String,
+}
+
+/// Renames the channel. Its viewers get a `channel_rename` message when the
+/// name actually changes.
+#[utoipa::path(
+    put,
+    path = "/channels/{channel_id}",
+    tag = "channels",
+    params(("channel_id" = i64, Path)),
+    request_body = UpdateChannelRequest,
+    responses(
+        (status = 200, body = ApiResponse<Channel>),
+        (status = 400, description = "Direct message channels have no name", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel or lack the manage channels permission", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn update_channel(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<UpdateChannelRequest>,
+) -> AppResult<impl IntoResponse> {
+    let channel = state.channel_access.authorize(channel_id, auth.user_id).await?;
+
+    if channel.server_id.is_none() {
+        return Err(AppError::bad_request("Direct message channels cannot be renamed"));
+    }
+
+    let permissions = state.channel_access.permissions(&channel, auth.user_id).await?;
+    if !permissions.contains(Permissions::MANAGE_CHANNELS) {
+        return Err(AppError::missing_permissions("You need the manage channels permission"));
+    }
+
+    let channel = state.channels.rename(channel_id, payload.name, auth.user_id).await?;
+
+    Ok(ApiResponse::ok(channel))
+}
+
+/// Deletes the channel and its messages. Whoever could see it is told.
+#[utoipa::path(
+    delete,
+    path = "/channels/{channel_id}",
+    tag = "channels",
+    params(("channel_id" = i64, Path)),
+    responses(
+        (status = 204, description = "The channel is gone"),
+        (status = 400, description = "Direct message channels cannot be deleted", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel or lack the manage channels permission", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn delete_channel(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    let channel = state.channel_access.authorize(channel_id, auth.user_id).await?;
+
+    if channel.server_id.is_none() {
+        return Err(AppError::bad_request("Direct message channels cannot be deleted"));
+    }
+
+    let permissions = state.channel_access.permissions(&channel, auth.user_id).await?;
+    if !permissions.contains(Permissions::MANAGE_CHANNELS) {
+        return Err(AppError::missing_permissions("You need the manage channels permission"));
+    }
+
+    if !state.channels.delete(channel_id).await? {
+        return Err(ChannelAccessError::NotFound.into());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/channels/{channel_id}/typing",
+    tag = "channels",
+    params(("channel_id" = i64, Path)),
+    responses(
+        (status = 204, description = "Other viewers are told you are typing"),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn trigger_typing(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    state.typing.start(channel_id, auth.user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/channels/{channel_id}/slowmode",
+    tag = "channels",
+    params(("channel_id" = i64, Path)),
+    request_body = UpdateSlowmodeRequest,
+    responses(
+        (status = 200, body = ApiResponse<Channel>),
+        (status = 400, description = "Direct message channels have no slowmode", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel or lack the manage channels permission", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn update_channel_slowmode(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<UpdateSlowmodeRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Updating channel slowmode...");
+
+    let channel = state.channel_access.authorize(channel_id, auth.user_id).await?;
+
+    if channel.server_id.is_none() {
+        return Err(AppError::bad_request("Direct message channels do not support slowmode"));
+    }
+
+    let permissions = state.channel_access.permissions(&channel, auth.user_id).await?;
+    if !permissions.contains(Permissions::MANAGE_CHANNELS) {
+        return Err(AppError::missing_permissions("You need the manage channels permission"));
+    }
+
+    let channel = state
+        .channels
+        .set_rate_limit(channel_id, payload.rate_limit_per_user)
+        .await?
+        .ok_or(ChannelAccessError::NotFound)?;
+
+    Ok(ApiResponse::ok(channel))
+}
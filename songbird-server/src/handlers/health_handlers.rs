@@ -0,0 +1,91 @@
+// src/handlers/health_handlers.rs
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::handlers::ApiResponse;
+use crate::openapi::ErrorResponse;
+use crate::router::AppState;
+use axum::{
+    extract::State,
+    http::header::CONTENT_TYPE,
+    response::IntoResponse,
+};
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Probes usually give up after a few seconds; answer before they do.
+const READINESS_TIMEOUT: Duration = Duration::from_secs(3);
+
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// Highest migration version applied to the database.
+    pub schema_version: i64,
+}
+
+/// The process is up and serving requests. Says nothing about its
+/// dependencies, so an orchestrator only restarts it when it is wedged.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "meta",
+    responses((status = 200, body = ApiResponse<String>))
+)]
+pub async fn healthz() -> impl IntoResponse {
+    ApiResponse::ok("ok")
+}
+
+/// Whether this instance should get traffic: the database answers, every
+/// migration this build knows about has been applied, and it is not
+/// shutting down.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "meta",
+    responses(
+        (status = 200, body = ApiResponse<ReadinessResponse>),
+        (status = 503, description = "Shutting down, the database is unreachable or migrations are pending", body = ErrorResponse),
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> AppResult<impl IntoResponse> {
+    if state.shutdown.is_triggered() {
+        return Err(AppError::new(ErrorCode::ServiceUnavailable, "Shutting down"));
+    }
+
+    let status = match tokio::time::timeout(READINESS_TIMEOUT, state.health_repository.schema_status()).await {
+        Ok(Ok(status)) => status,
+        Ok(Err(e)) => {
+            tracing::warn!("Readiness check failed: {}", e);
+            return Err(AppError::new(ErrorCode::ServiceUnavailable, "Database unavailable"));
+        }
+        Err(_) => {
+            tracing::warn!("Readiness check timed out");
+            return Err(AppError::new(ErrorCode::ServiceUnavailable, "Database unavailable"));
+        }
+    };
+
+    if status.pending > 0 {
+        return Err(AppError::new(
+            ErrorCode::ServiceUnavailable,
+            format!("{} migrations have not been applied", status.pending),
+        ));
+    }
+
+    Ok(ApiResponse::ok(ReadinessResponse {
+        schema_version: status.version,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "meta",
+    responses((status = 200, description = "Prometheus metrics", content_type = "application/openmetrics-text", body = String))
+)]
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    if let Some(pool) = state.health_repository.pool_status() {
+        state.metrics.observe_pool(pool);
+    }
+
+    ([(CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)], state.metrics.render())
+}
@@ -0,0 +1,325 @@
+// src/handlers/message_handlers.rs
+use crate::auth::AuthUser;
+use crate::gateway::events::{ChannelPinsUpdateEvent, DispatchEvent};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::handlers::ApiResponse;
+use crate::models::models::{Channel, MessageType, NewMessage, Permissions};
+use crate::models::response_types::MessageWithAuthorResponse;
+use crate::openapi::ErrorResponse;
+use crate::rate_limit::RateLimitedResponse;
+use crate::repositories::PinOutcome;
+use crate::router::AppState;
+use crate::validation::{validate_not_blank, ValidatedJson, ValidationErrorResponse};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+const DEFAULT_MESSAGE_LIMIT: i64 = 50;
+const MAX_MESSAGE_LIMIT: i64 = 100;
+pub const MAX_PINS_PER_CHANNEL: i64 = 50;
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct CreateMessageRequest {
+    #[validate(length(max = 4000, message = "Message content must be at most 4000 characters"), custom(function = "validate_not_blank"))]
+    pub content: String,
+    /// Set to reply to another message in the same channel.
+    #[serde(default, with = "crate::snowflake::option_string")]
+    #[schema(value_type = Option<String>)]
+    pub referenced_message_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MessageListQuery {
+    /// How many of the newest messages to return, 1 to 100. Defaults to 50.
+    pub limit: Option<i64>,
+    /// Only return messages older than this message ID. Pass the last ID of
+    /// a page to get the next one.
+    pub before: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MessageSearchQuery {
+    /// Words that must all appear in the message; case and punctuation are
+    /// ignored.
+    pub q: String,
+    /// How many of the newest matches to return, 1 to 100. Defaults to 50.
+    pub limit: Option<i64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/channels/{channel_id}/messages",
+    tag = "messages",
+    params(("channel_id" = i64, Path)),
+    request_body = CreateMessageRequest,
+    responses(
+        (status = 201, body = ApiResponse<MessageWithAuthorResponse>),
+        (status = 400, description = "The referenced message is not in this channel", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+        (status = 429, description = "Slowmode is on and you posted too recently", body = ApiResponse<RateLimitedResponse>),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn create_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<CreateMessageRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Creating message...");
+
+    let channel = state.channel_access.authorize(channel_id, auth.user_id).await?;
+
+    state.slowmode.check(&channel, auth.user_id).await?;
+
+    let message_type = match payload.referenced_message_id {
+        Some(referenced_message_id) => match state.message_repository.find_by_id(referenced_message_id).await? {
+            Some(referenced) if referenced.channel_id == channel_id => MessageType::Reply,
+            _ => {
+                return Err(AppError::new(
+                    ErrorCode::InvalidReference,
+                    "Referenced message not found in this channel",
+                ))
+            }
+        },
+        None => MessageType::Default,
+    };
+
+    let new_message = NewMessage {
+        channel_id,
+        author_user_id: auth.user_id,
+        content: payload.content,
+        message_type,
+        referenced_message_id: payload.referenced_message_id,
+    };
+
+    let message = state.messages.post(&channel, new_message).await?;
+
+    Ok((StatusCode::CREATED, ApiResponse::ok(message)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channels/{channel_id}/messages",
+    tag = "messages",
+    params(("channel_id" = i64, Path), MessageListQuery),
+    responses(
+        (status = 200, body = ApiResponse<Vec<MessageWithAuthorResponse>>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn get_channel_messages(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+    Query(query): Query<MessageListQuery>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Getting channel messages...");
+
+    state.channel_access.authorize(channel_id, auth.user_id).await?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_MESSAGE_LIMIT)
+        .clamp(1, MAX_MESSAGE_LIMIT);
+
+    let messages = state
+        .message_repository
+        .find_by_channel_with_authors(channel_id, query.before, limit)
+        .await?;
+
+    Ok(ApiResponse::ok(messages))
+}
+
+/// Whole words only: `seed` does not find `seeds`.
+#[utoipa::path(
+    get,
+    path = "/channels/{channel_id}/messages/search",
+    tag = "messages",
+    params(("channel_id" = i64, Path), MessageSearchQuery),
+    responses(
+        (status = 200, body = ApiResponse<Vec<MessageWithAuthorResponse>>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn search_channel_messages(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+    Query(query): Query<MessageSearchQuery>,
+) -> AppResult<impl IntoResponse> {
+    state.channel_access.authorize(channel_id, auth.user_id).await?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_MESSAGE_LIMIT)
+        .clamp(1, MAX_MESSAGE_LIMIT);
+
+    let messages = state
+        .message_repository
+        .search_with_authors(channel_id, &query.q, limit)
+        .await?;
+
+    Ok(ApiResponse::ok(messages))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channels/{channel_id}/pins",
+    tag = "messages",
+    params(("channel_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<Vec<MessageWithAuthorResponse>>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel", body = ErrorResponse),
+        (status = 404, description = "No such channel", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn get_pinned_messages(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(channel_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Getting pinned messages...");
+
+    state.channel_access.authorize(channel_id, auth.user_id).await?;
+
+    let messages = state.message_repository.find_pinned_with_authors(channel_id).await?;
+
+    Ok(ApiResponse::ok(messages))
+}
+
+#[utoipa::path(
+    put,
+    path = "/channels/{channel_id}/pins/{message_id}",
+    tag = "messages",
+    params(("channel_id" = i64, Path), ("message_id" = i64, Path)),
+    responses(
+        (status = 200, description = "Pinned, or already was", body = ApiResponse<String>),
+        (status = 400, description = "The channel already has the maximum number of pins", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel or lack the manage messages permission", body = ErrorResponse),
+        (status = 404, description = "No such channel, or no such message in it", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn pin_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((channel_id, message_id)): Path<(i64, i64)>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Pinning message...");
+
+    let channel = authorize_manage_messages(&state, channel_id, auth.user_id).await?;
+
+    match state.message_repository.find_by_id(message_id).await? {
+        Some(message) if message.channel_id == channel_id => {}
+        _ => return Err(AppError::new(ErrorCode::UnknownMessage, "Message not found")),
+    }
+
+    match state
+        .message_repository
+        .pin(channel_id, message_id, auth.user_id, MAX_PINS_PER_CHANNEL)
+        .await?
+    {
+        PinOutcome::Pinned => {}
+        PinOutcome::AlreadyPinned => return Ok(ApiResponse::ok("Message already pinned".to_string())),
+        PinOutcome::LimitReached => {
+            return Err(AppError::new(
+                ErrorCode::PinLimitReached,
+                format!("A channel can have at most {} pinned messages", MAX_PINS_PER_CHANNEL),
+            ))
+        }
+    }
+
+    let system_message = NewMessage {
+        channel_id,
+        author_user_id: auth.user_id,
+        content: String::new(),
+        message_type: MessageType::Pin,
+        referenced_message_id: Some(message_id),
+    };
+    if let Err(e) = state.messages.post(&channel, system_message).await {
+        tracing::warn!("Failed to post pin notice in channel {}: {}", channel_id, e);
+    }
+
+    dispatch_pins_update(&state, &channel, message_id, true).await;
+    Ok(ApiResponse::ok("Message pinned".to_string()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/channels/{channel_id}/pins/{message_id}",
+    tag = "messages",
+    params(("channel_id" = i64, Path), ("message_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<String>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "You cannot see this channel or lack the manage messages permission", body = ErrorResponse),
+        (status = 404, description = "No such channel, or the message is not pinned", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((channel_id, message_id)): Path<(i64, i64)>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Unpinning message...");
+
+    let channel = authorize_manage_messages(&state, channel_id, auth.user_id).await?;
+
+    if !state.message_repository.unpin(channel_id, message_id).await? {
+        return Err(AppError::new(ErrorCode::UnknownMessage, "Message is not pinned"));
+    }
+
+    dispatch_pins_update(&state, &channel, message_id, false).await;
+    Ok(ApiResponse::ok("Message unpinned".to_string()))
+}
+
+async fn authorize_manage_messages(state: &AppState, channel_id: i64, user_id: i64) -> AppResult<Channel> {
+    let channel = state.channel_access.authorize(channel_id, user_id).await?;
+
+    let permissions = state.channel_access.permissions(&channel, user_id).await?;
+    if !permissions.contains(Permissions::MANAGE_MESSAGES) {
+        return Err(AppError::missing_permissions("You need the manage messages permission"));
+    }
+
+    Ok(channel)
+}
+
+async fn dispatch_pins_update(
+    state: &AppState,
+    channel: &Channel,
+    message_id: i64,
+    pinned: bool,
+) {
+    match state.channel_access.viewers(channel).await {
+        Ok(viewers) => {
+            let event = DispatchEvent::ChannelPinsUpdate(ChannelPinsUpdateEvent {
+                channel_id: channel.channel_id,
+                message_id,
+                pinned,
+            });
+            state.gateway.dispatch_to_users(viewers, &event);
+        }
+        Err(e) => tracing::warn!("Failed to fan out pins update: {}", e),
+    }
+}
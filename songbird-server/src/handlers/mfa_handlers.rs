@@ -0,0 +1,265 @@
+// src/handlers/mfa_handlers.rs
+use crate::auth::AuthUser;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::handlers::user_handlers::{account_disabled, user_not_found, LoginResponse};
+use crate::handlers::ApiResponse;
+use crate::mfa;
+use crate::openapi::ErrorResponse;
+use crate::rate_limit::{ClientIp, RateLimitedResponse};
+use crate::repositories::mfa_repository::UserTotp;
+use crate::router::AppState;
+use crate::validation::{ValidatedJson, ValidationErrorResponse};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct MfaCodeRequest {
+    /// A 6 digit TOTP code or one of the recovery codes.
+    #[validate(length(min = 1, max = 32, message = "Code must be between 1 and 32 characters"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct MfaLoginRequest {
+    #[validate(length(min = 1, max = 2048, message = "Ticket must be between 1 and 2048 characters"))]
+    pub ticket: String,
+    #[validate(length(min = 1, max = 32, message = "Code must be between 1 and 32 characters"))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/mfa/totp",
+    tag = "mfa",
+    params(("user_id" = i64, Path)),
+    responses(
+        (status = 200, description = "A secret to add to an authenticator app; confirm it with a code to finish", body = ApiResponse<TotpEnrollmentResponse>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Not your own account", body = ErrorResponse),
+        (status = 404, description = "No such user", body = ErrorResponse),
+        (status = 409, description = "2FA is already enabled", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn begin_totp_enrollment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Starting TOTP enrollment...");
+
+    if auth.user_id != user_id {
+        return Err(not_own_settings());
+    }
+
+    let user = state
+        .user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    let secret = mfa::generate_secret();
+    if !state.mfa_repository.begin_enrollment(user_id, &secret).await? {
+        return Err(already_enabled());
+    }
+
+    let otpauth_uri = mfa::otpauth_uri(&secret, &user.username);
+    Ok(ApiResponse::ok(TotpEnrollmentResponse { secret, otpauth_uri }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/mfa/totp/confirm",
+    tag = "mfa",
+    params(("user_id" = i64, Path)),
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "2FA is on; the recovery codes are only ever shown here", body = ApiResponse<RecoveryCodesResponse>),
+        (status = 401, description = "Not logged in, or a wrong code", body = ErrorResponse),
+        (status = 403, description = "Not your own account", body = ErrorResponse),
+        (status = 404, description = "No enrollment in progress", body = ErrorResponse),
+        (status = 409, description = "2FA is already enabled", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn confirm_totp_enrollment(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<MfaCodeRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Confirming TOTP enrollment...");
+
+    if auth.user_id != user_id {
+        return Err(not_own_settings());
+    }
+
+    let totp = match state.mfa_repository.find_totp(user_id).await? {
+        Some(totp) if totp.enabled => return Err(already_enabled()),
+        Some(totp) => totp,
+        None => return Err(AppError::new(ErrorCode::NotFound, "No enrollment in progress")),
+    };
+
+    let Some(step) = mfa::verify_code(&totp.secret, &payload.code, Utc::now().timestamp()) else {
+        return Err(AppError::new(ErrorCode::InvalidMfaCode, "Invalid two-factor code"));
+    };
+
+    let recovery_codes = mfa::generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes.iter().map(|code| mfa::hash_recovery_code(code)).collect();
+
+    if !state.mfa_repository.confirm_enrollment(user_id, step, &hashes).await? {
+        return Err(already_enabled());
+    }
+
+    Ok(ApiResponse::ok(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{user_id}/mfa/totp",
+    tag = "mfa",
+    params(("user_id" = i64, Path)),
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 204, description = "2FA is off"),
+        (status = 401, description = "Not logged in, or a wrong code", body = ErrorResponse),
+        (status = 403, description = "Not your own account", body = ErrorResponse),
+        (status = 404, description = "2FA is not enabled", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn disable_totp(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<MfaCodeRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Disabling TOTP...");
+
+    if auth.user_id != user_id {
+        return Err(not_own_settings());
+    }
+
+    let totp = match state.mfa_repository.find_totp(user_id).await? {
+        Some(totp) if totp.enabled => totp,
+        _ => return Err(AppError::new(ErrorCode::NotFound, "Two-factor authentication is not enabled")),
+    };
+
+    if !verify_second_factor(&state, &totp, &payload.code).await? {
+        return Err(AppError::new(ErrorCode::InvalidMfaCode, "Invalid two-factor code"));
+    }
+
+    state.mfa_repository.disable(user_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Second step of logging in to an account with 2FA: trades the ticket from
+/// `login_attempt` and a TOTP or recovery code for a session.
+#[utoipa::path(
+    post,
+    path = "/login/mfa",
+    tag = "auth",
+    request_body = MfaLoginRequest,
+    responses(
+        (status = 200, body = ApiResponse<LoginResponse>),
+        (status = 401, description = "Invalid ticket or code", body = ErrorResponse),
+        (status = 403, description = "The account is disabled", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+        (status = 429, description = "Too many failed attempts", body = ApiResponse<RateLimitedResponse>),
+    )
+)]
+pub async fn login_mfa(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    ValidatedJson(payload): ValidatedJson<MfaLoginRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("MFA login attempt");
+
+    let claims = state
+        .session_keys
+        .verify_mfa_ticket(&payload.ticket)
+        .map_err(|_| invalid_ticket())?;
+
+    let user = state
+        .user_repository
+        .find_by_id(claims.sub)
+        .await?
+        .ok_or_else(invalid_ticket)?;
+
+    // Code guesses count against the same backoff as password guesses.
+    if let Err(retry_after) = state.login_guard.check(&user.username, ip) {
+        return Err(AppError::rate_limited(retry_after, "Too many failed login attempts"));
+    }
+
+    let totp = match state.mfa_repository.find_totp(user.user_id).await? {
+        Some(totp) if totp.enabled => totp,
+        _ => return Err(invalid_ticket()),
+    };
+
+    if !verify_second_factor(&state, &totp, &payload.code).await? {
+        state.login_guard.record_failure(&user.username, ip);
+        return Err(AppError::new(ErrorCode::InvalidMfaCode, "Invalid two-factor code"));
+    }
+
+    state.login_guard.record_success(&user.username);
+
+    // Disabled between the password and the code
+    if user.disabled_at.is_some() {
+        return Err(account_disabled());
+    }
+
+    let token = state.session_keys.issue(user.user_id).map_err(AppError::internal)?;
+
+    Ok(ApiResponse::ok(LoginResponse {
+        token,
+        user: state.user_repository.to_response(user).await,
+    }))
+}
+
+/// Accepts a current TOTP code (once) or an unused recovery code.
+pub(crate) async fn verify_second_factor(state: &AppState, totp: &UserTotp, code: &str) -> Result<bool, sqlx::Error> {
+    if mfa::is_totp_code(code) {
+        match mfa::verify_code(&totp.secret, code, Utc::now().timestamp()) {
+            Some(step) => state.mfa_repository.consume_step(totp.user_id, step).await,
+            None => Ok(false),
+        }
+    } else {
+        state
+            .mfa_repository
+            .consume_recovery_code(totp.user_id, &mfa::hash_recovery_code(code))
+            .await
+    }
+}
+
+fn not_own_settings() -> AppError {
+    AppError::forbidden("Cannot change another user's 2FA settings")
+}
+
+fn already_enabled() -> AppError {
+    AppError::new(ErrorCode::MfaAlreadyEnabled, "Two-factor authentication is already enabled")
+}
+
+fn invalid_ticket() -> AppError {
+    AppError::new(ErrorCode::InvalidMfaTicket, "Invalid or expired login ticket")
+}
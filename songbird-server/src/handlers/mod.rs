@@ -1,2 +1,34 @@
+pub mod account_handlers;
+pub mod channel_handlers;
+pub mod health_handlers;
+pub mod message_handlers;
+pub mod mfa_handlers;
 pub mod user_handlers;
-pub mod server_handlers;
\ No newline at end of file
+pub mod server_handlers;
+
+use crate::error::ErrorCode;
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// The envelope every endpoint answers with. Failures carry a stable `code`
+/// next to the human readable `error`; see `crate::error::AppError`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+}
+
+impl<T> ApiResponse<T> {
+    pub fn ok(data: T) -> Json<Self> {
+        Json(ApiResponse {
+            success: true,
+            data: Some(data),
+            error: None,
+            code: None,
+        })
+    }
+}
@@ -1,318 +1,497 @@
 // src/handlers/server_handlers.rs
-use crate::models::models::{NewServer, Server, ServerWithMembersResponse};
+use crate::auth::AuthUser;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::gateway::events::{DispatchEvent, ServerDeleteEvent};
+use crate::handlers::ApiResponse;
+use crate::models::models::{NewServer, NewServerMember, Permissions, Server, ServerMember};
+use crate::openapi::ErrorResponse;
 use crate::router::AppState;
+use crate::validation::{validate_http_url, validate_not_blank, ValidatedJson, ValidationErrorResponse};
 use axum::{
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
-    Json,
 };
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreateServerRequest {
+    #[validate(length(min = 1, max = 100, message = "Server name must be between 1 and 100 characters"), custom(function = "validate_not_blank"))]
     pub name: String,
+    #[validate(length(max = 1000, message = "Description must be at most 1000 characters"))]
     pub description: String,
-    pub owner_user_id: i32,
+    #[validate(custom(function = "validate_http_url"))]
     pub icon_url: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct UpdateServerRequest {
+    #[validate(length(min = 1, max = 100, message = "Server name must be between 1 and 100 characters"), custom(function = "validate_not_blank"))]
     pub name: Option<String>,
+    #[validate(length(max = 1000, message = "Description must be at most 1000 characters"))]
     pub description: Option<String>,
-    pub owner_user_id: Option<i32>,
+    #[validate(custom(function = "validate_http_url"))]
     pub icon_url: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TransferServerRequest {
+    /// Must already be a member of the server.
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub owner_user_id: i64,
 }
 
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdateMemberPermissionsRequest {
+    /// A `Permissions` bitfield; unknown bits are dropped.
+    pub permissions: i64,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdateServerMfaRequest {
+    pub mfa_required: bool,
+}
+
+/// The caller becomes the owner.
+#[utoipa::path(
+    post,
+    path = "/servers",
+    tag = "servers",
+    request_body = CreateServerRequest,
+    responses(
+        (status = 201, body = ApiResponse<Server>),
+        (status = 401, body = ErrorResponse),
+        (status = 409, description = "Server name already taken", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    ),
+    security(("bearer" = []))
+)]
 pub async fn create_server(
     State(state): State<AppState>,
-    Json(payload): Json<CreateServerRequest>,
-) -> impl IntoResponse {
+    auth: AuthUser,
+    ValidatedJson(payload): ValidatedJson<CreateServerRequest>,
+) -> AppResult<impl IntoResponse> {
     let new_server = NewServer {
         server_name: payload.name,
-        owner_user_id: payload.owner_user_id,
+        owner_user_id: auth.user_id,
         icon_url: payload.icon_url,
     };
 
-    match state.server_repository.create(new_server).await {
-        Ok(server) => (
-            StatusCode::CREATED,
-            Json(ApiResponse {
-                success: true,
-                data: Some(server),
-                error: None,
-            }),
-        ),
-        Err(e) => {
-            let error_message = if e.to_string().contains("duplicate key") {
-                "Server name already exists"
-            } else {
-                "Failed to create server"
-            };
-
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<Server>,
-                    error: Some(error_message.to_string()),
-                }),
-            )
-        }
-    }
+    // A taken name comes back as SERVER_NAME_TAKEN
+    let server = state.server_repository.create(new_server).await?;
+
+    Ok((StatusCode::CREATED, ApiResponse::ok(server)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/servers/{server_id}",
+    tag = "servers",
+    params(("server_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<Server>),
+        (status = 404, description = "No such server", body = ErrorResponse),
+    )
+)]
 pub async fn get_server(
     State(state): State<AppState>,
-    Path(server_id): Path<i32>,
-) -> impl IntoResponse {
-    match state.server_repository.find_by_id(server_id).await {
-        Ok(Some(server)) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some(server),
-                error: None,
-            }),
-        ),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse {
-                success: false,
-                data: None::<Server>,
-                error: Some("Server not found".to_string()),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<Server>,
-                error: Some("Failed to fetch server".to_string()),
-            }),
-        ),
-    }
+    Path(server_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    let server = state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    Ok(ApiResponse::ok(server))
 }
 
 pub async fn get_server_with_members(
     State(state): State<AppState>,
-    Path(server_id): Path<i32>,
-) -> impl IntoResponse {
-    match state
+    Path(server_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    let server_with_members = state
         .server_repository
         .get_server_with_members(server_id)
-        .await
-    {
-        Ok(Some(server_with_members)) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some(server_with_members),
-                error: None,
-            }),
-        ),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse {
-                success: false,
-                data: None::<ServerWithMembersResponse>,
-                error: Some("Server not found".to_string()),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<ServerWithMembersResponse>,
-                error: Some("Failed to fetch server with members".to_string()),
-            }),
-        ),
-    }
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    Ok(ApiResponse::ok(server_with_members))
 }
 
+#[utoipa::path(
+    put,
+    path = "/servers/{server_id}",
+    tag = "servers",
+    params(("server_id" = i64, Path)),
+    request_body = UpdateServerRequest,
+    responses(
+        (status = 200, body = ApiResponse<Server>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Only the owner can change the server", body = ErrorResponse),
+        (status = 404, description = "No such server", body = ErrorResponse),
+        (status = 409, description = "Server name already taken", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    ),
+    security(("bearer" = []))
+)]
 pub async fn update_server(
     State(state): State<AppState>,
-    Path(server_id): Path<i32>,
-    Json(payload): Json<UpdateServerRequest>,
-) -> impl IntoResponse {
+    auth: AuthUser,
+    Path(server_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<UpdateServerRequest>,
+) -> AppResult<impl IntoResponse> {
     // First, get the current server
-    let current_server = match state.server_repository.find_by_id(server_id).await {
-        Ok(Some(server)) => server,
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<Server>,
-                    error: Some("Server not found".to_string()),
-                }),
-            )
-        }
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<Server>,
-                    error: Some("Failed to fetch server".to_string()),
-                }),
-            )
-        }
-    };
+    let mut updated_server = state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
 
-    // Update the server fields
-    let mut updated_server = current_server;
+    if updated_server.owner_user_id != auth.user_id {
+        return Err(AppError::forbidden("Only the server owner can change the server"));
+    }
 
+    // Update the server fields
     if let Some(name) = payload.name {
         updated_server.server_name = name;
     }
 
-    if let Some(owner_user_id) = payload.owner_user_id {
-        updated_server.owner_user_id = owner_user_id;
-    }
-
     if let Some(icon_url) = payload.icon_url {
         updated_server.icon_url = Some(icon_url);
     }
 
     // Save the updated server
-    match state
+    let server = state
         .server_repository
         .update(server_id, updated_server)
-        .await
-    {
-        Ok(server) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some(server),
-                error: None,
-            }),
-        ),
-        Err(e) => {
-            let error_message = if e.to_string().contains("duplicate key") {
-                "Server name already exists"
-            } else {
-                "Failed to update server"
-            };
-
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<Server>,
-                    error: Some(error_message.to_string()),
-                }),
-            )
-        }
-    }
+        .await?;
+    publish_to_members(&state, server_id, DispatchEvent::ServerUpdate(server.clone())).await;
+
+    Ok(ApiResponse::ok(server))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/servers/{server_id}",
+    tag = "servers",
+    params(("server_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<String>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Only the owner can delete the server", body = ErrorResponse),
+        (status = 404, description = "No such server", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
 pub async fn delete_server(
     State(state): State<AppState>,
-    Path(server_id): Path<i32>,
-) -> impl IntoResponse {
-    match state.server_repository.delete(server_id).await {
-        Ok(true) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some("Server deleted successfully".to_string()),
-                error: None,
-            }),
-        ),
-        Ok(false) => (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse {
-                success: false,
-                data: None::<String>,
-                error: Some("Server not found".to_string()),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<String>,
-                error: Some("Failed to delete server".to_string()),
-            }),
-        ),
+    auth: AuthUser,
+    Path(server_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    let server = state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    if server.owner_user_id != auth.user_id {
+        return Err(AppError::forbidden("Only the server owner can delete the server"));
     }
+
+    // The memberships go with the server
+    let members = member_ids(&state, server_id).await;
+    if !state.server_repository.delete(server_id).await? {
+        return Err(server_not_found());
+    }
+    state
+        .gateway
+        .publish(members, DispatchEvent::ServerDelete(ServerDeleteEvent { server_id }));
+
+    Ok(ApiResponse::ok("Server deleted successfully".to_string()))
 }
 
-pub async fn get_all_servers(State(state): State<AppState>) -> impl IntoResponse {
-    match state.server_repository.find_all().await {
-        Ok(servers) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some(servers),
-                error: None,
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<Vec<Server>>,
-                error: Some("Failed to fetch servers".to_string()),
-            }),
-        ),
+/// Hands the server to another member. The previous owner stays a member,
+/// with whatever permissions they had as one.
+#[utoipa::path(
+    put,
+    path = "/servers/{server_id}/owner",
+    tag = "servers",
+    params(("server_id" = i64, Path)),
+    request_body = TransferServerRequest,
+    responses(
+        (status = 200, body = ApiResponse<Server>),
+        (status = 400, description = "Already the owner", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Only the owner can transfer the server", body = ErrorResponse),
+        (status = 404, description = "No such server, or the new owner is not a member", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn transfer_server(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<TransferServerRequest>,
+) -> AppResult<impl IntoResponse> {
+    let mut server = state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    if server.owner_user_id != auth.user_id {
+        return Err(AppError::forbidden("Only the server owner can transfer the server"));
     }
+
+    if payload.owner_user_id == server.owner_user_id {
+        return Err(AppError::bad_request("Already the owner of this server"));
+    }
+
+    if !state
+        .server_member_repository
+        .is_member(server_id, payload.owner_user_id)
+        .await?
+    {
+        return Err(AppError::new(
+            ErrorCode::UnknownMember,
+            "The new owner must be a member of the server",
+        ));
+    }
+
+    server.owner_user_id = payload.owner_user_id;
+    let server = state.server_repository.update(server_id, server).await?;
+    publish_to_members(&state, server_id, DispatchEvent::ServerUpdate(server.clone())).await;
+
+    Ok(ApiResponse::ok(server))
 }
 
+#[utoipa::path(
+    get,
+    path = "/servers",
+    tag = "servers",
+    responses((status = 200, body = ApiResponse<Vec<Server>>))
+)]
+pub async fn get_all_servers(State(state): State<AppState>) -> AppResult<impl IntoResponse> {
+    let servers = state.server_repository.find_all().await?;
+
+    Ok(ApiResponse::ok(servers))
+}
+
+#[utoipa::path(
+    get,
+    path = "/servers/owner/{owner_user_id}",
+    tag = "servers",
+    params(("owner_user_id" = i64, Path)),
+    responses((status = 200, body = ApiResponse<Vec<Server>>))
+)]
 pub async fn get_servers_by_owner(
     State(state): State<AppState>,
-    Path(owner_user_id): Path<i32>,
-) -> impl IntoResponse {
-    match state.server_repository.find_by_owner(owner_user_id).await {
-        Ok(servers) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some(servers),
-                error: None,
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<Vec<Server>>,
-                error: Some("Failed to fetch servers for owner".to_string()),
-            }),
-        ),
-    }
+    Path(owner_user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    let servers = state.server_repository.find_by_owner(owner_user_id).await?;
+
+    Ok(ApiResponse::ok(servers))
 }
 
 pub async fn get_servers_for_user(
     State(state): State<AppState>,
-    Path(user_id): Path<i32>,
-) -> impl IntoResponse {
-    match state.server_repository.find_servers_for_user(user_id).await {
-        Ok(servers) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some(servers),
-                error: None,
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<Vec<Server>>,
-                error: Some("Failed to fetch servers for user".to_string()),
-            }),
-        ),
+    Path(user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    let servers = state.server_repository.find_servers_for_user(user_id).await?;
+
+    Ok(ApiResponse::ok(servers))
+}
+
+/// The caller joins the server. Members who can see its system channel get
+/// a `member_join` message.
+#[utoipa::path(
+    post,
+    path = "/servers/{server_id}/members",
+    tag = "servers",
+    params(("server_id" = i64, Path)),
+    responses(
+        (status = 201, body = ApiResponse<ServerMember>),
+        (status = 401, body = ErrorResponse),
+        (status = 404, description = "No such server", body = ErrorResponse),
+        (status = 409, description = "Already a member", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn add_server_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    // Joining twice comes back as CONFLICT
+    let member = state
+        .members
+        .join(NewServerMember {
+            server_id,
+            user_id: auth.user_id,
+            nickname: None,
+        })
+        .await?;
+
+    Ok((StatusCode::CREATED, ApiResponse::ok(member)))
+}
+
+/// Members can leave, and the owner can remove anyone but themselves.
+/// Members who can see the system channel get a `member_leave` message.
+#[utoipa::path(
+    delete,
+    path = "/servers/{server_id}/members/{user_id}",
+    tag = "servers",
+    params(("server_id" = i64, Path), ("user_id" = i64, Path)),
+    responses(
+        (status = 204, description = "The member is gone"),
+        (status = 400, description = "The owner cannot leave without transferring the server", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Only the owner can remove other members", body = ErrorResponse),
+        (status = 404, description = "No such server or member", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn remove_server_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((server_id, user_id)): Path<(i64, i64)>,
+) -> AppResult<impl IntoResponse> {
+    let server = state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    if user_id != auth.user_id && server.owner_user_id != auth.user_id {
+        return Err(AppError::forbidden("Only the server owner can remove other members"));
+    }
+
+    if user_id == server.owner_user_id {
+        return Err(AppError::bad_request("Transfer the server before the owner leaves it"));
+    }
+
+    if !state.members.leave(server_id, user_id).await? {
+        return Err(AppError::new(ErrorCode::UnknownMember, "Member not found"));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    put,
+    path = "/servers/{server_id}/members/{user_id}/permissions",
+    tag = "servers",
+    params(("server_id" = i64, Path), ("user_id" = i64, Path)),
+    request_body = UpdateMemberPermissionsRequest,
+    responses(
+        (status = 200, body = ApiResponse<ServerMember>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Only the owner can change permissions", body = ErrorResponse),
+        (status = 404, description = "No such server or member", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn update_member_permissions(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((server_id, user_id)): Path<(i64, i64)>,
+    ValidatedJson(payload): ValidatedJson<UpdateMemberPermissionsRequest>,
+) -> AppResult<impl IntoResponse> {
+    let server = state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    if server.owner_user_id != auth.user_id {
+        return Err(AppError::forbidden("Only the server owner can change permissions"));
+    }
+
+    let permissions = Permissions::from_bits_truncate(payload.permissions);
+
+    let member = state
+        .server_member_repository
+        .update_permissions(server_id, user_id, permissions.bits())
+        .await?
+        .ok_or_else(|| AppError::new(ErrorCode::UnknownMember, "Member not found"))?;
+
+    Ok(ApiResponse::ok(member))
+}
+
+#[utoipa::path(
+    put,
+    path = "/servers/{server_id}/mfa",
+    tag = "servers",
+    params(("server_id" = i64, Path)),
+    request_body = UpdateServerMfaRequest,
+    responses(
+        (status = 200, body = ApiResponse<Server>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Not the owner, or the owner has no 2FA themselves", body = ErrorResponse),
+        (status = 404, description = "No such server", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn update_server_mfa(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(server_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<UpdateServerMfaRequest>,
+) -> AppResult<impl IntoResponse> {
+    let mut server = state
+        .server_repository
+        .find_by_id(server_id)
+        .await?
+        .ok_or_else(server_not_found)?;
+
+    if server.owner_user_id != auth.user_id {
+        return Err(AppError::forbidden(
+            "Only the server owner can change the 2FA requirement",
+        ));
+    }
+
+    // Otherwise the owner would lock themselves out of moderating
+    if payload.mfa_required && !state.mfa_repository.is_enabled(auth.user_id).await? {
+        return Err(AppError::new(
+            ErrorCode::MfaSetupRequired,
+            "Enable two-factor authentication on your own account first",
+        ));
     }
+
+    server.mfa_required = payload.mfa_required;
+    let server = state.server_repository.update(server_id, server).await?;
+    publish_to_members(&state, server_id, DispatchEvent::ServerUpdate(server.clone())).await;
+
+    Ok(ApiResponse::ok(server))
+}
+
+async fn publish_to_members(state: &AppState, server_id: i64, event: DispatchEvent) {
+    let members = member_ids(state, server_id).await;
+    state.gateway.publish(members, event);
+}
+
+/// The change has happened either way, so a failed lookup only costs the
+/// members their event.
+async fn member_ids(state: &AppState, server_id: i64) -> Vec<i64> {
+    match state.server_member_repository.find_by_server(server_id).await {
+        Ok(members) => members.into_iter().map(|member| member.user_id).collect(),
+        Err(e) => {
+            tracing::warn!("Failed to load the members of server {}: {}", server_id, e);
+            Vec::new()
+        }
+    }
+}
+
+fn server_not_found() -> AppError {
+    AppError::new(ErrorCode::UnknownServer, "Server not found")
 }
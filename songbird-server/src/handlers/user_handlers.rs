@@ -1,14 +1,21 @@
 // src/handlers/user_handlers.rs
+use crate::auth::{hash_password, AuthUser};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::gateway::events::DispatchEvent;
+use crate::handlers::mfa_handlers::verify_second_factor;
+use crate::handlers::ApiResponse;
 use crate::models::{
+    presence::{CustomStatus, PresenceResponse, PresenceStatus},
     response_types::UserResponse,
-    user::NewUser,
+    user::{NewUser, User},
 };
-use crate::repositories::user_repository;
+use crate::rate_limit::{ClientIp, RateLimitedResponse};
+use crate::openapi::ErrorResponse;
 use crate::router::AppState;
-use argon2::{PasswordHash, PasswordVerifier};
+use crate::validation::{validate_http_url, validate_password, validate_username, ValidatedJson, ValidationErrorResponse};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
-    Argon2,
+    Argon2, PasswordHash, PasswordVerifier,
 };
 use axum::{
     extract::{Path, State},
@@ -16,307 +23,346 @@ use axum::{
     response::IntoResponse,
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Serialize, Deserialize)]
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct CreateUserRequest {
+    #[validate(custom(function = "validate_username"))]
     pub username: String,
+    #[validate(email(message = "Must be a valid email address"), length(max = 255, message = "Email must be at most 255 characters"))]
     pub email: String,
+    #[validate(custom(function = "validate_password"))]
     pub password: String,
+    #[validate(custom(function = "validate_http_url"))]
     pub avatar_url: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UpdateUserRequest {
+    #[validate(custom(function = "validate_username"))]
     pub username: Option<String>,
+    #[validate(email(message = "Must be a valid email address"), length(max = 255, message = "Email must be at most 255 characters"))]
     pub email: Option<String>,
+    #[validate(custom(function = "validate_password"))]
     pub password: Option<String>,
+    #[validate(custom(function = "validate_http_url"))]
     pub avatar_url: Option<String>,
+    /// One of `online`, `idle`, `dnd` or `invisible`.
     pub status: Option<String>,
+    /// Needed to change the password or the email.
+    #[validate(length(min = 1, max = 128, message = "Password must be between 1 and 128 characters"))]
+    pub current_password: Option<String>,
+    /// A TOTP or recovery code, needed along with `current_password` when
+    /// the account has 2FA.
+    #[validate(length(min = 1, max = 32, message = "Code must be between 1 and 32 characters"))]
+    pub mfa_code: Option<String>,
+}
+
+/// The updated user. Changing the password ends every session, so the
+/// response then carries a new one for the caller.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UpdateUserResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Only bounded, not checked against the signup rules, so accounts made
+/// before those rules can still log in.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct UserLoginRequest {
+    #[validate(length(min = 1, max = 255, message = "Username must be between 1 and 255 characters"))]
     pub username: String,
+    #[validate(length(min = 1, max = 128, message = "Password must be between 1 and 128 characters"))]
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub data: Option<T>,
-    pub error: Option<String>,
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct CustomStatusRequest {
+    #[validate(length(max = 128, message = "Status text must be at most 128 characters"))]
+    pub text: Option<String>,
+    #[validate(length(max = 64, message = "Emoji must be at most 64 characters"))]
+    pub emoji: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LoginResponse {
+    pub token: String,
+    pub user: UserResponse,
+}
+
+/// Returned by `login_attempt` instead of a session when the account has
+/// 2FA; the ticket goes to `/api/v1/login/mfa` along with a code.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MfaRequiredResponse {
+    pub mfa_required: bool,
+    pub ticket: String,
 }
 
+/// A session, or a ticket to finish logging in with when the account has
+/// 2FA. Untagged, so clients tell the two apart by `mfa_required`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Session(LoginResponse),
+    MfaRequired(MfaRequiredResponse),
+}
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "auth",
+    request_body = UserLoginRequest,
+    responses(
+        (status = 200, description = "A session, or a ticket for `/api/v1/login/mfa` if the account has 2FA", body = ApiResponse<LoginOutcome>),
+        (status = 401, description = "Wrong username or password", body = ErrorResponse),
+        (status = 403, description = "The account is disabled", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+        (status = 429, description = "Too many failed attempts", body = ApiResponse<RateLimitedResponse>),
+    )
+)]
 pub async fn login_attempt(
     State(state): State<AppState>,
-    Json(payload): Json<UserLoginRequest>,
-) -> impl IntoResponse {
+    ClientIp(ip): ClientIp,
+    ValidatedJson(payload): ValidatedJson<UserLoginRequest>,
+) -> AppResult<Json<ApiResponse<LoginOutcome>>> {
     tracing::info!("Login attempt for user {}", &payload.username);
-    let argon2 = Argon2::default();
-    let (user_response, password_hash) = match state.user_repository.find_by_username(&payload.username).await {
-        Ok(Some(user)) => {
-            let user_response = UserResponse {
-                user_id: user.user_id,
-                username: user.username,
-                email: user.email,
-                avatar_url: user.avatar_url,
-                status: user.status,
-                created_at: user.created_at,
-            };
-            (user_response, user.password_hash)
-        }
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<UserResponse>,
-                    error: Some("User not found".to_string()),
-                }),
-            )
-        }
-        Err(_) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<UserResponse>,
-                    error: Some("User not found".to_string()),
-                }),
-            )
+
+    if let Err(retry_after) = state.login_guard.check(&payload.username, ip) {
+        tracing::info!("Login for user {} is backing off", &payload.username);
+        return Err(AppError::rate_limited(retry_after, "Too many failed login attempts"));
+    }
+
+    let user = state.user_repository.find_by_username(&payload.username).await?;
+
+    // Unknown users are checked against a dummy hash so they take as long as
+    // a wrong password does.
+    let password_hash = user
+        .as_ref()
+        .map_or(dummy_password_hash(), |user| user.password_hash.as_str());
+
+    let parsed_hash = PasswordHash::new(password_hash).map_err(AppError::internal)?;
+
+    let verified = Argon2::default()
+        .verify_password(payload.password.as_bytes(), &parsed_hash)
+        .is_ok();
+
+    let user = match user {
+        Some(user) if verified => user,
+        _ => {
+            state.login_guard.record_failure(&payload.username, ip);
+            return Err(AppError::new(
+                ErrorCode::InvalidCredentials,
+                "Invalid username or password",
+            ));
         }
     };
 
-    let parsed_hash = match PasswordHash::new(password_hash.as_str()) {
-        Ok(hash) => hash,
-        Err(_) => return ( // Invalid hash format
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<UserResponse>,
-                error: Some("Error parsing passowrd hash.".to_string()),
-            }),
-        ), 
-    };
-    
-    match argon2.verify_password(payload.password.as_bytes(), &parsed_hash) {
-        Ok(()) => {
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    success: true,
-                    data: Some(user_response),
-                    error: None,
-                }),
-            )
-        }
-        Err(_) => {
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    success: false,
-                    data: None,
-                    error: Some("Username or password incorrect.".to_string()),
-                }),
-            )
-        }
+    // Only after the password checks out, so this does not tell anyone else
+    // that the account exists.
+    if user.disabled_at.is_some() {
+        return Err(account_disabled());
+    }
+
+    if state.mfa_repository.is_enabled(user.user_id).await? {
+        return mfa_challenge(&state, user.user_id);
     }
+
+    state.login_guard.record_success(&payload.username);
+
+    let token = state.session_keys.issue(user.user_id).map_err(AppError::internal)?;
+
+    Ok(ApiResponse::ok(LoginOutcome::Session(LoginResponse {
+        token,
+        user: state.user_repository.to_response(user).await,
+    })))
 }
 
+/// The failure counter is left alone until the second factor is also
+/// right, so a known password does not reset the backoff on code guesses.
+fn mfa_challenge(state: &AppState, user_id: i64) -> AppResult<Json<ApiResponse<LoginOutcome>>> {
+    let ticket = state
+        .session_keys
+        .issue_mfa_ticket(user_id)
+        .map_err(AppError::internal)?;
+
+    Ok(ApiResponse::ok(LoginOutcome::MfaRequired(MfaRequiredResponse {
+        mfa_required: true,
+        ticket,
+    })))
+}
+
+fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(b"songbird-dummy-password", &salt)
+            .expect("hashing a constant password cannot fail")
+            .to_string()
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/create",
+    tag = "users",
+    request_body = CreateUserRequest,
+    responses(
+        (status = 201, description = "The new account; a verification email is sent", body = ApiResponse<UserResponse>),
+        (status = 409, description = "Username or email already taken", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    )
+)]
 pub async fn create_user(
     State(state): State<AppState>,
-    Json(payload): Json<CreateUserRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateUserRequest>,
+) -> AppResult<impl IntoResponse> {
     tracing::info!("Creating user...");
-    // Hash the password
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = match argon2.hash_password(payload.password.as_bytes(), &salt) {
-        Ok(hash) => hash.to_string(),
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<UserResponse>,
-                    error: Some("Failed to hash password".to_string()),
-                }),
-            )
-        }
-    };
 
     let new_user = NewUser {
         username: payload.username,
         email: payload.email,
-        password_hash,
+        password_hash: hash_password(&payload.password).map_err(AppError::internal)?,
         avatar_url: payload.avatar_url,
-        status: "online".to_string(),
+        // Users show up as online once they connect to the gateway
+        status: PresenceStatus::Offline.to_string(),
     };
 
-    match state.user_repository.create(new_user).await {
-        Ok(user) => {
-            let user_response = UserResponse {
-                user_id: user.user_id,
-                username: user.username,
-                email: user.email,
-                avatar_url: user.avatar_url,
-                status: user.status,
-                created_at: user.created_at,
-            };
-
-            (
-                StatusCode::CREATED,
-                Json(ApiResponse {
-                    success: true,
-                    data: Some(user_response),
-                    error: None,
-                }),
-            )
-        }
-        Err(e) => {
-            let error_message = if e.to_string().contains("duplicate key") {
-                if e.to_string().contains("username") {
-                    "Username already taken"
-                } else if e.to_string().contains("email") {
-                    "Email already registered"
-                } else {
-                    "Duplicate key violation"
-                }
-            } else {
-                "Failed to create user"
-            };
-
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<UserResponse>,
-                    error: Some(error_message.to_string()),
-                }),
-            )
-        }
-    }
+    // Taken usernames and emails come back as USERNAME_TAKEN / EMAIL_TAKEN
+    let user = state.user_repository.create(new_user).await?;
+    state.email.spawn_verification(user.clone());
+
+    Ok((
+        StatusCode::CREATED,
+        ApiResponse::ok(state.user_repository.to_response(user).await),
+    ))
 }
+
+#[utoipa::path(
+    get,
+    path = "/users/by_username/{username}",
+    tag = "users",
+    params(("username" = String, Path)),
+    responses(
+        (status = 200, body = ApiResponse<UserResponse>),
+        (status = 404, description = "No such user", body = ErrorResponse),
+    )
+)]
 pub async fn get_user_by_username(
     State(state): State<AppState>,
     Path(username): Path<String>,
-) -> impl IntoResponse {
+) -> AppResult<impl IntoResponse> {
     tracing::info!("Getting user by username...");
-    match state.user_repository.find_by_username(username.as_str()).await {
-        Ok(Some(user)) => {
-            let user_response = UserResponse {
-                user_id: user.user_id,
-                username: user.username,
-                email: user.email,
-                avatar_url: user.avatar_url,
-                status: user.status,
-                created_at: user.created_at,
-            };
-
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    success: true,
-                    data: Some(user_response),
-                    error: None,
-                }),
-            )
-        }
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse {
-                success: false,
-                data: None::<UserResponse>,
-                error: Some("User not found".to_string()),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<UserResponse>,
-                error: Some("Failed to fetch user".to_string()),
-            }),
-        ),
-    } 
+
+    let user = state
+        .user_repository
+        .find_by_username(username.as_str())
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    Ok(ApiResponse::ok(state.user_repository.to_response(user).await))
 }
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}",
+    tag = "users",
+    params(("user_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<UserResponse>),
+        (status = 404, description = "No such user", body = ErrorResponse),
+    )
+)]
 pub async fn get_user(
     State(state): State<AppState>,
-    Path(user_id): Path<i32>,
-) -> impl IntoResponse {
+    Path(user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
     tracing::info!("Getting user...");
-    match state.user_repository.find_by_id(user_id).await {
-        Ok(Some(user)) => {
-            let user_response = UserResponse {
-                user_id: user.user_id,
-                username: user.username,
-                email: user.email,
-                avatar_url: user.avatar_url,
-                status: user.status,
-                created_at: user.created_at,
-            };
-
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    success: true,
-                    data: Some(user_response),
-                    error: None,
-                }),
-            )
-        }
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse {
-                success: false,
-                data: None::<UserResponse>,
-                error: Some("User not found".to_string()),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<UserResponse>,
-                error: Some("Failed to fetch user".to_string()),
-            }),
-        ),
-    }
+
+    let user = state
+        .user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    Ok(ApiResponse::ok(state.user_repository.to_response(user).await))
 }
 
+#[utoipa::path(
+    put,
+    path = "/users/{user_id}",
+    tag = "users",
+    params(("user_id" = i64, Path)),
+    request_body = UpdateUserRequest,
+    responses(
+        (status = 200, description = "The updated user, and a new session if the password changed; changing the email sends a new verification email", body = ApiResponse<UpdateUserResponse>),
+        (status = 400, description = "Unknown status, or no current password for a password or email change", body = ErrorResponse),
+        (status = 401, description = "Not logged in, or a wrong current password or two-factor code", body = ErrorResponse),
+        (status = 403, description = "Not your own account", body = ErrorResponse),
+        (status = 404, description = "No such user", body = ErrorResponse),
+        (status = 409, description = "Username or email already taken", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+        (status = 429, description = "Too many failed attempts", body = ApiResponse<RateLimitedResponse>),
+    ),
+    security(("bearer" = []))
+)]
 pub async fn update_user(
     State(state): State<AppState>,
-    Path(user_id): Path<i32>,
-    Json(payload): Json<UpdateUserRequest>,
-) -> impl IntoResponse {
+    auth: AuthUser,
+    ClientIp(ip): ClientIp,
+    Path(user_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<UpdateUserRequest>,
+) -> AppResult<impl IntoResponse> {
     tracing::info!("Updating user...");
 
-    // First, get the current user
-    let current_user = match state.user_repository.find_by_id(user_id).await {
-        Ok(Some(user)) => user,
-        Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<UserResponse>,
-                    error: Some("User not found".to_string()),
-                }),
-            )
-        }
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<UserResponse>,
-                    error: Some("Failed to fetch user".to_string()),
-                }),
-            )
-        }
+    if auth.user_id != user_id {
+        return Err(not_own_account());
+    }
+
+    let preferred_status = match payload.status.as_deref().map(str::parse::<PresenceStatus>) {
+        None => None,
+        // "offline" is not something a user can pick; the closest thing is invisible
+        Some(Ok(PresenceStatus::Offline)) => Some(PresenceStatus::Invisible),
+        Some(Ok(status)) => Some(status),
+        Some(Err(e)) => return Err(AppError::bad_request(e)),
     };
 
+    // First, get the current user
+    let current_user = state
+        .user_repository
+        .find_by_id(user_id)
+        .await?
+        .ok_or_else(user_not_found)?;
+
+    let email_changed = payload
+        .email
+        .as_ref()
+        .is_some_and(|email| *email != current_user.email);
+    let password_changed = payload.password.is_some();
+
+    // Either one hands over the account, so a session left open somewhere
+    // is not enough for them.
+    if email_changed || password_changed {
+        reauthenticate(
+            &state,
+            &current_user,
+            ip,
+            payload.current_password.as_deref(),
+            payload.mfa_code.as_deref(),
+        )
+        .await?;
+    }
+
     // Update the user fields
+    let previous_username = current_user.username.clone();
     let mut updated_user = current_user;
 
     if let Some(username) = payload.username {
@@ -328,144 +374,260 @@ pub async fn update_user(
     }
 
     if let Some(password) = payload.password {
-        // Hash the new password
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        updated_user.password_hash = match argon2.hash_password(password.as_bytes(), &salt) {
-            Ok(hash) => hash.to_string(),
-            Err(_) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ApiResponse {
-                        success: false,
-                        data: None::<UserResponse>,
-                        error: Some("Failed to hash password".to_string()),
-                    }),
-                )
-            }
-        };
+        updated_user.password_hash = hash_password(&password).map_err(AppError::internal)?;
     }
 
     if let Some(avatar_url) = payload.avatar_url {
         updated_user.avatar_url = Some(avatar_url);
     }
 
-    if let Some(status) = payload.status {
-        updated_user.status = status;
+    // Save the updated user
+    let mut user = state.user_repository.update(user_id, updated_user).await?;
+
+    // The repository clears `email_verified` when the address changes
+    if email_changed {
+        state.email.spawn_verification(user.clone());
     }
 
-    // Save the updated user
-    match state.user_repository.update(user_id, updated_user).await {
-        Ok(user) => {
-            let user_response = UserResponse {
-                user_id: user.user_id,
-                username: user.username,
-                email: user.email,
-                avatar_url: user.avatar_url,
-                status: user.status,
-                created_at: user.created_at,
-            };
-
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    success: true,
-                    data: Some(user_response),
-                    error: None,
-                }),
-            )
-        }
-        Err(e) => {
-            let error_message = if e.to_string().contains("duplicate key") {
-                if e.to_string().contains("username") {
-                    "Username already taken"
-                } else if e.to_string().contains("email") {
-                    "Email already registered"
-                } else {
-                    "Duplicate key violation"
-                }
-            } else {
-                "Failed to update user"
-            };
-
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse {
-                    success: false,
-                    data: None::<UserResponse>,
-                    error: Some(error_message.to_string()),
-                }),
-            )
+    // Like a password reset: every other session ends and any lockout on
+    // the account is lifted. The caller gets a session that outlives that.
+    let mut token = None;
+    if password_changed {
+        state.user_repository.revoke_sessions(user_id).await?;
+        state.login_guard.clear_account(&previous_username);
+
+        user = state
+            .user_repository
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(user_not_found)?;
+        let revoked_at = user.sessions_revoked_at.unwrap_or_else(Utc::now);
+        token = Some(
+            state
+                .session_keys
+                .issue_after(user_id, revoked_at)
+                .map_err(AppError::internal)?,
+        );
+    }
+
+    if let Some(status) = preferred_status {
+        state.presence.set_preferred_status(user_id, status).await?;
+        if let Ok(Some(presence)) = state.presence.get(user_id).await {
+            user.status = presence.status.to_string();
         }
     }
+
+    // Only the user's own sessions, since the response carries the email
+    let user = state.user_repository.to_response(user).await;
+    state.gateway.publish([user_id], DispatchEvent::UserUpdate(user.clone()));
+
+    Ok(ApiResponse::ok(UpdateUserResponse { user, token }))
 }
 
+/// Checks the password again, and the second factor when the account has
+/// one, before a change that could lock the owner out. Failures count
+/// against the same backoff as logging in.
+async fn reauthenticate(
+    state: &AppState,
+    user: &User,
+    ip: Option<IpAddr>,
+    password: Option<&str>,
+    mfa_code: Option<&str>,
+) -> AppResult<()> {
+    if let Err(retry_after) = state.login_guard.check(&user.username, ip) {
+        return Err(AppError::rate_limited(retry_after, "Too many failed attempts"));
+    }
+
+    let Some(password) = password else {
+        return Err(AppError::bad_request(
+            "current_password is needed to change the password or email",
+        ));
+    };
+
+    let parsed_hash = PasswordHash::new(&user.password_hash).map_err(AppError::internal)?;
+    if Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_err()
+    {
+        state.login_guard.record_failure(&user.username, ip);
+        return Err(AppError::new(ErrorCode::InvalidCredentials, "Invalid password"));
+    }
+
+    let Some(totp) = state
+        .mfa_repository
+        .find_totp(user.user_id)
+        .await?
+        .filter(|totp| totp.enabled)
+    else {
+        return Ok(());
+    };
+
+    let verified = match mfa_code {
+        Some(code) => verify_second_factor(state, &totp, code).await?,
+        None => false,
+    };
+    if !verified {
+        state.login_guard.record_failure(&user.username, ip);
+        return Err(AppError::new(ErrorCode::InvalidMfaCode, "Invalid two-factor code"));
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{user_id}",
+    tag = "users",
+    params(("user_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<String>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Not your own account", body = ErrorResponse),
+        (status = 404, description = "No such user", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
 pub async fn delete_user(
     State(state): State<AppState>,
-    Path(user_id): Path<i32>,
-) -> impl IntoResponse {
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
     tracing::info!("Deleting user...");
 
-    match state.user_repository.delete(user_id).await {
-        Ok(true) => (
-            StatusCode::OK,
-            Json(ApiResponse {
-                success: true,
-                data: Some("User deleted successfully".to_string()),
-                error: None,
-            }),
-        ),
-        Ok(false) => (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse {
-                success: false,
-                data: None::<String>,
-                error: Some("User not found".to_string()),
-            }),
-        ),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<String>,
-                error: Some("Failed to delete user".to_string()),
-            }),
-        ),
+    if auth.user_id != user_id {
+        return Err(not_own_account());
+    }
+
+    if !state.user_repository.delete(user_id).await? {
+        return Err(user_not_found());
     }
+
+    Ok(ApiResponse::ok("User deleted successfully".to_string()))
 }
 
-pub async fn get_all_users(State(state): State<AppState>) -> impl IntoResponse {
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    responses((status = 200, body = ApiResponse<Vec<UserResponse>>))
+)]
+pub async fn get_all_users(State(state): State<AppState>) -> AppResult<impl IntoResponse> {
     tracing::info!("Getting all users...");
-    match state.user_repository.find_all().await {
-        Ok(users) => {
-            let user_responses: Vec<UserResponse> = users
-                .into_iter()
-                .map(|user| UserResponse {
-                    user_id: user.user_id,
-                    username: user.username,
-                    email: user.email,
-                    avatar_url: user.avatar_url,
-                    status: user.status,
-                    created_at: user.created_at,
-                })
-                .collect();
-
-            (
-                StatusCode::OK,
-                Json(ApiResponse {
-                    success: true,
-                    data: Some(user_responses),
-                    error: None,
-                }),
-            )
-        }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse {
-                success: false,
-                data: None::<Vec<UserResponse>>,
-                error: Some("Failed to fetch users".to_string()),
-            }),
-        ),
+
+    let mut user_responses: Vec<UserResponse> = Vec::new();
+    for user in state.user_repository.find_all().await? {
+        user_responses.push(state.user_repository.to_response(user).await);
+    }
+
+    Ok(ApiResponse::ok(user_responses))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/presence",
+    tag = "users",
+    params(("user_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<PresenceResponse>),
+        (status = 404, description = "No such user", body = ErrorResponse),
+    )
+)]
+pub async fn get_user_presence(
+    State(state): State<AppState>,
+    Path(user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Getting user presence...");
+
+    let presence = state.presence.get(user_id).await?.ok_or_else(user_not_found)?;
+
+    Ok(ApiResponse::ok(presence))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/{user_id}/custom_status",
+    tag = "users",
+    params(("user_id" = i64, Path)),
+    request_body = CustomStatusRequest,
+    responses(
+        (status = 200, body = ApiResponse<CustomStatus>),
+        (status = 400, description = "Neither text nor an emoji, or an expiry in the past", body = ErrorResponse),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Not your own status", body = ErrorResponse),
+        (status = 422, description = "The body broke a validation rule", body = ApiResponse<ValidationErrorResponse>),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn update_custom_status(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+    ValidatedJson(payload): ValidatedJson<CustomStatusRequest>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Updating custom status...");
+
+    if auth.user_id != user_id {
+        return Err(AppError::forbidden("Cannot change another user's status"));
     }
+
+    let custom_status = CustomStatus {
+        text: payload.text,
+        emoji: payload.emoji,
+        expires_at: payload.expires_at,
+    };
+
+    if custom_status.text.is_none() && custom_status.emoji.is_none() {
+        return Err(AppError::bad_request("Custom status needs text or an emoji"));
+    }
+
+    if custom_status.is_expired(Utc::now()) {
+        return Err(AppError::bad_request("Custom status expiry must be in the future"));
+    }
+
+    state
+        .presence
+        .set_custom_status(user_id, Some(custom_status.clone()))
+        .await?;
+
+    Ok(ApiResponse::ok(custom_status))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{user_id}/custom_status",
+    tag = "users",
+    params(("user_id" = i64, Path)),
+    responses(
+        (status = 200, body = ApiResponse<String>),
+        (status = 401, body = ErrorResponse),
+        (status = 403, description = "Not your own status", body = ErrorResponse),
+    ),
+    security(("bearer" = []))
+)]
+pub async fn clear_custom_status(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<i64>,
+) -> AppResult<impl IntoResponse> {
+    tracing::info!("Clearing custom status...");
+
+    if auth.user_id != user_id {
+        return Err(AppError::forbidden("Cannot change another user's status"));
+    }
+
+    state.presence.set_custom_status(user_id, None).await?;
+
+    Ok(ApiResponse::ok("Custom status cleared".to_string()))
+}
+
+pub(crate) fn user_not_found() -> AppError {
+    AppError::new(ErrorCode::UnknownUser, "User not found")
+}
+
+fn not_own_account() -> AppError {
+    AppError::forbidden("Cannot change another user's account")
+}
+
+pub(crate) fn account_disabled() -> AppError {
+    AppError::new(ErrorCode::AccountDisabled, "This account has been disabled")
 }
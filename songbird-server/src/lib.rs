@@ -0,0 +1,28 @@
+// src/lib.rs
+pub mod admin;
+pub mod app;
+pub mod auth;
+pub mod cache;
+pub mod config;
+pub mod database;
+pub mod error;
+pub mod gateway;
+pub mod handlers;
+pub mod mailer;
+pub mod metrics;
+pub mod mfa;
+pub mod models;
+pub mod openapi;
+pub mod rate_limit;
+pub mod repositories;
+pub mod router;
+pub mod services;
+pub mod shutdown;
+pub mod snowflake;
+pub mod telemetry;
+pub mod tls;
+pub mod validation;
+pub mod versioning;
+
+pub use app::{build_state, run};
+pub use router::{create_router, AppState};
@@ -1,54 +1,39 @@
 // src/main.rs
-mod database;
-mod handlers;
-mod models;
-mod repositories;
-mod router;
-
-use crate::{
-    database::establish_connection, repositories::ServerRepository, repositories::UserRepository,
-    router::create_router, router::AppState,
-};
-use std::net::SocketAddr;
-use tokio::net::TcpListener;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use clap::Parser;
+use songbird_server::config::{Cli, Config};
+use songbird_server::{database, telemetry};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load environment variables
     dotenv::dotenv().ok();
 
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    // Create a connection pool
-    let pool = establish_connection().await?;
-
-    // Initialize repositories
-    let user_repository = UserRepository::new(pool.clone());
-    let server_repository = ServerRepository::new(pool.clone());
-
-    // Create app state
-    let app_state = AppState {
-        pool: pool.clone(),
-        user_repository,
-        server_repository,
+    let cli = Cli::parse();
+    let config = match Config::load(&cli) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("songbird-server: {}", e);
+            std::process::exit(2);
+        }
     };
 
-    // Build the router
-    let app = create_router(app_state);
-
-    // Start the server
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    let listener = TcpListener::bind(addr).await?;
-    tracing::info!("listening on {}", addr);
-
-    axum::serve(listener, app).await?;
+    // Initialize tracing
+    let telemetry = telemetry::init(&config.log)?;
+    for var in &config.ignored_env {
+        tracing::warn!("Ignoring {}: there is no such setting", var);
+    }
+
+    // Connect to Postgres or SQLite, applying any pending migrations
+    let database = database::connect(&config.database).await?;
+
+    // Deploys run this as a separate step before starting the new servers
+    let result = if cli.migrate_only {
+        tracing::info!("Migrations applied, exiting (--migrate-only)");
+        Ok(())
+    } else {
+        songbird_server::run(config, database).await
+    };
 
-    Ok(())
+    telemetry.shutdown();
+    result
 }
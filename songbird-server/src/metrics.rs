@@ -0,0 +1,233 @@
+// src/metrics.rs
+use crate::repositories::health_repository::PoolStatus;
+use crate::router::AppState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::{Registry, Unit};
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct RequestLabels {
+    method: String,
+    /// The route template, e.g. `/api/v1/users/{user_id}`, so IDs do not each
+    /// get their own series.
+    route: String,
+    status: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct RouteLabels {
+    method: String,
+    route: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct PoolLabels {
+    state: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct EventLabels {
+    event: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct CacheLabels {
+    cache: &'static str,
+    /// `hit` or `miss`.
+    result: &'static str,
+}
+
+/// 5ms up to about 10s.
+fn latency_histogram() -> Histogram {
+    Histogram::new(exponential_buckets(0.005, 2.0, 12))
+}
+
+struct Inner {
+    registry: Registry,
+    http_requests: Family<RequestLabels, Counter>,
+    http_request_duration: Family<RouteLabels, Histogram, fn() -> Histogram>,
+    db_connections: Family<PoolLabels, Gauge>,
+    db_max_connections: Gauge,
+    gateway_connections: Gauge,
+    gateway_events: Family<EventLabels, Counter>,
+    messages_created: Counter,
+    cache_lookups: Family<CacheLabels, Counter>,
+}
+
+/// Prometheus metrics for the whole process, rendered by `GET /metrics`.
+/// Cheap to clone; every clone updates the same series.
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::with_prefix("songbird");
+
+        let http_requests = Family::<RequestLabels, Counter>::default();
+        registry.register("http_requests", "HTTP requests handled", http_requests.clone());
+
+        let http_request_duration =
+            Family::<RouteLabels, Histogram, fn() -> Histogram>::new_with_constructor(latency_histogram as fn() -> Histogram);
+        registry.register_with_unit(
+            "http_request_duration",
+            "Time from receiving a request to having its response",
+            Unit::Seconds,
+            http_request_duration.clone(),
+        );
+
+        let db_connections = Family::<PoolLabels, Gauge>::default();
+        registry.register("db_connections", "Database pool connections by state", db_connections.clone());
+
+        let db_max_connections = Gauge::default();
+        registry.register(
+            "db_max_connections",
+            "Largest number of connections the pool will open",
+            db_max_connections.clone(),
+        );
+
+        let gateway_connections = Gauge::default();
+        registry.register(
+            "gateway_connections",
+            "Identified gateway sessions",
+            gateway_connections.clone(),
+        );
+
+        let gateway_events = Family::<EventLabels, Counter>::default();
+        registry.register(
+            "gateway_events",
+            "Dispatch events delivered to gateway sessions",
+            gateway_events.clone(),
+        );
+
+        let messages_created = Counter::default();
+        registry.register("messages_created", "Messages posted", messages_created.clone());
+
+        let cache_lookups = Family::<CacheLabels, Counter>::default();
+        registry.register(
+            "cache_lookups",
+            "Lookups answered from the in-process cache (hit) or the database (miss)",
+            cache_lookups.clone(),
+        );
+
+        Self {
+            inner: Arc::new(Inner {
+                registry,
+                http_requests,
+                http_request_duration,
+                db_connections,
+                db_max_connections,
+                gateway_connections,
+                gateway_events,
+                messages_created,
+                cache_lookups,
+            }),
+        }
+    }
+
+    pub fn record_request(&self, method: &str, route: &str, status: u16, elapsed_secs: f64) {
+        self.inner
+            .http_requests
+            .get_or_create(&RequestLabels {
+                method: method.to_string(),
+                route: route.to_string(),
+                status,
+            })
+            .inc();
+        self.inner
+            .http_request_duration
+            .get_or_create(&RouteLabels {
+                method: method.to_string(),
+                route: route.to_string(),
+            })
+            .observe(elapsed_secs);
+    }
+
+    pub fn gateway_connected(&self) {
+        self.inner.gateway_connections.inc();
+    }
+
+    pub fn gateway_disconnected(&self) {
+        self.inner.gateway_connections.dec();
+    }
+
+    /// `sessions` is how many sockets the event went out to.
+    pub fn events_dispatched(&self, event: &'static str, sessions: u64) {
+        if sessions > 0 {
+            self.inner
+                .gateway_events
+                .get_or_create(&EventLabels { event })
+                .inc_by(sessions);
+        }
+    }
+
+    pub fn message_created(&self) {
+        self.inner.messages_created.inc();
+    }
+
+    pub fn cache_lookup(&self, cache: &'static str, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.inner.cache_lookups.get_or_create(&CacheLabels { cache, result }).inc();
+    }
+
+    /// Pool usage is sampled when scraped rather than tracked on every
+    /// acquire.
+    pub fn observe_pool(&self, pool: PoolStatus) {
+        let size = i64::from(pool.size);
+        let idle = i64::from(pool.idle);
+
+        let connections = &self.inner.db_connections;
+        connections.get_or_create(&PoolLabels { state: "idle" }).set(idle);
+        connections
+            .get_or_create(&PoolLabels { state: "in_use" })
+            .set((size - idle).max(0));
+        self.inner.db_max_connections.set(i64::from(pool.max));
+    }
+
+    /// The OpenMetrics text exposition of every series.
+    pub fn render(&self) -> String {
+        let mut buffer = String::new();
+        prometheus_client::encoding::text::encode(&mut buffer, &self.inner.registry)
+            .expect("writing to a String cannot fail");
+        buffer
+    }
+}
+
+/// Middleware counting and timing every request by route. Requests that
+/// match no route are grouped under `unmatched`.
+pub async fn track_requests(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    state.metrics.record_request(
+        &method,
+        &route,
+        response.status().as_u16(),
+        started.elapsed().as_secs_f64(),
+    );
+    response
+}
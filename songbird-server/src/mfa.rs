@@ -0,0 +1,121 @@
+// src/mfa.rs
+//! TOTP (RFC 6238, SHA-1, 6 digits, 30 second steps) and recovery codes.
+use data_encoding::{BASE32_NOPAD, HEXLOWER};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+pub const TOTP_ISSUER: &str = "Songbird";
+pub const TOTP_STEP_SECONDS: i64 = 30;
+pub const TOTP_DIGITS: u32 = 6;
+/// Codes from one step before or after the current one are accepted to
+/// allow for clock drift.
+pub const TOTP_SKEW_STEPS: i64 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// A new random secret, base32 encoded the way authenticator apps expect.
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_BYTES];
+    rand::rng().fill(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+pub fn otpauth_uri(secret: &str, account_name: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = TOTP_ISSUER,
+        account = percent_encode(account_name),
+        secret = secret,
+        digits = TOTP_DIGITS,
+        period = TOTP_STEP_SECONDS,
+    )
+}
+
+/// The time step a unix timestamp falls in.
+pub fn step_at(unix_seconds: i64) -> i64 {
+    unix_seconds.div_euclid(TOTP_STEP_SECONDS)
+}
+
+/// The code for `step`, or `None` if the secret is not valid base32.
+pub fn code_at(secret: &str, step: i64) -> Option<String> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    ))
+}
+
+/// Checks `code` against the steps around `unix_seconds` and returns the
+/// step it matched, which the caller stores to reject replays.
+pub fn verify_code(secret: &str, code: &str, unix_seconds: i64) -> Option<i64> {
+    if !is_totp_code(code) {
+        return None;
+    }
+    let code = code.trim();
+
+    let current = step_at(unix_seconds);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .find(|step| code_at(secret, *step).is_some_and(|expected| constant_time_eq(&expected, code)))
+}
+
+/// Whether `code` looks like a TOTP code rather than a recovery code.
+pub fn is_totp_code(code: &str) -> bool {
+    let code = code.trim();
+    code.len() == TOTP_DIGITS as usize && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Fresh recovery codes in `xxxxx-xxxxx` form. Only their hashes are stored.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String = (0..RECOVERY_CODE_LENGTH)
+                .map(|_| RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+                .collect();
+            format!("{}-{}", &code[..RECOVERY_CODE_LENGTH / 2], &code[RECOVERY_CODE_LENGTH / 2..])
+        })
+        .collect()
+}
+
+/// Recovery codes are long and random, so a plain digest is enough; the
+/// dash and case are ignored so users can type them loosely.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    HEXLOWER.encode(&Sha256::digest(normalized.as_bytes()))
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
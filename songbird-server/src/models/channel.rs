@@ -1,19 +1,27 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Channel {
-    pub channel_id: i32,
-    pub server_id: Option<i32>,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub channel_id: i64,
+    #[serde(default, with = "crate::snowflake::option_string")]
+    #[schema(value_type = Option<String>)]
+    pub server_id: Option<i64>,
     pub name: String,
     pub channel_type: String,
+    /// Slowmode: seconds a member has to wait between messages, 0 when off.
+    pub rate_limit_per_user: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewChannel {
-    pub server_id: Option<i32>,
+    #[serde(default, with = "crate::snowflake::option_string")]
+    pub server_id: Option<i64>,
     pub name: String,
     pub channel_type: String,
 }
@@ -1,13 +1,17 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirectMessageMember {
-    pub channel_id: i32,
-    pub user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewDirectMessageMember {
-    pub channel_id: i32,
-    pub user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
 }
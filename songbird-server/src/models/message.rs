@@ -1,12 +1,83 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageType {
+    #[default]
+    Default,
+    Reply,
+    MemberJoin,
+    MemberLeave,
+    Pin,
+    ChannelRename,
+    Call,
+    ThreadCreated,
+}
+
+impl MessageType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageType::Default => "default",
+            MessageType::Reply => "reply",
+            MessageType::MemberJoin => "member_join",
+            MessageType::MemberLeave => "member_leave",
+            MessageType::Pin => "pin",
+            MessageType::ChannelRename => "channel_rename",
+            MessageType::Call => "call",
+            MessageType::ThreadCreated => "thread_created",
+        }
+    }
+
+    /// System messages are generated by the server rather than typed by a user.
+    pub fn is_system(&self) -> bool {
+        !matches!(self, MessageType::Default | MessageType::Reply)
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MessageType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(MessageType::Default),
+            "reply" => Ok(MessageType::Reply),
+            "member_join" => Ok(MessageType::MemberJoin),
+            "member_leave" => Ok(MessageType::MemberLeave),
+            "pin" => Ok(MessageType::Pin),
+            "channel_rename" => Ok(MessageType::ChannelRename),
+            "call" => Ok(MessageType::Call),
+            "thread_created" => Ok(MessageType::ThreadCreated),
+            other => Err(format!("Unknown message type '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Message {
-    pub message_id: i32,
-    pub channel_id: i32,
-    pub author_user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub message_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub author_user_id: i64,
     pub content: String,
+    pub message_type: MessageType,
+    #[serde(default, with = "crate::snowflake::option_string")]
+    #[schema(value_type = Option<String>)]
+    pub referenced_message_id: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub edited_at: Option<DateTime<Utc>>,
@@ -14,7 +85,12 @@ pub struct Message {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewMessage {
-    pub channel_id: i32,
-    pub author_user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    pub channel_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub author_user_id: i64,
     pub content: String,
+    pub message_type: MessageType,
+    #[serde(default, with = "crate::snowflake::option_string")]
+    pub referenced_message_id: Option<i64>,
 }
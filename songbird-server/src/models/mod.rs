@@ -1,6 +1,8 @@
 pub mod channel;
 pub mod direct_message_member;
 pub mod message;
+pub mod permissions;
+pub mod presence;
 pub mod response_types;
 pub mod server;
 pub mod server_member;
@@ -8,4 +10,5 @@ pub mod user;
 
 // Keep the original models module for backward compatibility
 // but it will be deprecated in the future
+#[allow(clippy::module_inception)]
 pub mod models;
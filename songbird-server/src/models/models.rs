@@ -3,7 +3,9 @@
 
 pub use crate::models::channel::{Channel, NewChannel};
 pub use crate::models::direct_message_member::{DirectMessageMember, NewDirectMessageMember};
-pub use crate::models::message::{Message, NewMessage};
+pub use crate::models::message::{Message, MessageType, NewMessage};
+pub use crate::models::permissions::Permissions;
+pub use crate::models::presence::{CustomStatus, PresenceResponse, PresenceStatus};
 pub use crate::models::response_types::{
     ChannelWithMessagesResponse, MessageWithAuthorResponse, ServerWithMembersResponse, UserResponse,
 };
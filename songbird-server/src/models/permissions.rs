@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::ops::BitOr;
+
+/// Bit set of what a server member may do, stored in `server_members.permissions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Permissions(i64);
+
+impl Permissions {
+    pub const NONE: Permissions = Permissions(0);
+    pub const MANAGE_MESSAGES: Permissions = Permissions(1 << 0);
+    pub const MANAGE_CHANNELS: Permissions = Permissions(1 << 1);
+    pub const KICK_MEMBERS: Permissions = Permissions(1 << 2);
+    pub const BAN_MEMBERS: Permissions = Permissions(1 << 3);
+    pub const MANAGE_SERVER: Permissions = Permissions(1 << 4);
+    pub const ALL: Permissions = Permissions((1 << 5) - 1);
+
+    /// Drops any bits that do not correspond to a known permission.
+    pub fn from_bits_truncate(bits: i64) -> Self {
+        Permissions(bits & Self::ALL.0)
+    }
+
+    pub fn bits(&self) -> i64 {
+        self.0
+    }
+
+    pub fn contains(&self, other: Permissions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(&self, other: Permissions) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Permissions;
+
+    fn bitor(self, rhs: Permissions) -> Permissions {
+        Permissions(self.0 | rhs.0)
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceStatus {
+    Online,
+    Idle,
+    Dnd,
+    Invisible,
+    Offline,
+}
+
+impl PresenceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PresenceStatus::Online => "online",
+            PresenceStatus::Idle => "idle",
+            PresenceStatus::Dnd => "dnd",
+            PresenceStatus::Invisible => "invisible",
+            PresenceStatus::Offline => "offline",
+        }
+    }
+
+    /// Works out what other users see from the status the user picked and the
+    /// `afk` flag of each of their connected gateway sessions.
+    pub fn aggregate(preferred: PresenceStatus, sessions_afk: &[bool]) -> PresenceStatus {
+        if sessions_afk.is_empty() {
+            return PresenceStatus::Offline;
+        }
+
+        match preferred {
+            PresenceStatus::Invisible | PresenceStatus::Offline => PresenceStatus::Offline,
+            PresenceStatus::Dnd => PresenceStatus::Dnd,
+            PresenceStatus::Idle => PresenceStatus::Idle,
+            PresenceStatus::Online => {
+                if sessions_afk.iter().all(|afk| *afk) {
+                    PresenceStatus::Idle
+                } else {
+                    PresenceStatus::Online
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for PresenceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PresenceStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "online" => Ok(PresenceStatus::Online),
+            "idle" => Ok(PresenceStatus::Idle),
+            "dnd" => Ok(PresenceStatus::Dnd),
+            "invisible" => Ok(PresenceStatus::Invisible),
+            "offline" => Ok(PresenceStatus::Offline),
+            other => Err(format!("Unknown presence status '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CustomStatus {
+    pub text: Option<String>,
+    pub emoji: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl CustomStatus {
+    /// Builds a custom status from the nullable `users` columns, dropping it
+    /// when it is empty or has already expired.
+    pub fn from_columns(
+        text: Option<String>,
+        emoji: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Option<Self> {
+        if text.is_none() && emoji.is_none() {
+            return None;
+        }
+
+        let status = CustomStatus { text, emoji, expires_at };
+        if status.is_expired(Utc::now()) {
+            None
+        } else {
+            Some(status)
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PresenceResponse {
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub user_id: i64,
+    pub status: PresenceStatus,
+    pub custom_status: Option<CustomStatus>,
+}
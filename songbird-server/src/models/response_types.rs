@@ -1,11 +1,14 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::models::{channel::Channel, server::Server, user::User};
+use crate::models::{channel::Channel, message::MessageType, server::Server};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UserResponse {
-    pub user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub user_id: i64,
     pub username: String,
     pub email: String,
     pub avatar_url: Option<String>,
@@ -13,22 +16,31 @@ pub struct UserResponse {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ServerWithMembersResponse {
     pub server: Server,
     pub members: Vec<UserResponse>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ChannelWithMessagesResponse {
     pub channel: Channel,
     pub messages: Vec<MessageWithAuthorResponse>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct MessageWithAuthorResponse {
-    pub message_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub message_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub channel_id: i64,
     pub content: String,
+    pub message_type: MessageType,
+    #[serde(default, with = "crate::snowflake::option_string")]
+    #[schema(value_type = Option<String>)]
+    pub referenced_message_id: Option<i64>,
     pub author: UserResponse,
     pub created_at: DateTime<Utc>,
     pub edited_at: Option<DateTime<Utc>>,
@@ -1,12 +1,19 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Server {
-    pub server_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub server_id: i64,
     pub server_name: String,
-    pub owner_user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub owner_user_id: i64,
     pub icon_url: Option<String>,
+    /// Moderation permissions only apply to members with 2FA enabled.
+    pub mfa_required: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
@@ -14,6 +21,7 @@ pub struct Server {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewServer {
     pub server_name: String,
-    pub owner_user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    pub owner_user_id: i64,
     pub icon_url: Option<String>,
 }
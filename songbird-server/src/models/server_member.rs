@@ -1,17 +1,25 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ServerMember {
-    pub server_id: i32,
-    pub user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub server_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    #[schema(value_type = String)]
+    pub user_id: i64,
     pub nickname: Option<String>,
+    pub permissions: i64,
     pub joined_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NewServerMember {
-    pub server_id: i32,
-    pub user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    pub server_id: i64,
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
     pub nickname: Option<String>,
 }
@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
-    pub user_id: i32,
+    #[serde(with = "crate::snowflake::string")]
+    pub user_id: i64,
     pub username: String,
     pub email: String,
     #[serde(skip_serializing)]
@@ -12,6 +13,15 @@ pub struct User {
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub status: String,
+    pub preferred_status: String,
+    pub custom_status_text: Option<String>,
+    pub custom_status_emoji: Option<String>,
+    pub custom_status_expires_at: Option<DateTime<Utc>>,
+    pub email_verified: bool,
+    /// Set by `songbird-admin disable-user`; the account cannot log in.
+    pub disabled_at: Option<DateTime<Utc>>,
+    /// Sessions issued up to this second no longer work.
+    pub sessions_revoked_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
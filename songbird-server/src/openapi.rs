@@ -0,0 +1,131 @@
+// src/openapi.rs
+//! The OpenAPI description of the HTTP API, generated from the handlers'
+//! `#[utoipa::path]` annotations and the DTOs' `ToSchema` derives. A copy is
+//! committed as `openapi.json` for clients to generate code from;
+//! `tests/openapi_test.rs` fails when it goes stale.
+
+use crate::error::ErrorCode;
+use crate::handlers::{
+    account_handlers, channel_handlers, health_handlers, message_handlers, mfa_handlers, server_handlers, user_handlers,
+};
+use crate::gateway;
+use axum::Json;
+use serde::Serialize;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Songbird API",
+        description = "Every endpoint answers with the same envelope: `data` on success, `error` and a stable `code` on failure. \
+                       Requests under `/api` are rate limited and may be answered with 429 and a `Retry-After` header.\n\n\
+                       The unversioned `/api/...` paths are a deprecated alias for `/api/v1/...`; responses from them carry \
+                       `Deprecation`, `Sunset` and `Link: rel=\"successor-version\"` headers."
+    ),
+    nest((path = "/api/v1", api = ApiV1)),
+    paths(
+        health_handlers::healthz,
+        health_handlers::readyz,
+        health_handlers::metrics,
+        openapi_json,
+    ),
+    modifiers(&Extras),
+    tags(
+        (name = "auth", description = "Logging in and account recovery"),
+        (name = "users"),
+        (name = "mfa", description = "Two-factor authentication"),
+        (name = "servers"),
+        (name = "channels"),
+        (name = "messages"),
+        (name = "gateway", description = "The WebSocket connection for real-time events"),
+        (name = "meta", description = "Probes, metrics and this document"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Version 1 of the API, with paths relative to `/api/v1`.
+#[derive(OpenApi)]
+#[openapi(paths(
+    user_handlers::login_attempt,
+    mfa_handlers::login_mfa,
+    account_handlers::verify_email,
+    account_handlers::forgot_password,
+    account_handlers::reset_password,
+    gateway::gateway_handler,
+    user_handlers::create_user,
+    user_handlers::get_all_users,
+    user_handlers::get_user,
+    user_handlers::get_user_by_username,
+    user_handlers::update_user,
+    user_handlers::delete_user,
+    user_handlers::get_user_presence,
+    user_handlers::update_custom_status,
+    user_handlers::clear_custom_status,
+    account_handlers::resend_verification,
+    mfa_handlers::begin_totp_enrollment,
+    mfa_handlers::disable_totp,
+    mfa_handlers::confirm_totp_enrollment,
+    server_handlers::create_server,
+    server_handlers::get_all_servers,
+    server_handlers::get_server,
+    server_handlers::update_server,
+    server_handlers::delete_server,
+    server_handlers::get_servers_by_owner,
+    server_handlers::transfer_server,
+    server_handlers::update_server_mfa,
+    server_handlers::add_server_member,
+    server_handlers::remove_server_member,
+    server_handlers::update_member_permissions,
+    channel_handlers::update_channel,
+    channel_handlers::delete_channel,
+    channel_handlers::update_channel_slowmode,
+    channel_handlers::trigger_typing,
+    message_handlers::create_message,
+    message_handlers::get_channel_messages,
+    message_handlers::search_channel_messages,
+    message_handlers::get_pinned_messages,
+    message_handlers::pin_message,
+    message_handlers::unpin_message,
+))]
+pub struct ApiV1;
+
+/// What the derive cannot express: the `bearer` scheme authenticated
+/// operations refer to (the session token from logging in, sent as
+/// `Authorization: Bearer <token>`), and no license, which it would
+/// otherwise fill in from the empty one in Cargo.toml.
+struct Extras;
+
+impl Modify for Extras {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info.license = None;
+
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+/// The envelope as it looks on failure. Only used for documentation;
+/// handlers build it through `AppError`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    pub success: bool,
+    /// Always null for these errors.
+    #[schema(value_type = Option<Object>)]
+    pub data: Option<()>,
+    pub error: String,
+    pub code: ErrorCode,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    tag = "meta",
+    responses((status = 200, description = "This document", content_type = "application/json"))
+)]
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
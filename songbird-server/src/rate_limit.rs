@@ -0,0 +1,366 @@
+// src/rate_limit.rs
+use crate::error::AppError;
+use crate::router::AppState;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, header::FORWARDED, request::Parts, Extensions, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BucketConfig {
+    pub capacity: u32,
+    pub refill_per_second: f64,
+}
+
+/// Budget for each signed-in user, across all of their connections.
+pub const PER_USER: BucketConfig = BucketConfig {
+    capacity: 50,
+    refill_per_second: 5.0,
+};
+
+/// Budget for each client address, signed in or not. This is what keeps
+/// unauthenticated routes such as `/api/v1/login` from being hammered.
+pub const PER_IP: BucketConfig = BucketConfig {
+    capacity: 100,
+    refill_per_second: 10.0,
+};
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(config: BucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.capacity as f64,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, config: BucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.refill_per_second).min(config.capacity as f64);
+        self.updated_at = now;
+    }
+
+    /// How long until a whole token is available, zero if one already is.
+    fn retry_after(&self, config: BucketConfig) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / config.refill_per_second)
+        }
+    }
+
+    fn is_full(&self, config: BucketConfig, now: Instant) -> bool {
+        let mut bucket = self.clone();
+        bucket.refill(config, now);
+        bucket.tokens >= config.capacity as f64
+    }
+
+    fn status(&self, config: BucketConfig) -> RateLimitStatus {
+        let missing = config.capacity as f64 - self.tokens;
+        RateLimitStatus {
+            allowed: true,
+            limit: config.capacity,
+            remaining: self.tokens.floor() as u32,
+            retry_after: Duration::ZERO,
+            reset_after: Duration::from_secs_f64(missing.max(0.0) / config.refill_per_second),
+        }
+    }
+
+    /// Takes one token if there is one.
+    pub fn try_acquire(&mut self, config: BucketConfig, now: Instant) -> RateLimitStatus {
+        self.refill(config, now);
+
+        if self.tokens < 1.0 {
+            return RateLimitStatus {
+                allowed: false,
+                retry_after: self.retry_after(config),
+                ..self.status(config)
+            };
+        }
+
+        self.tokens -= 1.0;
+        self.status(config)
+    }
+}
+
+/// The outcome of a rate limit check, and what goes in the response headers.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub retry_after: Duration,
+    /// Until the bucket is full again.
+    pub reset_after: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BucketKey {
+    User(i64),
+    Ip(IpAddr),
+}
+
+/// Token buckets for every route in `create_router`, keyed both by the
+/// signed-in user and by the client address. A request has to fit in all of
+/// the buckets it maps to.
+#[derive(Clone)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<BucketKey, TokenBucket>>>,
+    per_user: BucketConfig,
+    per_ip: BucketConfig,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(PER_USER, PER_IP)
+    }
+}
+
+impl RateLimiter {
+    pub fn new(per_user: BucketConfig, per_ip: BucketConfig) -> Self {
+        Self {
+            buckets: Arc::default(),
+            per_user,
+            per_ip,
+        }
+    }
+
+    fn config(&self, key: &BucketKey) -> BucketConfig {
+        match key {
+            BucketKey::User(_) => self.per_user,
+            BucketKey::Ip(_) => self.per_ip,
+        }
+    }
+
+    /// Checks the request against its buckets and only spends tokens if all
+    /// of them have one, so a rejected request does not cost anything.
+    pub fn check(&self, user_id: Option<i64>, ip: Option<IpAddr>) -> Option<RateLimitStatus> {
+        let keys: Vec<BucketKey> = user_id
+            .map(BucketKey::User)
+            .into_iter()
+            .chain(ip.map(BucketKey::Ip))
+            .collect();
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        for key in &keys {
+            let config = self.config(key);
+            buckets
+                .entry(*key)
+                .or_insert_with(|| TokenBucket::new(config, now))
+                .refill(config, now);
+        }
+
+        let exhausted = keys
+            .iter()
+            .filter_map(|key| {
+                let bucket = &buckets[key];
+                let config = self.config(key);
+                (bucket.tokens < 1.0).then(|| RateLimitStatus {
+                    allowed: false,
+                    retry_after: bucket.retry_after(config),
+                    ..bucket.status(config)
+                })
+            })
+            .max_by_key(|status| status.retry_after);
+        if let Some(status) = exhausted {
+            return Some(status);
+        }
+
+        // Report whichever bucket is closest to running out.
+        keys.iter()
+            .map(|key| {
+                buckets
+                    .get_mut(key)
+                    .unwrap()
+                    .try_acquire(self.config(key), now)
+            })
+            .min_by_key(|status| status.remaining)
+    }
+
+    /// Periodically drops buckets that have refilled completely; they are
+    /// indistinguishable from a fresh one.
+    pub fn spawn_sweeper(&self) -> JoinHandle<()> {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let now = Instant::now();
+                let mut buckets = limiter.buckets.lock().unwrap();
+                buckets.retain(|key, bucket| !bucket.is_full(limiter.config(key), now));
+            }
+        })
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RateLimitedResponse {
+    /// Seconds to wait before retrying.
+    pub retry_after: f64,
+}
+
+/// Middleware applying the global `RateLimiter`. The client address is the
+/// one `TrustedProxies::client_ip` finds; requests without one (e.g. in
+/// tests) are only limited per user.
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let ip = state.trusted_proxies.client_ip(request.extensions(), request.headers());
+    let user_id = bearer_user_id(request.headers(), &state);
+
+    let Some(status) = state.rate_limiter.check(user_id, ip) else {
+        return next.run(request).await;
+    };
+
+    let mut response = if status.allowed {
+        next.run(request).await
+    } else {
+        tracing::info!("Rate limited request to {}", request.uri().path());
+        AppError::rate_limited(status.retry_after, "You are being rate limited").into_response()
+    };
+
+    let reset_at = SystemTime::now() + status.reset_after;
+    let reset_at = reset_at
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() + u64::from(d.subsec_nanos() > 0))
+        .unwrap_or_default();
+
+    let headers = response.headers_mut();
+    headers.insert("x-ratelimit-limit", HeaderValue::from(status.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(status.remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset_at));
+    response
+}
+
+/// The address a request came from, as `TrustedProxies::client_ip` finds
+/// it, when the server was started with connect info.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(state.trusted_proxies.client_ip(&parts.extensions, &parts.headers)))
+    }
+}
+
+/// The proxies in `rate_limit.trusted_proxies`, whose `Forwarded` and
+/// `X-Forwarded-For` headers are believed. Anyone else could put any
+/// address there, so for them the peer address is the client.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Arc<[IpNet]>);
+
+impl TrustedProxies {
+    /// Entries that are neither an address nor a CIDR range are skipped;
+    /// `Config::validate` reports them.
+    pub fn new(entries: &[String]) -> Self {
+        Self(entries.iter().filter_map(|entry| parse_trusted_proxy(entry)).collect())
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|net| net.contains(&ip))
+    }
+
+    /// The peer address, unless the peer is a trusted proxy. Then the
+    /// forwarded addresses are walked from the nearest hop outwards and the
+    /// first one that is not a trusted proxy is the client. A hop that is
+    /// missing or not an address (`unknown`, an obfuscated name) ends the
+    /// walk at the proxy that reported it.
+    pub fn client_ip(&self, extensions: &Extensions, headers: &HeaderMap) -> Option<IpAddr> {
+        let mut ip = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_canonical())?;
+
+        for hop in forwarded_for(headers).into_iter().rev() {
+            if !self.contains(ip) {
+                break;
+            }
+            match hop {
+                Some(hop) => ip = hop.to_canonical(),
+                None => break,
+            }
+        }
+        Some(ip)
+    }
+}
+
+/// An address such as `10.0.0.1` or a range such as `10.0.0.0/8`.
+pub fn parse_trusted_proxy(entry: &str) -> Option<IpNet> {
+    entry
+        .parse::<IpNet>()
+        .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+        .ok()
+}
+
+/// The addresses in `Forwarded`, or in `X-Forwarded-For` if there is no
+/// `Forwarded`, client first. `None` stands for a hop that is not an
+/// address.
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name| {
+        headers
+            .get_all(name)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>()
+    };
+
+    let forwarded = values(FORWARDED.as_str());
+    if !forwarded.is_empty() {
+        return forwarded
+            .into_iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node.trim_matches('"')))
+            })
+            .collect();
+    }
+
+    values("x-forwarded-for")
+        .into_iter()
+        .map(|hop| hop.parse().ok())
+        .collect()
+}
+
+/// A `Forwarded` node: `192.0.2.1`, `192.0.2.1:4711`, `[2001:db8::1]` or
+/// `[2001:db8::1]:4711`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    node.split(':').next()?.parse().ok()
+}
+
+/// Requests with a bad or expired token still count against their address.
+fn bearer_user_id(headers: &HeaderMap, state: &AppState) -> Option<i64> {
+    let token = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+
+    state.session_keys.verify(token).ok().map(|claims| claims.sub)
+}
@@ -0,0 +1,468 @@
+// src/repositories/cached.rs
+//! Caching wrappers around the user, server, channel and membership
+//! repositories, for the lookups behind most requests: `find_by_id` on the
+//! first three and membership checks on the last.
+//!
+//! Every write goes through the same wrappers and drops what it touched,
+//! including rows a delete cascades to, and the cache `follow`s the gateway
+//! hub to drop entries for the member, server, channel and user changes
+//! published there. Both only cover this instance: changes made by other
+//! instances or by `songbird-admin` show once the entries expire, up to
+//! `cache.ttl_secs` later. Session checks therefore skip the cache (see
+//! `AppState::session_user_repository`), so disabling an account or ending
+//! its sessions takes effect everywhere at once.
+
+use crate::cache::TtlCache;
+use crate::config::CacheConfig;
+use crate::gateway::events::DispatchEvent;
+use crate::gateway::GatewayHub;
+use crate::metrics::Metrics;
+use crate::models::models::{
+    Channel, ChannelWithMessagesResponse, CustomStatus, DirectMessageMember, Message, NewChannel, NewServer,
+    NewServerMember, NewUser, Server, ServerMember, User, UserResponse,
+};
+use crate::repositories::{
+    ChannelRepository, DirectMessageRepository, Repositories, ServerMemberRepository, ServerRepository,
+    UserRepository,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// The caches the wrappers share. Cheap to clone.
+#[derive(Clone)]
+pub struct RepositoryCache {
+    users: Arc<TtlCache<i64, Option<User>>>,
+    servers: Arc<TtlCache<i64, Option<Server>>>,
+    channels: Arc<TtlCache<i64, Option<Channel>>>,
+    // (server_id, user_id)
+    members: Arc<TtlCache<(i64, i64), Option<ServerMember>>>,
+}
+
+impl RepositoryCache {
+    pub fn new(config: &CacheConfig, metrics: Metrics) -> Self {
+        let ttl = Duration::from_secs(config.ttl_secs);
+        let capacity = config.max_entries;
+
+        Self {
+            users: Arc::new(TtlCache::new("users", ttl, capacity, metrics.clone())),
+            servers: Arc::new(TtlCache::new("servers", ttl, capacity, metrics.clone())),
+            channels: Arc::new(TtlCache::new("channels", ttl, capacity, metrics.clone())),
+            members: Arc::new(TtlCache::new("server_members", ttl, capacity, metrics)),
+        }
+    }
+
+    /// Drops the entries for whatever `event` says changed. Events that
+    /// change nothing cached are ignored.
+    pub fn invalidate_for(&self, event: &DispatchEvent) {
+        match event {
+            DispatchEvent::ServerMemberAdd(member) => self.members.invalidate(&(member.server_id, member.user_id)),
+            DispatchEvent::ServerMemberRemove(removed) => self.members.invalidate(&(removed.server_id, removed.user_id)),
+            DispatchEvent::ServerUpdate(server) => self.servers.invalidate(&server.server_id),
+            DispatchEvent::ServerDelete(deleted) => self.invalidate_server(deleted.server_id),
+            DispatchEvent::ChannelUpdate(channel) => self.channels.invalidate(&channel.channel_id),
+            DispatchEvent::ChannelDelete(deleted) => self.channels.invalidate(&deleted.channel_id),
+            DispatchEvent::UserUpdate(user) => self.users.invalidate(&user.user_id),
+            _ => {}
+        }
+    }
+
+    /// Applies `invalidate_for` to everything `hub` publishes until the task
+    /// is aborted. Falling behind the hub clears the whole cache, since the
+    /// missed events are gone.
+    pub fn follow(&self, hub: &GatewayHub) -> JoinHandle<()> {
+        let cache = self.clone();
+        let mut events = hub.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => cache.invalidate_for(&event),
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Cache missed {} gateway events, clearing it", missed);
+                        cache.clear();
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+
+    fn clear(&self) {
+        self.users.clear();
+        self.servers.clear();
+        self.channels.clear();
+        self.members.clear();
+    }
+
+    /// A server's channels and memberships go with it.
+    fn invalidate_server(&self, server_id: i64) {
+        self.servers.invalidate(&server_id);
+        self.channels
+            .invalidate_where(|_, channel| channel.as_ref().is_some_and(|channel| channel.server_id == Some(server_id)));
+        self.members.invalidate_where(|(member_server_id, _), _| *member_server_id == server_id);
+    }
+}
+
+impl Repositories {
+    /// Puts `cache` in front of the repositories that have one.
+    pub fn cached(self, cache: RepositoryCache) -> Self {
+        Self {
+            user_repository: Arc::new(CachedUserRepository {
+                inner: self.user_repository,
+                cache: cache.clone(),
+            }),
+            server_repository: Arc::new(CachedServerRepository {
+                inner: self.server_repository,
+                cache: cache.clone(),
+            }),
+            server_member_repository: Arc::new(CachedServerMemberRepository {
+                inner: self.server_member_repository,
+                cache: cache.clone(),
+            }),
+            channel_repository: Arc::new(CachedChannelRepository {
+                inner: self.channel_repository,
+                cache: cache.clone(),
+            }),
+            direct_message_repository: Arc::new(CachedDirectMessageRepository {
+                inner: self.direct_message_repository,
+                cache,
+            }),
+            ..self
+        }
+    }
+}
+
+struct CachedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    cache: RepositoryCache,
+}
+
+#[async_trait]
+impl UserRepository for CachedUserRepository {
+    async fn create(&self, new_user: NewUser) -> Result<User, sqlx::Error> {
+        self.inner.create(new_user).await
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+        self.cache
+            .users
+            .get_or_load(user_id, || self.inner.find_by_id(user_id))
+            .await
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        self.inner.find_by_username(username).await
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        self.inner.find_by_email(email).await
+    }
+
+    async fn find_all(&self) -> Result<Vec<User>, sqlx::Error> {
+        self.inner.find_all().await
+    }
+
+    async fn update(&self, user_id: i64, user: User) -> Result<User, sqlx::Error> {
+        let result = self.inner.update(user_id, user).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    /// Takes the servers the user owns with it, and everything in those.
+    async fn delete(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let result = self.inner.delete(user_id).await;
+        self.cache.clear();
+        result
+    }
+
+    async fn mark_email_verified(&self, user_id: i64, email: &str) -> Result<bool, sqlx::Error> {
+        let result = self.inner.mark_email_verified(user_id, email).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = self.inner.update_password(user_id, password_hash).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    async fn set_disabled(&self, user_id: i64, disabled: bool) -> Result<bool, sqlx::Error> {
+        let result = self.inner.set_disabled(user_id, disabled).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    async fn revoke_sessions(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let result = self.inner.revoke_sessions(user_id).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    /// Like `delete`, for every user it takes.
+    async fn purge_disabled(&self, disabled_before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let result = self.inner.purge_disabled(disabled_before).await;
+        self.cache.clear();
+        result
+    }
+
+    async fn update_status(&self, user_id: i64, status: &str) -> Result<bool, sqlx::Error> {
+        let result = self.inner.update_status(user_id, status).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    async fn update_preferred_status(&self, user_id: i64, preferred_status: &str) -> Result<bool, sqlx::Error> {
+        let result = self.inner.update_preferred_status(user_id, preferred_status).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    async fn update_custom_status(&self, user_id: i64, custom_status: Option<CustomStatus>) -> Result<bool, sqlx::Error> {
+        let result = self.inner.update_custom_status(user_id, custom_status).await;
+        self.cache.users.invalidate(&user_id);
+        result
+    }
+
+    async fn clear_expired_custom_statuses(&self) -> Result<Vec<i64>, sqlx::Error> {
+        let user_ids = self.inner.clear_expired_custom_statuses().await?;
+        for user_id in &user_ids {
+            self.cache.users.invalidate(user_id);
+        }
+        Ok(user_ids)
+    }
+
+    async fn reset_all_statuses(&self) -> Result<u64, sqlx::Error> {
+        let result = self.inner.reset_all_statuses().await;
+        self.cache.users.clear();
+        result
+    }
+
+    async fn find_presence_audience(&self, user_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        self.inner.find_presence_audience(user_id).await
+    }
+
+    async fn to_response(&self, user: User) -> UserResponse {
+        self.inner.to_response(user).await
+    }
+}
+
+struct CachedServerRepository {
+    inner: Arc<dyn ServerRepository>,
+    cache: RepositoryCache,
+}
+
+#[async_trait]
+impl ServerRepository for CachedServerRepository {
+    async fn create(&self, new_server: NewServer) -> Result<Server, sqlx::Error> {
+        self.inner.create(new_server).await
+    }
+
+    async fn find_by_id(&self, server_id: i64) -> Result<Option<Server>, sqlx::Error> {
+        self.cache
+            .servers
+            .get_or_load(server_id, || self.inner.find_by_id(server_id))
+            .await
+    }
+
+    async fn find_by_owner(&self, owner_user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
+        self.inner.find_by_owner(owner_user_id).await
+    }
+
+    async fn find_all(&self) -> Result<Vec<Server>, sqlx::Error> {
+        self.inner.find_all().await
+    }
+
+    async fn find_servers_for_user(&self, user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
+        self.inner.find_servers_for_user(user_id).await
+    }
+
+    async fn update(&self, server_id: i64, server: Server) -> Result<Server, sqlx::Error> {
+        let result = self.inner.update(server_id, server).await;
+        self.cache.servers.invalidate(&server_id);
+        result
+    }
+
+    async fn delete(&self, server_id: i64) -> Result<bool, sqlx::Error> {
+        let result = self.inner.delete(server_id).await;
+        self.cache.invalidate_server(server_id);
+        result
+    }
+
+    async fn get_server_members(&self, server_id: i64) -> Result<Vec<UserResponse>, sqlx::Error> {
+        self.inner.get_server_members(server_id).await
+    }
+}
+
+struct CachedServerMemberRepository {
+    inner: Arc<dyn ServerMemberRepository>,
+    cache: RepositoryCache,
+}
+
+#[async_trait]
+impl ServerMemberRepository for CachedServerMemberRepository {
+    async fn create(&self, new_server_member: NewServerMember) -> Result<(ServerMember, Option<Message>), sqlx::Error> {
+        let key = (new_server_member.server_id, new_server_member.user_id);
+        let result = self.inner.create(new_server_member).await;
+        self.cache.members.invalidate(&key);
+        result
+    }
+
+    async fn find_by_id(&self, server_id: i64, user_id: i64) -> Result<Option<ServerMember>, sqlx::Error> {
+        self.cache
+            .members
+            .get_or_load((server_id, user_id), || self.inner.find_by_id(server_id, user_id))
+            .await
+    }
+
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
+        self.inner.find_by_server(server_id).await
+    }
+
+    async fn find_by_user(&self, user_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
+        self.inner.find_by_user(user_id).await
+    }
+
+    async fn update_nickname(&self, server_id: i64, user_id: i64, nickname: Option<String>) -> Result<ServerMember, sqlx::Error> {
+        let result = self.inner.update_nickname(server_id, user_id, nickname).await;
+        self.cache.members.invalidate(&(server_id, user_id));
+        result
+    }
+
+    async fn update_permissions(&self, server_id: i64, user_id: i64, permissions: i64) -> Result<Option<ServerMember>, sqlx::Error> {
+        let result = self.inner.update_permissions(server_id, user_id, permissions).await;
+        self.cache.members.invalidate(&(server_id, user_id));
+        result
+    }
+
+    async fn delete(&self, server_id: i64, user_id: i64) -> Result<(bool, Option<Message>), sqlx::Error> {
+        let result = self.inner.delete(server_id, user_id).await;
+        self.cache.members.invalidate(&(server_id, user_id));
+        result
+    }
+
+    /// Answered from the same entries as `find_by_id`.
+    async fn is_member(&self, server_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.find_by_id(server_id, user_id).await?.is_some())
+    }
+
+    async fn count_members(&self, server_id: i64) -> Result<i64, sqlx::Error> {
+        self.inner.count_members(server_id).await
+    }
+}
+
+struct CachedChannelRepository {
+    inner: Arc<dyn ChannelRepository>,
+    cache: RepositoryCache,
+}
+
+#[async_trait]
+impl ChannelRepository for CachedChannelRepository {
+    async fn create(&self, new_channel: NewChannel) -> Result<Channel, sqlx::Error> {
+        self.inner.create(new_channel).await
+    }
+
+    async fn find_by_id(&self, channel_id: i64) -> Result<Option<Channel>, sqlx::Error> {
+        self.cache
+            .channels
+            .get_or_load(channel_id, || self.inner.find_by_id(channel_id))
+            .await
+    }
+
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        self.inner.find_by_server(server_id).await
+    }
+
+    async fn find_direct_message_channels(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        self.inner.find_direct_message_channels(user_id).await
+    }
+
+    async fn find_server_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        self.inner.find_server_channels_for_user(user_id).await
+    }
+
+    async fn update(&self, channel_id: i64, name: String, updated_by_user_id: i64) -> Result<(Channel, Option<Message>), sqlx::Error> {
+        let result = self.inner.update(channel_id, name, updated_by_user_id).await;
+        self.cache.channels.invalidate(&channel_id);
+        result
+    }
+
+    async fn update_rate_limit(&self, channel_id: i64, rate_limit_per_user: i32) -> Result<Option<Channel>, sqlx::Error> {
+        let result = self.inner.update_rate_limit(channel_id, rate_limit_per_user).await;
+        self.cache.channels.invalidate(&channel_id);
+        result
+    }
+
+    async fn delete(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
+        let result = self.inner.delete(channel_id).await;
+        self.cache.channels.invalidate(&channel_id);
+        result
+    }
+
+    async fn get_channel_with_messages(&self, channel_id: i64, limit: i64) -> Result<Option<ChannelWithMessagesResponse>, sqlx::Error> {
+        self.inner.get_channel_with_messages(channel_id, limit).await
+    }
+
+    async fn is_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        self.inner.is_direct_message_member(channel_id, user_id).await
+    }
+
+    async fn find_direct_message_member_ids(&self, channel_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        self.inner.find_direct_message_member_ids(channel_id).await
+    }
+
+    async fn add_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        self.inner.add_direct_message_member(channel_id, user_id).await
+    }
+
+    async fn remove_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        self.inner.remove_direct_message_member(channel_id, user_id).await
+    }
+}
+
+/// Nothing here is cached; deleting a DM channel still has to drop it from
+/// the channel cache.
+struct CachedDirectMessageRepository {
+    inner: Arc<dyn DirectMessageRepository>,
+    cache: RepositoryCache,
+}
+
+#[async_trait]
+impl DirectMessageRepository for CachedDirectMessageRepository {
+    async fn create_dm_channel(&self, user_id1: i64, user_id2: i64, name: String) -> Result<Channel, sqlx::Error> {
+        self.inner.create_dm_channel(user_id1, user_id2, name).await
+    }
+
+    async fn add_dm_member(&self, channel_id: i64, user_id: i64) -> Result<DirectMessageMember, sqlx::Error> {
+        self.inner.add_dm_member(channel_id, user_id).await
+    }
+
+    async fn remove_dm_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        self.inner.remove_dm_member(channel_id, user_id).await
+    }
+
+    async fn find_dm_members(&self, channel_id: i64) -> Result<Vec<DirectMessageMember>, sqlx::Error> {
+        self.inner.find_dm_members(channel_id).await
+    }
+
+    async fn find_dm_member(&self, channel_id: i64, user_id: i64) -> Result<Option<DirectMessageMember>, sqlx::Error> {
+        self.inner.find_dm_member(channel_id, user_id).await
+    }
+
+    async fn find_or_create_dm_channel(&self, user_id1: i64, user_id2: i64) -> Result<Channel, sqlx::Error> {
+        self.inner.find_or_create_dm_channel(user_id1, user_id2).await
+    }
+
+    async fn get_dm_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        self.inner.get_dm_channels_for_user(user_id).await
+    }
+
+    async fn delete_dm_channel(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
+        let result = self.inner.delete_dm_channel(channel_id).await;
+        self.cache.channels.invalidate(&channel_id);
+        result
+    }
+}
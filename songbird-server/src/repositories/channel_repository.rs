@@ -1,30 +1,73 @@
-use sqlx::{Pool, Postgres};
+use crate::models::models::{Channel, ChannelWithMessagesResponse, Message, MessageType, NewChannel, NewMessage};
+use crate::repositories::{MessageRepository, PgMessageRepository};
+use crate::snowflake;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use crate::models::models::{Channel, NewChannel, ChannelWithMessagesResponse, MessageWithAuthorResponse};
-use crate::repositories::MessageRepository;
+use sqlx::{Pool, Postgres};
+
+#[async_trait]
+pub trait ChannelRepository: Send + Sync {
+    async fn create(&self, new_channel: NewChannel) -> Result<Channel, sqlx::Error>;
+
+    async fn find_by_id(&self, channel_id: i64) -> Result<Option<Channel>, sqlx::Error>;
+
+    /// The server's channels, ordered by name.
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<Channel>, sqlx::Error>;
+
+    async fn find_direct_message_channels(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error>;
+
+    /// The channels of every server `user_id` is a member of, in one query,
+    /// ordered by server and then by name.
+    async fn find_server_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error>;
+
+    /// Renames the channel. When the name actually changes, a
+    /// `channel_rename` message authored by `updated_by_user_id` is posted in
+    /// the channel within the same transaction.
+    async fn update(&self, channel_id: i64, name: String, updated_by_user_id: i64) -> Result<(Channel, Option<Message>), sqlx::Error>;
+
+    async fn update_rate_limit(&self, channel_id: i64, rate_limit_per_user: i32) -> Result<Option<Channel>, sqlx::Error>;
+
+    async fn delete(&self, channel_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn get_channel_with_messages(&self, channel_id: i64, limit: i64) -> Result<Option<ChannelWithMessagesResponse>, sqlx::Error>;
+
+    async fn is_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error>;
 
-pub struct ChannelRepository {
+    async fn find_direct_message_member_ids(&self, channel_id: i64) -> Result<Vec<i64>, sqlx::Error>;
+
+    /// Returns `false` if the user already was a member.
+    async fn add_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn remove_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PgChannelRepository {
     pool: Pool<Postgres>,
-    message_repository: Option<MessageRepository>,
+    message_repository: Option<PgMessageRepository>,
 }
 
-impl ChannelRepository {
+impl PgChannelRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
         Self { pool, message_repository: None }
     }
 
-    pub fn with_message_repository(pool: Pool<Postgres>, message_repository: MessageRepository) -> Self {
+    pub fn with_message_repository(pool: Pool<Postgres>, message_repository: PgMessageRepository) -> Self {
         Self { pool, message_repository: Some(message_repository) }
     }
+}
 
-    pub async fn create(&self, new_channel: NewChannel) -> Result<Channel, sqlx::Error> {
-        let now = Utc::now();
+#[async_trait]
+impl ChannelRepository for PgChannelRepository {
+    #[tracing::instrument(name = "ChannelRepository::create", skip_all)]
+    async fn create(&self, new_channel: NewChannel) -> Result<Channel, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            INSERT INTO channels (server_id, name, type)
-            VALUES ($1, $2, $3)
-            RETURNING channel_id, server_id, name, type as "channel_type", created_at, updated_at
+            INSERT INTO channels (channel_id, server_id, name, type)
+            VALUES ($1, $2, $3, $4)
+            RETURNING channel_id, server_id, name, type as "channel_type", rate_limit_per_user, created_at, updated_at
             "#,
+            snowflake::next_id(),
             new_channel.server_id,
             new_channel.name,
             new_channel.channel_type
@@ -37,6 +80,7 @@ impl ChannelRepository {
             server_id: record.server_id,
             name: record.name,
             channel_type: record.channel_type,
+            rate_limit_per_user: record.rate_limit_per_user,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
         };
@@ -44,10 +88,11 @@ impl ChannelRepository {
         Ok(channel)
     }
 
-    pub async fn find_by_id(&self, channel_id: i32) -> Result<Option<Channel>, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, channel_id: i64) -> Result<Option<Channel>, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            SELECT channel_id, server_id, name, type as "channel_type", created_at, updated_at
+            SELECT channel_id, server_id, name, type as "channel_type", rate_limit_per_user, created_at, updated_at
             FROM channels
             WHERE channel_id = $1
             "#,
@@ -61,6 +106,7 @@ impl ChannelRepository {
             server_id: r.server_id,
             name: r.name,
             channel_type: r.channel_type,
+            rate_limit_per_user: r.rate_limit_per_user,
             created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
             updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
         });
@@ -68,10 +114,11 @@ impl ChannelRepository {
         Ok(channel)
     }
 
-    pub async fn find_by_server(&self, server_id: i32) -> Result<Vec<Channel>, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::find_by_server", skip_all)]
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT channel_id, server_id, name, type as "channel_type", created_at, updated_at
+            SELECT channel_id, server_id, name, type as "channel_type", rate_limit_per_user, created_at, updated_at
             FROM channels
             WHERE server_id = $1
             ORDER BY name
@@ -88,6 +135,7 @@ impl ChannelRepository {
                 server_id: r.server_id,
                 name: r.name,
                 channel_type: r.channel_type,
+                rate_limit_per_user: r.rate_limit_per_user,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
             })
@@ -96,10 +144,11 @@ impl ChannelRepository {
         Ok(channels)
     }
 
-    pub async fn find_direct_message_channels(&self, user_id: i32) -> Result<Vec<Channel>, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::find_direct_message_channels", skip_all)]
+    async fn find_direct_message_channels(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT c.channel_id, c.server_id, c.name, c.type as "channel_type", c.created_at, c.updated_at
+            SELECT c.channel_id, c.server_id, c.name, c.type as "channel_type", c.rate_limit_per_user, c.created_at, c.updated_at
             FROM channels c
             JOIN direct_message_members dm ON c.channel_id = dm.channel_id
             WHERE dm.user_id = $1 AND c.type = 'dm'
@@ -116,6 +165,38 @@ impl ChannelRepository {
                 server_id: r.server_id,
                 name: r.name,
                 channel_type: r.channel_type,
+                rate_limit_per_user: r.rate_limit_per_user,
+                created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
+                updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
+            })
+            .collect();
+
+        Ok(channels)
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::find_server_channels_for_user", skip_all)]
+    async fn find_server_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            SELECT c.channel_id, c.server_id, c.name, c.type as "channel_type", c.rate_limit_per_user, c.created_at, c.updated_at
+            FROM channels c
+            JOIN server_members sm ON c.server_id = sm.server_id
+            WHERE sm.user_id = $1
+            ORDER BY c.server_id, c.name
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let channels = records
+            .into_iter()
+            .map(|r| Channel {
+                channel_id: r.channel_id,
+                server_id: r.server_id,
+                name: r.name,
+                channel_type: r.channel_type,
+                rate_limit_per_user: r.rate_limit_per_user,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
             })
@@ -124,20 +205,35 @@ impl ChannelRepository {
         Ok(channels)
     }
 
-    pub async fn update(&self, channel_id: i32, name: String) -> Result<Channel, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::update", skip_all)]
+    async fn update(&self, channel_id: i64, name: String, updated_by_user_id: i64) -> Result<(Channel, Option<Message>), sqlx::Error> {
         let now = Utc::now();
+        let mut tx = self.pool.begin().await?;
+
+        let previous = sqlx::query!(
+            r#"
+            SELECT name
+            FROM channels
+            WHERE channel_id = $1
+            FOR UPDATE
+            "#,
+            channel_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
         let record = sqlx::query!(
             r#"
             UPDATE channels
             SET name = $1, updated_at = $2
             WHERE channel_id = $3
-            RETURNING channel_id, server_id, name, type as "channel_type", created_at, updated_at
+            RETURNING channel_id, server_id, name, type as "channel_type", rate_limit_per_user, created_at, updated_at
             "#,
             name,
             now as _,
             channel_id
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
 
         let updated_channel = Channel {
@@ -145,14 +241,61 @@ impl ChannelRepository {
             server_id: record.server_id,
             name: record.name,
             channel_type: record.channel_type,
+            rate_limit_per_user: record.rate_limit_per_user,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
         };
 
-        Ok(updated_channel)
+        let system_message = if previous.name != updated_channel.name {
+            let new_message = NewMessage {
+                channel_id,
+                author_user_id: updated_by_user_id,
+                content: updated_channel.name.clone(),
+                message_type: MessageType::ChannelRename,
+                referenced_message_id: None,
+            };
+            Some(PgMessageRepository::create_tx(&mut tx, new_message).await?)
+        } else {
+            None
+        };
+
+        tx.commit().await?;
+
+        Ok((updated_channel, system_message))
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::update_rate_limit", skip_all)]
+    async fn update_rate_limit(&self, channel_id: i64, rate_limit_per_user: i32) -> Result<Option<Channel>, sqlx::Error> {
+        let now = Utc::now();
+        let record = sqlx::query!(
+            r#"
+            UPDATE channels
+            SET rate_limit_per_user = $1, updated_at = $2
+            WHERE channel_id = $3
+            RETURNING channel_id, server_id, name, type as "channel_type", rate_limit_per_user, created_at, updated_at
+            "#,
+            rate_limit_per_user,
+            now as _,
+            channel_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let channel = record.map(|r| Channel {
+            channel_id: r.channel_id,
+            server_id: r.server_id,
+            name: r.name,
+            channel_type: r.channel_type,
+            rate_limit_per_user: r.rate_limit_per_user,
+            created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
+            updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
+        });
+
+        Ok(channel)
     }
 
-    pub async fn delete(&self, channel_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::delete", skip_all)]
+    async fn delete(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             DELETE FROM channels
@@ -166,7 +309,8 @@ impl ChannelRepository {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn get_channel_with_messages(&self, channel_id: i32, limit: i64) -> Result<Option<ChannelWithMessagesResponse>, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::get_channel_with_messages", skip_all)]
+    async fn get_channel_with_messages(&self, channel_id: i64, limit: i64) -> Result<Option<ChannelWithMessagesResponse>, sqlx::Error> {
         if self.message_repository.is_none() {
             return Err(sqlx::Error::RowNotFound);
         }
@@ -174,7 +318,7 @@ impl ChannelRepository {
         let channel = self.find_by_id(channel_id).await?;
         
         if let Some(channel) = channel {
-            let messages = self.message_repository.as_ref().unwrap().find_by_channel_with_authors(channel_id, limit).await?;
+            let messages = self.message_repository.as_ref().unwrap().find_by_channel_with_authors(channel_id, None, limit).await?;
             Ok(Some(ChannelWithMessagesResponse {
                 channel,
                 messages,
@@ -184,7 +328,8 @@ impl ChannelRepository {
         }
     }
 
-    pub async fn is_direct_message_member(&self, channel_id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::is_direct_message_member", skip_all)]
+    async fn is_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             SELECT 1 as exists
@@ -200,7 +345,24 @@ impl ChannelRepository {
         Ok(result.is_some())
     }
 
-    pub async fn add_direct_message_member(&self, channel_id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::find_direct_message_member_ids", skip_all)]
+    async fn find_direct_message_member_ids(&self, channel_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            SELECT user_id
+            FROM direct_message_members
+            WHERE channel_id = $1
+            "#,
+            channel_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records.into_iter().map(|r| r.user_id).collect())
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::add_direct_message_member", skip_all)]
+    async fn add_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             INSERT INTO direct_message_members (channel_id, user_id)
@@ -216,7 +378,8 @@ impl ChannelRepository {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn remove_direct_message_member(&self, channel_id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "ChannelRepository::remove_direct_message_member", skip_all)]
+    async fn remove_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             DELETE FROM direct_message_members
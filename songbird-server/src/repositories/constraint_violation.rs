@@ -0,0 +1,88 @@
+use sqlx::error::{DatabaseError, ErrorKind};
+use std::borrow::Cow;
+
+/// A constraint violation, reported the way Postgres reports it, for the
+/// backends that are not Postgres. `AppError` maps errors by SQLSTATE and
+/// constraint name, so these turn into the same error codes.
+#[derive(Debug)]
+pub(crate) struct ConstraintViolation {
+    code: &'static str,
+    constraint: Option<String>,
+    message: String,
+}
+
+impl ConstraintViolation {
+    pub(crate) fn unique(constraint: impl Into<String>) -> sqlx::Error {
+        let constraint = constraint.into();
+        let message = format!("duplicate key value violates unique constraint \"{}\"", constraint);
+        Self::error("23505", Some(constraint), message)
+    }
+
+    pub(crate) fn foreign_key(constraint: impl Into<String>) -> sqlx::Error {
+        let constraint = constraint.into();
+        let message = format!("insert or update violates foreign key constraint \"{}\"", constraint);
+        Self::error("23503", Some(constraint), message)
+    }
+
+    /// For backends that do not say which foreign key failed.
+    pub(crate) fn unnamed_foreign_key() -> sqlx::Error {
+        Self::error("23503", None, "insert or update violates a foreign key constraint".to_string())
+    }
+
+    pub(crate) fn check(constraint: impl Into<String>) -> sqlx::Error {
+        let constraint = constraint.into();
+        let message = format!("new row violates check constraint \"{}\"", constraint);
+        Self::error("23514", Some(constraint), message)
+    }
+
+    fn error(code: &'static str, constraint: Option<String>, message: String) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(Self {
+            code,
+            constraint,
+            message,
+        }))
+    }
+}
+
+impl std::fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+impl DatabaseError for ConstraintViolation {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.code))
+    }
+
+    fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+        self
+    }
+
+    fn constraint(&self) -> Option<&str> {
+        self.constraint.as_deref()
+    }
+
+    fn kind(&self) -> ErrorKind {
+        match self.code {
+            "23505" => ErrorKind::UniqueViolation,
+            "23503" => ErrorKind::ForeignKeyViolation,
+            "23514" => ErrorKind::CheckViolation,
+            _ => ErrorKind::Other,
+        }
+    }
+}
@@ -1,19 +1,65 @@
-use sqlx::{Pool, Postgres, Transaction};
+use crate::models::models::{Channel, DirectMessageMember};
+use crate::repositories::{ChannelRepository, PgChannelRepository};
+use crate::snowflake;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use crate::models::models::{DirectMessageMember, NewDirectMessageMember, Channel, NewChannel};
-use crate::repositories::ChannelRepository;
+use sqlx::{Pool, Postgres, Transaction};
+
+#[async_trait]
+pub trait DirectMessageRepository: Send + Sync {
+    /// Creates a DM channel with both users as members.
+    async fn create_dm_channel(&self, user_id1: i64, user_id2: i64, name: String) -> Result<Channel, sqlx::Error>;
+
+    async fn add_dm_member(&self, channel_id: i64, user_id: i64) -> Result<DirectMessageMember, sqlx::Error>;
+
+    async fn remove_dm_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn find_dm_members(&self, channel_id: i64) -> Result<Vec<DirectMessageMember>, sqlx::Error>;
+
+    async fn find_dm_member(&self, channel_id: i64, user_id: i64) -> Result<Option<DirectMessageMember>, sqlx::Error>;
+
+    /// The DM channel between the two users, created if there is none yet.
+    async fn find_or_create_dm_channel(&self, user_id1: i64, user_id2: i64) -> Result<Channel, sqlx::Error>;
+
+    async fn get_dm_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error>;
+
+    /// Deletes the DM channel with its members and messages.
+    async fn delete_dm_channel(&self, channel_id: i64) -> Result<bool, sqlx::Error>;
+}
 
-pub struct DirectMessageRepository {
+pub struct PgDirectMessageRepository {
     pool: Pool<Postgres>,
-    channel_repository: ChannelRepository,
+    channel_repository: PgChannelRepository,
 }
 
-impl DirectMessageRepository {
-    pub fn new(pool: Pool<Postgres>, channel_repository: ChannelRepository) -> Self {
+impl PgDirectMessageRepository {
+    pub fn new(pool: Pool<Postgres>, channel_repository: PgChannelRepository) -> Self {
         Self { pool, channel_repository }
     }
 
-    pub async fn create_dm_channel(&self, user_id1: i32, user_id2: i32, name: String) -> Result<Channel, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::add_dm_member_tx", skip_all)]
+    async fn add_dm_member_tx(&self, tx: &mut Transaction<'_, Postgres>, channel_id: i64, user_id: i64) -> Result<DirectMessageMember, sqlx::Error> {
+        let member = sqlx::query_as!(
+            DirectMessageMember,
+            r#"
+            INSERT INTO direct_message_members (channel_id, user_id)
+            VALUES ($1, $2)
+            RETURNING channel_id, user_id
+            "#,
+            channel_id,
+            user_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(member)
+    }
+}
+
+#[async_trait]
+impl DirectMessageRepository for PgDirectMessageRepository {
+    #[tracing::instrument(name = "DirectMessageRepository::create_dm_channel", skip_all)]
+    async fn create_dm_channel(&self, user_id1: i64, user_id2: i64, name: String) -> Result<Channel, sqlx::Error> {
         // Start a transaction
         let mut tx = self.pool.begin().await?;
 
@@ -21,10 +67,11 @@ impl DirectMessageRepository {
         let now = Utc::now();
         let record = sqlx::query!(
             r#"
-            INSERT INTO channels (server_id, name, type, created_at, updated_at)
-            VALUES (NULL, $1, $2, $3, $3)
-            RETURNING channel_id, server_id, name, type as "channel_type", created_at, updated_at
+            INSERT INTO channels (channel_id, server_id, name, type, created_at, updated_at)
+            VALUES ($1, NULL, $2, $3, $4, $4)
+            RETURNING channel_id, server_id, name, type as "channel_type", rate_limit_per_user, created_at, updated_at
             "#,
+            snowflake::next_id(),
             name,
             "dm",
             now as _
@@ -37,6 +84,7 @@ impl DirectMessageRepository {
             server_id: record.server_id,
             name: record.name,
             channel_type: record.channel_type,
+            rate_limit_per_user: record.rate_limit_per_user,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
         };
@@ -51,24 +99,8 @@ impl DirectMessageRepository {
         Ok(channel)
     }
 
-    async fn add_dm_member_tx(&self, tx: &mut Transaction<'_, Postgres>, channel_id: i32, user_id: i32) -> Result<DirectMessageMember, sqlx::Error> {
-        let member = sqlx::query_as!(
-            DirectMessageMember,
-            r#"
-            INSERT INTO direct_message_members (channel_id, user_id)
-            VALUES ($1, $2)
-            RETURNING channel_id, user_id
-            "#,
-            channel_id,
-            user_id
-        )
-        .fetch_one(&mut **tx)
-        .await?;
-
-        Ok(member)
-    }
-
-    pub async fn add_dm_member(&self, channel_id: i32, user_id: i32) -> Result<DirectMessageMember, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::add_dm_member", skip_all)]
+    async fn add_dm_member(&self, channel_id: i64, user_id: i64) -> Result<DirectMessageMember, sqlx::Error> {
         let member = sqlx::query_as!(
             DirectMessageMember,
             r#"
@@ -85,7 +117,8 @@ impl DirectMessageRepository {
         Ok(member)
     }
 
-    pub async fn remove_dm_member(&self, channel_id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::remove_dm_member", skip_all)]
+    async fn remove_dm_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             DELETE FROM direct_message_members
@@ -100,7 +133,8 @@ impl DirectMessageRepository {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn find_dm_members(&self, channel_id: i32) -> Result<Vec<DirectMessageMember>, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::find_dm_members", skip_all)]
+    async fn find_dm_members(&self, channel_id: i64) -> Result<Vec<DirectMessageMember>, sqlx::Error> {
         let members = sqlx::query_as!(
             DirectMessageMember,
             r#"
@@ -116,7 +150,8 @@ impl DirectMessageRepository {
         Ok(members)
     }
 
-    pub async fn find_dm_member(&self, channel_id: i32, user_id: i32) -> Result<Option<DirectMessageMember>, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::find_dm_member", skip_all)]
+    async fn find_dm_member(&self, channel_id: i64, user_id: i64) -> Result<Option<DirectMessageMember>, sqlx::Error> {
         let member = sqlx::query_as!(
             DirectMessageMember,
             r#"
@@ -133,11 +168,12 @@ impl DirectMessageRepository {
         Ok(member)
     }
 
-    pub async fn find_or_create_dm_channel(&self, user_id1: i32, user_id2: i32) -> Result<Channel, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::find_or_create_dm_channel", skip_all)]
+    async fn find_or_create_dm_channel(&self, user_id1: i64, user_id2: i64) -> Result<Channel, sqlx::Error> {
         // First, check if a DM channel already exists between these users
         let record = sqlx::query!(
             r#"
-            SELECT c.channel_id, c.server_id, c.name, c.type as "channel_type", c.created_at, c.updated_at
+            SELECT c.channel_id, c.server_id, c.name, c.type as "channel_type", c.rate_limit_per_user, c.created_at, c.updated_at
             FROM channels c
             JOIN direct_message_members dm1 ON c.channel_id = dm1.channel_id
             JOIN direct_message_members dm2 ON c.channel_id = dm2.channel_id
@@ -155,6 +191,7 @@ impl DirectMessageRepository {
                 server_id: r.server_id,
                 name: r.name,
                 channel_type: r.channel_type,
+                rate_limit_per_user: r.rate_limit_per_user,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
             };
@@ -166,11 +203,13 @@ impl DirectMessageRepository {
         }
     }
 
-    pub async fn get_dm_channels_for_user(&self, user_id: i32) -> Result<Vec<Channel>, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::get_dm_channels_for_user", skip_all)]
+    async fn get_dm_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
         self.channel_repository.find_direct_message_channels(user_id).await
     }
 
-    pub async fn delete_dm_channel(&self, channel_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "DirectMessageRepository::delete_dm_channel", skip_all)]
+    async fn delete_dm_channel(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
         // Start a transaction
         let mut tx = self.pool.begin().await?;
 
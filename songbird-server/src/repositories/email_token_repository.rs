@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres};
+
+/// The `jti` records behind email verification and password reset tokens.
+#[async_trait]
+pub trait EmailTokenRepository: Send + Sync {
+    /// Records a newly issued token. Any earlier unused token of the same
+    /// purpose stops working, so only the latest email is valid.
+    async fn create(
+        &self,
+        token_id: &str,
+        user_id: i64,
+        purpose: &str,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+
+    /// Uses up the token if it exists, matches `purpose`, has not been used
+    /// and has not expired. Returns the user and email it was issued for.
+    async fn consume(&self, token_id: &str, purpose: &str) -> Result<Option<(i64, String)>, sqlx::Error>;
+
+    /// Deletes the records of tokens that have expired, used or not. Their
+    /// JWTs are rejected by then anyway. Returns how many were deleted.
+    async fn purge_expired(&self) -> Result<u64, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PgEmailTokenRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PgEmailTokenRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EmailTokenRepository for PgEmailTokenRepository {
+    #[tracing::instrument(name = "EmailTokenRepository::create", skip_all)]
+    async fn create(
+        &self,
+        token_id: &str,
+        user_id: i64,
+        purpose: &str,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE email_tokens
+            SET used_at = NOW() AT TIME ZONE 'UTC'
+            WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            purpose
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO email_tokens (token_id, user_id, purpose, email, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            token_id,
+            user_id,
+            purpose,
+            email,
+            expires_at.naive_utc()
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "EmailTokenRepository::consume", skip_all)]
+    async fn consume(&self, token_id: &str, purpose: &str) -> Result<Option<(i64, String)>, sqlx::Error> {
+        let record = sqlx::query!(
+            r#"
+            UPDATE email_tokens
+            SET used_at = NOW() AT TIME ZONE 'UTC'
+            WHERE token_id = $1 AND purpose = $2 AND used_at IS NULL
+                  AND expires_at > NOW() AT TIME ZONE 'UTC'
+            RETURNING user_id, email
+            "#,
+            token_id,
+            purpose
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record.map(|r| (r.user_id, r.email)))
+    }
+
+    #[tracing::instrument(name = "EmailTokenRepository::purge_expired", skip_all)]
+    async fn purge_expired(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM email_tokens
+            WHERE expires_at <= NOW() AT TIME ZONE 'UTC'
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
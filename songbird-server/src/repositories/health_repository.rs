@@ -0,0 +1,70 @@
+use crate::database::MIGRATOR;
+use async_trait::async_trait;
+use sqlx::migrate::Migrate;
+use sqlx::{Pool, Postgres};
+
+/// How far the database schema is from what this build expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaStatus {
+    /// Highest migration version applied.
+    pub version: i64,
+    /// Migrations this build has that the database does not.
+    pub pending: usize,
+}
+
+/// A snapshot of connection pool usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStatus {
+    pub size: u32,
+    pub idle: u32,
+    pub max: u32,
+}
+
+/// What `/readyz` and `/metrics` need to know about the database itself.
+#[async_trait]
+pub trait HealthRepository: Send + Sync {
+    /// Fails if the database cannot be reached.
+    async fn schema_status(&self) -> Result<SchemaStatus, sqlx::Error>;
+
+    /// `None` for backends without a connection pool.
+    fn pool_status(&self) -> Option<PoolStatus>;
+}
+
+#[derive(Clone)]
+pub struct PgHealthRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PgHealthRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl HealthRepository for PgHealthRepository {
+    #[tracing::instrument(name = "HealthRepository::schema_status", skip_all)]
+    async fn schema_status(&self) -> Result<SchemaStatus, sqlx::Error> {
+        let mut connection = self.pool.acquire().await?;
+        let applied = connection.list_applied_migrations().await?;
+
+        let pending = MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .filter(|migration| !applied.iter().any(|applied| applied.version == migration.version))
+            .count();
+
+        Ok(SchemaStatus {
+            version: applied.iter().map(|migration| migration.version).max().unwrap_or_default(),
+            pending,
+        })
+    }
+
+    fn pool_status(&self) -> Option<PoolStatus> {
+        Some(PoolStatus {
+            size: self.pool.size(),
+            idle: self.pool.num_idle() as u32,
+            max: self.pool.options().get_max_connections(),
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use super::{now, ConstraintViolation, MemoryDatabase};
+use crate::models::models::{Channel, ChannelWithMessagesResponse, Message, MessageType, NewChannel, NewMessage};
+use crate::repositories::ChannelRepository;
+use crate::snowflake;
+use async_trait::async_trait;
+
+/// Bounds of `channels_rate_limit_per_user_check`.
+const RATE_LIMIT_PER_USER: std::ops::RangeInclusive<i32> = 0..=21600;
+
+#[async_trait]
+impl ChannelRepository for MemoryDatabase {
+    async fn create(&self, new_channel: NewChannel) -> Result<Channel, sqlx::Error> {
+        let mut tables = self.lock();
+        if let Some(server_id) = new_channel.server_id {
+            tables.require(tables.servers.contains_key(&server_id), "channels_server_id_fkey")?;
+        }
+
+        let channel = Channel {
+            channel_id: snowflake::next_id(),
+            server_id: new_channel.server_id,
+            name: new_channel.name,
+            channel_type: new_channel.channel_type,
+            rate_limit_per_user: 0,
+            created_at: now(),
+            updated_at: None,
+        };
+        tables.channels.insert(channel.channel_id, channel.clone());
+
+        Ok(channel)
+    }
+
+    async fn find_by_id(&self, channel_id: i64) -> Result<Option<Channel>, sqlx::Error> {
+        Ok(self.lock().channels.get(&channel_id).cloned())
+    }
+
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        let mut channels: Vec<Channel> = self
+            .lock()
+            .channels
+            .values()
+            .filter(|channel| channel.server_id == Some(server_id))
+            .cloned()
+            .collect();
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(channels)
+    }
+
+    async fn find_direct_message_channels(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        Ok(self.lock().dm_channels_of(user_id))
+    }
+
+    async fn find_server_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        let tables = self.lock();
+        let mut channels: Vec<Channel> = tables
+            .channels
+            .values()
+            .filter(|channel| {
+                channel
+                    .server_id
+                    .is_some_and(|server_id| tables.server_members.contains_key(&(server_id, user_id)))
+            })
+            .cloned()
+            .collect();
+        channels.sort_by(|a, b| (a.server_id, &a.name).cmp(&(b.server_id, &b.name)));
+        Ok(channels)
+    }
+
+    async fn update(&self, channel_id: i64, name: String, updated_by_user_id: i64) -> Result<(Channel, Option<Message>), sqlx::Error> {
+        let mut tables = self.lock();
+        let previous = tables.channels.get(&channel_id).ok_or(sqlx::Error::RowNotFound)?;
+        let renamed = previous.name != name;
+        if renamed {
+            tables.require(tables.users.contains_key(&updated_by_user_id), "messages_author_user_id_fkey")?;
+        }
+
+        let channel = tables.channels.get_mut(&channel_id).unwrap();
+        channel.name = name;
+        channel.updated_at = Some(now());
+        let channel = channel.clone();
+
+        let system_message = if renamed {
+            Some(tables.insert_message(NewMessage {
+                channel_id,
+                author_user_id: updated_by_user_id,
+                content: channel.name.clone(),
+                message_type: MessageType::ChannelRename,
+                referenced_message_id: None,
+            })?)
+        } else {
+            None
+        };
+
+        Ok((channel, system_message))
+    }
+
+    async fn update_rate_limit(&self, channel_id: i64, rate_limit_per_user: i32) -> Result<Option<Channel>, sqlx::Error> {
+        let mut tables = self.lock();
+        let Some(channel) = tables.channels.get_mut(&channel_id) else {
+            return Ok(None);
+        };
+        if !RATE_LIMIT_PER_USER.contains(&rate_limit_per_user) {
+            return Err(ConstraintViolation::check("channels_rate_limit_per_user_check"));
+        }
+
+        channel.rate_limit_per_user = rate_limit_per_user;
+        channel.updated_at = Some(now());
+
+        Ok(Some(channel.clone()))
+    }
+
+    async fn delete(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().delete_channel(channel_id))
+    }
+
+    async fn get_channel_with_messages(&self, channel_id: i64, limit: i64) -> Result<Option<ChannelWithMessagesResponse>, sqlx::Error> {
+        let tables = self.lock();
+        Ok(tables.channels.get(&channel_id).map(|channel| ChannelWithMessagesResponse {
+            channel: channel.clone(),
+            messages: tables
+                .channel_messages(channel_id, None, limit)
+                .into_iter()
+                .map(|message| tables.with_author(message))
+                .collect(),
+        }))
+    }
+
+    async fn is_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().direct_message_members.contains(&(channel_id, user_id)))
+    }
+
+    async fn find_direct_message_member_ids(&self, channel_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .direct_message_members
+            .iter()
+            .filter(|&&(channel, _)| channel == channel_id)
+            .map(|&(_, user_id)| user_id)
+            .collect())
+    }
+
+    async fn add_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        let mut tables = self.lock();
+        tables.check_dm_member(channel_id, user_id)?;
+        Ok(tables.direct_message_members.insert((channel_id, user_id)))
+    }
+
+    async fn remove_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().direct_message_members.remove(&(channel_id, user_id)))
+    }
+}
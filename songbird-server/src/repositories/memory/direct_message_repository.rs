@@ -0,0 +1,99 @@
+use super::{now, ConstraintViolation, MemoryDatabase, Tables};
+use crate::models::models::{Channel, DirectMessageMember};
+use crate::repositories::DirectMessageRepository;
+use crate::snowflake;
+use async_trait::async_trait;
+
+impl Tables {
+    fn create_dm_channel(&mut self, user_id1: i64, user_id2: i64, name: String) -> Result<Channel, sqlx::Error> {
+        self.require(self.users.contains_key(&user_id1), "direct_message_members_user_id_fkey")?;
+        self.require(self.users.contains_key(&user_id2), "direct_message_members_user_id_fkey")?;
+        if user_id1 == user_id2 {
+            return Err(ConstraintViolation::unique("direct_message_members_pkey"));
+        }
+
+        let now = now();
+        let channel = Channel {
+            channel_id: snowflake::next_id(),
+            server_id: None,
+            name,
+            channel_type: "dm".to_string(),
+            rate_limit_per_user: 0,
+            created_at: now,
+            updated_at: Some(now),
+        };
+        self.channels.insert(channel.channel_id, channel.clone());
+        self.direct_message_members.insert((channel.channel_id, user_id1));
+        self.direct_message_members.insert((channel.channel_id, user_id2));
+
+        Ok(channel)
+    }
+}
+
+#[async_trait]
+impl DirectMessageRepository for MemoryDatabase {
+    async fn create_dm_channel(&self, user_id1: i64, user_id2: i64, name: String) -> Result<Channel, sqlx::Error> {
+        self.lock().create_dm_channel(user_id1, user_id2, name)
+    }
+
+    async fn add_dm_member(&self, channel_id: i64, user_id: i64) -> Result<DirectMessageMember, sqlx::Error> {
+        let mut tables = self.lock();
+        tables.check_dm_member(channel_id, user_id)?;
+        if !tables.direct_message_members.insert((channel_id, user_id)) {
+            return Err(ConstraintViolation::unique("direct_message_members_pkey"));
+        }
+
+        Ok(DirectMessageMember { channel_id, user_id })
+    }
+
+    async fn remove_dm_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().direct_message_members.remove(&(channel_id, user_id)))
+    }
+
+    async fn find_dm_members(&self, channel_id: i64) -> Result<Vec<DirectMessageMember>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .direct_message_members
+            .iter()
+            .filter(|&&(channel, _)| channel == channel_id)
+            .map(|&(channel_id, user_id)| DirectMessageMember { channel_id, user_id })
+            .collect())
+    }
+
+    async fn find_dm_member(&self, channel_id: i64, user_id: i64) -> Result<Option<DirectMessageMember>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .direct_message_members
+            .contains(&(channel_id, user_id))
+            .then_some(DirectMessageMember { channel_id, user_id }))
+    }
+
+    async fn find_or_create_dm_channel(&self, user_id1: i64, user_id2: i64) -> Result<Channel, sqlx::Error> {
+        let mut tables = self.lock();
+        let existing = tables
+            .dm_channels_of(user_id1)
+            .into_iter()
+            .find(|channel| tables.direct_message_members.contains(&(channel.channel_id, user_id2)));
+
+        match existing {
+            Some(channel) => Ok(channel),
+            None => tables.create_dm_channel(user_id1, user_id2, format!("dm_{}_{}", user_id1, user_id2)),
+        }
+    }
+
+    async fn get_dm_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        Ok(self.lock().dm_channels_of(user_id))
+    }
+
+    async fn delete_dm_channel(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
+        let mut tables = self.lock();
+        tables.direct_message_members.retain(|&(channel, _)| channel != channel_id);
+        tables.delete_channel_messages(channel_id);
+
+        let is_dm = tables
+            .channels
+            .get(&channel_id)
+            .is_some_and(|channel| channel.channel_type == "dm");
+        Ok(is_dm && tables.delete_channel(channel_id))
+    }
+}
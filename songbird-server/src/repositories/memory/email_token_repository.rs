@@ -0,0 +1,65 @@
+use super::{now, ConstraintViolation, EmailToken, MemoryDatabase};
+use crate::repositories::EmailTokenRepository;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// Allowed by `email_tokens_purpose_check`.
+const PURPOSES: [&str; 2] = ["verify_email", "reset_password"];
+
+#[async_trait]
+impl EmailTokenRepository for MemoryDatabase {
+    async fn create(
+        &self,
+        token_id: &str,
+        user_id: i64,
+        purpose: &str,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tables = self.lock();
+        if tables.email_tokens.contains_key(token_id) {
+            return Err(ConstraintViolation::unique("email_tokens_pkey"));
+        }
+        tables.require(tables.users.contains_key(&user_id), "email_tokens_user_id_fkey")?;
+        if !PURPOSES.contains(&purpose) {
+            return Err(ConstraintViolation::check("email_tokens_purpose_check"));
+        }
+
+        for token in tables.email_tokens.values_mut() {
+            if token.user_id == user_id && token.purpose == purpose {
+                token.used = true;
+            }
+        }
+        tables.email_tokens.insert(
+            token_id.to_string(),
+            EmailToken {
+                user_id,
+                purpose: purpose.to_string(),
+                email: email.to_string(),
+                expires_at,
+                used: false,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn consume(&self, token_id: &str, purpose: &str) -> Result<Option<(i64, String)>, sqlx::Error> {
+        let now = now();
+        match self.lock().email_tokens.get_mut(token_id) {
+            Some(token) if token.purpose == purpose && !token.used && token.expires_at > now => {
+                token.used = true;
+                Ok(Some((token.user_id, token.email.clone())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn purge_expired(&self) -> Result<u64, sqlx::Error> {
+        let now = now();
+        let mut tables = self.lock();
+        let before = tables.email_tokens.len();
+        tables.email_tokens.retain(|_, token| token.expires_at > now);
+        Ok((before - tables.email_tokens.len()) as u64)
+    }
+}
@@ -0,0 +1,19 @@
+use super::MemoryDatabase;
+use crate::database::MIGRATOR;
+use crate::repositories::health_repository::{HealthRepository, PoolStatus, SchemaStatus};
+use async_trait::async_trait;
+
+#[async_trait]
+impl HealthRepository for MemoryDatabase {
+    /// The tables always have the current schema.
+    async fn schema_status(&self) -> Result<SchemaStatus, sqlx::Error> {
+        Ok(SchemaStatus {
+            version: MIGRATOR.iter().map(|migration| migration.version).max().unwrap_or_default(),
+            pending: 0,
+        })
+    }
+
+    fn pool_status(&self) -> Option<PoolStatus> {
+        None
+    }
+}
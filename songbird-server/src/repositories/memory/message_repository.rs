@@ -0,0 +1,154 @@
+use super::{now, MemoryDatabase, Pin};
+use crate::models::models::{Message, MessageWithAuthorResponse, NewMessage};
+use crate::repositories::{search_terms, MessageRepository, PinOutcome};
+use async_trait::async_trait;
+
+#[async_trait]
+impl MessageRepository for MemoryDatabase {
+    async fn create(&self, new_message: NewMessage) -> Result<Message, sqlx::Error> {
+        self.lock().insert_message(new_message)
+    }
+
+    async fn find_by_id(&self, message_id: i64) -> Result<Option<Message>, sqlx::Error> {
+        Ok(self.lock().messages.get(&message_id).cloned())
+    }
+
+    async fn find_by_channel(&self, channel_id: i64, limit: i64) -> Result<Vec<Message>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .channel_messages(channel_id, None, limit)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    async fn find_by_channel_with_authors(
+        &self,
+        channel_id: i64,
+        before: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        let tables = self.lock();
+        Ok(tables
+            .channel_messages(channel_id, before, limit)
+            .into_iter()
+            .map(|message| tables.with_author(message))
+            .collect())
+    }
+
+    async fn update_content(&self, message_id: i64, content: String) -> Result<Message, sqlx::Error> {
+        let mut tables = self.lock();
+        let message = tables.messages.get_mut(&message_id).ok_or(sqlx::Error::RowNotFound)?;
+        let now = now();
+        message.content = content;
+        message.updated_at = Some(now);
+        message.edited_at = Some(now);
+
+        Ok(message.clone())
+    }
+
+    async fn delete(&self, message_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().delete_message(message_id))
+    }
+
+    async fn pin(&self, channel_id: i64, message_id: i64, pinned_by_user_id: i64, max_pins: i64) -> Result<PinOutcome, sqlx::Error> {
+        let mut tables = self.lock();
+        if tables.pinned_messages.contains_key(&message_id) {
+            return Ok(PinOutcome::AlreadyPinned);
+        }
+
+        let pinned = tables
+            .pinned_messages
+            .values()
+            .filter(|pin| pin.channel_id == channel_id)
+            .count() as i64;
+        if pinned >= max_pins {
+            return Ok(PinOutcome::LimitReached);
+        }
+
+        tables.require(tables.messages.contains_key(&message_id), "pinned_messages_message_id_fkey")?;
+        tables.require(tables.channels.contains_key(&channel_id), "pinned_messages_channel_id_fkey")?;
+        tables.require(
+            tables.users.contains_key(&pinned_by_user_id),
+            "pinned_messages_pinned_by_user_id_fkey",
+        )?;
+        tables.pinned_messages.insert(
+            message_id,
+            Pin {
+                channel_id,
+                pinned_by_user_id: Some(pinned_by_user_id),
+                pinned_at: now(),
+            },
+        );
+
+        Ok(PinOutcome::Pinned)
+    }
+
+    async fn unpin(&self, channel_id: i64, message_id: i64) -> Result<bool, sqlx::Error> {
+        let mut tables = self.lock();
+        match tables.pinned_messages.get(&message_id) {
+            Some(pin) if pin.channel_id == channel_id => {
+                tables.pinned_messages.remove(&message_id);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn find_pinned_with_authors(&self, channel_id: i64) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        let tables = self.lock();
+        let mut pins: Vec<(&i64, &Pin)> = tables
+            .pinned_messages
+            .iter()
+            .filter(|(_, pin)| pin.channel_id == channel_id)
+            .collect();
+        pins.sort_by(|(a_id, a), (b_id, b)| (b.pinned_at, *b_id).cmp(&(a.pinned_at, *a_id)));
+
+        Ok(pins
+            .into_iter()
+            .map(|(message_id, _)| tables.with_author(&tables.messages[message_id]))
+            .collect())
+    }
+
+    async fn count_by_channel(&self, channel_id: i64) -> Result<i64, sqlx::Error> {
+        Ok(self
+            .lock()
+            .messages
+            .values()
+            .filter(|message| message.channel_id == channel_id)
+            .count() as i64)
+    }
+
+    async fn count_by_user(&self, user_id: i64) -> Result<i64, sqlx::Error> {
+        Ok(self
+            .lock()
+            .messages
+            .values()
+            .filter(|message| message.author_user_id == user_id)
+            .count() as i64)
+    }
+
+    async fn search_with_authors(
+        &self,
+        channel_id: i64,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tables = self.lock();
+        Ok(tables
+            .channel_messages(channel_id, None, i64::MAX)
+            .into_iter()
+            .filter(|message| {
+                let words = search_terms(&message.content);
+                terms.iter().all(|term| words.contains(term))
+            })
+            .take(limit.max(0) as usize)
+            .map(|message| tables.with_author(message))
+            .collect())
+    }
+}
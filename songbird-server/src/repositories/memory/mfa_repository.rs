@@ -0,0 +1,86 @@
+use super::{ConstraintViolation, MemoryDatabase, Totp};
+use crate::repositories::mfa_repository::UserTotp;
+use crate::repositories::MfaRepository;
+use async_trait::async_trait;
+use std::collections::BTreeSet;
+
+#[async_trait]
+impl MfaRepository for MemoryDatabase {
+    async fn find_totp(&self, user_id: i64) -> Result<Option<UserTotp>, sqlx::Error> {
+        Ok(self.lock().user_totp.get(&user_id).map(|totp| UserTotp {
+            user_id,
+            secret: totp.secret.clone(),
+            enabled: totp.enabled,
+        }))
+    }
+
+    async fn is_enabled(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().user_totp.get(&user_id).is_some_and(|totp| totp.enabled))
+    }
+
+    async fn begin_enrollment(&self, user_id: i64, secret: &str) -> Result<bool, sqlx::Error> {
+        let mut tables = self.lock();
+        tables.require(tables.users.contains_key(&user_id), "user_totp_user_id_fkey")?;
+        if tables.user_totp.get(&user_id).is_some_and(|totp| totp.enabled) {
+            return Ok(false);
+        }
+
+        tables.user_totp.insert(
+            user_id,
+            Totp {
+                secret: secret.to_string(),
+                enabled: false,
+                last_used_step: None,
+            },
+        );
+        Ok(true)
+    }
+
+    async fn confirm_enrollment(&self, user_id: i64, step: i64, recovery_code_hashes: &[String]) -> Result<bool, sqlx::Error> {
+        let mut tables = self.lock();
+        if tables.user_totp.get(&user_id).is_none_or(|totp| totp.enabled) {
+            return Ok(false);
+        }
+        let distinct: BTreeSet<&String> = recovery_code_hashes.iter().collect();
+        if distinct.len() != recovery_code_hashes.len() {
+            return Err(ConstraintViolation::unique("recovery_codes_user_id_code_hash_key"));
+        }
+
+        let totp = tables.user_totp.get_mut(&user_id).unwrap();
+        totp.enabled = true;
+        totp.last_used_step = Some(step);
+
+        tables.recovery_codes.retain(|(owner, _), _| *owner != user_id);
+        for code_hash in recovery_code_hashes {
+            tables.recovery_codes.insert((user_id, code_hash.clone()), false);
+        }
+
+        Ok(true)
+    }
+
+    async fn disable(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let mut tables = self.lock();
+        tables.recovery_codes.retain(|(owner, _), _| *owner != user_id);
+        Ok(tables.user_totp.remove(&user_id).is_some())
+    }
+
+    async fn consume_step(&self, user_id: i64, step: i64) -> Result<bool, sqlx::Error> {
+        match self.lock().user_totp.get_mut(&user_id) {
+            Some(totp) if totp.last_used_step.is_none_or(|last| last < step) => {
+                totp.last_used_step = Some(step);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn consume_recovery_code(&self, user_id: i64, code_hash: &str) -> Result<bool, sqlx::Error> {
+        match self.lock().recovery_codes.get_mut(&(user_id, code_hash.to_string())) {
+            Some(used) if !*used => {
+                *used = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,307 @@
+// src/repositories/memory/mod.rs
+//! Every repository backed by plain collections, so handlers can be tested
+//! without Postgres.
+//!
+//! The tables keep the schema's primary keys, unique and check constraints,
+//! foreign keys and `ON DELETE` actions. Violations come back as
+//! `sqlx::Error::Database` with the SQLSTATE and constraint name Postgres
+//! would report, so `AppError` turns them into the same error codes. Each
+//! call holds the lock for its whole duration and checks everything before
+//! writing, which makes it as atomic as the transaction it stands in for.
+
+mod channel_repository;
+mod direct_message_repository;
+mod email_token_repository;
+mod health_repository;
+mod message_repository;
+mod mfa_repository;
+mod server_member_repository;
+mod server_repository;
+mod user_repository;
+
+use crate::models::models::{
+    Channel, Message, MessageType, MessageWithAuthorResponse, NewMessage, Server, ServerMember, User, UserResponse,
+};
+use crate::repositories::constraint_violation::ConstraintViolation;
+use crate::snowflake;
+use chrono::{DateTime, SubsecRound, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// One in-memory database. Clones share the same tables.
+#[derive(Clone, Default)]
+pub struct MemoryDatabase {
+    tables: Arc<Mutex<Tables>>,
+}
+
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Pin {
+    channel_id: i64,
+    pinned_by_user_id: Option<i64>,
+    pinned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct Totp {
+    secret: String,
+    enabled: bool,
+    last_used_step: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+struct EmailToken {
+    user_id: i64,
+    purpose: String,
+    email: String,
+    expires_at: DateTime<Utc>,
+    used: bool,
+}
+
+#[derive(Default)]
+struct Tables {
+    users: BTreeMap<i64, User>,
+    servers: BTreeMap<i64, Server>,
+    // (server_id, user_id)
+    server_members: BTreeMap<(i64, i64), ServerMember>,
+    channels: BTreeMap<i64, Channel>,
+    messages: BTreeMap<i64, Message>,
+    // message_id -> pin
+    pinned_messages: BTreeMap<i64, Pin>,
+    // (channel_id, user_id)
+    direct_message_members: BTreeSet<(i64, i64)>,
+    user_totp: BTreeMap<i64, Totp>,
+    // (user_id, code_hash) -> used
+    recovery_codes: BTreeMap<(i64, String), bool>,
+    email_tokens: BTreeMap<String, EmailToken>,
+}
+
+/// Postgres keeps timestamps to the microsecond; so do we, so values
+/// compare the same after a round trip through either backend.
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(6)
+}
+
+fn user_response(user: &User) -> UserResponse {
+    UserResponse {
+        user_id: user.user_id,
+        username: user.username.clone(),
+        email: user.email.clone(),
+        avatar_url: user.avatar_url.clone(),
+        status: user.status.clone(),
+        created_at: user.created_at,
+    }
+}
+
+impl Tables {
+    fn require(&self, exists: bool, constraint: &'static str) -> Result<(), sqlx::Error> {
+        if exists {
+            Ok(())
+        } else {
+            Err(ConstraintViolation::foreign_key(constraint))
+        }
+    }
+
+    fn insert_message(&mut self, new_message: NewMessage) -> Result<Message, sqlx::Error> {
+        self.require(self.channels.contains_key(&new_message.channel_id), "messages_channel_id_fkey")?;
+        self.require(self.users.contains_key(&new_message.author_user_id), "messages_author_user_id_fkey")?;
+        if let Some(referenced_message_id) = new_message.referenced_message_id {
+            self.require(
+                self.messages.contains_key(&referenced_message_id),
+                "messages_referenced_message_id_fkey",
+            )?;
+        }
+
+        let message = Message {
+            message_id: snowflake::next_id(),
+            channel_id: new_message.channel_id,
+            author_user_id: new_message.author_user_id,
+            content: new_message.content,
+            message_type: new_message.message_type,
+            referenced_message_id: new_message.referenced_message_id,
+            created_at: now(),
+            updated_at: None,
+            edited_at: None,
+        };
+        self.messages.insert(message.message_id, message.clone());
+
+        Ok(message)
+    }
+
+    /// The system channel is the server's oldest text channel. Servers
+    /// without one get no join or leave messages.
+    fn post_system_message(
+        &mut self,
+        server_id: i64,
+        user_id: i64,
+        message_type: MessageType,
+    ) -> Result<Option<Message>, sqlx::Error> {
+        let system_channel = self
+            .channels
+            .values()
+            .filter(|channel| channel.server_id == Some(server_id) && channel.channel_type == "text")
+            .min_by_key(|channel| (channel.created_at, channel.channel_id))
+            .map(|channel| channel.channel_id);
+
+        let Some(channel_id) = system_channel else {
+            return Ok(None);
+        };
+
+        self.insert_message(NewMessage {
+            channel_id,
+            author_user_id: user_id,
+            content: String::new(),
+            message_type,
+            referenced_message_id: None,
+        })
+        .map(Some)
+    }
+
+    /// Newest first, like `ORDER BY message_id DESC`, and only those older
+    /// than `before` if given.
+    fn channel_messages(&self, channel_id: i64, before: Option<i64>, limit: i64) -> Vec<&Message> {
+        self.messages
+            .range(..before.unwrap_or(i64::MAX))
+            .rev()
+            .map(|(_, message)| message)
+            .filter(|message| message.channel_id == channel_id)
+            .take(limit.max(0) as usize)
+            .collect()
+    }
+
+    fn check_dm_member(&self, channel_id: i64, user_id: i64) -> Result<(), sqlx::Error> {
+        self.require(self.channels.contains_key(&channel_id), "direct_message_members_channel_id_fkey")?;
+        self.require(self.users.contains_key(&user_id), "direct_message_members_user_id_fkey")
+    }
+
+    fn with_author(&self, message: &Message) -> MessageWithAuthorResponse {
+        MessageWithAuthorResponse {
+            message_id: message.message_id,
+            channel_id: message.channel_id,
+            content: message.content.clone(),
+            message_type: message.message_type,
+            referenced_message_id: message.referenced_message_id,
+            author: user_response(&self.users[&message.author_user_id]),
+            created_at: message.created_at,
+            edited_at: message.edited_at,
+        }
+    }
+
+    fn dm_channels_of(&self, user_id: i64) -> Vec<Channel> {
+        self.direct_message_members
+            .iter()
+            .filter(|&&(_, member)| member == user_id)
+            .filter_map(|(channel_id, _)| self.channels.get(channel_id))
+            .filter(|channel| channel.channel_type == "dm")
+            .cloned()
+            .collect()
+    }
+
+    // The ON DELETE actions of the schema
+
+    fn delete_user(&mut self, user_id: i64) -> bool {
+        if self.users.remove(&user_id).is_none() {
+            return false;
+        }
+
+        let owned: Vec<i64> = self
+            .servers
+            .values()
+            .filter(|server| server.owner_user_id == user_id)
+            .map(|server| server.server_id)
+            .collect();
+        for server_id in owned {
+            self.delete_server(server_id);
+        }
+
+        let authored: Vec<i64> = self
+            .messages
+            .values()
+            .filter(|message| message.author_user_id == user_id)
+            .map(|message| message.message_id)
+            .collect();
+        for message_id in authored {
+            self.delete_message(message_id);
+        }
+
+        self.server_members.retain(|&(_, member), _| member != user_id);
+        self.direct_message_members.retain(|&(_, member)| member != user_id);
+        for pin in self.pinned_messages.values_mut() {
+            if pin.pinned_by_user_id == Some(user_id) {
+                pin.pinned_by_user_id = None;
+            }
+        }
+        self.user_totp.remove(&user_id);
+        self.recovery_codes.retain(|(owner, _), _| *owner != user_id);
+        self.email_tokens.retain(|_, token| token.user_id != user_id);
+
+        true
+    }
+
+    fn delete_server(&mut self, server_id: i64) -> bool {
+        if self.servers.remove(&server_id).is_none() {
+            return false;
+        }
+
+        self.server_members.retain(|&(server, _), _| server != server_id);
+        let channels: Vec<i64> = self
+            .channels
+            .values()
+            .filter(|channel| channel.server_id == Some(server_id))
+            .map(|channel| channel.channel_id)
+            .collect();
+        for channel_id in channels {
+            self.delete_channel(channel_id);
+        }
+
+        true
+    }
+
+    fn delete_channel(&mut self, channel_id: i64) -> bool {
+        if self.channels.remove(&channel_id).is_none() {
+            return false;
+        }
+
+        self.delete_channel_messages(channel_id);
+        self.direct_message_members.retain(|&(channel, _)| channel != channel_id);
+        self.pinned_messages.retain(|_, pin| pin.channel_id != channel_id);
+
+        true
+    }
+
+    fn delete_channel_messages(&mut self, channel_id: i64) {
+        let messages: Vec<i64> = self
+            .messages
+            .values()
+            .filter(|message| message.channel_id == channel_id)
+            .map(|message| message.message_id)
+            .collect();
+        for message_id in messages {
+            self.delete_message(message_id);
+        }
+    }
+
+    fn delete_message(&mut self, message_id: i64) -> bool {
+        if self.messages.remove(&message_id).is_none() {
+            return false;
+        }
+
+        self.pinned_messages.remove(&message_id);
+        for message in self.messages.values_mut() {
+            if message.referenced_message_id == Some(message_id) {
+                message.referenced_message_id = None;
+            }
+        }
+
+        true
+    }
+}
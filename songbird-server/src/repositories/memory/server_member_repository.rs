@@ -0,0 +1,96 @@
+use super::{now, ConstraintViolation, MemoryDatabase};
+use crate::models::models::{Message, MessageType, NewServerMember, ServerMember};
+use crate::repositories::ServerMemberRepository;
+use async_trait::async_trait;
+
+#[async_trait]
+impl ServerMemberRepository for MemoryDatabase {
+    async fn create(&self, new_server_member: NewServerMember) -> Result<(ServerMember, Option<Message>), sqlx::Error> {
+        let mut tables = self.lock();
+        let key = (new_server_member.server_id, new_server_member.user_id);
+        if tables.server_members.contains_key(&key) {
+            return Err(ConstraintViolation::unique("server_members_pkey"));
+        }
+        tables.require(tables.servers.contains_key(&key.0), "server_members_server_id_fkey")?;
+        tables.require(tables.users.contains_key(&key.1), "server_members_user_id_fkey")?;
+
+        let server_member = ServerMember {
+            server_id: new_server_member.server_id,
+            user_id: new_server_member.user_id,
+            nickname: new_server_member.nickname,
+            permissions: 0,
+            joined_at: now(),
+        };
+        tables.server_members.insert(key, server_member.clone());
+
+        let system_message = tables.post_system_message(key.0, key.1, MessageType::MemberJoin)?;
+
+        Ok((server_member, system_message))
+    }
+
+    async fn find_by_id(&self, server_id: i64, user_id: i64) -> Result<Option<ServerMember>, sqlx::Error> {
+        Ok(self.lock().server_members.get(&(server_id, user_id)).cloned())
+    }
+
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .server_members
+            .values()
+            .filter(|member| member.server_id == server_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_by_user(&self, user_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .server_members
+            .values()
+            .filter(|member| member.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_nickname(&self, server_id: i64, user_id: i64, nickname: Option<String>) -> Result<ServerMember, sqlx::Error> {
+        let mut tables = self.lock();
+        let member = tables
+            .server_members
+            .get_mut(&(server_id, user_id))
+            .ok_or(sqlx::Error::RowNotFound)?;
+        member.nickname = nickname;
+
+        Ok(member.clone())
+    }
+
+    async fn update_permissions(&self, server_id: i64, user_id: i64, permissions: i64) -> Result<Option<ServerMember>, sqlx::Error> {
+        Ok(self.lock().server_members.get_mut(&(server_id, user_id)).map(|member| {
+            member.permissions = permissions;
+            member.clone()
+        }))
+    }
+
+    async fn delete(&self, server_id: i64, user_id: i64) -> Result<(bool, Option<Message>), sqlx::Error> {
+        let mut tables = self.lock();
+        if tables.server_members.remove(&(server_id, user_id)).is_none() {
+            return Ok((false, None));
+        }
+
+        let system_message = tables.post_system_message(server_id, user_id, MessageType::MemberLeave)?;
+
+        Ok((true, system_message))
+    }
+
+    async fn is_member(&self, server_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().server_members.contains_key(&(server_id, user_id)))
+    }
+
+    async fn count_members(&self, server_id: i64) -> Result<i64, sqlx::Error> {
+        Ok(self
+            .lock()
+            .server_members
+            .keys()
+            .filter(|&&(server, _)| server == server_id)
+            .count() as i64)
+    }
+}
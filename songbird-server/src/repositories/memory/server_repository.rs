@@ -0,0 +1,100 @@
+use super::{now, user_response, ConstraintViolation, MemoryDatabase, Tables};
+use crate::models::models::{NewServer, Server, UserResponse};
+use crate::repositories::ServerRepository;
+use crate::snowflake;
+use async_trait::async_trait;
+
+impl Tables {
+    fn check_server(&self, server_id: i64, server_name: &str, owner_user_id: i64) -> Result<(), sqlx::Error> {
+        if self
+            .servers
+            .values()
+            .any(|other| other.server_id != server_id && other.server_name == server_name)
+        {
+            return Err(ConstraintViolation::unique("servers_server_name_key"));
+        }
+        self.require(self.users.contains_key(&owner_user_id), "servers_owner_user_id_fkey")
+    }
+}
+
+#[async_trait]
+impl ServerRepository for MemoryDatabase {
+    async fn create(&self, new_server: NewServer) -> Result<Server, sqlx::Error> {
+        let mut tables = self.lock();
+        tables.check_server(0, &new_server.server_name, new_server.owner_user_id)?;
+
+        let server = Server {
+            server_id: snowflake::next_id(),
+            server_name: new_server.server_name,
+            owner_user_id: new_server.owner_user_id,
+            icon_url: new_server.icon_url,
+            mfa_required: false,
+            created_at: now(),
+            updated_at: None,
+        };
+        tables.servers.insert(server.server_id, server.clone());
+
+        Ok(server)
+    }
+
+    async fn find_by_id(&self, server_id: i64) -> Result<Option<Server>, sqlx::Error> {
+        Ok(self.lock().servers.get(&server_id).cloned())
+    }
+
+    async fn find_by_owner(&self, owner_user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .servers
+            .values()
+            .filter(|server| server.owner_user_id == owner_user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_all(&self) -> Result<Vec<Server>, sqlx::Error> {
+        Ok(self.lock().servers.values().cloned().collect())
+    }
+
+    async fn find_servers_for_user(&self, user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
+        let tables = self.lock();
+        Ok(tables
+            .server_members
+            .keys()
+            .filter(|&&(_, member)| member == user_id)
+            .filter_map(|(server_id, _)| tables.servers.get(server_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(&self, server_id: i64, server: Server) -> Result<Server, sqlx::Error> {
+        let mut tables = self.lock();
+        if !tables.servers.contains_key(&server_id) {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        tables.check_server(server_id, &server.server_name, server.owner_user_id)?;
+
+        let stored = tables.servers.get_mut(&server_id).unwrap();
+        stored.server_name = server.server_name;
+        stored.owner_user_id = server.owner_user_id;
+        stored.icon_url = server.icon_url;
+        stored.mfa_required = server.mfa_required;
+        stored.updated_at = Some(now());
+
+        Ok(stored.clone())
+    }
+
+    async fn delete(&self, server_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().delete_server(server_id))
+    }
+
+    async fn get_server_members(&self, server_id: i64) -> Result<Vec<UserResponse>, sqlx::Error> {
+        let tables = self.lock();
+        Ok(tables
+            .server_members
+            .keys()
+            .filter(|&&(server, _)| server == server_id)
+            .filter_map(|(_, user_id)| tables.users.get(user_id))
+            .map(user_response)
+            .collect())
+    }
+}
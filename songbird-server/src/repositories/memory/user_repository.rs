@@ -0,0 +1,223 @@
+use super::{now, ConstraintViolation, MemoryDatabase, Tables};
+use crate::models::models::{CustomStatus, NewUser, User};
+use crate::repositories::UserRepository;
+use crate::snowflake;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+
+impl Tables {
+    fn check_user_unique(&self, user_id: i64, username: &str, email: &str) -> Result<(), sqlx::Error> {
+        for other in self.users.values().filter(|other| other.user_id != user_id) {
+            if other.username == username {
+                return Err(ConstraintViolation::unique("users_username_key"));
+            }
+            if other.email == email {
+                return Err(ConstraintViolation::unique("users_email_key"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryDatabase {
+    async fn create(&self, new_user: NewUser) -> Result<User, sqlx::Error> {
+        let mut tables = self.lock();
+        tables.check_user_unique(0, &new_user.username, &new_user.email)?;
+
+        let user = User {
+            user_id: snowflake::next_id(),
+            username: new_user.username,
+            email: new_user.email,
+            password_hash: new_user.password_hash,
+            avatar_url: new_user.avatar_url,
+            created_at: now(),
+            updated_at: None,
+            status: new_user.status,
+            preferred_status: "online".to_string(),
+            custom_status_text: None,
+            custom_status_emoji: None,
+            custom_status_expires_at: None,
+            email_verified: false,
+            disabled_at: None,
+            sessions_revoked_at: None,
+        };
+        tables.users.insert(user.user_id, user.clone());
+
+        Ok(user)
+    }
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+        Ok(self.lock().users.get(&user_id).cloned())
+    }
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        Ok(self.lock().users.values().find(|user| user.username == username).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        Ok(self.lock().users.values().find(|user| user.email == email).cloned())
+    }
+
+    async fn find_all(&self) -> Result<Vec<User>, sqlx::Error> {
+        let mut users: Vec<User> = self.lock().users.values().cloned().collect();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        Ok(users)
+    }
+
+    async fn update(&self, user_id: i64, user: User) -> Result<User, sqlx::Error> {
+        let mut tables = self.lock();
+        if !tables.users.contains_key(&user_id) {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        tables.check_user_unique(user_id, &user.username, &user.email)?;
+
+        let stored = tables.users.get_mut(&user_id).unwrap();
+        stored.email_verified = stored.email_verified && stored.email == user.email;
+        stored.username = user.username;
+        stored.email = user.email;
+        stored.password_hash = user.password_hash;
+        stored.avatar_url = user.avatar_url;
+        stored.updated_at = Some(now());
+        stored.preferred_status = user.preferred_status;
+
+        Ok(stored.clone())
+    }
+
+    async fn delete(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().delete_user(user_id))
+    }
+
+    async fn mark_email_verified(&self, user_id: i64, email: &str) -> Result<bool, sqlx::Error> {
+        match self.lock().users.get_mut(&user_id) {
+            Some(user) if user.email == email => {
+                user.email_verified = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().users.get_mut(&user_id).map(|user| {
+            user.password_hash = password_hash.to_string();
+            user.updated_at = Some(now());
+        }).is_some())
+    }
+
+    async fn set_disabled(&self, user_id: i64, disabled: bool) -> Result<bool, sqlx::Error> {
+        let now = now();
+        Ok(self.lock().users.get_mut(&user_id).map(|user| {
+            if disabled {
+                user.disabled_at = user.disabled_at.or(Some(now));
+                user.sessions_revoked_at = Some(now);
+            } else {
+                user.disabled_at = None;
+            }
+        }).is_some())
+    }
+
+    async fn revoke_sessions(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().users.get_mut(&user_id).map(|user| user.sessions_revoked_at = Some(now())).is_some())
+    }
+
+    async fn purge_disabled(&self, disabled_before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut tables = self.lock();
+        let disabled: Vec<i64> = tables
+            .users
+            .values()
+            .filter(|user| user.disabled_at.is_some_and(|disabled_at| disabled_at < disabled_before))
+            .map(|user| user.user_id)
+            .collect();
+        for &user_id in &disabled {
+            tables.delete_user(user_id);
+        }
+        Ok(disabled.len() as u64)
+    }
+
+    async fn update_status(&self, user_id: i64, status: &str) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().users.get_mut(&user_id).map(|user| user.status = status.to_string()).is_some())
+    }
+
+    async fn update_preferred_status(&self, user_id: i64, preferred_status: &str) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .lock()
+            .users
+            .get_mut(&user_id)
+            .map(|user| user.preferred_status = preferred_status.to_string())
+            .is_some())
+    }
+
+    async fn update_custom_status(&self, user_id: i64, custom_status: Option<CustomStatus>) -> Result<bool, sqlx::Error> {
+        let (text, emoji, expires_at) = match custom_status {
+            Some(status) => (status.text, status.emoji, status.expires_at),
+            None => (None, None, None),
+        };
+
+        Ok(self.lock().users.get_mut(&user_id).map(|user| {
+            user.custom_status_text = text;
+            user.custom_status_emoji = emoji;
+            user.custom_status_expires_at = expires_at;
+        }).is_some())
+    }
+
+    async fn clear_expired_custom_statuses(&self) -> Result<Vec<i64>, sqlx::Error> {
+        let now = now();
+        let mut cleared = Vec::new();
+        for user in self.lock().users.values_mut() {
+            if user.custom_status_expires_at.is_some_and(|expires_at| expires_at <= now) {
+                user.custom_status_text = None;
+                user.custom_status_emoji = None;
+                user.custom_status_expires_at = None;
+                cleared.push(user.user_id);
+            }
+        }
+        Ok(cleared)
+    }
+
+    async fn reset_all_statuses(&self) -> Result<u64, sqlx::Error> {
+        let mut reset = 0;
+        for user in self.lock().users.values_mut() {
+            if user.status != "offline" {
+                user.status = "offline".to_string();
+                reset += 1;
+            }
+        }
+        Ok(reset)
+    }
+
+    async fn find_presence_audience(&self, user_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        let tables = self.lock();
+
+        let servers: BTreeSet<i64> = tables
+            .server_members
+            .keys()
+            .filter(|&&(_, member)| member == user_id)
+            .map(|&(server_id, _)| server_id)
+            .collect();
+        let channels: BTreeSet<i64> = tables
+            .direct_message_members
+            .iter()
+            .filter(|&&(_, member)| member == user_id)
+            .map(|&(channel_id, _)| channel_id)
+            .collect();
+
+        let audience: BTreeSet<i64> = tables
+            .server_members
+            .keys()
+            .filter(|(server_id, _)| servers.contains(server_id))
+            .map(|&(_, member)| member)
+            .chain(
+                tables
+                    .direct_message_members
+                    .iter()
+                    .filter(|(channel_id, _)| channels.contains(channel_id))
+                    .map(|&(_, member)| member),
+            )
+            .filter(|&member| member != user_id)
+            .collect();
+
+        Ok(audience.into_iter().collect())
+    }
+}
@@ -1,28 +1,109 @@
-use sqlx::{Pool, Postgres};
+use crate::models::models::{Message, MessageWithAuthorResponse, NewMessage, UserResponse};
+use crate::snowflake;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use crate::models::models::{Message, NewMessage, MessageWithAuthorResponse, UserResponse};
+use sqlx::{Pool, Postgres, Transaction};
 
-pub struct MessageRepository {
+#[derive(Debug, PartialEq, Eq)]
+pub enum PinOutcome {
+    Pinned,
+    AlreadyPinned,
+    LimitReached,
+}
+
+#[async_trait]
+pub trait MessageRepository: Send + Sync {
+    async fn create(&self, new_message: NewMessage) -> Result<Message, sqlx::Error>;
+
+    async fn find_by_id(&self, message_id: i64) -> Result<Option<Message>, sqlx::Error>;
+
+    /// The latest `limit` messages of the channel, newest first.
+    async fn find_by_channel(&self, channel_id: i64, limit: i64) -> Result<Vec<Message>, sqlx::Error>;
+
+    /// Like `find_by_channel`, with each message's author. With `before`,
+    /// only messages older than that message ID, for paging back through
+    /// the history.
+    async fn find_by_channel_with_authors(
+        &self,
+        channel_id: i64,
+        before: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error>;
+
+    async fn update_content(&self, message_id: i64, content: String) -> Result<Message, sqlx::Error>;
+
+    async fn delete(&self, message_id: i64) -> Result<bool, sqlx::Error>;
+
+    /// Pins the message unless it already is or the channel has `max_pins`
+    /// pins. Concurrent pins cannot both slip under the cap.
+    async fn pin(&self, channel_id: i64, message_id: i64, pinned_by_user_id: i64, max_pins: i64) -> Result<PinOutcome, sqlx::Error>;
+
+    async fn unpin(&self, channel_id: i64, message_id: i64) -> Result<bool, sqlx::Error>;
+
+    /// The channel's pinned messages, most recently pinned first.
+    async fn find_pinned_with_authors(&self, channel_id: i64) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error>;
+
+    async fn count_by_channel(&self, channel_id: i64) -> Result<i64, sqlx::Error>;
+
+    async fn count_by_user(&self, user_id: i64) -> Result<i64, sqlx::Error>;
+
+    /// The latest `limit` messages of the channel that contain every word
+    /// of `query`, newest first, with their authors. See `search_terms`
+    /// for what counts as a word in the query.
+    ///
+    /// Every backend splits message content at punctuation and whitespace
+    /// the same way, so they agree on ASCII text; `search_test.rs` checks
+    /// that. Letters outside ASCII are where they can differ: Postgres
+    /// treats them according to the database's `LC_CTYPE`, and under the C
+    /// locale they separate words (`café` is indexed as `caf`, so `caf`
+    /// finds it there but nowhere else).
+    async fn search_with_authors(
+        &self,
+        channel_id: i64,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error>;
+}
+
+/// The words of a search, lowercased. Everything but letters and digits
+/// separates words, so nothing in a query is search syntax on any backend.
+pub fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct PgMessageRepository {
     pool: Pool<Postgres>,
 }
 
-impl MessageRepository {
+impl PgMessageRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
         Self { pool }
     }
 
-    pub async fn create(&self, new_message: NewMessage) -> Result<Message, sqlx::Error> {
+    /// Inserts a message as part of a larger transaction, used by other
+    /// repositories to write system messages atomically with their own change.
+    #[tracing::instrument(name = "MessageRepository::create_tx", skip_all)]
+    pub async fn create_tx(tx: &mut Transaction<'_, Postgres>, new_message: NewMessage) -> Result<Message, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            INSERT INTO messages (channel_id, author_user_id, content)
-            VALUES ($1, $2, $3)
-            RETURNING message_id, channel_id, author_user_id, content, created_at, updated_at, edited_at
+            INSERT INTO messages (message_id, channel_id, author_user_id, content, message_type, referenced_message_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING message_id, channel_id, author_user_id, content, message_type, referenced_message_id,
+                      created_at, updated_at, edited_at
             "#,
+            snowflake::next_id(),
             new_message.channel_id,
             new_message.author_user_id,
-            new_message.content
+            new_message.content,
+            new_message.message_type.as_str(),
+            new_message.referenced_message_id
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut **tx)
         .await?;
 
         let message = Message {
@@ -30,6 +111,8 @@ impl MessageRepository {
             channel_id: record.channel_id,
             author_user_id: record.author_user_id,
             content: record.content,
+            message_type: record.message_type.parse().unwrap_or_default(),
+            referenced_message_id: record.referenced_message_id,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
             edited_at: record.edited_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
@@ -37,11 +120,25 @@ impl MessageRepository {
 
         Ok(message)
     }
+}
+
+#[async_trait]
+impl MessageRepository for PgMessageRepository {
+    #[tracing::instrument(name = "MessageRepository::create", skip_all)]
+    async fn create(&self, new_message: NewMessage) -> Result<Message, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let message = Self::create_tx(&mut tx, new_message).await?;
+        tx.commit().await?;
 
-    pub async fn find_by_id(&self, message_id: i32) -> Result<Option<Message>, sqlx::Error> {
+        Ok(message)
+    }
+
+    #[tracing::instrument(name = "MessageRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, message_id: i64) -> Result<Option<Message>, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            SELECT message_id, channel_id, author_user_id, content, created_at, updated_at, edited_at
+            SELECT message_id, channel_id, author_user_id, content, message_type, referenced_message_id,
+                   created_at, updated_at, edited_at
             FROM messages
             WHERE message_id = $1
             "#,
@@ -55,6 +152,8 @@ impl MessageRepository {
             channel_id: r.channel_id,
             author_user_id: r.author_user_id,
             content: r.content,
+            message_type: r.message_type.parse().unwrap_or_default(),
+            referenced_message_id: r.referenced_message_id,
             created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
             updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
             edited_at: r.edited_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
@@ -63,13 +162,15 @@ impl MessageRepository {
         Ok(message)
     }
 
-    pub async fn find_by_channel(&self, channel_id: i32, limit: i64) -> Result<Vec<Message>, sqlx::Error> {
+    #[tracing::instrument(name = "MessageRepository::find_by_channel", skip_all)]
+    async fn find_by_channel(&self, channel_id: i64, limit: i64) -> Result<Vec<Message>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT message_id, channel_id, author_user_id, content, created_at, updated_at, edited_at
+            SELECT message_id, channel_id, author_user_id, content, message_type, referenced_message_id,
+                   created_at, updated_at, edited_at
             FROM messages
             WHERE channel_id = $1
-            ORDER BY created_at DESC
+            ORDER BY message_id DESC
             LIMIT $2
             "#,
             channel_id,
@@ -85,6 +186,8 @@ impl MessageRepository {
                 channel_id: r.channel_id,
                 author_user_id: r.author_user_id,
                 content: r.content,
+                message_type: r.message_type.parse().unwrap_or_default(),
+                referenced_message_id: r.referenced_message_id,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
                 edited_at: r.edited_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
@@ -94,19 +197,26 @@ impl MessageRepository {
         Ok(messages)
     }
 
-    pub async fn find_by_channel_with_authors(&self, channel_id: i32, limit: i64) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+    #[tracing::instrument(name = "MessageRepository::find_by_channel_with_authors", skip_all)]
+    async fn find_by_channel_with_authors(
+        &self,
+        channel_id: i64,
+        before: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
             SELECT 
-                m.message_id, m.content, m.created_at, m.edited_at,
+                m.message_id, m.channel_id, m.content, m.message_type, m.referenced_message_id, m.created_at, m.edited_at,
                 u.user_id, u.username, u.email, u.avatar_url, u.created_at as user_created_at, u.status
             FROM messages m
             JOIN users u ON m.author_user_id = u.user_id
-            WHERE m.channel_id = $1
-            ORDER BY m.created_at DESC
-            LIMIT $2
+            WHERE m.channel_id = $1 AND ($2::BIGINT IS NULL OR m.message_id < $2)
+            ORDER BY m.message_id DESC
+            LIMIT $3
             "#,
             channel_id,
+            before,
             limit
         )
         .fetch_all(&self.pool)
@@ -115,7 +225,10 @@ impl MessageRepository {
         let message_responses = records.into_iter().map(|r| {
             MessageWithAuthorResponse {
                 message_id: r.message_id,
+                channel_id: r.channel_id,
                 content: r.content,
+                message_type: r.message_type.parse().unwrap_or_default(),
+                referenced_message_id: r.referenced_message_id,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 edited_at: r.edited_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
                 author: UserResponse {
@@ -132,14 +245,16 @@ impl MessageRepository {
         Ok(message_responses)
     }
 
-    pub async fn update_content(&self, message_id: i32, content: String) -> Result<Message, sqlx::Error> {
+    #[tracing::instrument(name = "MessageRepository::update_content", skip_all)]
+    async fn update_content(&self, message_id: i64, content: String) -> Result<Message, sqlx::Error> {
         let now = Utc::now();
         let record = sqlx::query!(
             r#"
             UPDATE messages
             SET content = $1, updated_at = $2, edited_at = $2
             WHERE message_id = $3
-            RETURNING message_id, channel_id, author_user_id, content, created_at, updated_at, edited_at
+            RETURNING message_id, channel_id, author_user_id, content, message_type, referenced_message_id,
+                      created_at, updated_at, edited_at
             "#,
             content,
             now as _,
@@ -153,6 +268,8 @@ impl MessageRepository {
             channel_id: record.channel_id,
             author_user_id: record.author_user_id,
             content: record.content,
+            message_type: record.message_type.parse().unwrap_or_default(),
+            referenced_message_id: record.referenced_message_id,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
             edited_at: record.edited_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc))
@@ -161,7 +278,8 @@ impl MessageRepository {
         Ok(updated_message)
     }
 
-    pub async fn delete(&self, message_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "MessageRepository::delete", skip_all)]
+    async fn delete(&self, message_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             DELETE FROM messages
@@ -175,7 +293,129 @@ impl MessageRepository {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn count_by_channel(&self, channel_id: i32) -> Result<i64, sqlx::Error> {
+    #[tracing::instrument(name = "MessageRepository::pin", skip_all)]
+    async fn pin(&self, channel_id: i64, message_id: i64, pinned_by_user_id: i64, max_pins: i64) -> Result<PinOutcome, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Lock the channel so two concurrent pins cannot both slip under the cap
+        sqlx::query!(
+            r#"
+            SELECT channel_id
+            FROM channels
+            WHERE channel_id = $1
+            FOR UPDATE
+            "#,
+            channel_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let existing = sqlx::query!(
+            r#"
+            SELECT 1 as exists
+            FROM pinned_messages
+            WHERE message_id = $1
+            "#,
+            message_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if existing.is_some() {
+            return Ok(PinOutcome::AlreadyPinned);
+        }
+
+        let pinned = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count
+            FROM pinned_messages
+            WHERE channel_id = $1
+            "#,
+            channel_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if pinned.count.unwrap_or(0) >= max_pins {
+            return Ok(PinOutcome::LimitReached);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO pinned_messages (message_id, channel_id, pinned_by_user_id)
+            VALUES ($1, $2, $3)
+            "#,
+            message_id,
+            channel_id,
+            pinned_by_user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(PinOutcome::Pinned)
+    }
+
+    #[tracing::instrument(name = "MessageRepository::unpin", skip_all)]
+    async fn unpin(&self, channel_id: i64, message_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM pinned_messages
+            WHERE channel_id = $1 AND message_id = $2
+            "#,
+            channel_id,
+            message_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MessageRepository::find_pinned_with_authors", skip_all)]
+    async fn find_pinned_with_authors(&self, channel_id: i64) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                m.message_id, m.channel_id, m.content, m.message_type, m.referenced_message_id, m.created_at, m.edited_at,
+                u.user_id, u.username, u.email, u.avatar_url, u.created_at as user_created_at, u.status
+            FROM pinned_messages p
+            JOIN messages m ON p.message_id = m.message_id
+            JOIN users u ON m.author_user_id = u.user_id
+            WHERE p.channel_id = $1
+            ORDER BY p.pinned_at DESC
+            "#,
+            channel_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let message_responses = records.into_iter().map(|r| {
+            MessageWithAuthorResponse {
+                message_id: r.message_id,
+                channel_id: r.channel_id,
+                content: r.content,
+                message_type: r.message_type.parse().unwrap_or_default(),
+                referenced_message_id: r.referenced_message_id,
+                created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
+                edited_at: r.edited_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+                author: UserResponse {
+                    user_id: r.user_id,
+                    username: r.username,
+                    email: r.email,
+                    avatar_url: r.avatar_url,
+                    status: r.status,
+                    created_at: DateTime::from_naive_utc_and_offset(r.user_created_at, Utc),
+                },
+            }
+        }).collect();
+
+        Ok(message_responses)
+    }
+
+    #[tracing::instrument(name = "MessageRepository::count_by_channel", skip_all)]
+    async fn count_by_channel(&self, channel_id: i64) -> Result<i64, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             SELECT COUNT(*) as count
@@ -190,7 +430,8 @@ impl MessageRepository {
         Ok(result.count.unwrap_or(0))
     }
 
-    pub async fn count_by_user(&self, user_id: i32) -> Result<i64, sqlx::Error> {
+    #[tracing::instrument(name = "MessageRepository::count_by_user", skip_all)]
+    async fn count_by_user(&self, user_id: i64) -> Result<i64, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             SELECT COUNT(*) as count
@@ -204,4 +445,61 @@ impl MessageRepository {
 
         Ok(result.count.unwrap_or(0))
     }
-}
\ No newline at end of file
+
+    #[tracing::instrument(name = "MessageRepository::search_with_authors", skip_all)]
+    async fn search_with_authors(
+        &self,
+        channel_id: i64,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // plainto_tsquery ANDs the words, and the expression matches
+        // messages_content_search_idx, which splits at punctuation first
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                m.message_id, m.channel_id, m.content, m.message_type, m.referenced_message_id, m.created_at, m.edited_at,
+                u.user_id, u.username, u.email, u.avatar_url, u.created_at as user_created_at, u.status
+            FROM messages m
+            JOIN users u ON m.author_user_id = u.user_id
+            WHERE m.channel_id = $1
+              AND to_tsvector('simple', regexp_replace(m.content, '[[:punct:][:space:]]+', ' ', 'g'))
+                  @@ plainto_tsquery('simple', $2)
+            ORDER BY m.message_id DESC
+            LIMIT $3
+            "#,
+            channel_id,
+            terms.join(" "),
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let message_responses = records.into_iter().map(|r| {
+            MessageWithAuthorResponse {
+                message_id: r.message_id,
+                channel_id: r.channel_id,
+                content: r.content,
+                message_type: r.message_type.parse().unwrap_or_default(),
+                referenced_message_id: r.referenced_message_id,
+                created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
+                edited_at: r.edited_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+                author: UserResponse {
+                    user_id: r.user_id,
+                    username: r.username,
+                    email: r.email,
+                    avatar_url: r.avatar_url,
+                    status: r.status,
+                    created_at: DateTime::from_naive_utc_and_offset(r.user_created_at, Utc),
+                },
+            }
+        }).collect();
+
+        Ok(message_responses)
+    }
+}
@@ -0,0 +1,213 @@
+use async_trait::async_trait;
+use sqlx::{Pool, Postgres};
+
+/// A user's TOTP enrollment.
+#[derive(Debug, Clone)]
+pub struct UserTotp {
+    pub user_id: i64,
+    pub secret: String,
+    pub enabled: bool,
+}
+
+#[async_trait]
+pub trait MfaRepository: Send + Sync {
+    async fn find_totp(&self, user_id: i64) -> Result<Option<UserTotp>, sqlx::Error>;
+
+    async fn is_enabled(&self, user_id: i64) -> Result<bool, sqlx::Error>;
+
+    /// Starts (or restarts) enrollment with a new secret. Does nothing and
+    /// returns `false` if 2FA is already enabled.
+    async fn begin_enrollment(&self, user_id: i64, secret: &str) -> Result<bool, sqlx::Error>;
+
+    /// Turns 2FA on and replaces the user's recovery codes.
+    async fn confirm_enrollment(&self, user_id: i64, step: i64, recovery_code_hashes: &[String]) -> Result<bool, sqlx::Error>;
+
+    /// Removes the enrollment and any recovery codes.
+    async fn disable(&self, user_id: i64) -> Result<bool, sqlx::Error>;
+
+    /// Records that a code for `step` was used. Returns `false` if that step
+    /// or a later one was already used, i.e. the code is a replay.
+    async fn consume_step(&self, user_id: i64, step: i64) -> Result<bool, sqlx::Error>;
+
+    /// Marks an unused recovery code as used. Returns `false` if there is no
+    /// such unused code.
+    async fn consume_recovery_code(&self, user_id: i64, code_hash: &str) -> Result<bool, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PgMfaRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PgMfaRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl MfaRepository for PgMfaRepository {
+    #[tracing::instrument(name = "MfaRepository::find_totp", skip_all)]
+    async fn find_totp(&self, user_id: i64) -> Result<Option<UserTotp>, sqlx::Error> {
+        let record = sqlx::query!(
+            r#"
+            SELECT user_id, secret, enabled
+            FROM user_totp
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record.map(|r| UserTotp {
+            user_id: r.user_id,
+            secret: r.secret,
+            enabled: r.enabled,
+        }))
+    }
+
+    #[tracing::instrument(name = "MfaRepository::is_enabled", skip_all)]
+    async fn is_enabled(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let record = sqlx::query!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM user_totp WHERE user_id = $1 AND enabled
+            ) as "enabled!"
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(record.enabled)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::begin_enrollment", skip_all)]
+    async fn begin_enrollment(&self, user_id: i64, secret: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO user_totp (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = CURRENT_TIMESTAMP
+            WHERE NOT user_totp.enabled
+            "#,
+            user_id,
+            secret
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::confirm_enrollment", skip_all)]
+    async fn confirm_enrollment(&self, user_id: i64, step: i64, recovery_code_hashes: &[String]) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET enabled = TRUE, last_used_step = $2
+            WHERE user_id = $1 AND NOT enabled
+            "#,
+            user_id,
+            step
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (user_id, code_hash)
+            SELECT $1, UNNEST($2::VARCHAR[])
+            "#,
+            user_id,
+            recovery_code_hashes
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::disable", skip_all)]
+    async fn disable(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM user_totp
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::consume_step", skip_all)]
+    async fn consume_step(&self, user_id: i64, step: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_totp
+            SET last_used_step = $2
+            WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id,
+            step
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::consume_recovery_code", skip_all)]
+    async fn consume_recovery_code(&self, user_id: i64, code_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE recovery_codes
+            SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,14 +1,93 @@
 // src/repositories/mod.rs
+mod cached;
 pub mod channel_repository;
+mod constraint_violation;
 pub mod direct_message_repository;
+pub mod email_token_repository;
+pub mod health_repository;
+pub mod memory;
 pub mod message_repository;
+pub mod mfa_repository;
 pub mod server_member_repository;
 pub mod server_repository;
+pub mod sqlite;
 pub mod user_repository;
 
-pub use channel_repository::ChannelRepository;
-pub use direct_message_repository::DirectMessageRepository;
-pub use message_repository::MessageRepository;
-pub use server_member_repository::ServerMemberRepository;
-pub use server_repository::ServerRepository;
-pub use user_repository::UserRepository;
+pub use cached::RepositoryCache;
+pub use channel_repository::{ChannelRepository, PgChannelRepository};
+pub use direct_message_repository::{DirectMessageRepository, PgDirectMessageRepository};
+pub use email_token_repository::{EmailTokenRepository, PgEmailTokenRepository};
+pub use health_repository::{HealthRepository, PgHealthRepository};
+pub use memory::MemoryDatabase;
+pub use message_repository::{search_terms, MessageRepository, PgMessageRepository, PinOutcome};
+pub use mfa_repository::{MfaRepository, PgMfaRepository};
+pub use server_member_repository::{PgServerMemberRepository, ServerMemberRepository};
+pub use server_repository::{PgServerRepository, ServerRepository};
+pub use sqlite::SqliteDatabase;
+pub use user_repository::{PgUserRepository, UserRepository};
+
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+
+/// One backend's implementation of every repository.
+#[derive(Clone)]
+pub struct Repositories {
+    pub user_repository: Arc<dyn UserRepository>,
+    pub server_repository: Arc<dyn ServerRepository>,
+    pub server_member_repository: Arc<dyn ServerMemberRepository>,
+    pub message_repository: Arc<dyn MessageRepository>,
+    pub channel_repository: Arc<dyn ChannelRepository>,
+    pub direct_message_repository: Arc<dyn DirectMessageRepository>,
+    pub mfa_repository: Arc<dyn MfaRepository>,
+    pub email_token_repository: Arc<dyn EmailTokenRepository>,
+    pub health_repository: Arc<dyn HealthRepository>,
+}
+
+impl Repositories {
+    pub fn postgres(pool: Pool<Postgres>) -> Self {
+        let message_repository = PgMessageRepository::new(pool.clone());
+        let channel_repository = PgChannelRepository::with_message_repository(pool.clone(), message_repository.clone());
+
+        Self {
+            user_repository: Arc::new(PgUserRepository::new(pool.clone())),
+            server_repository: Arc::new(PgServerRepository::new(pool.clone())),
+            server_member_repository: Arc::new(PgServerMemberRepository::new(pool.clone())),
+            message_repository: Arc::new(message_repository),
+            channel_repository: Arc::new(channel_repository.clone()),
+            direct_message_repository: Arc::new(PgDirectMessageRepository::new(pool.clone(), channel_repository)),
+            mfa_repository: Arc::new(PgMfaRepository::new(pool.clone())),
+            email_token_repository: Arc::new(PgEmailTokenRepository::new(pool.clone())),
+            health_repository: Arc::new(PgHealthRepository::new(pool)),
+        }
+    }
+
+    /// Every repository on one set of tables in `database`.
+    pub fn in_memory(database: MemoryDatabase) -> Self {
+        Self {
+            user_repository: Arc::new(database.clone()),
+            server_repository: Arc::new(database.clone()),
+            server_member_repository: Arc::new(database.clone()),
+            message_repository: Arc::new(database.clone()),
+            channel_repository: Arc::new(database.clone()),
+            direct_message_repository: Arc::new(database.clone()),
+            mfa_repository: Arc::new(database.clone()),
+            email_token_repository: Arc::new(database.clone()),
+            health_repository: Arc::new(database),
+        }
+    }
+
+    /// Every repository on one SQLite database.
+    pub fn sqlite(database: SqliteDatabase) -> Self {
+        Self {
+            user_repository: Arc::new(database.clone()),
+            server_repository: Arc::new(database.clone()),
+            server_member_repository: Arc::new(database.clone()),
+            message_repository: Arc::new(database.clone()),
+            channel_repository: Arc::new(database.clone()),
+            direct_message_repository: Arc::new(database.clone()),
+            mfa_repository: Arc::new(database.clone()),
+            email_token_repository: Arc::new(database.clone()),
+            health_repository: Arc::new(database),
+        }
+    }
+}
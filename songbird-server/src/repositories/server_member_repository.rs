@@ -1,44 +1,117 @@
-use sqlx::{Pool, Postgres};
+use crate::models::models::{Message, MessageType, NewMessage, NewServerMember, ServerMember};
+use crate::repositories::PgMessageRepository;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use crate::models::models::{ServerMember, NewServerMember};
+use sqlx::{Pool, Postgres, Transaction};
 
-pub struct ServerMemberRepository {
+#[async_trait]
+pub trait ServerMemberRepository: Send + Sync {
+    /// Adds the member and posts a `member_join` message in the server's
+    /// system channel, if it has one, within the same transaction.
+    async fn create(&self, new_server_member: NewServerMember) -> Result<(ServerMember, Option<Message>), sqlx::Error>;
+
+    async fn find_by_id(&self, server_id: i64, user_id: i64) -> Result<Option<ServerMember>, sqlx::Error>;
+
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<ServerMember>, sqlx::Error>;
+
+    async fn find_by_user(&self, user_id: i64) -> Result<Vec<ServerMember>, sqlx::Error>;
+
+    async fn update_nickname(&self, server_id: i64, user_id: i64, nickname: Option<String>) -> Result<ServerMember, sqlx::Error>;
+
+    async fn update_permissions(&self, server_id: i64, user_id: i64, permissions: i64) -> Result<Option<ServerMember>, sqlx::Error>;
+
+    /// Removes the member and, if they were one, posts a `member_leave`
+    /// message in the server's system channel within the same transaction.
+    /// Returns whether a membership was removed along with that message.
+    async fn delete(&self, server_id: i64, user_id: i64) -> Result<(bool, Option<Message>), sqlx::Error>;
+
+    async fn is_member(&self, server_id: i64, user_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn count_members(&self, server_id: i64) -> Result<i64, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PgServerMemberRepository {
     pool: Pool<Postgres>,
 }
 
-impl ServerMemberRepository {
+impl PgServerMemberRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
         Self { pool }
     }
 
-    pub async fn create(&self, new_server_member: NewServerMember) -> Result<ServerMember, sqlx::Error> {
+    /// The system channel is the server's oldest text channel. Servers
+    /// without one get no join or leave messages.
+    #[tracing::instrument(name = "ServerMemberRepository::post_system_message_tx", skip_all)]
+    async fn post_system_message_tx(tx: &mut Transaction<'_, Postgres>, server_id: i64, user_id: i64, message_type: MessageType) -> Result<Option<Message>, sqlx::Error> {
+        let system_channel = sqlx::query!(
+            r#"
+            SELECT channel_id
+            FROM channels
+            WHERE server_id = $1 AND type = 'text'
+            ORDER BY created_at, channel_id
+            LIMIT 1
+            "#,
+            server_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        let Some(system_channel) = system_channel else {
+            return Ok(None);
+        };
+
+        let new_message = NewMessage {
+            channel_id: system_channel.channel_id,
+            author_user_id: user_id,
+            content: String::new(),
+            message_type,
+            referenced_message_id: None,
+        };
+
+        PgMessageRepository::create_tx(tx, new_message).await.map(Some)
+    }
+}
+
+#[async_trait]
+impl ServerMemberRepository for PgServerMemberRepository {
+    #[tracing::instrument(name = "ServerMemberRepository::create", skip_all)]
+    async fn create(&self, new_server_member: NewServerMember) -> Result<(ServerMember, Option<Message>), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
         let record = sqlx::query!(
             r#"
             INSERT INTO server_members (server_id, user_id, nickname)
             VALUES ($1, $2, $3)
-            RETURNING server_id, user_id, nickname, joined_at
+            RETURNING server_id, user_id, nickname, permissions, joined_at
             "#,
             new_server_member.server_id,
             new_server_member.user_id,
             new_server_member.nickname
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
 
         let server_member = ServerMember {
             server_id: record.server_id,
             user_id: record.user_id,
             nickname: record.nickname,
+            permissions: record.permissions,
             joined_at: DateTime::from_naive_utc_and_offset(record.joined_at, Utc)
         };
 
-        Ok(server_member)
+        let system_message = Self::post_system_message_tx(&mut tx, server_member.server_id, server_member.user_id, MessageType::MemberJoin).await?;
+
+        tx.commit().await?;
+
+        Ok((server_member, system_message))
     }
 
-    pub async fn find_by_id(&self, server_id: i32, user_id: i32) -> Result<Option<ServerMember>, sqlx::Error> {
+    #[tracing::instrument(name = "ServerMemberRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, server_id: i64, user_id: i64) -> Result<Option<ServerMember>, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            SELECT server_id, user_id, nickname, joined_at
+            SELECT server_id, user_id, nickname, permissions, joined_at
             FROM server_members
             WHERE server_id = $1 AND user_id = $2
             "#,
@@ -52,16 +125,18 @@ impl ServerMemberRepository {
             server_id: r.server_id,
             user_id: r.user_id,
             nickname: r.nickname,
+            permissions: r.permissions,
             joined_at: DateTime::from_naive_utc_and_offset(r.joined_at, Utc)
         });
 
         Ok(server_member)
     }
 
-    pub async fn find_by_server(&self, server_id: i32) -> Result<Vec<ServerMember>, sqlx::Error> {
+    #[tracing::instrument(name = "ServerMemberRepository::find_by_server", skip_all)]
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT server_id, user_id, nickname, joined_at
+            SELECT server_id, user_id, nickname, permissions, joined_at
             FROM server_members
             WHERE server_id = $1
             "#,
@@ -76,6 +151,7 @@ impl ServerMemberRepository {
                 server_id: r.server_id,
                 user_id: r.user_id,
                 nickname: r.nickname,
+                permissions: r.permissions,
                 joined_at: DateTime::from_naive_utc_and_offset(r.joined_at, Utc)
             })
             .collect();
@@ -83,10 +159,11 @@ impl ServerMemberRepository {
         Ok(server_members)
     }
 
-    pub async fn find_by_user(&self, user_id: i32) -> Result<Vec<ServerMember>, sqlx::Error> {
+    #[tracing::instrument(name = "ServerMemberRepository::find_by_user", skip_all)]
+    async fn find_by_user(&self, user_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT server_id, user_id, nickname, joined_at
+            SELECT server_id, user_id, nickname, permissions, joined_at
             FROM server_members
             WHERE user_id = $1
             "#,
@@ -101,6 +178,7 @@ impl ServerMemberRepository {
                 server_id: r.server_id,
                 user_id: r.user_id,
                 nickname: r.nickname,
+                permissions: r.permissions,
                 joined_at: DateTime::from_naive_utc_and_offset(r.joined_at, Utc)
             })
             .collect();
@@ -108,13 +186,14 @@ impl ServerMemberRepository {
         Ok(server_members)
     }
 
-    pub async fn update_nickname(&self, server_id: i32, user_id: i32, nickname: Option<String>) -> Result<ServerMember, sqlx::Error> {
+    #[tracing::instrument(name = "ServerMemberRepository::update_nickname", skip_all)]
+    async fn update_nickname(&self, server_id: i64, user_id: i64, nickname: Option<String>) -> Result<ServerMember, sqlx::Error> {
         let record = sqlx::query!(
             r#"
             UPDATE server_members
             SET nickname = $1
             WHERE server_id = $2 AND user_id = $3
-            RETURNING server_id, user_id, nickname, joined_at
+            RETURNING server_id, user_id, nickname, permissions, joined_at
             "#,
             nickname,
             server_id,
@@ -127,13 +206,44 @@ impl ServerMemberRepository {
             server_id: record.server_id,
             user_id: record.user_id,
             nickname: record.nickname,
+            permissions: record.permissions,
             joined_at: DateTime::from_naive_utc_and_offset(record.joined_at, Utc)
         };
 
         Ok(updated_server_member)
     }
 
-    pub async fn delete(&self, server_id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "ServerMemberRepository::update_permissions", skip_all)]
+    async fn update_permissions(&self, server_id: i64, user_id: i64, permissions: i64) -> Result<Option<ServerMember>, sqlx::Error> {
+        let record = sqlx::query!(
+            r#"
+            UPDATE server_members
+            SET permissions = $1
+            WHERE server_id = $2 AND user_id = $3
+            RETURNING server_id, user_id, nickname, permissions, joined_at
+            "#,
+            permissions,
+            server_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let updated_server_member = record.map(|r| ServerMember {
+            server_id: r.server_id,
+            user_id: r.user_id,
+            nickname: r.nickname,
+            permissions: r.permissions,
+            joined_at: DateTime::from_naive_utc_and_offset(r.joined_at, Utc)
+        });
+
+        Ok(updated_server_member)
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::delete", skip_all)]
+    async fn delete(&self, server_id: i64, user_id: i64) -> Result<(bool, Option<Message>), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
         let result = sqlx::query!(
             r#"
             DELETE FROM server_members
@@ -142,13 +252,22 @@ impl ServerMemberRepository {
             server_id,
             user_id
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(result.rows_affected() > 0)
+        if result.rows_affected() == 0 {
+            return Ok((false, None));
+        }
+
+        let system_message = Self::post_system_message_tx(&mut tx, server_id, user_id, MessageType::MemberLeave).await?;
+
+        tx.commit().await?;
+
+        Ok((true, system_message))
     }
 
-    pub async fn is_member(&self, server_id: i32, user_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "ServerMemberRepository::is_member", skip_all)]
+    async fn is_member(&self, server_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             SELECT 1 as exists
@@ -164,7 +283,8 @@ impl ServerMemberRepository {
         Ok(result.is_some())
     }
 
-    pub async fn count_members(&self, server_id: i32) -> Result<i64, sqlx::Error> {
+    #[tracing::instrument(name = "ServerMemberRepository::count_members", skip_all)]
+    async fn count_members(&self, server_id: i64) -> Result<i64, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             SELECT COUNT(*) as count
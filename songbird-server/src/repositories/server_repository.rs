@@ -1,24 +1,63 @@
 use crate::models::models::{NewServer, Server, ServerWithMembersResponse, UserResponse};
+use crate::snowflake;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use sqlx::{Pool, Postgres};
 
+#[async_trait]
+pub trait ServerRepository: Send + Sync {
+    async fn create(&self, new_server: NewServer) -> Result<Server, sqlx::Error>;
+
+    async fn find_by_id(&self, server_id: i64) -> Result<Option<Server>, sqlx::Error>;
+
+    async fn find_by_owner(&self, owner_user_id: i64) -> Result<Vec<Server>, sqlx::Error>;
+
+    async fn find_all(&self) -> Result<Vec<Server>, sqlx::Error>;
+
+    /// Servers `user_id` is a member of.
+    async fn find_servers_for_user(&self, user_id: i64) -> Result<Vec<Server>, sqlx::Error>;
+
+    async fn update(&self, server_id: i64, server: Server) -> Result<Server, sqlx::Error>;
+
+    /// Deletes the server along with its members and channels.
+    async fn delete(&self, server_id: i64) -> Result<bool, sqlx::Error>;
+
+    async fn get_server_members(&self, server_id: i64) -> Result<Vec<UserResponse>, sqlx::Error>;
+
+    async fn get_server_with_members(&self, server_id: i64) -> Result<Option<ServerWithMembersResponse>, sqlx::Error> {
+        let server = self.find_by_id(server_id).await?;
+
+        if let Some(server) = server {
+            let members = self.get_server_members(server_id).await?;
+            Ok(Some(ServerWithMembersResponse { server, members }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
 #[derive(Clone)]
-pub struct ServerRepository {
+pub struct PgServerRepository {
     pool: Pool<Postgres>,
 }
 
-impl ServerRepository {
+impl PgServerRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
         Self { pool }
     }
+}
 
-    pub async fn create(&self, new_server: NewServer) -> Result<Server, sqlx::Error> {
+#[async_trait]
+impl ServerRepository for PgServerRepository {
+    #[tracing::instrument(name = "ServerRepository::create", skip_all)]
+    async fn create(&self, new_server: NewServer) -> Result<Server, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            INSERT INTO servers (server_name, owner_user_id, icon_url)
-            VALUES ($1, $2, $3)
-            RETURNING server_id, server_name, owner_user_id, icon_url, created_at, updated_at
+            INSERT INTO servers (server_id, server_name, owner_user_id, icon_url)
+            VALUES ($1, $2, $3, $4)
+            RETURNING server_id, server_name, owner_user_id, icon_url, mfa_required, created_at, updated_at
             "#,
+            snowflake::next_id(),
             new_server.server_name,
             new_server.owner_user_id,
             new_server.icon_url
@@ -31,6 +70,7 @@ impl ServerRepository {
             server_name: record.server_name,
             owner_user_id: record.owner_user_id,
             icon_url: record.icon_url,
+            mfa_required: record.mfa_required,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record
                 .updated_at
@@ -40,10 +80,11 @@ impl ServerRepository {
         Ok(server)
     }
 
-    pub async fn find_by_id(&self, server_id: i32) -> Result<Option<Server>, sqlx::Error> {
+    #[tracing::instrument(name = "ServerRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, server_id: i64) -> Result<Option<Server>, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            SELECT server_id, server_name, owner_user_id, icon_url, created_at, updated_at
+            SELECT server_id, server_name, owner_user_id, icon_url, mfa_required, created_at, updated_at
             FROM servers
             WHERE server_id = $1
             "#,
@@ -57,6 +98,7 @@ impl ServerRepository {
             server_name: r.server_name,
             owner_user_id: r.owner_user_id,
             icon_url: r.icon_url,
+            mfa_required: r.mfa_required,
             created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
             updated_at: r
                 .updated_at
@@ -66,10 +108,11 @@ impl ServerRepository {
         Ok(server)
     }
 
-    pub async fn find_by_owner(&self, owner_user_id: i32) -> Result<Vec<Server>, sqlx::Error> {
+    #[tracing::instrument(name = "ServerRepository::find_by_owner", skip_all)]
+    async fn find_by_owner(&self, owner_user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT server_id, server_name, owner_user_id, icon_url, created_at, updated_at
+            SELECT server_id, server_name, owner_user_id, icon_url, mfa_required, created_at, updated_at
             FROM servers
             WHERE owner_user_id = $1
             "#,
@@ -85,6 +128,7 @@ impl ServerRepository {
                 server_name: r.server_name,
                 owner_user_id: r.owner_user_id,
                 icon_url: r.icon_url,
+                mfa_required: r.mfa_required,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 updated_at: r
                     .updated_at
@@ -95,10 +139,11 @@ impl ServerRepository {
         Ok(servers)
     }
 
-    pub async fn find_all(&self) -> Result<Vec<Server>, sqlx::Error> {
+    #[tracing::instrument(name = "ServerRepository::find_all", skip_all)]
+    async fn find_all(&self) -> Result<Vec<Server>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT server_id, server_name, owner_user_id, icon_url, created_at, updated_at
+            SELECT server_id, server_name, owner_user_id, icon_url, mfa_required, created_at, updated_at
             FROM servers
             "#
         )
@@ -112,6 +157,7 @@ impl ServerRepository {
                 server_name: r.server_name,
                 owner_user_id: r.owner_user_id,
                 icon_url: r.icon_url,
+                mfa_required: r.mfa_required,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 updated_at: r
                     .updated_at
@@ -122,10 +168,11 @@ impl ServerRepository {
         Ok(servers)
     }
 
-    pub async fn find_servers_for_user(&self, user_id: i32) -> Result<Vec<Server>, sqlx::Error> {
+    #[tracing::instrument(name = "ServerRepository::find_servers_for_user", skip_all)]
+    async fn find_servers_for_user(&self, user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT s.server_id, s.server_name, s.owner_user_id, s.icon_url, s.created_at, s.updated_at
+            SELECT s.server_id, s.server_name, s.owner_user_id, s.icon_url, s.mfa_required, s.created_at, s.updated_at
             FROM servers s
             JOIN server_members sm ON s.server_id = sm.server_id
             WHERE sm.user_id = $1
@@ -142,6 +189,7 @@ impl ServerRepository {
                 server_name: r.server_name,
                 owner_user_id: r.owner_user_id,
                 icon_url: r.icon_url,
+                mfa_required: r.mfa_required,
                 created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
                 updated_at: r
                     .updated_at
@@ -152,18 +200,20 @@ impl ServerRepository {
         Ok(servers)
     }
 
-    pub async fn update(&self, server_id: i32, server: Server) -> Result<Server, sqlx::Error> {
+    #[tracing::instrument(name = "ServerRepository::update", skip_all)]
+    async fn update(&self, server_id: i64, server: Server) -> Result<Server, sqlx::Error> {
         let now = Utc::now();
         let record = sqlx::query!(
             r#"
             UPDATE servers
-            SET server_name = $1, owner_user_id = $2, icon_url = $3, updated_at = $4
-            WHERE server_id = $5
-            RETURNING server_id, server_name, owner_user_id, icon_url, created_at, updated_at
+            SET server_name = $1, owner_user_id = $2, icon_url = $3, mfa_required = $4, updated_at = $5
+            WHERE server_id = $6
+            RETURNING server_id, server_name, owner_user_id, icon_url, mfa_required, created_at, updated_at
             "#,
             server.server_name,
             server.owner_user_id,
             server.icon_url,
+            server.mfa_required,
             now as _,
             server_id
         )
@@ -175,6 +225,7 @@ impl ServerRepository {
             server_name: record.server_name,
             owner_user_id: record.owner_user_id,
             icon_url: record.icon_url,
+            mfa_required: record.mfa_required,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record
                 .updated_at
@@ -184,7 +235,8 @@ impl ServerRepository {
         Ok(updated_server)
     }
 
-    pub async fn delete(&self, server_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "ServerRepository::delete", skip_all)]
+    async fn delete(&self, server_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             DELETE FROM servers
@@ -198,9 +250,10 @@ impl ServerRepository {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn get_server_members(
+    #[tracing::instrument(name = "ServerRepository::get_server_members", skip_all)]
+    async fn get_server_members(
         &self,
-        server_id: i32,
+        server_id: i64,
     ) -> Result<Vec<UserResponse>, sqlx::Error> {
         let user_records = sqlx::query!(
             r#"
@@ -228,18 +281,4 @@ impl ServerRepository {
 
         Ok(members)
     }
-
-    pub async fn get_server_with_members(
-        &self,
-        server_id: i32,
-    ) -> Result<Option<ServerWithMembersResponse>, sqlx::Error> {
-        let server = self.find_by_id(server_id).await?;
-
-        if let Some(server) = server {
-            let members = self.get_server_members(server_id).await?;
-            Ok(Some(ServerWithMembersResponse { server, members }))
-        } else {
-            Ok(None)
-        }
-    }
 }
@@ -0,0 +1,216 @@
+use super::{channel_from_row, insert_message, now, translate_error, SqliteDatabase, CHANNEL_COLUMNS};
+use crate::models::models::{Channel, ChannelWithMessagesResponse, Message, MessageType, NewChannel, NewMessage};
+use crate::repositories::{ChannelRepository, MessageRepository};
+use crate::snowflake;
+use async_trait::async_trait;
+
+#[async_trait]
+impl ChannelRepository for SqliteDatabase {
+    #[tracing::instrument(name = "ChannelRepository::create", skip_all)]
+    async fn create(&self, new_channel: NewChannel) -> Result<Channel, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO channels (channel_id, server_id, name, type, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            CHANNEL_COLUMNS
+        ))
+        .bind(snowflake::next_id())
+        .bind(new_channel.server_id)
+        .bind(new_channel.name)
+        .bind(new_channel.channel_type)
+        .bind(now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        channel_from_row(&row)
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, channel_id: i64) -> Result<Option<Channel>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM channels WHERE channel_id = ?", CHANNEL_COLUMNS))
+            .bind(channel_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(channel_from_row)
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::find_by_server", skip_all)]
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        sqlx::query(&format!(
+            "SELECT {} FROM channels WHERE server_id = ? ORDER BY name",
+            CHANNEL_COLUMNS
+        ))
+        .bind(server_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(channel_from_row)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::find_direct_message_channels", skip_all)]
+    async fn find_direct_message_channels(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM channels
+            WHERE type = 'dm'
+              AND channel_id IN (SELECT channel_id FROM direct_message_members WHERE user_id = ?)
+            "#,
+            CHANNEL_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(channel_from_row)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::find_server_channels_for_user", skip_all)]
+    async fn find_server_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM channels
+            WHERE server_id IN (SELECT server_id FROM server_members WHERE user_id = ?)
+            ORDER BY server_id, name
+            "#,
+            CHANNEL_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(channel_from_row)
+        .collect()
+    }
+
+    /// Writes before it reads: the first update only matches when the name
+    /// actually changes, which replaces Postgres's `FOR UPDATE` read of the
+    /// previous name.
+    #[tracing::instrument(name = "ChannelRepository::update", skip_all)]
+    async fn update(&self, channel_id: i64, name: String, updated_by_user_id: i64) -> Result<(Channel, Option<Message>), sqlx::Error> {
+        let now = now();
+        let mut tx = self.pool.begin().await?;
+
+        let renamed = sqlx::query("UPDATE channels SET name = ?1, updated_at = ?2 WHERE channel_id = ?3 AND name <> ?1")
+            .bind(&name)
+            .bind(now)
+            .bind(channel_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(translate_error)?
+            .rows_affected()
+            > 0;
+
+        let row = sqlx::query(&format!(
+            "UPDATE channels SET updated_at = ? WHERE channel_id = ? RETURNING {}",
+            CHANNEL_COLUMNS
+        ))
+        .bind(now)
+        .bind(channel_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let updated_channel = channel_from_row(&row)?;
+
+        let system_message = if renamed {
+            let new_message = NewMessage {
+                channel_id,
+                author_user_id: updated_by_user_id,
+                content: updated_channel.name.clone(),
+                message_type: MessageType::ChannelRename,
+                referenced_message_id: None,
+            };
+            Some(insert_message(&mut tx, new_message).await?)
+        } else {
+            None
+        };
+
+        tx.commit().await?;
+
+        Ok((updated_channel, system_message))
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::update_rate_limit", skip_all)]
+    async fn update_rate_limit(&self, channel_id: i64, rate_limit_per_user: i32) -> Result<Option<Channel>, sqlx::Error> {
+        sqlx::query(&format!(
+            "UPDATE channels SET rate_limit_per_user = ?, updated_at = ? WHERE channel_id = ? RETURNING {}",
+            CHANNEL_COLUMNS
+        ))
+        .bind(rate_limit_per_user)
+        .bind(now())
+        .bind(channel_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(translate_error)?
+        .as_ref()
+        .map(channel_from_row)
+        .transpose()
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::delete", skip_all)]
+    async fn delete(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM channels WHERE channel_id = ?")
+            .bind(channel_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::get_channel_with_messages", skip_all)]
+    async fn get_channel_with_messages(&self, channel_id: i64, limit: i64) -> Result<Option<ChannelWithMessagesResponse>, sqlx::Error> {
+        let Some(channel) = ChannelRepository::find_by_id(self, channel_id).await? else {
+            return Ok(None);
+        };
+        let messages = self.find_by_channel_with_authors(channel_id, None, limit).await?;
+
+        Ok(Some(ChannelWithMessagesResponse { channel, messages }))
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::is_direct_message_member", skip_all)]
+    async fn is_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM direct_message_members WHERE channel_id = ? AND user_id = ?)")
+            .bind(channel_id)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::find_direct_message_member_ids", skip_all)]
+    async fn find_direct_message_member_ids(&self, channel_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar("SELECT user_id FROM direct_message_members WHERE channel_id = ?")
+            .bind(channel_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::add_direct_message_member", skip_all)]
+    async fn add_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("INSERT INTO direct_message_members (channel_id, user_id) VALUES (?, ?) ON CONFLICT DO NOTHING")
+            .bind(channel_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(translate_error)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "ChannelRepository::remove_direct_message_member", skip_all)]
+    async fn remove_direct_message_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM direct_message_members WHERE channel_id = ? AND user_id = ?")
+            .bind(channel_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
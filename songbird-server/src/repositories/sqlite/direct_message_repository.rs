@@ -0,0 +1,144 @@
+use super::{channel_from_row, now, translate_error, SqliteDatabase, CHANNEL_COLUMNS};
+use crate::models::models::{Channel, DirectMessageMember};
+use crate::repositories::{ChannelRepository, DirectMessageRepository};
+use crate::snowflake;
+use async_trait::async_trait;
+use sqlx::SqliteConnection;
+
+/// Inserts on `connection`, which may be inside a transaction.
+async fn insert_dm_member(connection: &mut SqliteConnection, channel_id: i64, user_id: i64) -> Result<DirectMessageMember, sqlx::Error> {
+    let (channel_id, user_id) = sqlx::query_as(
+        "INSERT INTO direct_message_members (channel_id, user_id) VALUES (?, ?) RETURNING channel_id, user_id",
+    )
+    .bind(channel_id)
+    .bind(user_id)
+    .fetch_one(connection)
+    .await
+    .map_err(translate_error)?;
+
+    Ok(DirectMessageMember { channel_id, user_id })
+}
+
+#[async_trait]
+impl DirectMessageRepository for SqliteDatabase {
+    #[tracing::instrument(name = "DirectMessageRepository::create_dm_channel", skip_all)]
+    async fn create_dm_channel(&self, user_id1: i64, user_id2: i64, name: String) -> Result<Channel, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO channels (channel_id, server_id, name, type, created_at, updated_at)
+            VALUES (?1, NULL, ?2, 'dm', ?3, ?3)
+            RETURNING {}
+            "#,
+            CHANNEL_COLUMNS
+        ))
+        .bind(snowflake::next_id())
+        .bind(name)
+        .bind(now())
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(translate_error)?;
+        let channel = channel_from_row(&row)?;
+
+        insert_dm_member(&mut tx, channel.channel_id, user_id1).await?;
+        insert_dm_member(&mut tx, channel.channel_id, user_id2).await?;
+
+        tx.commit().await?;
+
+        Ok(channel)
+    }
+
+    #[tracing::instrument(name = "DirectMessageRepository::add_dm_member", skip_all)]
+    async fn add_dm_member(&self, channel_id: i64, user_id: i64) -> Result<DirectMessageMember, sqlx::Error> {
+        let mut connection = self.pool.acquire().await?;
+        insert_dm_member(&mut connection, channel_id, user_id).await
+    }
+
+    #[tracing::instrument(name = "DirectMessageRepository::remove_dm_member", skip_all)]
+    async fn remove_dm_member(&self, channel_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        ChannelRepository::remove_direct_message_member(self, channel_id, user_id).await
+    }
+
+    #[tracing::instrument(name = "DirectMessageRepository::find_dm_members", skip_all)]
+    async fn find_dm_members(&self, channel_id: i64) -> Result<Vec<DirectMessageMember>, sqlx::Error> {
+        let members: Vec<(i64, i64)> =
+            sqlx::query_as("SELECT channel_id, user_id FROM direct_message_members WHERE channel_id = ?")
+                .bind(channel_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(members
+            .into_iter()
+            .map(|(channel_id, user_id)| DirectMessageMember { channel_id, user_id })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "DirectMessageRepository::find_dm_member", skip_all)]
+    async fn find_dm_member(&self, channel_id: i64, user_id: i64) -> Result<Option<DirectMessageMember>, sqlx::Error> {
+        let member: Option<(i64, i64)> = sqlx::query_as(
+            "SELECT channel_id, user_id FROM direct_message_members WHERE channel_id = ? AND user_id = ?",
+        )
+        .bind(channel_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(member.map(|(channel_id, user_id)| DirectMessageMember { channel_id, user_id }))
+    }
+
+    #[tracing::instrument(name = "DirectMessageRepository::find_or_create_dm_channel", skip_all)]
+    async fn find_or_create_dm_channel(&self, user_id1: i64, user_id2: i64) -> Result<Channel, sqlx::Error> {
+        let existing = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM channels
+            WHERE type = 'dm'
+              AND channel_id IN (SELECT channel_id FROM direct_message_members WHERE user_id = ?)
+              AND channel_id IN (SELECT channel_id FROM direct_message_members WHERE user_id = ?)
+            "#,
+            CHANNEL_COLUMNS
+        ))
+        .bind(user_id1)
+        .bind(user_id2)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match existing {
+            Some(row) => channel_from_row(&row),
+            None => {
+                let channel_name = format!("dm_{}_{}", user_id1, user_id2);
+                self.create_dm_channel(user_id1, user_id2, channel_name).await
+            }
+        }
+    }
+
+    #[tracing::instrument(name = "DirectMessageRepository::get_dm_channels_for_user", skip_all)]
+    async fn get_dm_channels_for_user(&self, user_id: i64) -> Result<Vec<Channel>, sqlx::Error> {
+        self.find_direct_message_channels(user_id).await
+    }
+
+    #[tracing::instrument(name = "DirectMessageRepository::delete_dm_channel", skip_all)]
+    async fn delete_dm_channel(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM direct_message_members WHERE channel_id = ?")
+            .bind(channel_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM messages WHERE channel_id = ?")
+            .bind(channel_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM channels WHERE channel_id = ? AND type = 'dm'")
+            .bind(channel_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
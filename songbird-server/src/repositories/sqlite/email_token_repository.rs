@@ -0,0 +1,74 @@
+use super::{now, translate_error, SqliteDatabase};
+use crate::repositories::EmailTokenRepository;
+use async_trait::async_trait;
+use chrono::{DateTime, SubsecRound, Utc};
+
+#[async_trait]
+impl EmailTokenRepository for SqliteDatabase {
+    #[tracing::instrument(name = "EmailTokenRepository::create", skip_all)]
+    async fn create(
+        &self,
+        token_id: &str,
+        user_id: i64,
+        purpose: &str,
+        email: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let now = now();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE email_tokens SET used_at = ? WHERE user_id = ? AND purpose = ? AND used_at IS NULL")
+            .bind(now)
+            .bind(user_id)
+            .bind(purpose)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO email_tokens (token_id, user_id, purpose, email, expires_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .bind(purpose)
+        .bind(email)
+        .bind(expires_at.trunc_subsecs(6))
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(translate_error)?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "EmailTokenRepository::consume", skip_all)]
+    async fn consume(&self, token_id: &str, purpose: &str) -> Result<Option<(i64, String)>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            UPDATE email_tokens
+            SET used_at = ?1
+            WHERE token_id = ?2 AND purpose = ?3 AND used_at IS NULL AND expires_at > ?1
+            RETURNING user_id, email
+            "#,
+        )
+        .bind(now())
+        .bind(token_id)
+        .bind(purpose)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    #[tracing::instrument(name = "EmailTokenRepository::purge_expired", skip_all)]
+    async fn purge_expired(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM email_tokens WHERE expires_at <= ?")
+            .bind(now())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,33 @@
+use super::SqliteDatabase;
+use crate::database::SQLITE_MIGRATOR;
+use crate::repositories::health_repository::{HealthRepository, PoolStatus, SchemaStatus};
+use async_trait::async_trait;
+use sqlx::migrate::Migrate;
+
+#[async_trait]
+impl HealthRepository for SqliteDatabase {
+    #[tracing::instrument(name = "HealthRepository::schema_status", skip_all)]
+    async fn schema_status(&self) -> Result<SchemaStatus, sqlx::Error> {
+        let mut connection = self.pool.acquire().await?;
+        let applied = connection.list_applied_migrations().await?;
+
+        let pending = SQLITE_MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .filter(|migration| !applied.iter().any(|applied| applied.version == migration.version))
+            .count();
+
+        Ok(SchemaStatus {
+            version: applied.iter().map(|migration| migration.version).max().unwrap_or_default(),
+            pending,
+        })
+    }
+
+    fn pool_status(&self) -> Option<PoolStatus> {
+        Some(PoolStatus {
+            size: self.pool.size(),
+            idle: self.pool.num_idle() as u32,
+            max: self.pool.options().get_max_connections(),
+        })
+    }
+}
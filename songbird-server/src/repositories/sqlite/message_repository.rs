@@ -0,0 +1,235 @@
+use super::{
+    insert_message, message_from_row, message_with_author_from_row, now, translate_error, SqliteDatabase,
+    MESSAGE_COLUMNS, MESSAGE_WITH_AUTHOR_COLUMNS,
+};
+use crate::models::models::{Message, MessageWithAuthorResponse, NewMessage};
+use crate::repositories::{search_terms, MessageRepository, PinOutcome};
+use async_trait::async_trait;
+
+#[async_trait]
+impl MessageRepository for SqliteDatabase {
+    #[tracing::instrument(name = "MessageRepository::create", skip_all)]
+    async fn create(&self, new_message: NewMessage) -> Result<Message, sqlx::Error> {
+        let mut connection = self.pool.acquire().await?;
+        insert_message(&mut connection, new_message).await
+    }
+
+    #[tracing::instrument(name = "MessageRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, message_id: i64) -> Result<Option<Message>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM messages WHERE message_id = ?", MESSAGE_COLUMNS))
+            .bind(message_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(message_from_row)
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "MessageRepository::find_by_channel", skip_all)]
+    async fn find_by_channel(&self, channel_id: i64, limit: i64) -> Result<Vec<Message>, sqlx::Error> {
+        sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM messages
+            WHERE channel_id = ?
+            ORDER BY message_id DESC
+            LIMIT ?
+            "#,
+            MESSAGE_COLUMNS
+        ))
+        .bind(channel_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(message_from_row)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "MessageRepository::find_by_channel_with_authors", skip_all)]
+    async fn find_by_channel_with_authors(
+        &self,
+        channel_id: i64,
+        before: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM messages m
+            JOIN users u ON m.author_user_id = u.user_id
+            WHERE m.channel_id = ?1 AND (?2 IS NULL OR m.message_id < ?2)
+            ORDER BY m.message_id DESC
+            LIMIT ?3
+            "#,
+            MESSAGE_WITH_AUTHOR_COLUMNS
+        ))
+        .bind(channel_id)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(message_with_author_from_row)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "MessageRepository::update_content", skip_all)]
+    async fn update_content(&self, message_id: i64, content: String) -> Result<Message, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE messages
+            SET content = ?1, updated_at = ?2, edited_at = ?2
+            WHERE message_id = ?3
+            RETURNING {}
+            "#,
+            MESSAGE_COLUMNS
+        ))
+        .bind(content)
+        .bind(now())
+        .bind(message_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        message_from_row(&row)
+    }
+
+    #[tracing::instrument(name = "MessageRepository::delete", skip_all)]
+    async fn delete(&self, message_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM messages WHERE message_id = ?")
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The cap is checked by the insert itself, so two pins cannot both slip
+    /// under it. When nothing was inserted, the message was either pinned
+    /// already or the cap was reached.
+    #[tracing::instrument(name = "MessageRepository::pin", skip_all)]
+    async fn pin(&self, channel_id: i64, message_id: i64, pinned_by_user_id: i64, max_pins: i64) -> Result<PinOutcome, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO pinned_messages (message_id, channel_id, pinned_by_user_id, pinned_at)
+            SELECT ?1, ?2, ?3, ?4
+            WHERE (SELECT COUNT(*) FROM pinned_messages WHERE channel_id = ?2) < ?5
+            ON CONFLICT (message_id) DO NOTHING
+            "#,
+        )
+        .bind(message_id)
+        .bind(channel_id)
+        .bind(pinned_by_user_id)
+        .bind(now())
+        .bind(max_pins)
+        .execute(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        if result.rows_affected() > 0 {
+            return Ok(PinOutcome::Pinned);
+        }
+
+        let already_pinned: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pinned_messages WHERE message_id = ?)")
+                .bind(message_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        if already_pinned {
+            Ok(PinOutcome::AlreadyPinned)
+        } else {
+            Ok(PinOutcome::LimitReached)
+        }
+    }
+
+    #[tracing::instrument(name = "MessageRepository::unpin", skip_all)]
+    async fn unpin(&self, channel_id: i64, message_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM pinned_messages WHERE channel_id = ? AND message_id = ?")
+            .bind(channel_id)
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MessageRepository::find_pinned_with_authors", skip_all)]
+    async fn find_pinned_with_authors(&self, channel_id: i64) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM pinned_messages p
+            JOIN messages m ON p.message_id = m.message_id
+            JOIN users u ON m.author_user_id = u.user_id
+            WHERE p.channel_id = ?
+            ORDER BY p.pinned_at DESC
+            "#,
+            MESSAGE_WITH_AUTHOR_COLUMNS
+        ))
+        .bind(channel_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(message_with_author_from_row)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "MessageRepository::count_by_channel", skip_all)]
+    async fn count_by_channel(&self, channel_id: i64) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE channel_id = ?")
+            .bind(channel_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    #[tracing::instrument(name = "MessageRepository::count_by_user", skip_all)]
+    async fn count_by_user(&self, user_id: i64) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE author_user_id = ?")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Through the FTS5 index, with each word quoted so it is matched as a
+    /// plain token.
+    #[tracing::instrument(name = "MessageRepository::search_with_authors", skip_all)]
+    async fn search_with_authors(
+        &self,
+        channel_id: i64,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<MessageWithAuthorResponse>, sqlx::Error> {
+        let terms = search_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let match_query = terms
+            .iter()
+            .map(|term| format!("\"{}\"", term))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM messages_fts
+            JOIN messages m ON m.message_id = messages_fts.rowid
+            JOIN users u ON m.author_user_id = u.user_id
+            WHERE messages_fts MATCH ?1 AND m.channel_id = ?2
+            ORDER BY m.message_id DESC
+            LIMIT ?3
+            "#,
+            MESSAGE_WITH_AUTHOR_COLUMNS
+        ))
+        .bind(match_query)
+        .bind(channel_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(message_with_author_from_row)
+        .collect()
+    }
+}
@@ -0,0 +1,129 @@
+use super::{now, translate_error, SqliteDatabase};
+use crate::repositories::mfa_repository::{MfaRepository, UserTotp};
+use async_trait::async_trait;
+
+#[async_trait]
+impl MfaRepository for SqliteDatabase {
+    #[tracing::instrument(name = "MfaRepository::find_totp", skip_all)]
+    async fn find_totp(&self, user_id: i64) -> Result<Option<UserTotp>, sqlx::Error> {
+        let record: Option<(i64, String, bool)> =
+            sqlx::query_as("SELECT user_id, secret, enabled FROM user_totp WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(record.map(|(user_id, secret, enabled)| UserTotp { user_id, secret, enabled }))
+    }
+
+    #[tracing::instrument(name = "MfaRepository::is_enabled", skip_all)]
+    async fn is_enabled(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM user_totp WHERE user_id = ? AND enabled)")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    #[tracing::instrument(name = "MfaRepository::begin_enrollment", skip_all)]
+    async fn begin_enrollment(&self, user_id: i64, secret: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO user_totp (user_id, secret, created_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = excluded.secret, last_used_step = NULL, created_at = excluded.created_at
+            WHERE NOT user_totp.enabled
+            "#,
+        )
+        .bind(user_id)
+        .bind(secret)
+        .bind(now())
+        .execute(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::confirm_enrollment", skip_all)]
+    async fn confirm_enrollment(&self, user_id: i64, step: i64, recovery_code_hashes: &[String]) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("UPDATE user_totp SET enabled = TRUE, last_used_step = ? WHERE user_id = ? AND NOT enabled")
+            .bind(step)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for code_hash in recovery_code_hashes {
+            sqlx::query("INSERT INTO recovery_codes (user_id, code_hash) VALUES (?, ?)")
+                .bind(user_id)
+                .bind(code_hash)
+                .execute(&mut *tx)
+                .await
+                .map_err(translate_error)?;
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::disable", skip_all)]
+    async fn disable(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("DELETE FROM user_totp WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM recovery_codes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::consume_step", skip_all)]
+    async fn consume_step(&self, user_id: i64, step: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_totp
+            SET last_used_step = ?2
+            WHERE user_id = ?1 AND (last_used_step IS NULL OR last_used_step < ?2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "MfaRepository::consume_recovery_code", skip_all)]
+    async fn consume_recovery_code(&self, user_id: i64, code_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE recovery_codes SET used_at = ? WHERE user_id = ? AND code_hash = ? AND used_at IS NULL",
+        )
+        .bind(now())
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,282 @@
+// src/repositories/sqlite/mod.rs
+//! Every repository on SQLite, for self-hosting without a Postgres server.
+//!
+//! The schema in `migrations_sqlite/` mirrors the Postgres one. Queries are
+//! checked at runtime rather than by `query!`, which only knows about the
+//! Postgres database it compiles against.
+//!
+//! SQLite allows one writer at a time. A transaction only takes the write
+//! lock at its first write, and in WAL mode one that read first can fail
+//! when it tries to write if someone else wrote in the meantime. So every
+//! transaction here writes first, and the checks Postgres does with
+//! `SELECT ... FOR UPDATE` are folded into single statements.
+//!
+//! Message search uses an FTS5 table, `messages_fts`, where Postgres has a
+//! GIN index over `to_tsvector`; triggers keep it in step with `messages`.
+//! Nothing else needs a substitute: the gateway fans events out in process
+//! on either backend.
+
+mod channel_repository;
+mod direct_message_repository;
+mod email_token_repository;
+mod health_repository;
+mod message_repository;
+mod mfa_repository;
+mod server_member_repository;
+mod server_repository;
+mod user_repository;
+
+use crate::models::models::{
+    Channel, Message, MessageType, MessageWithAuthorResponse, NewMessage, Server, ServerMember, User, UserResponse,
+};
+use crate::repositories::constraint_violation::ConstraintViolation;
+use crate::snowflake;
+use chrono::{DateTime, SubsecRound, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Pool, Row, Sqlite, SqliteConnection};
+
+/// One SQLite database, shared by every repository.
+#[derive(Clone)]
+pub struct SqliteDatabase {
+    pool: Pool<Sqlite>,
+}
+
+impl SqliteDatabase {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self { pool }
+    }
+}
+
+const USER_COLUMNS: &str = "user_id, username, email, password_hash, avatar_url, created_at, updated_at, status, \
+     preferred_status, custom_status_text, custom_status_emoji, custom_status_expires_at, email_verified, disabled_at, \
+     sessions_revoked_at";
+
+const SERVER_COLUMNS: &str = "server_id, server_name, owner_user_id, icon_url, mfa_required, created_at, updated_at";
+
+const SERVER_MEMBER_COLUMNS: &str = "server_id, user_id, nickname, permissions, joined_at";
+
+const CHANNEL_COLUMNS: &str = "channel_id, server_id, name, type, rate_limit_per_user, created_at, updated_at";
+
+const MESSAGE_COLUMNS: &str = "message_id, channel_id, author_user_id, content, message_type, referenced_message_id, \
+     created_at, updated_at, edited_at";
+
+/// Messages joined with their authors as `m` and `u`.
+const MESSAGE_WITH_AUTHOR_COLUMNS: &str = "m.message_id, m.channel_id, m.content, m.message_type, \
+     m.referenced_message_id, m.created_at, m.edited_at, u.user_id, u.username, u.email, u.avatar_url, \
+     u.created_at AS user_created_at, u.status";
+
+/// Timestamps are stored as text, which only sorts right if every value has
+/// the same precision. Postgres keeps microseconds, so we do too.
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(6)
+}
+
+fn user_from_row(row: &SqliteRow) -> Result<User, sqlx::Error> {
+    Ok(User {
+        user_id: row.try_get("user_id")?,
+        username: row.try_get("username")?,
+        email: row.try_get("email")?,
+        password_hash: row.try_get("password_hash")?,
+        avatar_url: row.try_get("avatar_url")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        status: row.try_get("status")?,
+        preferred_status: row.try_get("preferred_status")?,
+        custom_status_text: row.try_get("custom_status_text")?,
+        custom_status_emoji: row.try_get("custom_status_emoji")?,
+        custom_status_expires_at: row.try_get("custom_status_expires_at")?,
+        email_verified: row.try_get("email_verified")?,
+        disabled_at: row.try_get("disabled_at")?,
+        sessions_revoked_at: row.try_get("sessions_revoked_at")?,
+    })
+}
+
+fn user_response_from_row(row: &SqliteRow) -> Result<UserResponse, sqlx::Error> {
+    Ok(UserResponse {
+        user_id: row.try_get("user_id")?,
+        username: row.try_get("username")?,
+        email: row.try_get("email")?,
+        avatar_url: row.try_get("avatar_url")?,
+        status: row.try_get("status")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn server_from_row(row: &SqliteRow) -> Result<Server, sqlx::Error> {
+    Ok(Server {
+        server_id: row.try_get("server_id")?,
+        server_name: row.try_get("server_name")?,
+        owner_user_id: row.try_get("owner_user_id")?,
+        icon_url: row.try_get("icon_url")?,
+        mfa_required: row.try_get("mfa_required")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn server_member_from_row(row: &SqliteRow) -> Result<ServerMember, sqlx::Error> {
+    Ok(ServerMember {
+        server_id: row.try_get("server_id")?,
+        user_id: row.try_get("user_id")?,
+        nickname: row.try_get("nickname")?,
+        permissions: row.try_get("permissions")?,
+        joined_at: row.try_get("joined_at")?,
+    })
+}
+
+fn channel_from_row(row: &SqliteRow) -> Result<Channel, sqlx::Error> {
+    Ok(Channel {
+        channel_id: row.try_get("channel_id")?,
+        server_id: row.try_get("server_id")?,
+        name: row.try_get("name")?,
+        channel_type: row.try_get("type")?,
+        rate_limit_per_user: row.try_get("rate_limit_per_user")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn message_from_row(row: &SqliteRow) -> Result<Message, sqlx::Error> {
+    Ok(Message {
+        message_id: row.try_get("message_id")?,
+        channel_id: row.try_get("channel_id")?,
+        author_user_id: row.try_get("author_user_id")?,
+        content: row.try_get("content")?,
+        message_type: row.try_get::<String, _>("message_type")?.parse().unwrap_or_default(),
+        referenced_message_id: row.try_get("referenced_message_id")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        edited_at: row.try_get("edited_at")?,
+    })
+}
+
+fn message_with_author_from_row(row: &SqliteRow) -> Result<MessageWithAuthorResponse, sqlx::Error> {
+    Ok(MessageWithAuthorResponse {
+        message_id: row.try_get("message_id")?,
+        channel_id: row.try_get("channel_id")?,
+        content: row.try_get("content")?,
+        message_type: row.try_get::<String, _>("message_type")?.parse().unwrap_or_default(),
+        referenced_message_id: row.try_get("referenced_message_id")?,
+        created_at: row.try_get("created_at")?,
+        edited_at: row.try_get("edited_at")?,
+        author: UserResponse {
+            user_id: row.try_get("user_id")?,
+            username: row.try_get("username")?,
+            email: row.try_get("email")?,
+            avatar_url: row.try_get("avatar_url")?,
+            status: row.try_get("status")?,
+            created_at: row.try_get("user_created_at")?,
+        },
+    })
+}
+
+/// Inserts a message on `connection`, which may be inside a transaction.
+async fn insert_message(connection: &mut SqliteConnection, new_message: NewMessage) -> Result<Message, sqlx::Error> {
+    let row = sqlx::query(&format!(
+        r#"
+        INSERT INTO messages (message_id, channel_id, author_user_id, content, message_type, referenced_message_id, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        RETURNING {}
+        "#,
+        MESSAGE_COLUMNS
+    ))
+    .bind(snowflake::next_id())
+    .bind(new_message.channel_id)
+    .bind(new_message.author_user_id)
+    .bind(new_message.content)
+    .bind(new_message.message_type.as_str())
+    .bind(new_message.referenced_message_id)
+    .bind(now())
+    .fetch_one(connection)
+    .await
+    .map_err(translate_error)?;
+
+    message_from_row(&row)
+}
+
+/// The system channel is the server's oldest text channel. Servers without
+/// one get no join or leave messages.
+async fn post_system_message(
+    connection: &mut SqliteConnection,
+    server_id: i64,
+    user_id: i64,
+    message_type: MessageType,
+) -> Result<Option<Message>, sqlx::Error> {
+    let system_channel: Option<i64> = sqlx::query_scalar(
+        r#"
+        SELECT channel_id
+        FROM channels
+        WHERE server_id = ? AND type = 'text'
+        ORDER BY created_at, channel_id
+        LIMIT 1
+        "#,
+    )
+    .bind(server_id)
+    .fetch_optional(&mut *connection)
+    .await?;
+
+    let Some(channel_id) = system_channel else {
+        return Ok(None);
+    };
+
+    let new_message = NewMessage {
+        channel_id,
+        author_user_id: user_id,
+        content: String::new(),
+        message_type,
+        referenced_message_id: None,
+    };
+
+    insert_message(connection, new_message).await.map(Some)
+}
+
+// SQLite's extended result codes for constraint violations
+const SQLITE_CONSTRAINT_CHECK: &str = "275";
+const SQLITE_CONSTRAINT_FOREIGNKEY: &str = "787";
+const SQLITE_CONSTRAINT_PRIMARYKEY: &str = "1555";
+const SQLITE_CONSTRAINT_UNIQUE: &str = "2067";
+
+/// Rewrites a SQLite constraint violation as the error Postgres would have
+/// raised, so it maps to the same `AppError`. Other errors pass through.
+///
+/// SQLite names the columns of a unique or primary key violation ("UNIQUE
+/// constraint failed: users.username") rather than the constraint, so the
+/// name is rebuilt the way Postgres derives it. Foreign key violations name
+/// nothing at all.
+fn translate_error(error: sqlx::Error) -> sqlx::Error {
+    let Some(database_error) = error.as_database_error() else {
+        return error;
+    };
+    let Some(code) = database_error.code() else {
+        return error;
+    };
+    let detail = database_error
+        .message()
+        .split_once(": ")
+        .map(|(_, detail)| detail.to_string());
+
+    match (code.as_ref(), detail) {
+        (SQLITE_CONSTRAINT_UNIQUE, Some(columns)) => ConstraintViolation::unique(unique_key_name(&columns)),
+        (SQLITE_CONSTRAINT_PRIMARYKEY, Some(columns)) => {
+            let table = columns.split('.').next().unwrap_or_default();
+            ConstraintViolation::unique(format!("{}_pkey", table))
+        }
+        (SQLITE_CONSTRAINT_FOREIGNKEY, _) => ConstraintViolation::unnamed_foreign_key(),
+        (SQLITE_CONSTRAINT_CHECK, Some(constraint)) => ConstraintViolation::check(constraint),
+        _ => error,
+    }
+}
+
+/// "recovery_codes.user_id, recovery_codes.code_hash" becomes
+/// "recovery_codes_user_id_code_hash_key".
+fn unique_key_name(columns: &str) -> String {
+    let mut table = "";
+    let mut name = Vec::new();
+    for column in columns.split(", ") {
+        let (column_table, column) = column.split_once('.').unwrap_or(("", column));
+        table = column_table;
+        name.push(column);
+    }
+
+    format!("{}_{}_key", table, name.join("_"))
+}
@@ -0,0 +1,143 @@
+use super::{now, post_system_message, server_member_from_row, translate_error, SqliteDatabase, SERVER_MEMBER_COLUMNS};
+use crate::models::models::{Message, MessageType, NewServerMember, ServerMember};
+use crate::repositories::ServerMemberRepository;
+use async_trait::async_trait;
+
+#[async_trait]
+impl ServerMemberRepository for SqliteDatabase {
+    #[tracing::instrument(name = "ServerMemberRepository::create", skip_all)]
+    async fn create(&self, new_server_member: NewServerMember) -> Result<(ServerMember, Option<Message>), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO server_members (server_id, user_id, nickname, joined_at)
+            VALUES (?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            SERVER_MEMBER_COLUMNS
+        ))
+        .bind(new_server_member.server_id)
+        .bind(new_server_member.user_id)
+        .bind(new_server_member.nickname)
+        .bind(now())
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(translate_error)?;
+        let server_member = server_member_from_row(&row)?;
+
+        let system_message =
+            post_system_message(&mut tx, server_member.server_id, server_member.user_id, MessageType::MemberJoin).await?;
+
+        tx.commit().await?;
+
+        Ok((server_member, system_message))
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, server_id: i64, user_id: i64) -> Result<Option<ServerMember>, sqlx::Error> {
+        sqlx::query(&format!(
+            "SELECT {} FROM server_members WHERE server_id = ? AND user_id = ?",
+            SERVER_MEMBER_COLUMNS
+        ))
+        .bind(server_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .as_ref()
+        .map(server_member_from_row)
+        .transpose()
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::find_by_server", skip_all)]
+    async fn find_by_server(&self, server_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM server_members WHERE server_id = ?", SERVER_MEMBER_COLUMNS))
+            .bind(server_id)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(server_member_from_row)
+            .collect()
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::find_by_user", skip_all)]
+    async fn find_by_user(&self, user_id: i64) -> Result<Vec<ServerMember>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM server_members WHERE user_id = ?", SERVER_MEMBER_COLUMNS))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(server_member_from_row)
+            .collect()
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::update_nickname", skip_all)]
+    async fn update_nickname(&self, server_id: i64, user_id: i64, nickname: Option<String>) -> Result<ServerMember, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "UPDATE server_members SET nickname = ? WHERE server_id = ? AND user_id = ? RETURNING {}",
+            SERVER_MEMBER_COLUMNS
+        ))
+        .bind(nickname)
+        .bind(server_id)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        server_member_from_row(&row)
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::update_permissions", skip_all)]
+    async fn update_permissions(&self, server_id: i64, user_id: i64, permissions: i64) -> Result<Option<ServerMember>, sqlx::Error> {
+        sqlx::query(&format!(
+            "UPDATE server_members SET permissions = ? WHERE server_id = ? AND user_id = ? RETURNING {}",
+            SERVER_MEMBER_COLUMNS
+        ))
+        .bind(permissions)
+        .bind(server_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .as_ref()
+        .map(server_member_from_row)
+        .transpose()
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::delete", skip_all)]
+    async fn delete(&self, server_id: i64, user_id: i64) -> Result<(bool, Option<Message>), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query("DELETE FROM server_members WHERE server_id = ? AND user_id = ?")
+            .bind(server_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok((false, None));
+        }
+
+        let system_message = post_system_message(&mut tx, server_id, user_id, MessageType::MemberLeave).await?;
+
+        tx.commit().await?;
+
+        Ok((true, system_message))
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::is_member", skip_all)]
+    async fn is_member(&self, server_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM server_members WHERE server_id = ? AND user_id = ?)")
+            .bind(server_id)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    #[tracing::instrument(name = "ServerMemberRepository::count_members", skip_all)]
+    async fn count_members(&self, server_id: i64) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM server_members WHERE server_id = ?")
+            .bind(server_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+}
@@ -0,0 +1,132 @@
+use super::{now, server_from_row, translate_error, user_response_from_row, SqliteDatabase, SERVER_COLUMNS};
+use crate::models::models::{NewServer, Server, UserResponse};
+use crate::repositories::ServerRepository;
+use crate::snowflake;
+use async_trait::async_trait;
+
+#[async_trait]
+impl ServerRepository for SqliteDatabase {
+    #[tracing::instrument(name = "ServerRepository::create", skip_all)]
+    async fn create(&self, new_server: NewServer) -> Result<Server, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO servers (server_id, server_name, owner_user_id, icon_url, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            SERVER_COLUMNS
+        ))
+        .bind(snowflake::next_id())
+        .bind(new_server.server_name)
+        .bind(new_server.owner_user_id)
+        .bind(new_server.icon_url)
+        .bind(now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        server_from_row(&row)
+    }
+
+    #[tracing::instrument(name = "ServerRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, server_id: i64) -> Result<Option<Server>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM servers WHERE server_id = ?", SERVER_COLUMNS))
+            .bind(server_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(server_from_row)
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "ServerRepository::find_by_owner", skip_all)]
+    async fn find_by_owner(&self, owner_user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM servers WHERE owner_user_id = ?", SERVER_COLUMNS))
+            .bind(owner_user_id)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(server_from_row)
+            .collect()
+    }
+
+    #[tracing::instrument(name = "ServerRepository::find_all", skip_all)]
+    async fn find_all(&self) -> Result<Vec<Server>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM servers", SERVER_COLUMNS))
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(server_from_row)
+            .collect()
+    }
+
+    #[tracing::instrument(name = "ServerRepository::find_servers_for_user", skip_all)]
+    async fn find_servers_for_user(&self, user_id: i64) -> Result<Vec<Server>, sqlx::Error> {
+        sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM servers
+            WHERE server_id IN (SELECT server_id FROM server_members WHERE user_id = ?)
+            "#,
+            SERVER_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(server_from_row)
+        .collect()
+    }
+
+    #[tracing::instrument(name = "ServerRepository::update", skip_all)]
+    async fn update(&self, server_id: i64, server: Server) -> Result<Server, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE servers
+            SET server_name = ?, owner_user_id = ?, icon_url = ?, mfa_required = ?, updated_at = ?
+            WHERE server_id = ?
+            RETURNING {}
+            "#,
+            SERVER_COLUMNS
+        ))
+        .bind(server.server_name)
+        .bind(server.owner_user_id)
+        .bind(server.icon_url)
+        .bind(server.mfa_required)
+        .bind(now())
+        .bind(server_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        server_from_row(&row)
+    }
+
+    #[tracing::instrument(name = "ServerRepository::delete", skip_all)]
+    async fn delete(&self, server_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM servers WHERE server_id = ?")
+            .bind(server_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "ServerRepository::get_server_members", skip_all)]
+    async fn get_server_members(&self, server_id: i64) -> Result<Vec<UserResponse>, sqlx::Error> {
+        sqlx::query(
+            r#"
+            SELECT u.user_id, u.username, u.email, u.avatar_url, u.created_at, u.status
+            FROM users u
+            JOIN server_members sm ON u.user_id = sm.user_id
+            WHERE sm.server_id = ?
+            "#,
+        )
+        .bind(server_id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(user_response_from_row)
+        .collect()
+    }
+}
@@ -0,0 +1,266 @@
+use super::{now, translate_error, user_from_row, SqliteDatabase, USER_COLUMNS};
+use crate::models::models::{CustomStatus, NewUser, User};
+use crate::repositories::UserRepository;
+use crate::snowflake;
+use async_trait::async_trait;
+use chrono::{DateTime, SubsecRound, Utc};
+
+#[async_trait]
+impl UserRepository for SqliteDatabase {
+    #[tracing::instrument(name = "UserRepository::create", skip_all)]
+    async fn create(&self, new_user: NewUser) -> Result<User, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            r#"
+            INSERT INTO users (user_id, username, email, password_hash, avatar_url, status, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING {}
+            "#,
+            USER_COLUMNS
+        ))
+        .bind(snowflake::next_id())
+        .bind(new_user.username)
+        .bind(new_user.email)
+        .bind(new_user.password_hash)
+        .bind(new_user.avatar_url)
+        .bind(new_user.status)
+        .bind(now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        user_from_row(&row)
+    }
+
+    #[tracing::instrument(name = "UserRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM users WHERE user_id = ?", USER_COLUMNS))
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(user_from_row)
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "UserRepository::find_by_username", skip_all)]
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM users WHERE username = ?", USER_COLUMNS))
+            .bind(username)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(user_from_row)
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "UserRepository::find_by_email", skip_all)]
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM users WHERE email = ?", USER_COLUMNS))
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?
+            .as_ref()
+            .map(user_from_row)
+            .transpose()
+    }
+
+    #[tracing::instrument(name = "UserRepository::find_all", skip_all)]
+    async fn find_all(&self) -> Result<Vec<User>, sqlx::Error> {
+        sqlx::query(&format!("SELECT {} FROM users ORDER BY username", USER_COLUMNS))
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(user_from_row)
+            .collect()
+    }
+
+    #[tracing::instrument(name = "UserRepository::update", skip_all)]
+    async fn update(&self, user_id: i64, user: User) -> Result<User, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE users
+            SET username = ?1, email = ?2, password_hash = ?3, avatar_url = ?4, updated_at = ?5, preferred_status = ?6,
+                email_verified = email_verified AND email = ?2
+            WHERE user_id = ?7
+            RETURNING {}
+            "#,
+            USER_COLUMNS
+        ))
+        .bind(user.username)
+        .bind(user.email)
+        .bind(user.password_hash)
+        .bind(user.avatar_url)
+        .bind(now())
+        .bind(user.preferred_status)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(translate_error)?;
+
+        user_from_row(&row)
+    }
+
+    #[tracing::instrument(name = "UserRepository::delete", skip_all)]
+    async fn delete(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM users WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::mark_email_verified", skip_all)]
+    async fn mark_email_verified(&self, user_id: i64, email: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET email_verified = TRUE WHERE user_id = ? AND email = ?")
+            .bind(user_id)
+            .bind(email)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_password", skip_all)]
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET password_hash = ?, updated_at = ? WHERE user_id = ?")
+            .bind(password_hash)
+            .bind(now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::set_disabled", skip_all)]
+    async fn set_disabled(&self, user_id: i64, disabled: bool) -> Result<bool, sqlx::Error> {
+        let result = if disabled {
+            sqlx::query(
+                "UPDATE users SET disabled_at = COALESCE(disabled_at, ?1), sessions_revoked_at = ?1 WHERE user_id = ?2",
+            )
+            .bind(now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?
+        } else {
+            sqlx::query("UPDATE users SET disabled_at = NULL WHERE user_id = ?")
+                .bind(user_id)
+                .execute(&self.pool)
+                .await?
+        };
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::revoke_sessions", skip_all)]
+    async fn revoke_sessions(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET sessions_revoked_at = ? WHERE user_id = ?")
+            .bind(now())
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::purge_disabled", skip_all)]
+    async fn purge_disabled(&self, disabled_before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM users WHERE disabled_at < ?")
+            .bind(disabled_before.trunc_subsecs(6))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_status", skip_all)]
+    async fn update_status(&self, user_id: i64, status: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET status = ? WHERE user_id = ?")
+            .bind(status)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_preferred_status", skip_all)]
+    async fn update_preferred_status(&self, user_id: i64, preferred_status: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET preferred_status = ? WHERE user_id = ?")
+            .bind(preferred_status)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_custom_status", skip_all)]
+    async fn update_custom_status(&self, user_id: i64, custom_status: Option<CustomStatus>) -> Result<bool, sqlx::Error> {
+        let (text, emoji, expires_at) = match custom_status {
+            Some(status) => (status.text, status.emoji, status.expires_at),
+            None => (None, None, None),
+        };
+
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET custom_status_text = ?, custom_status_emoji = ?, custom_status_expires_at = ?
+            WHERE user_id = ?
+            "#,
+        )
+        .bind(text)
+        .bind(emoji)
+        .bind(expires_at)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::clear_expired_custom_statuses", skip_all)]
+    async fn clear_expired_custom_statuses(&self) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE users
+            SET custom_status_text = NULL, custom_status_emoji = NULL, custom_status_expires_at = NULL
+            WHERE custom_status_expires_at IS NOT NULL AND custom_status_expires_at <= ?
+            RETURNING user_id
+            "#,
+        )
+        .bind(now())
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    #[tracing::instrument(name = "UserRepository::reset_all_statuses", skip_all)]
+    async fn reset_all_statuses(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("UPDATE users SET status = 'offline' WHERE status <> 'offline'")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "UserRepository::find_presence_audience", skip_all)]
+    async fn find_presence_audience(&self, user_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT other.user_id
+            FROM server_members mine
+            JOIN server_members other ON other.server_id = mine.server_id
+            WHERE mine.user_id = ?1 AND other.user_id <> ?1
+            UNION
+            SELECT other.user_id
+            FROM direct_message_members mine
+            JOIN direct_message_members other ON other.channel_id = mine.channel_id
+            WHERE mine.user_id = ?1 AND other.user_id <> ?1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
@@ -1,24 +1,99 @@
-use sqlx::{Pool, Postgres};
+use crate::models::models::{CustomStatus, NewUser, User, UserResponse};
+use crate::snowflake;
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use crate::models::models::{User, NewUser, UserResponse};
+use sqlx::{Pool, Postgres};
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn create(&self, new_user: NewUser) -> Result<User, sqlx::Error>;
+
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error>;
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error>;
+
+    /// Every user, ordered by username.
+    async fn find_all(&self) -> Result<Vec<User>, sqlx::Error>;
+
+    /// Saves the profile fields of `user`. Changing the email clears
+    /// `email_verified`.
+    async fn update(&self, user_id: i64, user: User) -> Result<User, sqlx::Error>;
+
+    async fn delete(&self, user_id: i64) -> Result<bool, sqlx::Error>;
+
+    /// Marks the address verified, provided it is still the user's address.
+    async fn mark_email_verified(&self, user_id: i64, email: &str) -> Result<bool, sqlx::Error>;
+
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error>;
+
+    /// Disables the account and revokes its sessions, or enables it again.
+    /// Disabling an account that already is keeps its `disabled_at`, so
+    /// `purge_disabled` counts from the first time.
+    async fn set_disabled(&self, user_id: i64, disabled: bool) -> Result<bool, sqlx::Error>;
+
+    /// Ends every session issued so far, see `auth::session_is_current`.
+    async fn revoke_sessions(&self, user_id: i64) -> Result<bool, sqlx::Error>;
+
+    /// Deletes the accounts disabled before `disabled_before`, along with
+    /// everything that cascades from them. Returns how many were deleted.
+    async fn purge_disabled(&self, disabled_before: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+
+    async fn update_status(&self, user_id: i64, status: &str) -> Result<bool, sqlx::Error>;
+
+    async fn update_preferred_status(&self, user_id: i64, preferred_status: &str) -> Result<bool, sqlx::Error>;
+
+    async fn update_custom_status(&self, user_id: i64, custom_status: Option<CustomStatus>) -> Result<bool, sqlx::Error>;
+
+    /// Clears every custom status whose expiry has passed and returns the
+    /// affected user ids so their presence can be re-broadcast.
+    async fn clear_expired_custom_statuses(&self) -> Result<Vec<i64>, sqlx::Error>;
+
+    /// Marks every user offline. Called at startup, before any gateway session
+    /// can exist, so statuses left behind by a crash do not linger.
+    async fn reset_all_statuses(&self) -> Result<u64, sqlx::Error>;
+
+    /// Users allowed to see this user's presence: anyone sharing a server or a
+    /// direct message channel with them.
+    async fn find_presence_audience(&self, user_id: i64) -> Result<Vec<i64>, sqlx::Error>;
+
+    async fn to_response(&self, user: User) -> UserResponse {
+        UserResponse {
+            user_id: user.user_id,
+            username: user.username,
+            email: user.email,
+            avatar_url: user.avatar_url,
+            status: user.status,
+            created_at: user.created_at,
+        }
+    }
+}
 
 #[derive(Clone)]
-pub struct UserRepository {
+pub struct PgUserRepository {
     pool: Pool<Postgres>,
 }
 
-impl UserRepository {
+impl PgUserRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
         Self { pool }
     }
+}
 
-    pub async fn create(&self, new_user: NewUser) -> Result<User, sqlx::Error> {
+#[async_trait]
+impl UserRepository for PgUserRepository {
+    #[tracing::instrument(name = "UserRepository::create", skip_all)]
+    async fn create(&self, new_user: NewUser) -> Result<User, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            INSERT INTO users (username, email, password_hash, avatar_url, status)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING user_id, username, email, password_hash, avatar_url, created_at, updated_at, status
+            INSERT INTO users (user_id, username, email, password_hash, avatar_url, status)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING user_id, username, email, password_hash, avatar_url, created_at, updated_at, status,
+                      preferred_status, custom_status_text, custom_status_emoji, custom_status_expires_at, email_verified,
+                      disabled_at, sessions_revoked_at
             "#,
+            snowflake::next_id(),
             new_user.username,
             new_user.email,
             new_user.password_hash,
@@ -36,15 +111,25 @@ impl UserRepository {
             avatar_url: record.avatar_url,
             created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
             updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
-            status: record.status
+            status: record.status,
+            preferred_status: record.preferred_status,
+            custom_status_text: record.custom_status_text,
+            custom_status_emoji: record.custom_status_emoji,
+            custom_status_expires_at: record.custom_status_expires_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            email_verified: record.email_verified,
+            disabled_at: record.disabled_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            sessions_revoked_at: record.sessions_revoked_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
         };
         Ok(user)
     }
 
-    pub async fn find_by_id(&self, user_id: i32) -> Result<Option<User>, sqlx::Error> {
+    #[tracing::instrument(name = "UserRepository::find_by_id", skip_all)]
+    async fn find_by_id(&self, user_id: i64) -> Result<Option<User>, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status
+            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status,
+                   preferred_status, custom_status_text, custom_status_emoji, custom_status_expires_at, email_verified,
+                   disabled_at, sessions_revoked_at
             FROM users
             WHERE user_id = $1
             "#,
@@ -61,15 +146,25 @@ impl UserRepository {
             avatar_url: r.avatar_url,
             created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
             updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
-            status: r.status
+            status: r.status,
+            preferred_status: r.preferred_status,
+            custom_status_text: r.custom_status_text,
+            custom_status_emoji: r.custom_status_emoji,
+            custom_status_expires_at: r.custom_status_expires_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            email_verified: r.email_verified,
+            disabled_at: r.disabled_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            sessions_revoked_at: r.sessions_revoked_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
         }))
     }
 
-    pub async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
+    #[tracing::instrument(name = "UserRepository::find_by_username", skip_all)]
+    async fn find_by_username(&self, username: &str) -> Result<Option<User>, sqlx::Error> {
         let record = sqlx::query!(
 
             r#"
-            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status
+            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status,
+                   preferred_status, custom_status_text, custom_status_emoji, custom_status_expires_at, email_verified,
+                   disabled_at, sessions_revoked_at
             FROM users
             WHERE username = $1
             "#,
@@ -86,14 +181,24 @@ impl UserRepository {
             avatar_url: r.avatar_url,
             created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
             updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
-            status: r.status
+            status: r.status,
+            preferred_status: r.preferred_status,
+            custom_status_text: r.custom_status_text,
+            custom_status_emoji: r.custom_status_emoji,
+            custom_status_expires_at: r.custom_status_expires_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            email_verified: r.email_verified,
+            disabled_at: r.disabled_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            sessions_revoked_at: r.sessions_revoked_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
         }))
     }
 
-    pub async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+    #[tracing::instrument(name = "UserRepository::find_by_email", skip_all)]
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
         let record = sqlx::query!(
             r#"
-            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status
+            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status,
+                   preferred_status, custom_status_text, custom_status_emoji, custom_status_expires_at, email_verified,
+                   disabled_at, sessions_revoked_at
             FROM users
             WHERE email = $1
             "#,
@@ -110,14 +215,24 @@ impl UserRepository {
             avatar_url: r.avatar_url,
             created_at: DateTime::from_naive_utc_and_offset(r.created_at, Utc),
             updated_at: r.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
-            status: r.status
+            status: r.status,
+            preferred_status: r.preferred_status,
+            custom_status_text: r.custom_status_text,
+            custom_status_emoji: r.custom_status_emoji,
+            custom_status_expires_at: r.custom_status_expires_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            email_verified: r.email_verified,
+            disabled_at: r.disabled_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+            sessions_revoked_at: r.sessions_revoked_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
         }))
     }
 
-    pub async fn find_all(&self) -> Result<Vec<User>, sqlx::Error> {
+    #[tracing::instrument(name = "UserRepository::find_all", skip_all)]
+    async fn find_all(&self) -> Result<Vec<User>, sqlx::Error> {
         let records = sqlx::query!(
             r#"
-            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status
+            SELECT user_id, username, email, password_hash, avatar_url, created_at, updated_at, status,
+                   preferred_status, custom_status_text, custom_status_emoji, custom_status_expires_at, email_verified,
+                   disabled_at, sessions_revoked_at
             FROM users
             ORDER BY username
             "#
@@ -135,7 +250,14 @@ impl UserRepository {
                 avatar_url: record.avatar_url.clone(),
                 created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
                 updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
-                status: record.status.clone()
+                status: record.status.clone(),
+                preferred_status: record.preferred_status.clone(),
+                custom_status_text: record.custom_status_text.clone(),
+                custom_status_emoji: record.custom_status_emoji.clone(),
+                custom_status_expires_at: record.custom_status_expires_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+                email_verified: record.email_verified,
+                disabled_at: record.disabled_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+                sessions_revoked_at: record.sessions_revoked_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
             };
             vec_users.push(user);
         }
@@ -143,21 +265,25 @@ impl UserRepository {
         Ok(vec_users)
     }
 
-    pub async fn update(&self, user_id: i32, user: User) -> Result<User, sqlx::Error> {
+    #[tracing::instrument(name = "UserRepository::update", skip_all)]
+    async fn update(&self, user_id: i64, user: User) -> Result<User, sqlx::Error> {
         let now = Utc::now();
         let record = sqlx::query!(
             r#"
             UPDATE users
-            SET username = $1, email = $2, password_hash = $3, avatar_url = $4, updated_at = $5, status = $6
+            SET username = $1, email = $2, password_hash = $3, avatar_url = $4, updated_at = $5, preferred_status = $6,
+                email_verified = email_verified AND email = $2::VARCHAR
             WHERE user_id = $7
-            RETURNING user_id, username, email, password_hash, avatar_url, created_at, updated_at, status
+            RETURNING user_id, username, email, password_hash, avatar_url, created_at, updated_at, status,
+                      preferred_status, custom_status_text, custom_status_emoji, custom_status_expires_at, email_verified,
+                      disabled_at, sessions_revoked_at
             "#,
             user.username,
             user.email,
             user.password_hash,
             user.avatar_url,
             now as _,
-            user.status,
+            user.preferred_status,
             user_id
         )
         .fetch_one(&self.pool)
@@ -171,12 +297,20 @@ impl UserRepository {
         avatar_url: record.avatar_url,
         created_at: DateTime::from_naive_utc_and_offset(record.created_at, Utc),
         updated_at: record.updated_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
-        status: record.status
+        status: record.status,
+        preferred_status: record.preferred_status,
+        custom_status_text: record.custom_status_text,
+        custom_status_emoji: record.custom_status_emoji,
+        custom_status_expires_at: record.custom_status_expires_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+        email_verified: record.email_verified,
+        disabled_at: record.disabled_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+        sessions_revoked_at: record.sessions_revoked_at.map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
     };
         Ok(updated_user)
     }
 
-    pub async fn delete(&self, user_id: i32) -> Result<bool, sqlx::Error> {
+    #[tracing::instrument(name = "UserRepository::delete", skip_all)]
+    async fn delete(&self, user_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query!(
             r#"
             DELETE FROM users
@@ -190,14 +324,214 @@ impl UserRepository {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn to_response(&self, user: User) -> UserResponse {
-        UserResponse {
-            user_id: user.user_id,
-            username: user.username,
-            email: user.email,
-            avatar_url: user.avatar_url,
-            status: user.status,
-            created_at: user.created_at,
-        }
+    #[tracing::instrument(name = "UserRepository::mark_email_verified", skip_all)]
+    async fn mark_email_verified(&self, user_id: i64, email: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email_verified = TRUE
+            WHERE user_id = $1 AND email = $2
+            "#,
+            user_id,
+            email
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_password", skip_all)]
+    async fn update_password(&self, user_id: i64, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $1, updated_at = $2
+            WHERE user_id = $3
+            "#,
+            password_hash,
+            now as _,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::set_disabled", skip_all)]
+    async fn set_disabled(&self, user_id: i64, disabled: bool) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let result = if disabled {
+            sqlx::query!(
+                r#"
+                UPDATE users
+                SET disabled_at = COALESCE(disabled_at, $1), sessions_revoked_at = $1
+                WHERE user_id = $2
+                "#,
+                now.naive_utc(),
+                user_id
+            )
+            .execute(&self.pool)
+            .await?
+        } else {
+            sqlx::query!(
+                r#"
+                UPDATE users
+                SET disabled_at = NULL
+                WHERE user_id = $1
+                "#,
+                user_id
+            )
+            .execute(&self.pool)
+            .await?
+        };
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::revoke_sessions", skip_all)]
+    async fn revoke_sessions(&self, user_id: i64) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET sessions_revoked_at = $1
+            WHERE user_id = $2
+            "#,
+            now.naive_utc(),
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::purge_disabled", skip_all)]
+    async fn purge_disabled(&self, disabled_before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE disabled_at < $1
+            "#,
+            disabled_before.naive_utc()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_status", skip_all)]
+    async fn update_status(&self, user_id: i64, status: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET status = $1
+            WHERE user_id = $2
+            "#,
+            status,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_preferred_status", skip_all)]
+    async fn update_preferred_status(&self, user_id: i64, preferred_status: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET preferred_status = $1
+            WHERE user_id = $2
+            "#,
+            preferred_status,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::update_custom_status", skip_all)]
+    async fn update_custom_status(&self, user_id: i64, custom_status: Option<CustomStatus>) -> Result<bool, sqlx::Error> {
+        let (text, emoji, expires_at) = match custom_status {
+            Some(status) => (status.text, status.emoji, status.expires_at),
+            None => (None, None, None),
+        };
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET custom_status_text = $1, custom_status_emoji = $2, custom_status_expires_at = $3
+            WHERE user_id = $4
+            "#,
+            text,
+            emoji,
+            expires_at.map(|dt| dt.naive_utc()),
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "UserRepository::clear_expired_custom_statuses", skip_all)]
+    async fn clear_expired_custom_statuses(&self) -> Result<Vec<i64>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            UPDATE users
+            SET custom_status_text = NULL, custom_status_emoji = NULL, custom_status_expires_at = NULL
+            WHERE custom_status_expires_at IS NOT NULL AND custom_status_expires_at <= NOW() AT TIME ZONE 'UTC'
+            RETURNING user_id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records.into_iter().map(|r| r.user_id).collect())
+    }
+
+    #[tracing::instrument(name = "UserRepository::reset_all_statuses", skip_all)]
+    async fn reset_all_statuses(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET status = 'offline'
+            WHERE status <> 'offline'
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "UserRepository::find_presence_audience", skip_all)]
+    async fn find_presence_audience(&self, user_id: i64) -> Result<Vec<i64>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            SELECT other.user_id as "user_id!"
+            FROM server_members mine
+            JOIN server_members other ON other.server_id = mine.server_id
+            WHERE mine.user_id = $1 AND other.user_id <> $1
+            UNION
+            SELECT other.user_id as "user_id!"
+            FROM direct_message_members mine
+            JOIN direct_message_members other ON other.channel_id = mine.channel_id
+            WHERE mine.user_id = $1 AND other.user_id <> $1
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records.into_iter().map(|r| r.user_id).collect())
     }
 }
@@ -1,64 +1,240 @@
 // src/router.rs
+use crate::auth::SessionKeys;
+use crate::config::{Config, CorsConfig};
+use crate::gateway::{gateway_handler, GatewayHub};
 use crate::handlers::{
+    account_handlers::{forgot_password, resend_verification, reset_password, verify_email},
+    channel_handlers::{delete_channel, trigger_typing, update_channel, update_channel_slowmode},
+    health_handlers::{healthz, metrics, readyz},
+    mfa_handlers::{begin_totp_enrollment, confirm_totp_enrollment, disable_totp, login_mfa},
+    message_handlers::{
+        create_message, get_channel_messages, get_pinned_messages, pin_message, search_channel_messages,
+        unpin_message,
+    },
     server_handlers::{
-        create_server, delete_server, get_all_servers, get_server, get_servers_by_owner,
-        update_server,
+        add_server_member, create_server, delete_server, get_all_servers, get_server, get_servers_by_owner,
+        remove_server_member, transfer_server, update_member_permissions, update_server, update_server_mfa,
+    },
+    user_handlers::{
+        clear_custom_status, create_user, login_attempt, delete_user, get_all_users, get_user,
+        get_user_by_username, get_user_presence, update_custom_status, update_user,
     },
-    user_handlers::{create_user, login_attempt, delete_user, get_all_users, get_user, get_user_by_username, update_user},
 };
+use crate::metrics::{track_requests, Metrics};
+use crate::openapi::openapi_json;
+use crate::rate_limit::{rate_limit, RateLimiter, TrustedProxies};
+use crate::repositories::{
+    ChannelRepository, HealthRepository, MessageRepository, MfaRepository, RepositoryCache, ServerMemberRepository,
+    ServerRepository, UserRepository,
+};
+use crate::services::{
+    ChannelAccess, ChannelService, EmailService, LoginGuard, MemberService, MessageService, PresenceService, ReadyService,
+    SlowmodeService, TypingService,
+};
+use crate::shutdown::Shutdown;
+use crate::telemetry::{record_response, request_span, REQUEST_ID_HEADER};
+use crate::versioning::{deprecated, Deprecation};
 use axum::{
+    extract::DefaultBodyLimit,
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware,
     routing::{delete, get, post, put},
     Router,
 };
-
-use sqlx::{Pool, Postgres};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use std::sync::Arc;
 
 // Create a common AppState that combines both repositories
 #[derive(Clone)]
 pub struct AppState {
-    pub pool: Pool<Postgres>,
-    pub user_repository: crate::repositories::UserRepository,
-    pub server_repository: crate::repositories::ServerRepository,
+    pub user_repository: Arc<dyn UserRepository>,
+    /// The user repository without the cache in front, for checking
+    /// sessions: a disabled account or revoked session has to be refused
+    /// at once, even when another instance or `songbird-admin` changed it.
+    pub session_user_repository: Arc<dyn UserRepository>,
+    pub server_repository: Arc<dyn ServerRepository>,
+    pub server_member_repository: Arc<dyn ServerMemberRepository>,
+    pub message_repository: Arc<dyn MessageRepository>,
+    pub channel_repository: Arc<dyn ChannelRepository>,
+    pub mfa_repository: Arc<dyn MfaRepository>,
+    pub health_repository: Arc<dyn HealthRepository>,
+    pub session_keys: SessionKeys,
+    pub gateway: GatewayHub,
+    /// In front of the repositories above when `cache.enabled`.
+    pub cache: Option<RepositoryCache>,
+    pub presence: PresenceService,
+    pub ready: ReadyService,
+    pub channel_access: ChannelAccess,
+    pub typing: TypingService,
+    pub messages: MessageService,
+    pub members: MemberService,
+    pub channels: ChannelService,
+    pub slowmode: SlowmodeService,
+    pub rate_limiter: RateLimiter,
+    /// Whose forwarded client addresses the rate limiter and login guard
+    /// believe.
+    pub trusted_proxies: TrustedProxies,
+    pub login_guard: LoginGuard,
+    pub email: EmailService,
+    pub shutdown: Shutdown,
+    pub metrics: Metrics,
+}
+
+pub fn create_router(app_state: AppState, config: &Config) -> Router {
+    let v1 = api_v1(config);
+
+    let mut router = Router::new()
+        .nest("/api/v1", v1.clone())
+        // The unversioned paths from before v1, an alias for it until the sunset
+        .nest(
+            "/api",
+            v1.layer(middleware::from_fn_with_state(Deprecation::unversioned_api(), deprecated)),
+        );
+
+    if config.rate_limit.enabled {
+        router = router.layer(middleware::from_fn_with_state(app_state.clone(), rate_limit));
+    }
+    // Outside the rate limiter so rejected requests are counted and logged too
+    router = router
+        .layer(middleware::from_fn_with_state(app_state.clone(), track_requests))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_request(())
+                .on_response(record_response)
+                .on_failure(()),
+        );
+
+    // Probes, scrapes and the API description are neither rate limited nor counted
+    router = router
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
+        .route("/api/openapi.json", get(openapi_json));
+
+    #[cfg(feature = "swagger-ui")]
+    {
+        router = router.merge(
+            utoipa_swagger_ui::SwaggerUi::new("/api/docs")
+                .config(utoipa_swagger_ui::Config::from("/api/openapi.json")),
+        );
+    }
+
+    // Keeps an X-Request-Id from a proxy in front of us, or makes one up,
+    // and echoes it back so clients can quote it
+    router = router
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid));
+
+    // Outermost, so preflight requests are answered before anything else
+    if let Some(cors) = cors_layer(&config.cors) {
+        router = router.layer(cors);
+    }
+
+    router.with_state(app_state)
 }
 
-pub fn create_router(app_state: AppState) -> Router {
+/// Version 1 of the API, relative to wherever it is nested.
+fn api_v1(config: &Config) -> Router<AppState> {
     Router::new()
         // Login Route
-        .route("/api/login", post(login_attempt))
+        .route("/login", post(login_attempt))
+        .route("/login/mfa", post(login_mfa))
+        // Account recovery and email verification
+        .route("/email/verify", post(verify_email))
+        .route("/password/forgot", post(forgot_password))
+        .route("/password/reset", post(reset_password))
+        // Gateway
+        .route("/gateway", get(gateway_handler))
         // User routes
-        .route("/api/users/create", post(create_user))
-        .route("/api/users", get(get_all_users))
-        .route("/api/users/{user_id}", get(get_user))
-        .route("/api/users/by_username/{username}", get(get_user_by_username))
-        .route("/api/users/{user_id}", put(update_user))
-        .route("/api/users/{user_id}", delete(delete_user))
+        .route("/users/create", post(create_user))
+        .route("/users", get(get_all_users))
+        .route("/users/{user_id}", get(get_user))
+        .route("/users/by_username/{username}", get(get_user_by_username))
+        .route("/users/{user_id}", put(update_user))
+        .route("/users/{user_id}", delete(delete_user))
+        .route("/users/{user_id}/presence", get(get_user_presence))
+        .route("/users/{user_id}/custom_status", put(update_custom_status))
+        .route("/users/{user_id}/custom_status", delete(clear_custom_status))
+        .route("/users/{user_id}/email/verification", post(resend_verification))
+        .route("/users/{user_id}/mfa/totp", post(begin_totp_enrollment))
+        .route("/users/{user_id}/mfa/totp", delete(disable_totp))
+        .route("/users/{user_id}/mfa/totp/confirm", post(confirm_totp_enrollment))
         // Server routes
-        .route("/api/servers", post(create_server))
-        .route("/api/servers", get(get_all_servers))
-        .route("/api/servers/{server_id}", get(get_server))
-        .route("/api/servers/{server_id}", put(update_server))
-        .route("/api/servers/{server_id}", delete(delete_server))
-        .route("/api/servers/owner/{owner_user_id}", get(get_servers_by_owner))
+        .route("/servers", post(create_server))
+        .route("/servers", get(get_all_servers))
+        .route("/servers/{server_id}", get(get_server))
+        .route("/servers/{server_id}", put(update_server))
+        .route("/servers/{server_id}", delete(delete_server))
+        .route("/servers/owner/{owner_user_id}", get(get_servers_by_owner))
+        .route("/servers/{server_id}/owner", put(transfer_server))
+        .route("/servers/{server_id}/mfa", put(update_server_mfa))
+        .route(
+            "/servers/{server_id}/members/{user_id}/permissions",
+            put(update_member_permissions),
+        )
+        .route("/servers/{server_id}/members", post(add_server_member))
+        .route("/servers/{server_id}/members/{user_id}", delete(remove_server_member))
         // Commented out routes for server members until they are implemented
-        // .route("/api/servers/:server_id/members", get(get_server_members))
-        // .route("/api/servers/:server_id/members", post(add_server_member))
-        // .route(
-        //     "/api/servers/:server_id/members/:user_id",
-        //     delete(remove_server_member),
-        // )
+        // .route("/servers/:server_id/members", get(get_server_members))
         // Channel routes
-        // .route("/api/channels", post(create_channel))
-        // .route("/api/channels/:channel_id", get(get_channel))
-        // .route("/api/channels/:channel_id", put(update_channel))
-        // .route("/api/channels/:channel_id", delete(delete_channel))
-        // .route("/api/servers/:server_id/channels", get(get_server_channels))
+        // .route("/channels", post(create_channel))
+        // .route("/channels/:channel_id", get(get_channel))
+        .route("/channels/{channel_id}", put(update_channel))
+        .route("/channels/{channel_id}", delete(delete_channel))
+        // .route("/servers/:server_id/channels", get(get_server_channels))
+        .route("/channels/{channel_id}/slowmode", put(update_channel_slowmode))
+        .route("/channels/{channel_id}/typing", post(trigger_typing))
         // Message routes
-        // .route("/api/channels/:channel_id/messages", post(create_message))
-        // .route("/api/channels/:channel_id/messages", get(get_channel_messages))
-        // .route("/api/messages/:message_id", put(update_message))
-        // .route("/api/messages/:message_id", delete(delete_message))
+        .route("/channels/{channel_id}/messages", post(create_message))
+        .route("/channels/{channel_id}/messages", get(get_channel_messages))
+        .route("/channels/{channel_id}/messages/search", get(search_channel_messages))
+        .route("/channels/{channel_id}/pins", get(get_pinned_messages))
+        .route("/channels/{channel_id}/pins/{message_id}", put(pin_message))
+        .route("/channels/{channel_id}/pins/{message_id}", delete(unpin_message))
+        // .route("/messages/:message_id", put(update_message))
+        // .route("/messages/:message_id", delete(delete_message))
         // Direct message routes
-        // .route("/api/dm", post(create_dm_channel))
-        // .route("/api/users/:user_id/dm", get(get_user_dm_channels))
-        .with_state(app_state)
+        // .route("/dm", post(create_dm_channel))
+        // .route("/users/:user_id/dm", get(get_user_dm_channels))
+        .layer(DefaultBodyLimit::max(config.limits.max_body_bytes))
+}
+
+/// `None` when no origins are configured, in which case browsers only allow
+/// same-origin requests.
+fn cors_layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+
+    let origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        // Checked by `Config::validate`
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+            .expose_headers([
+                header::RETRY_AFTER,
+                HeaderName::from_static("x-ratelimit-limit"),
+                HeaderName::from_static("x-ratelimit-remaining"),
+                HeaderName::from_static("x-ratelimit-reset"),
+                REQUEST_ID_HEADER,
+                header::LINK,
+                HeaderName::from_static("deprecation"),
+                HeaderName::from_static("sunset"),
+            ]),
+    )
 }
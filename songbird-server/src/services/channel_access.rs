@@ -0,0 +1,116 @@
+// src/services/channel_access.rs
+use crate::models::models::{Channel, Permissions};
+use crate::repositories::{ChannelRepository, MfaRepository, ServerMemberRepository, ServerRepository};
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum ChannelAccessError {
+    NotFound,
+    Forbidden,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ChannelAccessError {
+    fn from(e: sqlx::Error) -> Self {
+        ChannelAccessError::Database(e)
+    }
+}
+
+/// Answers "who can see this channel": server members for server channels,
+/// and the participants for direct message channels.
+#[derive(Clone)]
+pub struct ChannelAccess {
+    channel_repository: Arc<dyn ChannelRepository>,
+    server_repository: Arc<dyn ServerRepository>,
+    server_member_repository: Arc<dyn ServerMemberRepository>,
+    mfa_repository: Arc<dyn MfaRepository>,
+}
+
+impl ChannelAccess {
+    pub fn new(
+        channel_repository: Arc<dyn ChannelRepository>,
+        server_repository: Arc<dyn ServerRepository>,
+        server_member_repository: Arc<dyn ServerMemberRepository>,
+        mfa_repository: Arc<dyn MfaRepository>,
+    ) -> Self {
+        Self {
+            channel_repository,
+            server_repository,
+            server_member_repository,
+            mfa_repository,
+        }
+    }
+
+    /// Loads the channel and checks that `user_id` is allowed to see it.
+    pub async fn authorize(&self, channel_id: i64, user_id: i64) -> Result<Channel, ChannelAccessError> {
+        let channel = self
+            .channel_repository
+            .find_by_id(channel_id)
+            .await?
+            .ok_or(ChannelAccessError::NotFound)?;
+
+        let allowed = match channel.server_id {
+            Some(server_id) => self.server_member_repository.is_member(server_id, user_id).await?,
+            None => {
+                self.channel_repository
+                    .is_direct_message_member(channel_id, user_id)
+                    .await?
+            }
+        };
+
+        if allowed {
+            Ok(channel)
+        } else {
+            Err(ChannelAccessError::Forbidden)
+        }
+    }
+
+    pub async fn viewers(&self, channel: &Channel) -> Result<Vec<i64>, sqlx::Error> {
+        match channel.server_id {
+            Some(server_id) => Ok(self
+                .server_member_repository
+                .find_by_server(server_id)
+                .await?
+                .into_iter()
+                .map(|member| member.user_id)
+                .collect()),
+            None => {
+                self.channel_repository
+                    .find_direct_message_member_ids(channel.channel_id)
+                    .await
+            }
+        }
+    }
+
+    /// What `user_id` may do in `channel`. Server owners can do everything,
+    /// other members get the bits stored on their membership. Both
+    /// participants of a direct message may manage its messages.
+    ///
+    /// On servers that require 2FA, moderation permissions (owner's
+    /// included) only apply once the user has 2FA enabled.
+    pub async fn permissions(&self, channel: &Channel, user_id: i64) -> Result<Permissions, sqlx::Error> {
+        let Some(server_id) = channel.server_id else {
+            return Ok(Permissions::MANAGE_MESSAGES);
+        };
+
+        let Some(server) = self.server_repository.find_by_id(server_id).await? else {
+            return Ok(Permissions::NONE);
+        };
+
+        let permissions = if server.owner_user_id == user_id {
+            Permissions::ALL
+        } else {
+            self.server_member_repository
+                .find_by_id(server_id, user_id)
+                .await?
+                .map(|member| Permissions::from_bits_truncate(member.permissions))
+                .unwrap_or(Permissions::NONE)
+        };
+
+        if server.mfa_required && permissions != Permissions::NONE && !self.mfa_repository.is_enabled(user_id).await? {
+            return Ok(Permissions::NONE);
+        }
+
+        Ok(permissions)
+    }
+}
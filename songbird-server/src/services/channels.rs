@@ -0,0 +1,96 @@
+// src/services/channels.rs
+use crate::gateway::events::{ChannelDeleteEvent, DispatchEvent};
+use crate::gateway::GatewayHub;
+use crate::models::models::Channel;
+use crate::repositories::ChannelRepository;
+use crate::services::{ChannelAccess, MessageService};
+use std::sync::Arc;
+
+/// Channel changes that clients are told about as they happen.
+#[derive(Clone)]
+pub struct ChannelService {
+    channel_repository: Arc<dyn ChannelRepository>,
+    channel_access: ChannelAccess,
+    messages: MessageService,
+    hub: GatewayHub,
+}
+
+impl ChannelService {
+    pub fn new(
+        channel_repository: Arc<dyn ChannelRepository>,
+        channel_access: ChannelAccess,
+        messages: MessageService,
+        hub: GatewayHub,
+    ) -> Self {
+        Self {
+            channel_repository,
+            channel_access,
+            messages,
+            hub,
+        }
+    }
+
+    /// Renames the channel and sends the `channel_rename` message the
+    /// repository posts when the name actually changes.
+    pub async fn rename(&self, channel_id: i64, name: String, renamed_by_user_id: i64) -> Result<Channel, sqlx::Error> {
+        let (channel, system_message) = self
+            .channel_repository
+            .update(channel_id, name, renamed_by_user_id)
+            .await?;
+
+        if let Some(message) = system_message {
+            self.publish_update(&channel).await;
+            self.messages.publish(message).await;
+        }
+
+        Ok(channel)
+    }
+
+    /// `None` if there is no such channel.
+    pub async fn set_rate_limit(&self, channel_id: i64, rate_limit_per_user: i32) -> Result<Option<Channel>, sqlx::Error> {
+        let channel = self
+            .channel_repository
+            .update_rate_limit(channel_id, rate_limit_per_user)
+            .await?;
+
+        if let Some(channel) = &channel {
+            self.publish_update(channel).await;
+        }
+
+        Ok(channel)
+    }
+
+    /// Returns whether the channel existed. Whoever could see it is told.
+    pub async fn delete(&self, channel_id: i64) -> Result<bool, sqlx::Error> {
+        let Some(channel) = self.channel_repository.find_by_id(channel_id).await? else {
+            return Ok(false);
+        };
+        let audience = self.viewers(&channel).await;
+
+        if !self.channel_repository.delete(channel_id).await? {
+            return Ok(false);
+        }
+
+        let event = DispatchEvent::ChannelDelete(ChannelDeleteEvent {
+            channel_id,
+            server_id: channel.server_id,
+        });
+        self.hub.publish(audience, event);
+
+        Ok(true)
+    }
+
+    async fn publish_update(&self, channel: &Channel) {
+        let audience = self.viewers(channel).await;
+        self.hub.publish(audience, DispatchEvent::ChannelUpdate(channel.clone()));
+    }
+
+    /// The change has happened either way, so a failed lookup only costs
+    /// the viewers their event.
+    async fn viewers(&self, channel: &Channel) -> Vec<i64> {
+        self.channel_access.viewers(channel).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to load who can see channel {}: {}", channel.channel_id, e);
+            Vec::new()
+        })
+    }
+}
@@ -0,0 +1,153 @@
+// src/services/email.rs
+use crate::auth::{EmailTokenPurpose, SessionKeys};
+use crate::mailer::{Email, Mailer, MailerError};
+use crate::models::models::User;
+use crate::repositories::EmailTokenRepository;
+use crate::shutdown::Shutdown;
+use chrono::DateTime;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum EmailError {
+    Token(jsonwebtoken::errors::Error),
+    Database(sqlx::Error),
+    Mail(MailerError),
+}
+
+impl From<jsonwebtoken::errors::Error> for EmailError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        EmailError::Token(e)
+    }
+}
+
+impl From<sqlx::Error> for EmailError {
+    fn from(e: sqlx::Error) -> Self {
+        EmailError::Database(e)
+    }
+}
+
+impl From<MailerError> for EmailError {
+    fn from(e: MailerError) -> Self {
+        EmailError::Mail(e)
+    }
+}
+
+impl std::fmt::Display for EmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmailError::Token(e) => write!(f, "token error: {}", e),
+            EmailError::Database(e) => write!(f, "database error: {}", e),
+            EmailError::Mail(e) => write!(f, "mail error: {}", e),
+        }
+    }
+}
+
+/// A redeemed email token: who it was for and the address it was sent to.
+#[derive(Debug, Clone)]
+pub struct RedeemedToken {
+    pub user_id: i64,
+    pub email: String,
+}
+
+/// Sends verification and password reset emails and redeems the tokens in
+/// them. Links point at `public_url`, where the client picks up the token.
+#[derive(Clone)]
+pub struct EmailService {
+    mailer: Arc<dyn Mailer>,
+    session_keys: SessionKeys,
+    email_token_repository: Arc<dyn EmailTokenRepository>,
+    public_url: String,
+    shutdown: Shutdown,
+}
+
+impl EmailService {
+    pub fn new(
+        mailer: Arc<dyn Mailer>,
+        session_keys: SessionKeys,
+        email_token_repository: Arc<dyn EmailTokenRepository>,
+        public_url: String,
+        shutdown: Shutdown,
+    ) -> Self {
+        Self {
+            mailer,
+            session_keys,
+            email_token_repository,
+            public_url: public_url.trim_end_matches('/').to_string(),
+            shutdown,
+        }
+    }
+
+    pub async fn send_verification(&self, user: &User) -> Result<(), EmailError> {
+        let token = self.issue(user, EmailTokenPurpose::VerifyEmail).await?;
+        let body = format!(
+            "Hi {},\n\nConfirm your email address for Songbird by opening this link:\n\n{}/verify-email?token={}\n\nThe link expires in 24 hours.\n",
+            user.username, self.public_url, token
+        );
+
+        self.send(&user.email, "Verify your Songbird email address", body).await
+    }
+
+    /// Sends the verification email without holding up the request; a
+    /// failure is only logged since the user can ask for another one.
+    pub fn spawn_verification(&self, user: User) {
+        let service = self.clone();
+        self.shutdown.spawn(async move {
+            if let Err(e) = service.send_verification(&user).await {
+                tracing::warn!("Failed to send verification email to user {}: {}", user.user_id, e);
+            }
+        });
+    }
+
+    pub async fn send_password_reset(&self, user: &User) -> Result<(), EmailError> {
+        let token = self.issue(user, EmailTokenPurpose::ResetPassword).await?;
+        let body = format!(
+            "Hi {},\n\nSomeone asked to reset the password of your Songbird account. If it was you, open this link:\n\n{}/reset-password?token={}\n\nThe link expires in an hour. If you did not ask for this, you can ignore this email.\n",
+            user.username, self.public_url, token
+        );
+
+        self.send(&user.email, "Reset your Songbird password", body).await
+    }
+
+    /// Checks the signature, expiry and purpose of `token` and uses it up.
+    /// `None` means the token is invalid, expired or already used.
+    pub async fn redeem(&self, token: &str, purpose: EmailTokenPurpose) -> Result<Option<RedeemedToken>, EmailError> {
+        let Ok(claims) = self.session_keys.verify_email_token(token) else {
+            return Ok(None);
+        };
+        if claims.purpose != purpose {
+            return Ok(None);
+        }
+
+        let redeemed = self
+            .email_token_repository
+            .consume(&claims.jti, purpose.as_str())
+            .await?;
+
+        Ok(redeemed.and_then(|(user_id, email)| {
+            (user_id == claims.sub && email == claims.email).then_some(RedeemedToken { user_id, email })
+        }))
+    }
+
+    async fn issue(&self, user: &User, purpose: EmailTokenPurpose) -> Result<String, EmailError> {
+        let (token, claims) = self
+            .session_keys
+            .issue_email_token(user.user_id, purpose, &user.email)?;
+        let expires_at = DateTime::from_timestamp(claims.exp, 0).unwrap_or_default();
+
+        self.email_token_repository
+            .create(&claims.jti, user.user_id, purpose.as_str(), &user.email, expires_at)
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), EmailError> {
+        let email = Email {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body,
+        };
+
+        self.mailer.send(email).await.map_err(EmailError::from)
+    }
+}
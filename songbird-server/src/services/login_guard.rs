@@ -0,0 +1,111 @@
+// src/services/login_guard.rs
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Failures an account can have before every further attempt is delayed.
+pub const ACCOUNT_FREE_ATTEMPTS: u32 = 3;
+/// Addresses get more room since several people can share one.
+pub const IP_FREE_ATTEMPTS: u32 = 10;
+/// The backoff doubles up to this, at which point the account or address is
+/// effectively locked out until it expires.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+/// Counters are forgotten once nothing has failed for this long.
+pub const FAILURE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// How long to refuse logins after `failures` consecutive failures:
+/// nothing for the first `free_attempts`, then 1s, 2s, 4s, ... up to
+/// `MAX_BACKOFF`.
+pub fn backoff(failures: u32, free_attempts: u32) -> Duration {
+    if failures < free_attempts {
+        return Duration::ZERO;
+    }
+
+    let exponent = (failures - free_attempts).min(16);
+    Duration::from_secs(1u64 << exponent).min(MAX_BACKOFF)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum LoginKey {
+    // Keyed by the submitted username whether or not it exists, so locked
+    // out and unknown accounts look the same from outside.
+    Account(Arc<str>),
+    Ip(IpAddr),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+}
+
+/// Counts failed logins per account and per client address and makes
+/// `login_attempt` back off exponentially as they pile up.
+#[derive(Clone, Default)]
+pub struct LoginGuard {
+    failures: Arc<Mutex<HashMap<LoginKey, Failures>>>,
+}
+
+impl LoginGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long the caller has to wait before trying this username from this
+    /// address again, if at all.
+    pub fn check(&self, username: &str, ip: Option<IpAddr>) -> Result<(), Duration> {
+        let now = Instant::now();
+        let failures = self.failures.lock().unwrap();
+
+        let wait = Self::keys(username, ip)
+            .filter_map(|(key, free_attempts)| {
+                let entry = failures.get(&key)?;
+                let locked_until = entry.last_failure + backoff(entry.count, free_attempts);
+                locked_until.checked_duration_since(now)
+            })
+            .max()
+            .unwrap_or_default();
+
+        if wait.is_zero() {
+            Ok(())
+        } else {
+            Err(wait)
+        }
+    }
+
+    pub fn record_failure(&self, username: &str, ip: Option<IpAddr>) {
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+        failures.retain(|_, entry| now.duration_since(entry.last_failure) < FAILURE_WINDOW);
+
+        for (key, _) in Self::keys(username, ip) {
+            let entry = failures.entry(key).or_insert(Failures {
+                count: 0,
+                last_failure: now,
+            });
+            entry.count += 1;
+            entry.last_failure = now;
+        }
+    }
+
+    /// Clears the account's counter after a successful login. The address
+    /// keeps its count, otherwise logging into a throwaway account between
+    /// guesses would reset it.
+    pub fn record_success(&self, username: &str) {
+        self.clear_account(username);
+    }
+
+    /// Lifts a lockout, e.g. once the owner has reset their password.
+    pub fn clear_account(&self, username: &str) {
+        self.failures
+            .lock()
+            .unwrap()
+            .remove(&LoginKey::Account(username.into()));
+    }
+
+    fn keys(username: &str, ip: Option<IpAddr>) -> impl Iterator<Item = (LoginKey, u32)> {
+        std::iter::once((LoginKey::Account(username.into()), ACCOUNT_FREE_ATTEMPTS))
+            .chain(ip.map(|ip| (LoginKey::Ip(ip), IP_FREE_ATTEMPTS)))
+    }
+}
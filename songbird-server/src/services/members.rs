@@ -0,0 +1,75 @@
+// src/services/members.rs
+use crate::gateway::events::{DispatchEvent, ServerMemberRemoveEvent};
+use crate::gateway::GatewayHub;
+use crate::models::models::{NewServerMember, ServerMember};
+use crate::repositories::ServerMemberRepository;
+use crate::services::MessageService;
+use std::sync::Arc;
+
+/// Joins and leaves servers. The server's members hear about the change,
+/// and the repository posts a `member_join` or `member_leave` message in
+/// the server's system channel along with it; this sends that to the
+/// members who can see that channel.
+#[derive(Clone)]
+pub struct MemberService {
+    server_member_repository: Arc<dyn ServerMemberRepository>,
+    messages: MessageService,
+    hub: GatewayHub,
+}
+
+impl MemberService {
+    pub fn new(
+        server_member_repository: Arc<dyn ServerMemberRepository>,
+        messages: MessageService,
+        hub: GatewayHub,
+    ) -> Self {
+        Self {
+            server_member_repository,
+            messages,
+            hub,
+        }
+    }
+
+    pub async fn join(&self, new_server_member: NewServerMember) -> Result<ServerMember, sqlx::Error> {
+        let (member, system_message) = self.server_member_repository.create(new_server_member).await?;
+
+        let audience = self.member_ids(member.server_id).await;
+        self.hub.publish(audience, DispatchEvent::ServerMemberAdd(member.clone()));
+
+        if let Some(message) = system_message {
+            self.messages.publish(message).await;
+        }
+
+        Ok(member)
+    }
+
+    /// Returns whether the user was a member.
+    pub async fn leave(&self, server_id: i64, user_id: i64) -> Result<bool, sqlx::Error> {
+        let (removed, system_message) = self.server_member_repository.delete(server_id, user_id).await?;
+
+        if removed {
+            let mut audience = self.member_ids(server_id).await;
+            audience.push(user_id);
+            let event = DispatchEvent::ServerMemberRemove(ServerMemberRemoveEvent { server_id, user_id });
+            self.hub.publish(audience, event);
+        }
+
+        if let Some(message) = system_message {
+            self.messages.publish(message).await;
+        }
+
+        Ok(removed)
+    }
+
+    /// The change has happened either way, so a failed lookup only costs
+    /// the members their event.
+    async fn member_ids(&self, server_id: i64) -> Vec<i64> {
+        match self.server_member_repository.find_by_server(server_id).await {
+            Ok(members) => members.into_iter().map(|member| member.user_id).collect(),
+            Err(e) => {
+                tracing::warn!("Failed to load the members of server {}: {}", server_id, e);
+                Vec::new()
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// src/services/messages.rs
+use crate::gateway::events::DispatchEvent;
+use crate::gateway::GatewayHub;
+use crate::metrics::Metrics;
+use crate::models::models::{Channel, Message, MessageWithAuthorResponse, NewMessage, UserResponse};
+use crate::repositories::{ChannelRepository, MessageRepository, UserRepository};
+use crate::services::{ChannelAccess, TypingService};
+use std::sync::Arc;
+
+/// Stores a message and fans it out to everyone who can see the channel.
+/// Both user messages and the system messages the server generates go
+/// through here so clients receive them the same way.
+#[derive(Clone)]
+pub struct MessageService {
+    message_repository: Arc<dyn MessageRepository>,
+    user_repository: Arc<dyn UserRepository>,
+    channel_repository: Arc<dyn ChannelRepository>,
+    channel_access: ChannelAccess,
+    typing: TypingService,
+    hub: GatewayHub,
+    metrics: Metrics,
+}
+
+impl MessageService {
+    pub fn new(
+        message_repository: Arc<dyn MessageRepository>,
+        user_repository: Arc<dyn UserRepository>,
+        channel_repository: Arc<dyn ChannelRepository>,
+        channel_access: ChannelAccess,
+        typing: TypingService,
+        hub: GatewayHub,
+        metrics: Metrics,
+    ) -> Self {
+        Self {
+            message_repository,
+            user_repository,
+            channel_repository,
+            channel_access,
+            typing,
+            hub,
+            metrics,
+        }
+    }
+
+    pub async fn post(&self, channel: &Channel, new_message: NewMessage) -> Result<MessageWithAuthorResponse, sqlx::Error> {
+        let author = self
+            .user_repository
+            .find_by_id(new_message.author_user_id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let author = self.user_repository.to_response(author).await;
+
+        let message = self.message_repository.create(new_message).await?;
+        self.metrics.message_created();
+        let response = with_author(message, author);
+
+        match self.channel_access.viewers(channel).await {
+            Ok(viewers) => {
+                self.typing
+                    .stop(channel.channel_id, response.author.user_id, &viewers);
+                let event = DispatchEvent::MessageCreate(response.clone());
+                self.hub.dispatch_to_users(viewers, &event);
+            }
+            Err(e) => tracing::warn!("Failed to fan out message {}: {}", response.message_id, e),
+        }
+
+        Ok(response)
+    }
+
+    /// Fans out a message that is already stored: the system messages the
+    /// repositories post in the same transaction as a join, a leave or a
+    /// channel rename. Failures are logged, since the change they announce
+    /// has happened either way.
+    pub async fn publish(&self, message: Message) {
+        let message_id = message.message_id;
+        if let Err(e) = self.try_publish(message).await {
+            tracing::warn!("Failed to fan out message {}: {}", message_id, e);
+        }
+    }
+
+    async fn try_publish(&self, message: Message) -> Result<(), sqlx::Error> {
+        self.metrics.message_created();
+
+        let channel = self
+            .channel_repository
+            .find_by_id(message.channel_id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let author = self
+            .user_repository
+            .find_by_id(message.author_user_id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let author = self.user_repository.to_response(author).await;
+
+        let viewers = self.channel_access.viewers(&channel).await?;
+        let event = DispatchEvent::MessageCreate(with_author(message, author));
+        self.hub.dispatch_to_users(viewers, &event);
+
+        Ok(())
+    }
+}
+
+fn with_author(message: Message, author: UserResponse) -> MessageWithAuthorResponse {
+    MessageWithAuthorResponse {
+        message_id: message.message_id,
+        channel_id: message.channel_id,
+        content: message.content,
+        message_type: message.message_type,
+        referenced_message_id: message.referenced_message_id,
+        author,
+        created_at: message.created_at,
+        edited_at: message.edited_at,
+    }
+}
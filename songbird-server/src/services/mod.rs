@@ -0,0 +1,21 @@
+pub mod channel_access;
+pub mod channels;
+pub mod email;
+pub mod login_guard;
+pub mod members;
+pub mod messages;
+pub mod presence;
+pub mod ready;
+pub mod slowmode;
+pub mod typing;
+
+pub use channel_access::{ChannelAccess, ChannelAccessError};
+pub use channels::ChannelService;
+pub use email::EmailService;
+pub use login_guard::LoginGuard;
+pub use members::MemberService;
+pub use messages::MessageService;
+pub use presence::PresenceService;
+pub use ready::ReadyService;
+pub use slowmode::{SlowmodeError, SlowmodeService};
+pub use typing::TypingService;
@@ -0,0 +1,176 @@
+// src/services/presence.rs
+use crate::gateway::events::{DispatchEvent, PresenceUpdateEvent};
+use crate::gateway::GatewayHub;
+use crate::models::models::{CustomStatus, PresenceResponse, PresenceStatus, User};
+use crate::repositories::UserRepository;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const CUSTOM_STATUS_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tracks the gateway sessions of every connected user and turns them into
+/// the presence other users see. The computed status is written back to
+/// `users.status` so plain user lookups stay accurate.
+#[derive(Clone)]
+pub struct PresenceService {
+    // user_id -> session_id -> afk
+    sessions: Arc<Mutex<HashMap<i64, HashMap<u64, bool>>>>,
+    user_repository: Arc<dyn UserRepository>,
+    hub: GatewayHub,
+}
+
+impl PresenceService {
+    pub fn new(user_repository: Arc<dyn UserRepository>, hub: GatewayHub) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            user_repository,
+            hub,
+        }
+    }
+
+    pub async fn connect(&self, user_id: i64, session_id: u64) -> Result<(), sqlx::Error> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .entry(user_id)
+            .or_default()
+            .insert(session_id, false);
+
+        self.refresh(user_id, false).await
+    }
+
+    pub async fn disconnect(&self, user_id: i64, session_id: u64) -> Result<(), sqlx::Error> {
+        {
+            let mut sessions = self.sessions.lock().unwrap();
+            if let Some(user_sessions) = sessions.get_mut(&user_id) {
+                user_sessions.remove(&session_id);
+                if user_sessions.is_empty() {
+                    sessions.remove(&user_id);
+                }
+            }
+        }
+
+        self.refresh(user_id, false).await
+    }
+
+    pub async fn set_afk(&self, user_id: i64, session_id: u64, afk: bool) -> Result<(), sqlx::Error> {
+        if let Some(session) = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get_mut(&user_id)
+            .and_then(|user_sessions| user_sessions.get_mut(&session_id))
+        {
+            *session = afk;
+        }
+
+        self.refresh(user_id, false).await
+    }
+
+    pub async fn set_preferred_status(&self, user_id: i64, status: PresenceStatus) -> Result<(), sqlx::Error> {
+        self.user_repository
+            .update_preferred_status(user_id, status.as_str())
+            .await?;
+
+        self.refresh(user_id, false).await
+    }
+
+    pub async fn set_custom_status(&self, user_id: i64, custom_status: Option<CustomStatus>) -> Result<(), sqlx::Error> {
+        self.user_repository
+            .update_custom_status(user_id, custom_status)
+            .await?;
+
+        self.refresh(user_id, true).await
+    }
+
+    /// Presence of `user_id` as seen by other users.
+    pub async fn get(&self, user_id: i64) -> Result<Option<PresenceResponse>, sqlx::Error> {
+        let user = self.user_repository.find_by_id(user_id).await?;
+        Ok(user.map(|user| {
+            let status = user.status.parse().unwrap_or(PresenceStatus::Offline);
+            PresenceResponse {
+                user_id,
+                status,
+                custom_status: visible_custom_status(status, user),
+            }
+        }))
+    }
+
+    /// Periodically clears expired custom statuses and tells the affected
+    /// users' audiences about it.
+    pub fn spawn_custom_status_sweeper(&self) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CUSTOM_STATUS_SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let user_ids = match service.user_repository.clear_expired_custom_statuses().await {
+                    Ok(user_ids) => user_ids,
+                    Err(e) => {
+                        tracing::warn!("Failed to clear expired custom statuses: {}", e);
+                        continue;
+                    }
+                };
+                for user_id in user_ids {
+                    if let Err(e) = service.refresh(user_id, true).await {
+                        tracing::warn!("Failed to refresh presence for user {}: {}", user_id, e);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Recomputes the user's status from their sessions, persists it when it
+    /// changed and broadcasts it. `force` broadcasts even if the status is
+    /// unchanged, which is needed when only the custom status moved.
+    async fn refresh(&self, user_id: i64, force: bool) -> Result<(), sqlx::Error> {
+        let Some(user) = self.user_repository.find_by_id(user_id).await? else {
+            return Ok(());
+        };
+
+        let sessions_afk: Vec<bool> = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&user_id)
+            .map(|user_sessions| user_sessions.values().copied().collect())
+            .unwrap_or_default();
+
+        let preferred = user.preferred_status.parse().unwrap_or(PresenceStatus::Online);
+        let status = PresenceStatus::aggregate(preferred, &sessions_afk);
+        let changed = user.status != status.as_str();
+
+        if changed {
+            self.user_repository.update_status(user_id, status.as_str()).await?;
+        }
+
+        if changed || force {
+            let mut audience = self.user_repository.find_presence_audience(user_id).await?;
+            audience.push(user_id);
+
+            let event = DispatchEvent::PresenceUpdate(PresenceUpdateEvent {
+                user_id,
+                status,
+                custom_status: visible_custom_status(status, user),
+            });
+            self.hub.dispatch_to_users(audience, &event);
+        }
+
+        Ok(())
+    }
+}
+
+/// Offline and invisible users do not show a custom status to anyone.
+fn visible_custom_status(status: PresenceStatus, user: User) -> Option<CustomStatus> {
+    if status == PresenceStatus::Offline {
+        return None;
+    }
+
+    CustomStatus::from_columns(
+        user.custom_status_text,
+        user.custom_status_emoji,
+        user.custom_status_expires_at,
+    )
+}
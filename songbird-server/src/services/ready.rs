@@ -0,0 +1,77 @@
+// src/services/ready.rs
+use crate::gateway::events::{ReadyPayload, ReadyServer};
+use crate::models::models::{Channel, PresenceStatus, User};
+use crate::repositories::{ChannelRepository, ServerMemberRepository, ServerRepository, UserRepository};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Builds the READY payload a gateway session starts with.
+///
+/// However many servers the user is in, this takes the same four queries:
+/// their servers, their memberships, the channels of all of those servers
+/// and their DM channels. The results are stitched together here rather
+/// than with a query per server.
+#[derive(Clone)]
+pub struct ReadyService {
+    user_repository: Arc<dyn UserRepository>,
+    server_repository: Arc<dyn ServerRepository>,
+    server_member_repository: Arc<dyn ServerMemberRepository>,
+    channel_repository: Arc<dyn ChannelRepository>,
+}
+
+impl ReadyService {
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        server_repository: Arc<dyn ServerRepository>,
+        server_member_repository: Arc<dyn ServerMemberRepository>,
+        channel_repository: Arc<dyn ChannelRepository>,
+    ) -> Self {
+        Self {
+            user_repository,
+            server_repository,
+            server_member_repository,
+            channel_repository,
+        }
+    }
+
+    /// Servers come out oldest first and channels ordered by name. A server
+    /// the user leaves while this runs is left out.
+    pub async fn build(&self, session_id: u64, user: User) -> Result<ReadyPayload, sqlx::Error> {
+        let user_id = user.user_id;
+        let (servers, memberships, channels, private_channels) = tokio::try_join!(
+            self.server_repository.find_servers_for_user(user_id),
+            self.server_member_repository.find_by_user(user_id),
+            self.channel_repository.find_server_channels_for_user(user_id),
+            self.channel_repository.find_direct_message_channels(user_id),
+        )?;
+
+        let mut memberships: HashMap<i64, _> = memberships
+            .into_iter()
+            .map(|member| (member.server_id, member))
+            .collect();
+        let mut channels_by_server: HashMap<i64, Vec<Channel>> = HashMap::new();
+        for channel in channels {
+            if let Some(server_id) = channel.server_id {
+                channels_by_server.entry(server_id).or_default().push(channel);
+            }
+        }
+
+        let mut servers: Vec<ReadyServer> = servers
+            .into_iter()
+            .filter_map(|server| {
+                let member = memberships.remove(&server.server_id)?;
+                let channels = channels_by_server.remove(&server.server_id).unwrap_or_default();
+                Some(ReadyServer { server, member, channels })
+            })
+            .collect();
+        servers.sort_by_key(|ready| ready.server.server_id);
+
+        Ok(ReadyPayload {
+            session_id,
+            preferred_status: user.preferred_status.parse().unwrap_or(PresenceStatus::Online),
+            user: self.user_repository.to_response(user).await,
+            servers,
+            private_channels,
+        })
+    }
+}
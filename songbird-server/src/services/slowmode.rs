@@ -0,0 +1,72 @@
+// src/services/slowmode.rs
+use crate::models::models::{Channel, Permissions};
+use crate::services::channel_access::ChannelAccess;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest slowmode a channel can have, six hours.
+pub const MAX_RATE_LIMIT_PER_USER: i32 = 21_600;
+
+#[derive(Debug)]
+pub enum SlowmodeError {
+    RateLimited { retry_after: Duration },
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for SlowmodeError {
+    fn from(e: sqlx::Error) -> Self {
+        SlowmodeError::Database(e)
+    }
+}
+
+/// Enforces a channel's `rate_limit_per_user`. Members who can manage
+/// messages or channels are not slowed down.
+#[derive(Clone)]
+pub struct SlowmodeService {
+    // (channel_id, user_id) -> when the user last sent a message there
+    last_sent: Arc<Mutex<HashMap<(i64, i64), Instant>>>,
+    channel_access: ChannelAccess,
+}
+
+impl SlowmodeService {
+    pub fn new(channel_access: ChannelAccess) -> Self {
+        Self {
+            last_sent: Arc::new(Mutex::new(HashMap::new())),
+            channel_access,
+        }
+    }
+
+    /// Records a send by `user_id` in `channel`, or says how long they have
+    /// to wait. The wait is measured against the channel's current setting,
+    /// so lowering the slowmode takes effect immediately.
+    pub async fn check(&self, channel: &Channel, user_id: i64) -> Result<(), SlowmodeError> {
+        if channel.rate_limit_per_user <= 0 {
+            return Ok(());
+        }
+
+        let permissions = self.channel_access.permissions(channel, user_id).await?;
+        if permissions.intersects(Permissions::MANAGE_MESSAGES | Permissions::MANAGE_CHANNELS) {
+            return Ok(());
+        }
+
+        let rate_limit = Duration::from_secs(channel.rate_limit_per_user as u64);
+        let now = Instant::now();
+        let mut last_sent = self.last_sent.lock().unwrap();
+        last_sent.retain(|_, sent| {
+            now.duration_since(*sent) < Duration::from_secs(MAX_RATE_LIMIT_PER_USER as u64)
+        });
+
+        if let Some(sent) = last_sent.get(&(channel.channel_id, user_id)) {
+            let elapsed = now.duration_since(*sent);
+            if elapsed < rate_limit {
+                return Err(SlowmodeError::RateLimited {
+                    retry_after: rate_limit - elapsed,
+                });
+            }
+        }
+        last_sent.insert((channel.channel_id, user_id), now);
+
+        Ok(())
+    }
+}
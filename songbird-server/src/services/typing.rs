@@ -0,0 +1,83 @@
+// src/services/typing.rs
+use crate::gateway::events::{DispatchEvent, TypingStartEvent, TypingStopEvent};
+use crate::gateway::GatewayHub;
+use crate::services::channel_access::{ChannelAccess, ChannelAccessError};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a client shows the indicator after a TYPING_START.
+pub const TYPING_DURATION: Duration = Duration::from_secs(10);
+/// Repeated signals from the same user in the same channel inside this window
+/// are dropped instead of being re-broadcast.
+pub const TYPING_THROTTLE: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct TypingService {
+    // (channel_id, user_id) -> when the last broadcast TYPING_START was sent
+    active: Arc<Mutex<HashMap<(i64, i64), Instant>>>,
+    channel_access: ChannelAccess,
+    hub: GatewayHub,
+}
+
+impl TypingService {
+    pub fn new(channel_access: ChannelAccess, hub: GatewayHub) -> Self {
+        Self {
+            active: Arc::new(Mutex::new(HashMap::new())),
+            channel_access,
+            hub,
+        }
+    }
+
+    /// Records that `user_id` is typing in `channel_id` and tells the other
+    /// viewers of the channel. Returns `false` when the signal was throttled.
+    pub async fn start(&self, channel_id: i64, user_id: i64) -> Result<bool, ChannelAccessError> {
+        let channel = self.channel_access.authorize(channel_id, user_id).await?;
+
+        {
+            let now = Instant::now();
+            let mut active = self.active.lock().unwrap();
+            active.retain(|_, started| now.duration_since(*started) < TYPING_DURATION);
+
+            if let Some(started) = active.get(&(channel_id, user_id)) {
+                if now.duration_since(*started) < TYPING_THROTTLE {
+                    return Ok(false);
+                }
+            }
+            active.insert((channel_id, user_id), now);
+        }
+
+        let started_at = Utc::now();
+        let event = DispatchEvent::TypingStart(TypingStartEvent {
+            channel_id,
+            user_id,
+            timestamp: started_at,
+            expires_at: started_at + TYPING_DURATION,
+        });
+        let viewers = self.channel_access.viewers(&channel).await?;
+        self.hub
+            .dispatch_to_users(viewers.into_iter().filter(|id| *id != user_id), &event);
+
+        Ok(true)
+    }
+
+    /// Clears the indicator once the user's message arrives, so viewers do not
+    /// keep showing it until it times out.
+    pub fn stop(&self, channel_id: i64, user_id: i64, viewers: &[i64]) {
+        let was_typing = self
+            .active
+            .lock()
+            .unwrap()
+            .remove(&(channel_id, user_id))
+            .is_some_and(|started| started.elapsed() < TYPING_DURATION);
+
+        if was_typing {
+            let event = DispatchEvent::TypingStop(TypingStopEvent { channel_id, user_id });
+            self.hub.dispatch_to_users(
+                viewers.iter().copied().filter(|id| *id != user_id),
+                &event,
+            );
+        }
+    }
+}
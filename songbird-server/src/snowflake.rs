@@ -0,0 +1,156 @@
+// src/snowflake.rs
+//! 64-bit, time-ordered IDs for users, servers, channels and messages.
+//!
+//! From the top: a zero sign bit, 41 bits of milliseconds since [`EPOCH_MS`]
+//! (good until 2089), a 10 bit worker ID and a 12 bit sequence. IDs from
+//! one worker only ever go up, and IDs from different workers sort by time,
+//! so an ID is both a pagination cursor and its row's creation time.
+//!
+//! IDs go out as JSON strings, since many clients parse numbers as doubles
+//! and would lose the low bits.
+
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+/// 2020-01-01T00:00:00Z in unix milliseconds.
+pub const EPOCH_MS: i64 = 1_577_836_800_000;
+
+const WORKER_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+const TIMESTAMP_SHIFT: u32 = WORKER_BITS + SEQUENCE_BITS;
+
+pub const MAX_WORKER_ID: u16 = (1 << WORKER_BITS) - 1;
+const SEQUENCE_MASK: i64 = (1 << SEQUENCE_BITS) - 1;
+
+/// Set once at startup; instances sharing a database need different ones.
+static WORKER_ID: AtomicI64 = AtomicI64::new(0);
+
+/// The millisecond the last ID was made in and the sequence used in it.
+static LAST: Mutex<(i64, i64)> = Mutex::new((0, 0));
+
+/// Sets the worker ID that [`next_id`] stamps into new IDs.
+///
+/// # Panics
+/// If `worker_id` is above [`MAX_WORKER_ID`]; the config rejects those.
+pub fn set_worker_id(worker_id: u16) {
+    assert!(worker_id <= MAX_WORKER_ID, "worker id {} is out of range", worker_id);
+    WORKER_ID.store(i64::from(worker_id), Ordering::Relaxed);
+}
+
+/// A new ID, greater than every ID this process has made before.
+///
+/// When the clock steps back, or more than 4096 IDs are needed in one
+/// millisecond, the timestamp runs on ahead of the clock instead of
+/// waiting, so the timestamps of such IDs can be slightly in the future.
+pub fn next_id() -> i64 {
+    let now = Utc::now().timestamp_millis() - EPOCH_MS;
+
+    let mut last = LAST.lock().unwrap();
+    let (last_ms, last_sequence) = *last;
+    *last = if now > last_ms {
+        (now, 0)
+    } else if last_sequence < SEQUENCE_MASK {
+        (last_ms, last_sequence + 1)
+    } else {
+        (last_ms + 1, 0)
+    };
+    let (ms, sequence) = *last;
+    drop(last);
+
+    (ms << TIMESTAMP_SHIFT) | (WORKER_ID.load(Ordering::Relaxed) << SEQUENCE_BITS) | sequence
+}
+
+/// When `id` was made.
+pub fn timestamp(id: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis((id >> TIMESTAMP_SHIFT) + EPOCH_MS).unwrap_or_default()
+}
+
+/// The worker that made `id`.
+pub fn worker_id(id: i64) -> u16 {
+    ((id >> SEQUENCE_BITS) & i64::from(MAX_WORKER_ID)) as u16
+}
+
+/// The smallest ID that can have been made at `at`, for turning a time into
+/// a cursor: everything made before `at` has a smaller ID.
+pub fn lower_bound(at: DateTime<Utc>) -> i64 {
+    (at.timestamp_millis() - EPOCH_MS).max(0) << TIMESTAMP_SHIFT
+}
+
+/// `#[serde(with = "snowflake::string")]`: writes an ID as a JSON string
+/// and reads it from a string or, for older clients, a number.
+pub mod string {
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(id: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(id)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+        deserializer.deserialize_any(IdVisitor)
+    }
+
+    struct IdVisitor;
+
+    impl Visitor<'_> for IdVisitor {
+        type Value = i64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an ID as a string of digits")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<i64, E> {
+            value.parse().map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<i64, E> {
+            Ok(value)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<i64, E> {
+            i64::try_from(value).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(value), &self))
+        }
+    }
+}
+
+/// [`string`] for optional IDs.
+pub mod option_string {
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(id: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match id {
+            Some(id) => serializer.collect_str(id),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+        deserializer.deserialize_option(OptionIdVisitor)
+    }
+
+    struct OptionIdVisitor;
+
+    impl<'de> Visitor<'de> for OptionIdVisitor {
+        type Value = Option<i64>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an ID as a string of digits, or null")
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Option<i64>, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Option<i64>, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<i64>, D::Error> {
+            super::string::deserialize(deserializer).map(Some)
+        }
+    }
+}
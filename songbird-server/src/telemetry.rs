@@ -0,0 +1,128 @@
+// src/telemetry.rs
+use crate::config::{LogConfig, LogFormat};
+use axum::extract::MatchedPath;
+use axum::http::{HeaderName, Request, Response};
+use std::time::Duration;
+use tracing::Span;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Keeps the trace exporter alive; `shutdown` flushes whatever it still
+/// buffers.
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("songbird-server: failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber: logs in the configured format, plus the
+/// OTLP exporter when `log.otlp_endpoint` is set.
+pub fn init(config: &LogConfig) -> Result<Telemetry, Box<dyn std::error::Error>> {
+    let fmt = match config.format {
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+    };
+    let registry = tracing_subscriber::registry()
+        .with(EnvFilter::new(&config.filter))
+        .with(fmt);
+
+    #[cfg(feature = "otlp")]
+    {
+        let (layer, provider) = match &config.otlp_endpoint {
+            Some(endpoint) => {
+                let (layer, provider) = otlp::layer(endpoint, &config.service_name)?;
+                (Some(layer), Some(provider))
+            }
+            None => (None, None),
+        };
+        registry.with(layer).init();
+        Ok(Telemetry { provider })
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        registry.init();
+        Ok(Telemetry {})
+    }
+}
+
+#[cfg(feature = "otlp")]
+mod otlp {
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+    use tracing_opentelemetry::OpenTelemetryLayer;
+
+    const TRACES_PATH: &str = "/v1/traces";
+
+    pub fn layer<S>(
+        endpoint: &str,
+        service_name: &str,
+    ) -> Result<(OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>, SdkTracerProvider), Box<dyn std::error::Error>>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        // Collectors take OTLP/HTTP traces under /v1/traces
+        let endpoint = endpoint.trim_end_matches('/');
+        let endpoint = if endpoint.ends_with(TRACES_PATH) {
+            endpoint.to_string()
+        } else {
+            format!("{}{}", endpoint, TRACES_PATH)
+        };
+
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(service_name.to_string()).build())
+            .build();
+
+        let tracer = provider.tracer("songbird-server");
+        Ok((tracing_opentelemetry::layer().with_tracer(tracer), provider))
+    }
+}
+
+/// The span every API request runs in. `user_id` is filled in by `AuthUser`,
+/// `status` and `latency_ms` once the response is ready.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or("unmatched");
+
+    tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        route,
+        user_id = tracing::field::Empty,
+        status = tracing::field::Empty,
+        latency_ms = tracing::field::Empty,
+    )
+}
+
+/// One line per request, carrying everything in its span.
+pub fn record_response<B>(response: &Response<B>, latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency.as_millis() as u64);
+    tracing::info!("request finished");
+}
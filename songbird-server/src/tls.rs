@@ -0,0 +1,114 @@
+// src/tls.rs
+use crate::config::TlsConfig;
+use axum::serve::Listener;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_native_tls::{native_tls, TlsAcceptor, TlsStream};
+
+/// Clients that connect but never finish the handshake are dropped after
+/// this long.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Handshakes finished but not yet picked up by the server.
+const ACCEPT_BACKLOG: usize = 64;
+
+/// A listener serving HTTPS with the configured certificate. Handshakes run
+/// in their own tasks so a slow client cannot hold up everyone else.
+pub struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+#[derive(Debug)]
+pub enum TlsError {
+    Io(std::io::Error),
+    Tls(native_tls::Error),
+}
+
+impl std::fmt::Display for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsError::Io(e) => write!(f, "{}", e),
+            TlsError::Tls(e) => write!(f, "invalid TLS certificate or key: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+impl From<std::io::Error> for TlsError {
+    fn from(e: std::io::Error) -> Self {
+        TlsError::Io(e)
+    }
+}
+
+impl From<native_tls::Error> for TlsError {
+    fn from(e: native_tls::Error) -> Self {
+        TlsError::Tls(e)
+    }
+}
+
+impl TlsListener {
+    pub async fn bind(addr: SocketAddr, config: &TlsConfig) -> Result<Self, TlsError> {
+        let cert = tokio::fs::read(&config.cert_path).await?;
+        let key = tokio::fs::read(&config.key_path).await?;
+        let identity = native_tls::Identity::from_pkcs8(&cert, &key)?;
+        let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity)?);
+
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+
+        let (sender, incoming) = mpsc::channel(ACCEPT_BACKLOG);
+        tokio::spawn(accept_loop(listener, acceptor, sender));
+
+        Ok(Self { incoming, local_addr })
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    while !sender.is_closed() {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Usually running out of file descriptors; give it a moment
+                tracing::warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let acceptor = acceptor.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = sender.send((stream, addr)).await;
+                }
+                Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", addr, e),
+                Err(_) => tracing::debug!("TLS handshake with {} timed out", addr),
+            }
+        });
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(accepted) => accepted,
+            // The accept loop only stops once this receiver is gone
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
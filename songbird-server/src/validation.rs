@@ -0,0 +1,165 @@
+// src/validation.rs
+use crate::error::AppError;
+use axum::{
+    extract::{FromRequest, Request},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// Matches `PASSWORD_MIN_LEN` in the client.
+pub const PASSWORD_MIN_LEN: usize = 8;
+/// Argon2 hashes whatever it is given, so cap it.
+pub const PASSWORD_MAX_LEN: usize = 128;
+pub const USERNAME_MIN_LEN: usize = 2;
+pub const USERNAME_MAX_LEN: usize = 32;
+pub const URL_MAX_LEN: usize = 2048;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    pub code: String,
+    pub message: String,
+}
+
+/// The `data` of a 422: every failed rule, grouped by field.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidationErrorResponse {
+    pub fields: BTreeMap<String, Vec<FieldError>>,
+}
+
+impl From<&ValidationErrors> for ValidationErrorResponse {
+    fn from(errors: &ValidationErrors) -> Self {
+        let fields = errors
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let errors = errors
+                    .iter()
+                    .map(|error| FieldError {
+                        code: error.code.to_string(),
+                        message: error
+                            .message
+                            .as_ref()
+                            .map(|message| message.to_string())
+                            .unwrap_or_else(|| format!("{} is invalid", field)),
+                    })
+                    .collect();
+                (field.to_string(), errors)
+            })
+            .collect();
+
+        Self { fields }
+    }
+}
+
+/// Like `Json<T>`, but also runs the DTO's `Validate` rules. Bodies that do
+/// not parse keep axum's status; bodies that parse but break a rule get a
+/// 422 listing the failures per field.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate()?;
+
+        Ok(ValidatedJson(value))
+    }
+}
+
+fn invalid(code: &'static str, message: impl Into<Cow<'static, str>>) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+/// Letters, digits, `_`, `.` and `-`, between `USERNAME_MIN_LEN` and
+/// `USERNAME_MAX_LEN` characters.
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    let length = username.chars().count();
+    if !(USERNAME_MIN_LEN..=USERNAME_MAX_LEN).contains(&length) {
+        return Err(invalid(
+            "length",
+            format!(
+                "Username must be between {} and {} characters",
+                USERNAME_MIN_LEN, USERNAME_MAX_LEN
+            ),
+        ));
+    }
+
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+    {
+        return Err(invalid(
+            "charset",
+            "Username may only contain letters, digits, '_', '.' and '-'",
+        ));
+    }
+
+    Ok(())
+}
+
+/// At least `PASSWORD_MIN_LEN` characters with a letter and something that
+/// is not a letter.
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    let length = password.chars().count();
+    if length < PASSWORD_MIN_LEN {
+        return Err(invalid(
+            "length",
+            format!("Password must be at least {} characters long", PASSWORD_MIN_LEN),
+        ));
+    }
+    if length > PASSWORD_MAX_LEN {
+        return Err(invalid(
+            "length",
+            format!("Password must be at most {} characters long", PASSWORD_MAX_LEN),
+        ));
+    }
+
+    let has_letter = password.chars().any(char::is_alphabetic);
+    let has_other = password.chars().any(|c| !c.is_alphabetic());
+    if !has_letter || !has_other {
+        return Err(invalid(
+            "strength",
+            "Password must contain a letter and a digit or symbol",
+        ));
+    }
+
+    Ok(())
+}
+
+/// An absolute `http` or `https` URL, e.g. for avatars and icons.
+pub fn validate_http_url(url: &str) -> Result<(), ValidationError> {
+    if url.len() > URL_MAX_LEN {
+        return Err(invalid(
+            "length",
+            format!("URL must be at most {} characters", URL_MAX_LEN),
+        ));
+    }
+
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Err(invalid("url", "Must be an http or https URL"));
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if !matches!(scheme, "http" | "https") || host.is_empty() || url.chars().any(char::is_whitespace) {
+        return Err(invalid("url", "Must be an http or https URL"));
+    }
+
+    Ok(())
+}
+
+/// Rejects values that are empty once surrounding whitespace is removed.
+pub fn validate_not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(invalid("blank", "Must not be blank"));
+    }
+
+    Ok(())
+}
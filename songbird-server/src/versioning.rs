@@ -0,0 +1,62 @@
+// src/versioning.rs
+//! The API is served as versioned route trees under `/api/v1`, `/api/v2`
+//! and so on, side by side. A tree that is on its way out gets a
+//! [`Deprecation`] layer, so clients can see from any response when it goes
+//! away and where to move to.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, TimeZone, Utc};
+
+/// A route tree scheduled for removal, announced with the `Deprecation`
+/// (RFC 9745), `Sunset` (RFC 8594) and `Link: rel="successor-version"`
+/// headers on every response from it.
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    pub since: DateTime<Utc>,
+    pub sunset: DateTime<Utc>,
+    /// Where the same routes live in the replacement tree.
+    pub successor_prefix: &'static str,
+}
+
+impl Deprecation {
+    /// The unversioned `/api/...` paths, kept as an alias for v1 while
+    /// clients move over.
+    pub fn unversioned_api() -> Self {
+        Self {
+            since: Utc.with_ymd_and_hms(2026, 10, 19, 0, 0, 0).unwrap(),
+            sunset: Utc.with_ymd_and_hms(2027, 4, 19, 0, 0, 0).unwrap(),
+            successor_prefix: "/api/v1",
+        }
+    }
+
+    fn deprecation_header(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!("@{}", self.since.timestamp())).expect("a timestamp is a valid header value")
+    }
+
+    fn sunset_header(&self) -> HeaderValue {
+        HeaderValue::from_str(&self.sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+            .expect("an HTTP date is a valid header value")
+    }
+}
+
+/// Middleware for a deprecated tree. Runs inside the nest, so the path it
+/// sees is relative to the tree and maps straight onto the successor.
+pub async fn deprecated(State(deprecation): State<Deprecation>, request: Request, next: Next) -> Response {
+    let successor = format!("<{}{}>; rel=\"successor-version\"", deprecation.successor_prefix, request.uri().path());
+
+    let mut response = next.run(request).await;
+
+    let headers = response.headers_mut();
+    headers.insert("deprecation", deprecation.deprecation_header());
+    headers.insert("sunset", deprecation.sunset_header());
+    // Paths are plain ASCII by the time they are routed
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.append(header::LINK, link);
+    }
+    response
+}
@@ -0,0 +1,322 @@
+use clap::Parser;
+use rand::distr::{Alphanumeric, SampleString};
+use serde_json::Value;
+use songbird_server::admin::{execute, AdminCli, AdminError, Command, Output, OutputFormat, StatsTarget};
+use songbird_server::config::DatabaseConfig;
+use songbird_server::database::{open, Database, SQLITE_MIGRATOR};
+use songbird_server::models::models::{MessageType, NewChannel, NewMessage, NewServer, NewServerMember, User};
+use std::path::PathBuf;
+
+/// A SQLite file of its own, removed along with the value.
+struct TempDatabase {
+    database: Database,
+    path: PathBuf,
+}
+
+impl TempDatabase {
+    /// Empty: not migrated until `Command::Migrate` runs.
+    async fn open() -> Self {
+        let suffix = Alphanumeric.sample_string(&mut rand::rng(), 12);
+        let path = std::env::temp_dir().join(format!("songbird_admin_{}.db", suffix));
+        let config = DatabaseConfig {
+            url: format!("sqlite://{}", path.display()),
+            ..DatabaseConfig::default()
+        };
+        let database = open(&config).await.unwrap();
+        Self { database, path }
+    }
+
+    async fn migrated() -> Self {
+        let database = Self::open().await;
+        database.run(Command::Migrate).await.unwrap();
+        database
+    }
+
+    async fn run(&self, command: Command) -> Result<Output, AdminError> {
+        execute(command, &self.database).await
+    }
+
+    async fn create_user(&self, username: &str) -> User {
+        match self
+            .run(Command::CreateUser {
+                username: username.to_string(),
+                email: format!("{}@example.com", username),
+                password: Some("correct horse battery".to_string()),
+            })
+            .await
+            .unwrap()
+        {
+            Output::UserCreated { user, .. } => user,
+            other => panic!("expected a created user, got {:?}", other),
+        }
+    }
+
+    async fn find_user(&self, user_id: i64) -> Option<User> {
+        self.database.repositories().user_repository.find_by_id(user_id).await.unwrap()
+    }
+}
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.path.display(), suffix));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_migrate_applies_pending_migrations_once() {
+    let database = TempDatabase::open().await;
+
+    let Output::Migrated { applied } = database.run(Command::Migrate).await.unwrap() else {
+        panic!("expected migrations");
+    };
+    let versions: Vec<i64> = applied.iter().map(|migration| migration.version).collect();
+    let expected: Vec<i64> = SQLITE_MIGRATOR.iter().map(|migration| migration.version).collect();
+    assert_eq!(versions, expected);
+
+    let again = database.run(Command::Migrate).await.unwrap();
+    assert!(matches!(again, Output::Migrated { ref applied } if applied.is_empty()));
+    assert_eq!(again.render(OutputFormat::Human), "The schema is already up to date");
+}
+
+#[tokio::test]
+async fn test_create_user_generates_a_password_and_checks_the_rules() {
+    let database = TempDatabase::migrated().await;
+
+    let Output::UserCreated { user, generated_password } = database
+        .run(Command::CreateUser {
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password: None,
+        })
+        .await
+        .unwrap()
+    else {
+        panic!("expected a created user");
+    };
+    assert_eq!(user.username, "alice");
+    assert_eq!(generated_password.map(|password| password.len()), Some(20));
+
+    let taken = database
+        .run(Command::CreateUser {
+            username: "alice".to_string(),
+            email: "other@example.com".to_string(),
+            password: None,
+        })
+        .await;
+    assert!(matches!(taken, Err(AdminError::Invalid(_))));
+
+    let weak = database
+        .run(Command::CreateUser {
+            username: "bob".to_string(),
+            email: "bob@example.com".to_string(),
+            password: Some("short".to_string()),
+        })
+        .await;
+    assert!(matches!(weak, Err(AdminError::Invalid(_))));
+}
+
+#[tokio::test]
+async fn test_disable_enable_and_logout() {
+    let database = TempDatabase::migrated().await;
+    let alice = database.create_user("alice").await;
+
+    database
+        .run(Command::DisableUser { user: "alice".to_string() })
+        .await
+        .unwrap();
+    let disabled = database.find_user(alice.user_id).await.unwrap();
+    assert!(disabled.disabled_at.is_some());
+    assert!(disabled.sessions_revoked_at.is_some());
+
+    // By ID as well as by name
+    database
+        .run(Command::EnableUser { user: alice.user_id.to_string() })
+        .await
+        .unwrap();
+    let enabled = database.find_user(alice.user_id).await.unwrap();
+    assert!(enabled.disabled_at.is_none());
+    assert_eq!(enabled.sessions_revoked_at, disabled.sessions_revoked_at);
+
+    database.run(Command::Logout { user: "alice".to_string() }).await.unwrap();
+    let logged_out = database.find_user(alice.user_id).await.unwrap();
+    assert!(logged_out.sessions_revoked_at > enabled.sessions_revoked_at);
+
+    let missing = database.run(Command::Logout { user: "nobody".to_string() }).await;
+    assert!(matches!(missing, Err(AdminError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_reset_password_replaces_the_hash_and_ends_sessions() {
+    let database = TempDatabase::migrated().await;
+    let alice = database.create_user("alice").await;
+
+    let Output::PasswordReset { user, generated_password } = database
+        .run(Command::ResetPassword {
+            user: "alice".to_string(),
+            password: None,
+        })
+        .await
+        .unwrap()
+    else {
+        panic!("expected a password reset");
+    };
+
+    assert!(generated_password.is_some());
+    assert_ne!(user.password_hash, alice.password_hash);
+    assert!(user.sessions_revoked_at.is_some());
+}
+
+#[tokio::test]
+async fn test_purge_deletes_users_disabled_long_enough() {
+    let database = TempDatabase::migrated().await;
+    let alice = database.create_user("alice").await;
+    let bob = database.create_user("bob").await;
+    database
+        .run(Command::DisableUser { user: "alice".to_string() })
+        .await
+        .unwrap();
+
+    let kept = database.run(Command::Purge { older_than_days: 30 }).await.unwrap();
+    assert!(matches!(kept, Output::Purged { users: 0, .. }));
+
+    let purged = database.run(Command::Purge { older_than_days: 0 }).await.unwrap();
+    assert!(matches!(purged, Output::Purged { users: 1, .. }));
+    assert!(database.find_user(alice.user_id).await.is_none());
+    assert!(database.find_user(bob.user_id).await.is_some());
+}
+
+#[tokio::test]
+async fn test_transfer_stats_and_delete_server() {
+    let database = TempDatabase::migrated().await;
+    let alice = database.create_user("alice").await;
+    let bob = database.create_user("bob").await;
+    let repositories = database.database.repositories();
+
+    let server = repositories
+        .server_repository
+        .create(NewServer {
+            server_name: "Birdhouse".to_string(),
+            owner_user_id: alice.user_id,
+            icon_url: None,
+        })
+        .await
+        .unwrap();
+    let channel = repositories
+        .channel_repository
+        .create(NewChannel {
+            server_id: Some(server.server_id),
+            name: "general".to_string(),
+            channel_type: "text".to_string(),
+        })
+        .await
+        .unwrap();
+    repositories
+        .message_repository
+        .create(NewMessage {
+            channel_id: channel.channel_id,
+            author_user_id: alice.user_id,
+            content: "First!".to_string(),
+            message_type: MessageType::Default,
+            referenced_message_id: None,
+        })
+        .await
+        .unwrap();
+
+    let not_member = database
+        .run(Command::TransferServer {
+            server_id: server.server_id,
+            new_owner: "bob".to_string(),
+        })
+        .await;
+    assert!(matches!(not_member, Err(AdminError::Invalid(_))));
+
+    for user_id in [alice.user_id, bob.user_id] {
+        repositories
+            .server_member_repository
+            .create(NewServerMember {
+                server_id: server.server_id,
+                user_id,
+                nickname: None,
+            })
+            .await
+            .unwrap();
+    }
+    let transferred = database
+        .run(Command::TransferServer {
+            server_id: server.server_id,
+            new_owner: "bob".to_string(),
+        })
+        .await
+        .unwrap();
+    let Output::ServerTransferred { server: ref updated, previous_owner_user_id } = transferred else {
+        panic!("expected a transfer");
+    };
+    assert_eq!(updated.owner_user_id, bob.user_id);
+    assert_eq!(previous_owner_user_id, alice.user_id);
+
+    let Output::ServerStats(stats) = database
+        .run(Command::Stats {
+            target: StatsTarget::Server { server_id: server.server_id },
+        })
+        .await
+        .unwrap()
+    else {
+        panic!("expected server stats");
+    };
+    assert_eq!(stats.members, 2);
+    let channels: Vec<(&str, i64)> = stats
+        .channels
+        .iter()
+        .map(|channel| (channel.name.as_str(), channel.messages))
+        .collect();
+    // The join messages land in the server's only channel
+    assert_eq!(channels, vec![("general", 3)]);
+
+    database
+        .run(Command::DeleteServer { server_id: server.server_id })
+        .await
+        .unwrap();
+    let gone = database
+        .run(Command::Stats {
+            target: StatsTarget::Channel { channel_id: channel.channel_id },
+        })
+        .await;
+    assert!(matches!(gone, Err(AdminError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_json_output_is_tagged_with_string_ids() {
+    let database = TempDatabase::migrated().await;
+    let alice = database.create_user("alice").await;
+
+    let output = database
+        .run(Command::Logout { user: "alice".to_string() })
+        .await
+        .unwrap();
+    let json: Value = serde_json::from_str(&output.render(OutputFormat::Json)).unwrap();
+
+    assert_eq!(json["result"], "logged_out");
+    assert_eq!(json["user"]["user_id"], alice.user_id.to_string());
+    assert!(json["user"].get("password_hash").is_none());
+}
+
+#[test]
+fn test_cli_parses_global_flags_after_the_command() {
+    let cli = AdminCli::try_parse_from([
+        "songbird-admin",
+        "stats",
+        "server",
+        "42",
+        "--format",
+        "json",
+        "--database-url",
+        "sqlite://songbird.db",
+    ])
+    .unwrap();
+
+    assert_eq!(cli.format, OutputFormat::Json);
+    assert!(matches!(cli.command, Command::Stats { target: StatsTarget::Server { server_id: 42 } }));
+    assert_eq!(cli.server_cli().database_url.as_deref(), Some("sqlite://songbird.db"));
+}
@@ -1,4 +1,5 @@
-use songbird_server::handlers::user_handlers::ApiResponse;
+use songbird_server::error::ErrorCode;
+use songbird_server::handlers::ApiResponse;
 
 #[test]
 fn test_api_response_success() {
@@ -6,9 +7,10 @@ fn test_api_response_success() {
         success: true,
         data: Some("Test data".to_string()),
         error: None,
+        code: None,
     };
 
-    assert_eq!(response.success, true);
+    assert!(response.success);
     assert_eq!(response.data, Some("Test data".to_string()));
     assert_eq!(response.error, None);
 }
@@ -19,18 +21,20 @@ fn test_api_response_error() {
         success: false,
         data: None,
         error: Some("Test error".to_string()),
+        code: Some(ErrorCode::InternalError),
     };
 
-    assert_eq!(response.success, false);
+    assert!(!response.success);
     assert_eq!(response.data, None);
     assert_eq!(response.error, Some("Test error".to_string()));
+    assert_eq!(response.code, Some(ErrorCode::InternalError));
 }
 
 #[test]
 fn test_api_response_with_complex_data() {
     #[derive(Debug, PartialEq, Clone)]
     struct TestData {
-        id: i32,
+        id: i64,
         name: String,
     }
 
@@ -43,9 +47,20 @@ fn test_api_response_with_complex_data() {
         success: true,
         data: Some(test_data.clone()),
         error: None,
+        code: None,
     };
 
-    assert_eq!(response.success, true);
+    assert!(response.success);
     assert_eq!(response.data, Some(test_data));
     assert_eq!(response.error, None);
 }
+
+#[test]
+fn test_api_response_ok_omits_code() {
+    let response = ApiResponse::ok("Test data".to_string());
+
+    let json = serde_json::to_value(&response.0).unwrap();
+    assert_eq!(json["success"], true);
+    assert_eq!(json["data"], "Test data");
+    assert!(json.get("code").is_none());
+}
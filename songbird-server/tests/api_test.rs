@@ -0,0 +1,852 @@
+use axum_test::TestServer;
+use chrono::Utc;
+use serde_json::{json, Value};
+use songbird_server::config::{Cli, Config};
+use songbird_server::mfa;
+use songbird_server::error::{AppError, ErrorCode};
+use songbird_server::models::models::{NewChannel, NewServer, NewServerMember};
+use songbird_server::repositories::{
+    ChannelRepository, MemoryDatabase, Repositories, ServerMemberRepository, ServerRepository, UserRepository,
+};
+use songbird_server::shutdown::Shutdown;
+use songbird_server::{build_state, create_router};
+
+/// Every route against one in-memory database. The database is handed back
+/// too, for seeding what no route creates yet (channels, members).
+fn server() -> (TestServer, MemoryDatabase) {
+    let vars = vec![
+        ("DATABASE_URL".to_string(), "postgres://localhost/songbird_unused".to_string()),
+        ("JWT_SECRET".to_string(), "api-test-secret".to_string()),
+        ("MAIL_BACKEND".to_string(), "log".to_string()),
+    ];
+    let config = Config::load_from(&Cli::default(), vars).unwrap();
+
+    let database = MemoryDatabase::new();
+    let state = build_state(&config, Repositories::in_memory(database.clone()), Shutdown::new()).unwrap();
+    let server = TestServer::new(create_router(state, &config)).unwrap();
+
+    (server, database)
+}
+
+/// Signs up and logs in; returns the user ID and a bearer token.
+async fn sign_up(server: &TestServer, username: &str) -> (i64, String) {
+    let created = server
+        .post("/api/v1/users/create")
+        .json(&json!({
+            "username": username,
+            "email": format!("{}@example.com", username),
+            "password": "correct horse battery",
+        }))
+        .await;
+    created.assert_status(axum::http::StatusCode::CREATED);
+    let user_id: i64 = created.json::<Value>()["data"]["user_id"].as_str().unwrap().parse().unwrap();
+
+    let login = server
+        .post("/api/v1/login")
+        .json(&json!({ "username": username, "password": "correct horse battery" }))
+        .await;
+    login.assert_status_ok();
+    let token = login.json::<Value>()["data"]["token"].as_str().unwrap().to_string();
+
+    (user_id, format!("Bearer {}", token))
+}
+
+/// A server owned by whoever `owner_token` belongs to.
+async fn create_server(server: &TestServer, name: &str, owner_token: &str) -> i64 {
+    let response = server
+        .post("/api/v1/servers")
+        .authorization(owner_token)
+        .json(&json!({ "name": name, "description": "" }))
+        .await;
+    response.assert_status(axum::http::StatusCode::CREATED);
+    response.json::<Value>()["data"]["server_id"].as_str().unwrap().parse().unwrap()
+}
+
+/// A text channel on `server_id` with each user in `members` joined.
+async fn seed_channel(database: &MemoryDatabase, server_id: i64, members: &[i64]) -> i64 {
+    for &user_id in members {
+        ServerMemberRepository::create(
+            database,
+            NewServerMember {
+                server_id,
+                user_id,
+                nickname: None,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let channel = ChannelRepository::create(
+        database,
+        NewChannel {
+            server_id: Some(server_id),
+            name: "general".to_string(),
+            channel_type: "text".to_string(),
+        },
+    )
+    .await
+    .unwrap();
+
+    channel.channel_id as i64
+}
+
+#[tokio::test]
+async fn test_signup_conflicts() {
+    let (server, _) = server();
+    sign_up(&server, "alice").await;
+
+    let same_username = server
+        .post("/api/v1/users/create")
+        .json(&json!({ "username": "alice", "email": "other@example.com", "password": "correct horse battery" }))
+        .await;
+    same_username.assert_status(axum::http::StatusCode::CONFLICT);
+    assert_eq!(same_username.json::<Value>()["code"], "USERNAME_TAKEN");
+
+    let same_email = server
+        .post("/api/v1/users/create")
+        .json(&json!({ "username": "alicia", "email": "alice@example.com", "password": "correct horse battery" }))
+        .await;
+    same_email.assert_status(axum::http::StatusCode::CONFLICT);
+    assert_eq!(same_email.json::<Value>()["code"], "EMAIL_TAKEN");
+}
+
+#[tokio::test]
+async fn test_login_rejects_wrong_password() {
+    let (server, _) = server();
+    sign_up(&server, "alice").await;
+
+    let response = server
+        .post("/api/v1/login")
+        .json(&json!({ "username": "alice", "password": "wrong password entirely" }))
+        .await;
+
+    response.assert_status_unauthorized();
+    assert_eq!(response.json::<Value>()["code"], "INVALID_CREDENTIALS");
+}
+
+#[tokio::test]
+async fn test_user_crud() {
+    let (server, _) = server();
+    let (user_id, token) = sign_up(&server, "alice").await;
+    sign_up(&server, "bob").await;
+
+    server.get(&format!("/api/v1/users/{}", user_id)).await.assert_status_ok();
+    let by_name = server.get("/api/v1/users/by_username/alice").await;
+    assert_eq!(by_name.json::<Value>()["data"]["user_id"], user_id.to_string());
+    assert_eq!(server.get("/api/v1/users").await.json::<Value>()["data"].as_array().unwrap().len(), 2);
+
+    let renamed = server
+        .put(&format!("/api/v1/users/{}", user_id))
+        .authorization(&token)
+        .json(&json!({ "username": "alice2" }))
+        .await;
+    renamed.assert_status_ok();
+    assert_eq!(renamed.json::<Value>()["data"]["username"], "alice2");
+
+    let taken = server
+        .put(&format!("/api/v1/users/{}", user_id))
+        .authorization(&token)
+        .json(&json!({ "username": "bob" }))
+        .await;
+    assert_eq!(taken.json::<Value>()["code"], "USERNAME_TAKEN");
+
+    server
+        .delete(&format!("/api/v1/users/{}", user_id))
+        .authorization(&token)
+        .await
+        .assert_status_ok();
+    let gone = server.get(&format!("/api/v1/users/{}", user_id)).await;
+    gone.assert_status_not_found();
+    assert_eq!(gone.json::<Value>()["code"], "UNKNOWN_USER");
+}
+
+// The user changes below go straight to the database, past the cache, so
+// each test changes a user before any authorized request looks them up.
+
+#[tokio::test]
+async fn test_disabled_user_is_locked_out() {
+    let (server, database) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+
+    UserRepository::set_disabled(&database, alice, true).await.unwrap();
+
+    server
+        .put(&format!("/api/v1/users/{}/custom_status", alice))
+        .authorization(&alice_token)
+        .json(&json!({ "text": "Still here" }))
+        .await
+        .assert_status_unauthorized();
+
+    let login = server
+        .post("/api/v1/login")
+        .json(&json!({ "username": "alice", "password": "correct horse battery" }))
+        .await;
+    login.assert_status_forbidden();
+    assert_eq!(login.json::<Value>()["code"], "ACCOUNT_DISABLED");
+
+    UserRepository::set_disabled(&database, alice, false).await.unwrap();
+    server
+        .post("/api/v1/login")
+        .json(&json!({ "username": "alice", "password": "correct horse battery" }))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_revoked_sessions_are_rejected() {
+    let (server, database) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+
+    UserRepository::revoke_sessions(&database, alice).await.unwrap();
+
+    server
+        .put(&format!("/api/v1/users/{}/custom_status", alice))
+        .authorization(&alice_token)
+        .json(&json!({ "text": "Still here" }))
+        .await
+        .assert_status_unauthorized();
+}
+
+/// Another instance, or `songbird-admin`, changes the database without going
+/// through this app's cache; sessions must still end at once.
+#[tokio::test]
+async fn test_sessions_end_despite_a_cached_user() {
+    let (server, database) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+    let (bob, bob_token) = sign_up(&server, "bob").await;
+    for (user_id, token) in [(alice, &alice_token), (bob, &bob_token)] {
+        server
+            .put(&format!("/api/v1/users/{}/custom_status", user_id))
+            .authorization(token)
+            .json(&json!({ "text": "Here" }))
+            .await
+            .assert_status_ok();
+    }
+
+    UserRepository::set_disabled(&database, alice, true).await.unwrap();
+    UserRepository::revoke_sessions(&database, bob).await.unwrap();
+
+    for (user_id, token) in [(alice, &alice_token), (bob, &bob_token)] {
+        server
+            .put(&format!("/api/v1/users/{}/custom_status", user_id))
+            .authorization(token)
+            .json(&json!({ "text": "Still here" }))
+            .await
+            .assert_status_unauthorized();
+    }
+}
+
+#[tokio::test]
+async fn test_custom_status_is_own_only() {
+    let (server, _) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+    let (bob, _) = sign_up(&server, "bob").await;
+
+    server
+        .put(&format!("/api/v1/users/{}/custom_status", alice))
+        .authorization(&alice_token)
+        .json(&json!({ "text": "Out to lunch" }))
+        .await
+        .assert_status_ok();
+
+    server
+        .put(&format!("/api/v1/users/{}/custom_status", bob))
+        .authorization(&alice_token)
+        .json(&json!({ "text": "Hijacked" }))
+        .await
+        .assert_status_forbidden();
+
+    server
+        .delete(&format!("/api/v1/users/{}/custom_status", alice))
+        .authorization(&alice_token)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_account_changes_are_own_only() {
+    let (server, _) = server();
+    let (alice, _) = sign_up(&server, "alice").await;
+    let (_, bob_token) = sign_up(&server, "bob").await;
+
+    server
+        .put(&format!("/api/v1/users/{}", alice))
+        .json(&json!({ "email": "mallory@example.com" }))
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .put(&format!("/api/v1/users/{}", alice))
+        .authorization(&bob_token)
+        .json(&json!({ "password": "a new password", "current_password": "correct horse battery" }))
+        .await
+        .assert_status_forbidden();
+
+    server.delete(&format!("/api/v1/users/{}", alice)).await.assert_status_unauthorized();
+    server
+        .delete(&format!("/api/v1/users/{}", alice))
+        .authorization(&bob_token)
+        .await
+        .assert_status_forbidden();
+    server.get(&format!("/api/v1/users/{}", alice)).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_password_change_needs_the_password_and_ends_other_sessions() {
+    let (server, _) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+    let path = format!("/api/v1/users/{}", alice);
+
+    let missing = server
+        .put(&path)
+        .authorization(&alice_token)
+        .json(&json!({ "password": "a new password" }))
+        .await;
+    missing.assert_status_bad_request();
+
+    let wrong = server
+        .put(&path)
+        .authorization(&alice_token)
+        .json(&json!({ "password": "a new password", "current_password": "not the password" }))
+        .await;
+    wrong.assert_status_unauthorized();
+    assert_eq!(wrong.json::<Value>()["code"], "INVALID_CREDENTIALS");
+
+    let changed = server
+        .put(&path)
+        .authorization(&alice_token)
+        .json(&json!({ "password": "a new password", "current_password": "correct horse battery" }))
+        .await;
+    changed.assert_status_ok();
+    let body = changed.json::<Value>();
+    assert_eq!(body["data"]["username"], "alice");
+    let new_token = format!("Bearer {}", body["data"]["token"].as_str().unwrap());
+
+    let custom_status = format!("/api/v1/users/{}/custom_status", alice);
+    server
+        .put(&custom_status)
+        .authorization(&alice_token)
+        .json(&json!({ "text": "Still here" }))
+        .await
+        .assert_status_unauthorized();
+    server
+        .put(&custom_status)
+        .authorization(&new_token)
+        .json(&json!({ "text": "Still here" }))
+        .await
+        .assert_status_ok();
+
+    // Renaming needs no password, and hands out no new session
+    let renamed = server
+        .put(&path)
+        .authorization(&new_token)
+        .json(&json!({ "username": "alice2" }))
+        .await;
+    renamed.assert_status_ok();
+    assert!(renamed.json::<Value>()["data"].get("token").is_none());
+
+    server
+        .post("/api/v1/login")
+        .json(&json!({ "username": "alice2", "password": "a new password" }))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_email_change_needs_the_second_factor() {
+    let (server, _) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+
+    let enrollment = server
+        .post(&format!("/api/v1/users/{}/mfa/totp", alice))
+        .authorization(&alice_token)
+        .await;
+    let secret = enrollment.json::<Value>()["data"]["secret"].as_str().unwrap().to_string();
+    let code = mfa::code_at(&secret, mfa::step_at(Utc::now().timestamp())).unwrap();
+    let confirmed = server
+        .post(&format!("/api/v1/users/{}/mfa/totp/confirm", alice))
+        .authorization(&alice_token)
+        .json(&json!({ "code": code }))
+        .await;
+    confirmed.assert_status_ok();
+    let recovery_code = confirmed.json::<Value>()["data"]["recovery_codes"][0].as_str().unwrap().to_string();
+
+    let path = format!("/api/v1/users/{}", alice);
+    let without_code = server
+        .put(&path)
+        .authorization(&alice_token)
+        .json(&json!({ "email": "alice@example.org", "current_password": "correct horse battery" }))
+        .await;
+    without_code.assert_status_unauthorized();
+    assert_eq!(without_code.json::<Value>()["code"], "INVALID_MFA_CODE");
+
+    let changed = server
+        .put(&path)
+        .authorization(&alice_token)
+        .json(&json!({
+            "email": "alice@example.org",
+            "current_password": "correct horse battery",
+            "mfa_code": recovery_code,
+        }))
+        .await;
+    changed.assert_status_ok();
+    assert_eq!(changed.json::<Value>()["data"]["email"], "alice@example.org");
+}
+
+#[tokio::test]
+async fn test_status_is_own_only() {
+    let (server, database) = server();
+    let (alice, _) = sign_up(&server, "alice").await;
+    let (bob, bob_token) = sign_up(&server, "bob").await;
+
+    server
+        .put(&format!("/api/v1/users/{}", alice))
+        .json(&json!({ "status": "invisible" }))
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .put(&format!("/api/v1/users/{}", alice))
+        .authorization(&bob_token)
+        .json(&json!({ "status": "dnd" }))
+        .await
+        .assert_status_forbidden();
+
+    server
+        .put(&format!("/api/v1/users/{}", bob))
+        .authorization(&bob_token)
+        .json(&json!({ "status": "dnd" }))
+        .await
+        .assert_status_ok();
+
+    let preferred = |user_id| {
+        let database = database.clone();
+        async move {
+            UserRepository::find_by_id(&database, user_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .preferred_status
+        }
+    };
+    assert_eq!(preferred(alice).await, "online");
+    assert_eq!(preferred(bob).await, "dnd");
+}
+
+#[tokio::test]
+async fn test_server_crud_and_constraints() {
+    let (server, database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    let created = server.get(&format!("/api/v1/servers/{}", server_id)).await;
+    assert_eq!(created.json::<Value>()["data"]["owner_user_id"], owner.to_string());
+
+    let duplicate = server
+        .post("/api/v1/servers")
+        .authorization(&owner_token)
+        .json(&json!({ "name": "Birdhouse", "description": "" }))
+        .await;
+    duplicate.assert_status(axum::http::StatusCode::CONFLICT);
+    assert_eq!(duplicate.json::<Value>()["code"], "SERVER_NAME_TAKEN");
+
+    // The owner comes from the session, so only the repository can be
+    // handed one that does not exist
+    let unknown_owner = ServerRepository::create(
+        &database,
+        NewServer {
+            server_name: "Nobody's".to_string(),
+            owner_user_id: 999,
+            icon_url: None,
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(AppError::from(unknown_owner).code(), ErrorCode::InvalidReference);
+
+    let renamed = server
+        .put(&format!("/api/v1/servers/{}", server_id))
+        .authorization(&owner_token)
+        .json(&json!({ "name": "Aviary" }))
+        .await;
+    assert_eq!(renamed.json::<Value>()["data"]["server_name"], "Aviary");
+
+    let owned = server.get(&format!("/api/v1/servers/owner/{}", owner)).await;
+    assert_eq!(owned.json::<Value>()["data"].as_array().unwrap().len(), 1);
+
+    server
+        .delete(&format!("/api/v1/servers/{}", server_id))
+        .authorization(&owner_token)
+        .await
+        .assert_status_ok();
+    server
+        .get(&format!("/api/v1/servers/{}", server_id))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_server_changes_need_the_owner() {
+    let (server, _) = server();
+    let (_, owner_token) = sign_up(&server, "alice").await;
+    let (mallory, mallory_token) = sign_up(&server, "mallory").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    let path = format!("/api/v1/servers/{}", server_id);
+
+    server
+        .post("/api/v1/servers")
+        .json(&json!({ "name": "Nest", "description": "" }))
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .put(&path)
+        .json(&json!({ "name": "Mine now" }))
+        .await
+        .assert_status_unauthorized();
+    server
+        .put(&path)
+        .authorization(&mallory_token)
+        .json(&json!({ "name": "Mine now" }))
+        .await
+        .assert_status_forbidden();
+    server
+        .put(&format!("{}/owner", path))
+        .authorization(&mallory_token)
+        .json(&json!({ "owner_user_id": mallory.to_string() }))
+        .await
+        .assert_status_forbidden();
+    server
+        .delete(&path)
+        .authorization(&mallory_token)
+        .await
+        .assert_status_forbidden();
+
+    let unchanged = server.get(&path).await.json::<Value>();
+    assert_eq!(unchanged["data"]["server_name"], "Birdhouse");
+}
+
+#[tokio::test]
+async fn test_owner_transfers_server_to_a_member() {
+    let (server, database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let (member, member_token) = sign_up(&server, "bob").await;
+    let (outsider, _) = sign_up(&server, "carol").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    seed_channel(&database, server_id, &[owner, member]).await;
+    let transfer = format!("/api/v1/servers/{}/owner", server_id);
+
+    let not_member = server
+        .put(&transfer)
+        .authorization(&owner_token)
+        .json(&json!({ "owner_user_id": outsider.to_string() }))
+        .await;
+    not_member.assert_status_not_found();
+    assert_eq!(not_member.json::<Value>()["code"], "UNKNOWN_MEMBER");
+
+    let transferred = server
+        .put(&transfer)
+        .authorization(&owner_token)
+        .json(&json!({ "owner_user_id": member.to_string() }))
+        .await;
+    transferred.assert_status_ok();
+    assert_eq!(transferred.json::<Value>()["data"]["owner_user_id"], member.to_string());
+
+    // The previous owner is now just a member
+    server
+        .put(&format!("/api/v1/servers/{}", server_id))
+        .authorization(&owner_token)
+        .json(&json!({ "name": "Mine again" }))
+        .await
+        .assert_status_forbidden();
+    server
+        .put(&format!("/api/v1/servers/{}", server_id))
+        .authorization(&member_token)
+        .json(&json!({ "name": "Aviary" }))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_deleting_owner_cascades_to_servers() {
+    let (server, _) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+
+    server
+        .delete(&format!("/api/v1/users/{}", owner))
+        .authorization(&owner_token)
+        .await
+        .assert_status_ok();
+
+    server
+        .get(&format!("/api/v1/servers/{}", server_id))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_only_owner_manages_server() {
+    let (server, database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let (member, member_token) = sign_up(&server, "bob").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    seed_channel(&database, server_id, &[owner, member]).await;
+
+    let path = format!("/api/v1/servers/{}/members/{}/permissions", server_id, member);
+    server
+        .put(&path)
+        .authorization(&member_token)
+        .json(&json!({ "permissions": 0 }))
+        .await
+        .assert_status_forbidden();
+    server
+        .put(&path)
+        .authorization(&owner_token)
+        .json(&json!({ "permissions": 0 }))
+        .await
+        .assert_status_ok();
+
+    let mfa = server
+        .put(&format!("/api/v1/servers/{}/mfa", server_id))
+        .authorization(&owner_token)
+        .json(&json!({ "mfa_required": true }))
+        .await;
+    mfa.assert_status_forbidden();
+    assert_eq!(mfa.json::<Value>()["code"], "MFA_SETUP_REQUIRED");
+}
+
+#[tokio::test]
+async fn test_messages_replies_and_pins() {
+    let (server, database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let (_, outsider_token) = sign_up(&server, "mallory").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    let channel_id = seed_channel(&database, server_id, &[owner]).await;
+    let messages = format!("/api/v1/channels/{}/messages", channel_id);
+
+    let posted = server
+        .post(&messages)
+        .authorization(&owner_token)
+        .json(&json!({ "content": "Hello" }))
+        .await;
+    posted.assert_status(axum::http::StatusCode::CREATED);
+    let message_id: i64 = posted.json::<Value>()["data"]["message_id"].as_str().unwrap().parse().unwrap();
+
+    let reply = server
+        .post(&messages)
+        .authorization(&owner_token)
+        .json(&json!({ "content": "Hi back", "referenced_message_id": message_id.to_string() }))
+        .await;
+    assert_eq!(reply.json::<Value>()["data"]["referenced_message_id"], message_id.to_string());
+
+    let bad_reply = server
+        .post(&messages)
+        .authorization(&owner_token)
+        .json(&json!({ "content": "To nothing", "referenced_message_id": 999 }))
+        .await;
+    assert_eq!(bad_reply.json::<Value>()["code"], "INVALID_REFERENCE");
+
+    server
+        .get(&messages)
+        .authorization(&outsider_token)
+        .await
+        .assert_status_forbidden();
+
+    let listed = server.get(&messages).authorization(&owner_token).await;
+    let listed = listed.json::<Value>();
+    assert_eq!(listed["data"].as_array().unwrap().len(), 2);
+    assert_eq!(listed["data"][0]["content"], "Hi back");
+    assert_eq!(listed["data"][0]["author"]["username"], "alice");
+
+    let pin = format!("/api/v1/channels/{}/pins/{}", channel_id, message_id);
+    server.put(&pin).authorization(&owner_token).await.assert_status_ok();
+    let pins = server
+        .get(&format!("/api/v1/channels/{}/pins", channel_id))
+        .authorization(&owner_token)
+        .await;
+    assert_eq!(pins.json::<Value>()["data"][0]["message_id"], message_id.to_string());
+
+    server.delete(&pin).authorization(&owner_token).await.assert_status_ok();
+    let unpinned_again = server.delete(&pin).authorization(&owner_token).await;
+    assert_eq!(unpinned_again.json::<Value>()["code"], "UNKNOWN_MESSAGE");
+}
+
+#[tokio::test]
+async fn test_pinning_needs_manage_messages() {
+    let (server, database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let (member, member_token) = sign_up(&server, "bob").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    let channel_id = seed_channel(&database, server_id, &[owner, member]).await;
+
+    let posted = server
+        .post(&format!("/api/v1/channels/{}/messages", channel_id))
+        .authorization(&owner_token)
+        .json(&json!({ "content": "Pin me" }))
+        .await;
+    let message_id: i64 = posted.json::<Value>()["data"]["message_id"].as_str().unwrap().parse().unwrap();
+
+    server
+        .put(&format!("/api/v1/servers/{}/members/{}/permissions", server_id, member))
+        .authorization(&owner_token)
+        .json(&json!({ "permissions": 0 }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .put(&format!("/api/v1/channels/{}/pins/{}", channel_id, message_id))
+        .authorization(&member_token)
+        .await;
+    response.assert_status_forbidden();
+    assert_eq!(response.json::<Value>()["code"], "MISSING_PERMISSIONS");
+}
+
+#[tokio::test]
+async fn test_joining_and_leaving_servers() {
+    let (server, _database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let (member, member_token) = sign_up(&server, "bob").await;
+    let (_, other_token) = sign_up(&server, "carol").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    let members = format!("/api/v1/servers/{}/members", server_id);
+
+    let joined = server.post(&members).authorization(&member_token).await;
+    joined.assert_status(axum::http::StatusCode::CREATED);
+    assert_eq!(joined.json::<Value>()["data"]["user_id"], member.to_string());
+
+    let again = server.post(&members).authorization(&member_token).await;
+    again.assert_status(axum::http::StatusCode::CONFLICT);
+    assert_eq!(again.json::<Value>()["code"], "CONFLICT");
+
+    let missing = server.post("/api/v1/servers/1/members").authorization(&member_token).await;
+    assert_eq!(missing.json::<Value>()["code"], "UNKNOWN_SERVER");
+
+    server
+        .delete(&format!("{}/{}", members, member))
+        .authorization(&other_token)
+        .await
+        .assert_status_forbidden();
+
+    server
+        .delete(&format!("{}/{}", members, owner))
+        .authorization(&owner_token)
+        .await
+        .assert_status_bad_request();
+
+    server
+        .delete(&format!("{}/{}", members, member))
+        .authorization(&member_token)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    let gone = server
+        .delete(&format!("{}/{}", members, member))
+        .authorization(&owner_token)
+        .await;
+    gone.assert_status_not_found();
+    assert_eq!(gone.json::<Value>()["code"], "UNKNOWN_MEMBER");
+
+    // The owner can remove members too
+    server.post(&members).authorization(&member_token).await;
+    server
+        .delete(&format!("{}/{}", members, member))
+        .authorization(&owner_token)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_channel_changes_need_manage_channels() {
+    let (server, database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let (member, member_token) = sign_up(&server, "bob").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    let channel_id = seed_channel(&database, server_id, &[owner, member]).await;
+    let channel = format!("/api/v1/channels/{}", channel_id);
+
+    server
+        .put(&format!("/api/v1/servers/{}/members/{}/permissions", server_id, member))
+        .authorization(&owner_token)
+        .json(&json!({ "permissions": 0 }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .put(&channel)
+        .authorization(&member_token)
+        .json(&json!({ "name": "lobby" }))
+        .await;
+    assert_eq!(response.json::<Value>()["code"], "MISSING_PERMISSIONS");
+    server
+        .delete(&channel)
+        .authorization(&member_token)
+        .await
+        .assert_status_forbidden();
+
+    server
+        .put(&channel)
+        .authorization(&owner_token)
+        .json(&json!({ "name": " " }))
+        .await
+        .assert_status_unprocessable_entity();
+
+    let renamed = server
+        .put(&channel)
+        .authorization(&owner_token)
+        .json(&json!({ "name": "lobby" }))
+        .await;
+    assert_eq!(renamed.json::<Value>()["data"]["name"], "lobby");
+
+    server
+        .delete(&channel)
+        .authorization(&owner_token)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    server
+        .put(&channel)
+        .authorization(&owner_token)
+        .json(&json!({ "name": "lobby" }))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_slowmode_and_typing() {
+    let (server, database) = server();
+    let (owner, owner_token) = sign_up(&server, "alice").await;
+    let server_id = create_server(&server, "Birdhouse", &owner_token).await;
+    let channel_id = seed_channel(&database, server_id, &[owner]).await;
+    let slowmode = format!("/api/v1/channels/{}/slowmode", channel_id);
+
+    let updated = server
+        .put(&slowmode)
+        .authorization(&owner_token)
+        .json(&json!({ "rate_limit_per_user": 30 }))
+        .await;
+    assert_eq!(updated.json::<Value>()["data"]["rate_limit_per_user"], 30);
+
+    server
+        .put(&slowmode)
+        .authorization(&owner_token)
+        .json(&json!({ "rate_limit_per_user": 21601 }))
+        .await
+        .assert_status_unprocessable_entity();
+
+    server
+        .post(&format!("/api/v1/channels/{}/typing", channel_id))
+        .authorization(&owner_token)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_readyz_and_metrics() {
+    let (server, _) = server();
+
+    let ready = server.get("/readyz").await;
+    ready.assert_status_ok();
+    assert!(ready.json::<Value>()["data"]["schema_version"].as_i64().unwrap() > 0);
+
+    server.get("/metrics").await.assert_status_ok();
+}
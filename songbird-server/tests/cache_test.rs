@@ -0,0 +1,206 @@
+// Only the fixtures are used here
+#[allow(dead_code)]
+mod common;
+
+use common::fixtures::{create_channel, create_server, create_user, new_member};
+use songbird_server::cache::TtlCache;
+use songbird_server::config::CacheConfig;
+use songbird_server::gateway::events::{DispatchEvent, ServerMemberRemoveEvent};
+use songbird_server::gateway::GatewayHub;
+use songbird_server::metrics::Metrics;
+use songbird_server::repositories::{MemoryDatabase, Repositories, RepositoryCache};
+use std::convert::Infallible;
+use std::time::Duration;
+
+fn cache(ttl: Duration, capacity: usize, metrics: &Metrics) -> TtlCache<i64, String> {
+    TtlCache::new("test", ttl, capacity, metrics.clone())
+}
+
+async fn load(value: &str) -> Result<String, Infallible> {
+    Ok(value.to_string())
+}
+
+fn line<'a>(rendered: &'a str, prefix: &str) -> Option<&'a str> {
+    rendered.lines().find(|line| line.starts_with(prefix))
+}
+
+fn cached_repositories() -> Repositories {
+    let cache = RepositoryCache::new(&CacheConfig::default(), Metrics::new());
+    Repositories::in_memory(MemoryDatabase::new()).cached(cache)
+}
+
+async fn seed(repositories: &Repositories) -> (i64, i64, i64) {
+    let user_id = create_user(repositories, "alice").await.user_id;
+    let server_id = create_server(repositories, "Birdhouse", user_id).await.server_id;
+    let channel_id = create_channel(repositories, server_id, "general").await.channel_id;
+    (user_id, server_id, channel_id)
+}
+
+#[tokio::test]
+async fn test_lookups_are_counted_as_hits_and_misses() {
+    let metrics = Metrics::new();
+    let cache = cache(Duration::from_secs(60), 10, &metrics);
+
+    assert_eq!(cache.get_or_load(1, || load("one")).await.unwrap(), "one");
+    assert_eq!(cache.get_or_load(1, || load("changed")).await.unwrap(), "one");
+    assert_eq!(cache.get(&1).as_deref(), Some("one"));
+
+    let rendered = metrics.render();
+    assert!(line(&rendered, r#"songbird_cache_lookups_total{cache="test",result="hit"} 2"#).is_some());
+    assert!(line(&rendered, r#"songbird_cache_lookups_total{cache="test",result="miss"} 1"#).is_some());
+}
+
+#[tokio::test]
+async fn test_entries_expire() {
+    let cache = cache(Duration::from_millis(20), 10, &Metrics::new());
+
+    cache.get_or_load(1, || load("one")).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(40)).await;
+
+    assert_eq!(cache.get(&1), None);
+    assert!(cache.is_empty());
+}
+
+#[tokio::test]
+async fn test_least_recently_used_entry_is_evicted_when_full() {
+    let cache = cache(Duration::from_secs(60), 2, &Metrics::new());
+
+    cache.get_or_load(1, || load("one")).await.unwrap();
+    cache.get_or_load(2, || load("two")).await.unwrap();
+    cache.get(&1);
+    cache.get_or_load(3, || load("three")).await.unwrap();
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(&1).is_some());
+    assert!(cache.get(&2).is_none());
+}
+
+#[tokio::test]
+async fn test_load_racing_an_invalidation_is_not_cached() {
+    let cache = cache(Duration::from_secs(60), 10, &Metrics::new());
+
+    let value = cache
+        .get_or_load(1, || async {
+            cache.invalidate(&1);
+            load("stale").await
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(value, "stale");
+    assert_eq!(cache.get(&1), None);
+}
+
+#[tokio::test]
+async fn test_errors_are_not_cached() {
+    let cache = cache(Duration::from_secs(60), 10, &Metrics::new());
+
+    let failed: Result<String, &str> = cache.get_or_load(1, || async { Err("database down") }).await;
+
+    assert!(failed.is_err());
+    assert!(cache.is_empty());
+}
+
+#[tokio::test]
+async fn test_membership_changes_take_effect_immediately() {
+    let cached = cached_repositories();
+    let (_, server_id, _) = seed(&cached).await;
+    let bob = create_user(&cached, "bob").await.user_id;
+    let members = &cached.server_member_repository;
+
+    assert!(!members.is_member(server_id, bob).await.unwrap());
+    members.create(new_member(server_id, bob)).await.unwrap();
+    assert!(members.is_member(server_id, bob).await.unwrap());
+
+    members.delete(server_id, bob).await.unwrap();
+    assert!(!members.is_member(server_id, bob).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_deleting_a_server_drops_its_channels() {
+    let cached = cached_repositories();
+    let (_, server_id, channel_id) = seed(&cached).await;
+
+    assert!(cached.channel_repository.find_by_id(channel_id).await.unwrap().is_some());
+    cached.server_repository.delete(server_id).await.unwrap();
+
+    assert!(cached.server_repository.find_by_id(server_id).await.unwrap().is_none());
+    assert!(cached.channel_repository.find_by_id(channel_id).await.unwrap().is_none());
+}
+
+/// `database` bypasses the cache the way another instance would.
+#[tokio::test]
+async fn test_writes_elsewhere_show_once_entries_expire() {
+    let database = Repositories::in_memory(MemoryDatabase::new());
+    let config = CacheConfig {
+        ttl_secs: 1,
+        ..CacheConfig::default()
+    };
+    let cached = database.clone().cached(RepositoryCache::new(&config, Metrics::new()));
+    let (_, _, channel_id) = seed(&cached).await;
+
+    cached.channel_repository.find_by_id(channel_id).await.unwrap();
+    database.channel_repository.update_rate_limit(channel_id, 30).await.unwrap();
+    let channel = cached.channel_repository.find_by_id(channel_id).await.unwrap().unwrap();
+    assert_eq!(channel.rate_limit_per_user, 0);
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let channel = cached.channel_repository.find_by_id(channel_id).await.unwrap().unwrap();
+    assert_eq!(channel.rate_limit_per_user, 30);
+}
+
+/// The same write as above, announced through the gateway hub instead of
+/// waiting out the TTL.
+#[tokio::test]
+async fn test_published_changes_drop_entries() {
+    let database = Repositories::in_memory(MemoryDatabase::new());
+    let cache = RepositoryCache::new(&CacheConfig::default(), Metrics::new());
+    let cached = database.clone().cached(cache.clone());
+    let hub = GatewayHub::new(Metrics::new());
+    let follower = cache.follow(&hub);
+    let (_, _, channel_id) = seed(&cached).await;
+
+    cached.channel_repository.find_by_id(channel_id).await.unwrap();
+    let channel = database
+        .channel_repository
+        .update_rate_limit(channel_id, 30)
+        .await
+        .unwrap()
+        .unwrap();
+    hub.publish([], DispatchEvent::ChannelUpdate(channel));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let channel = cached.channel_repository.find_by_id(channel_id).await.unwrap().unwrap();
+    assert_eq!(channel.rate_limit_per_user, 30);
+    follower.abort();
+}
+
+#[tokio::test]
+async fn test_published_membership_changes_drop_entries() {
+    let database = Repositories::in_memory(MemoryDatabase::new());
+    let cache = RepositoryCache::new(&CacheConfig::default(), Metrics::new());
+    let cached = database.clone().cached(cache.clone());
+    let hub = GatewayHub::new(Metrics::new());
+    let follower = cache.follow(&hub);
+    let (user_id, server_id, _) = seed(&cached).await;
+    let members = &cached.server_member_repository;
+
+    assert!(!members.is_member(server_id, user_id).await.unwrap());
+    let (member, _) = database
+        .server_member_repository
+        .create(new_member(server_id, user_id))
+        .await
+        .unwrap();
+    hub.publish([], DispatchEvent::ServerMemberAdd(member));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(members.is_member(server_id, user_id).await.unwrap());
+
+    database.server_member_repository.delete(server_id, user_id).await.unwrap();
+    hub.publish(
+        [],
+        DispatchEvent::ServerMemberRemove(ServerMemberRemoveEvent { server_id, user_id }),
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!members.is_member(server_id, user_id).await.unwrap());
+    follower.abort();
+}
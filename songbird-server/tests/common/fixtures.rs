@@ -0,0 +1,116 @@
+//! Rows made straight through the repositories, for tests and benchmarks
+//! with no app in front and for what no route creates yet. It only uses
+//! `songbird_server`, so `benches/ready.rs` includes it too.
+
+// Every target that includes this uses a different part of it
+#![allow(dead_code)]
+
+use songbird_server::models::models::{
+    Channel, Message, MessageType, NewChannel, NewMessage, NewServer, NewServerMember, NewUser, Server, User,
+};
+use songbird_server::repositories::Repositories;
+use songbird_server::services::ReadyService;
+
+/// `username`, with an address derived from it.
+pub fn new_user(username: &str) -> NewUser {
+    NewUser {
+        username: username.to_string(),
+        email: format!("{}@example.com", username),
+        password_hash: "not a real hash".to_string(),
+        avatar_url: None,
+        status: "offline".to_string(),
+    }
+}
+
+pub fn new_server(name: &str, owner_user_id: i64) -> NewServer {
+    NewServer {
+        server_name: name.to_string(),
+        owner_user_id,
+        icon_url: None,
+    }
+}
+
+pub fn new_member(server_id: i64, user_id: i64) -> NewServerMember {
+    NewServerMember {
+        server_id,
+        user_id,
+        nickname: None,
+    }
+}
+
+pub async fn create_user(repositories: &Repositories, username: &str) -> User {
+    repositories.user_repository.create(new_user(username)).await.unwrap()
+}
+
+/// The owner is not a member until they join.
+pub async fn create_server(repositories: &Repositories, name: &str, owner_user_id: i64) -> Server {
+    repositories
+        .server_repository
+        .create(new_server(name, owner_user_id))
+        .await
+        .unwrap()
+}
+
+pub async fn create_channel(repositories: &Repositories, server_id: i64, name: &str) -> Channel {
+    repositories
+        .channel_repository
+        .create(NewChannel {
+            server_id: Some(server_id),
+            name: name.to_string(),
+            channel_type: "text".to_string(),
+        })
+        .await
+        .unwrap()
+}
+
+pub async fn join(repositories: &Repositories, server_id: i64, user_id: i64) {
+    repositories
+        .server_member_repository
+        .create(new_member(server_id, user_id))
+        .await
+        .unwrap();
+}
+
+/// A server owned by and joined by `owner_user_id`, with channels named
+/// `channel_names`.
+pub async fn create_joined_server(
+    repositories: &Repositories,
+    name: &str,
+    owner_user_id: i64,
+    channel_names: &[&str],
+) -> i64 {
+    let server_id = create_server(repositories, name, owner_user_id).await.server_id;
+    for channel_name in channel_names {
+        create_channel(repositories, server_id, channel_name).await;
+    }
+    join(repositories, server_id, owner_user_id).await;
+    server_id
+}
+
+pub async fn create_message(
+    repositories: &Repositories,
+    channel_id: i64,
+    author_user_id: i64,
+    content: &str,
+) -> Message {
+    repositories
+        .message_repository
+        .create(NewMessage {
+            channel_id,
+            author_user_id,
+            content: content.to_string(),
+            message_type: MessageType::Default,
+            referenced_message_id: None,
+        })
+        .await
+        .unwrap()
+}
+
+pub fn ready_service(repositories: &Repositories) -> ReadyService {
+    ReadyService::new(
+        repositories.user_repository.clone(),
+        repositories.server_repository.clone(),
+        repositories.server_member_repository.clone(),
+        repositories.channel_repository.clone(),
+    )
+}
@@ -0,0 +1,320 @@
+//! End-to-end harness: every test gets a database of its own on a real
+//! Postgres, with the app built on top of it exactly as `run` builds it.
+//! `Backends` adds a SQLite file and the in-memory backend next to it, for
+//! tests that compare what the backends do.
+//!
+//! Databases are copied from a template that is migrated once, so a test
+//! pays for a `CREATE DATABASE` rather than for every migration. The server
+//! is the one in `TEST_DATABASE_URL`, falling back to `DATABASE_URL`; the
+//! role needs `CREATEDB`.
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use rand::distr::{Alphanumeric, SampleString};
+use serde_json::{json, Value};
+use songbird_server::config::{Cli, Config};
+use songbird_server::database::{connect, establish_connection, MIGRATOR};
+use songbird_server::models::models::Channel;
+use songbird_server::repositories::{MemoryDatabase, Repositories};
+use songbird_server::shutdown::Shutdown;
+use songbird_server::{build_state, create_router};
+use sqlx::migrate::MigrateError;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::path::PathBuf;
+use tokio::sync::OnceCell;
+
+pub mod fixtures;
+
+const TEMPLATE: &str = "songbird_test_template";
+pub const PASSWORD: &str = "correct horse battery";
+
+/// A running app on a throwaway database.
+pub struct TestApp {
+    pub server: TestServer,
+    /// The same repositories the app uses, for seeding what no route
+    /// creates yet.
+    pub repositories: Repositories,
+    pub pool: PgPool,
+    database: TestDatabase,
+}
+
+pub struct TestUser {
+    pub user_id: i64,
+    /// A ready-made `Authorization` header value.
+    pub token: String,
+}
+
+impl TestApp {
+    pub async fn spawn() -> Self {
+        let database = TestDatabase::create().await;
+
+        let vars = vec![
+            ("DATABASE_URL".to_string(), database.url.clone()),
+            ("JWT_SECRET".to_string(), "e2e-test-secret".to_string()),
+            ("MAIL_BACKEND".to_string(), "log".to_string()),
+        ];
+        let config = Config::load_from(&Cli::default(), vars).unwrap();
+
+        let pool = establish_connection(&config.database).await.unwrap();
+        let repositories = Repositories::postgres(pool.clone());
+        let state = build_state(&config, repositories.clone(), Shutdown::new()).unwrap();
+        let server = TestServer::new(create_router(state, &config)).unwrap();
+
+        Self {
+            server,
+            repositories,
+            pool,
+            database,
+        }
+    }
+
+    /// Signs up through `/api/v1/users/create` and logs in through `/api/v1/login`.
+    pub async fn sign_up(&self, username: &str) -> TestUser {
+        let created = self
+            .server
+            .post("/api/v1/users/create")
+            .json(&json!({
+                "username": username,
+                "email": format!("{}@example.com", username),
+                "password": PASSWORD,
+            }))
+            .await;
+        created.assert_status(StatusCode::CREATED);
+        let user_id: i64 = created.json::<Value>()["data"]["user_id"].as_str().unwrap().parse().unwrap();
+
+        let login = self
+            .server
+            .post("/api/v1/login")
+            .json(&json!({ "username": username, "password": PASSWORD }))
+            .await;
+        login.assert_status_ok();
+        let body = login.json::<Value>();
+
+        TestUser {
+            user_id,
+            token: format!("Bearer {}", body["data"]["token"].as_str().unwrap()),
+        }
+    }
+
+    /// Creates a server owned by `owner` through `/api/v1/servers`; returns
+    /// its ID.
+    pub async fn create_server(&self, name: &str, owner: &TestUser) -> i64 {
+        let response = self
+            .server
+            .post("/api/v1/servers")
+            .authorization(&owner.token)
+            .json(&json!({ "name": name, "description": "" }))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        response.json::<Value>()["data"]["server_id"].as_str().unwrap().parse().unwrap()
+    }
+
+    /// Joins through `/api/v1/servers/{server_id}/members`.
+    pub async fn join(&self, server_id: i64, user: &TestUser) {
+        self.server
+            .post(&format!("/api/v1/servers/{}/members", server_id))
+            .authorization(&user.token)
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+
+    pub async fn create_channel(&self, server_id: i64, name: &str) -> Channel {
+        fixtures::create_channel(&self.repositories, server_id, name).await
+    }
+
+    pub fn database_name(&self) -> &str {
+        &self.database.name
+    }
+}
+
+/// A SQLite file of its own, removed along with the value.
+pub struct SqliteFile {
+    pub repositories: Repositories,
+    path: PathBuf,
+}
+
+impl SqliteFile {
+    pub async fn create() -> Self {
+        let suffix = Alphanumeric.sample_string(&mut rand::rng(), 12);
+        let path = std::env::temp_dir().join(format!("songbird_test_{}.db", suffix));
+        let vars = vec![
+            ("DATABASE_URL".to_string(), format!("sqlite://{}", path.display())),
+            ("JWT_SECRET".to_string(), "e2e-test-secret".to_string()),
+            ("MAIL_BACKEND".to_string(), "log".to_string()),
+        ];
+        let config = Config::load_from(&Cli::default(), vars).unwrap();
+        let repositories = connect(&config.database).await.unwrap().repositories();
+
+        Self { repositories, path }
+    }
+}
+
+impl Drop for SqliteFile {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.path.display(), suffix));
+        }
+    }
+}
+
+/// An empty database on every backend. SQLite and the in-memory backend
+/// report violations under the Postgres constraint names, which is what
+/// the error codes are mapped from.
+pub struct Backends {
+    pub postgres: TestApp,
+    pub sqlite: SqliteFile,
+    pub memory: Repositories,
+}
+
+impl Backends {
+    pub async fn create() -> Self {
+        Self {
+            postgres: TestApp::spawn().await,
+            sqlite: SqliteFile::create().await,
+            memory: Repositories::in_memory(MemoryDatabase::new()),
+        }
+    }
+
+    pub fn all(&self) -> [(&'static str, &Repositories); 3] {
+        [
+            ("postgres", &self.postgres.repositories),
+            ("sqlite", &self.sqlite.repositories),
+            ("memory", &self.memory),
+        ]
+    }
+}
+
+/// A database that is dropped along with the value.
+struct TestDatabase {
+    name: String,
+    url: String,
+}
+
+impl TestDatabase {
+    async fn create() -> Self {
+        static TEMPLATE_READY: OnceCell<()> = OnceCell::const_new();
+        TEMPLATE_READY.get_or_init(prepare_template).await;
+
+        let suffix = Alphanumeric.sample_string(&mut rand::rng(), 12).to_lowercase();
+        let name = format!("songbird_test_{}", suffix);
+
+        let mut admin = admin_connection().await;
+        admin
+            .execute(format!(r#"CREATE DATABASE "{}" TEMPLATE "{}""#, name, TEMPLATE).as_str())
+            .await
+            .unwrap();
+
+        Self {
+            url: database_url(&server_url(), &name),
+            name,
+        }
+    }
+}
+
+impl Drop for TestDatabase {
+    /// `Drop` cannot await, and the test's runtime may already be shutting
+    /// down, so the cleanup gets a thread and a runtime of its own.
+    fn drop(&mut self) {
+        let name = self.name.clone();
+        let dropped = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async {
+                    let mut admin = admin_connection().await;
+                    admin
+                        .execute(format!(r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#, name).as_str())
+                        .await
+                })
+        })
+        .join();
+
+        if !matches!(dropped, Ok(Ok(_))) {
+            eprintln!("Failed to drop test database {}", self.name);
+        }
+    }
+}
+
+/// Creates the template if it is missing and brings it up to date. An
+/// advisory lock keeps test binaries running side by side from racing;
+/// a template built from since-edited migrations is rebuilt.
+async fn prepare_template() {
+    let mut admin = admin_connection().await;
+    admin
+        .execute("SELECT pg_advisory_lock(hashtext('songbird_test_template'))")
+        .await
+        .unwrap();
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+        .bind(TEMPLATE)
+        .fetch_one(&mut admin)
+        .await
+        .unwrap();
+    if !exists {
+        create_template(&mut admin).await;
+    }
+
+    match migrate_template().await {
+        Ok(()) => {}
+        Err(MigrateError::VersionMismatch(_)) => {
+            admin
+                .execute(format!(r#"DROP DATABASE "{}""#, TEMPLATE).as_str())
+                .await
+                .unwrap();
+            create_template(&mut admin).await;
+            migrate_template().await.unwrap();
+        }
+        Err(e) => panic!("Failed to migrate the test template: {}", e),
+    }
+
+    admin
+        .execute("SELECT pg_advisory_unlock(hashtext('songbird_test_template'))")
+        .await
+        .unwrap();
+}
+
+async fn create_template(admin: &mut PgConnection) {
+    admin
+        .execute(format!(r#"CREATE DATABASE "{}""#, TEMPLATE).as_str())
+        .await
+        .unwrap();
+}
+
+/// Uses a single connection and closes it, since Postgres will not copy a
+/// database someone is connected to.
+async fn migrate_template() -> Result<(), MigrateError> {
+    let mut connection = PgConnection::connect(&database_url(&server_url(), TEMPLATE))
+        .await
+        .unwrap();
+    let migrated = MIGRATOR.run(&mut connection).await;
+    connection.close().await.unwrap();
+    migrated
+}
+
+async fn admin_connection() -> PgConnection {
+    PgConnection::connect(&server_url())
+        .await
+        .expect("end-to-end tests need a Postgres server in TEST_DATABASE_URL or DATABASE_URL")
+}
+
+fn server_url() -> String {
+    std::env::var("TEST_DATABASE_URL")
+        .or_else(|_| std::env::var("DATABASE_URL"))
+        .expect("end-to-end tests need TEST_DATABASE_URL or DATABASE_URL")
+}
+
+/// `url` with its database swapped for `name`, keeping any query string.
+fn database_url(url: &str, name: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (url, None),
+    };
+    let (scheme, rest) = base.split_once("://").unwrap_or(("postgres", base));
+    let host = rest.split_once('/').map_or(rest, |(host, _)| host);
+
+    match query {
+        Some(query) => format!("{}://{}/{}?{}", scheme, host, name, query),
+        None => format!("{}://{}/{}", scheme, host, name),
+    }
+}
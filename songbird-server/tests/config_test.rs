@@ -0,0 +1,286 @@
+use clap::Parser;
+use songbird_server::config::{Cli, Config, ConfigError, LogFormat, MailBackend};
+use std::path::PathBuf;
+
+fn cli(args: &[&str]) -> Cli {
+    Cli::parse_from(std::iter::once("songbird-server").chain(args.iter().copied()))
+}
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+fn required() -> Vec<(String, String)> {
+    vars(&[
+        ("DATABASE_URL", "postgres://localhost/songbird"),
+        ("JWT_SECRET", "secret"),
+        ("MAIL_BACKEND", "log"),
+    ])
+}
+
+fn write_config(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("songbird-config-test-{}-{}.toml", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn problems(result: Result<Config, ConfigError>) -> Vec<String> {
+    match result {
+        Err(ConfigError::Invalid(problems)) => problems,
+        other => panic!("expected validation problems, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_defaults() {
+    let config = Config::load_from(&cli(&[]), required()).unwrap();
+
+    assert_eq!(config.server.bind.to_string(), "0.0.0.0:3000");
+    assert_eq!(config.database.max_connections, 5);
+    assert_eq!(config.log.format, LogFormat::Pretty);
+    assert!(config.rate_limit.enabled);
+    assert!(config.cors.allowed_origins.is_empty());
+}
+
+#[test]
+fn test_missing_required_settings_are_all_reported() {
+    let problems = problems(Config::load_from(&cli(&[]), vars(&[])));
+
+    assert_eq!(problems.len(), 3);
+    assert!(problems[0].contains("database.url"));
+    assert!(problems[1].contains("auth.jwt_secret"));
+    assert!(problems[2].contains("mail.smtp_url"));
+}
+
+#[test]
+fn test_log_mail_backend_must_be_chosen() {
+    let env = vars(&[
+        ("DATABASE_URL", "postgres://localhost/songbird"),
+        ("JWT_SECRET", "secret"),
+    ]);
+    let missing = problems(Config::load_from(&cli(&[]), env));
+    assert_eq!(missing.len(), 1);
+    assert!(missing[0].contains("mail.smtp_url is required"));
+
+    let config = Config::load_from(
+        &cli(&["--set", "mail.backend=smtp", "--set", "mail.smtp_url=smtp://localhost:1025"]),
+        required(),
+    )
+    .unwrap();
+    assert_eq!(config.mail.backend, MailBackend::Smtp);
+
+    let conflicting = problems(Config::load_from(&cli(&["--set", "mail.smtp_url=smtp://localhost:1025"]), required()));
+    assert_eq!(conflicting.len(), 1);
+    assert!(conflicting[0].contains("mail.backend is \"log\""));
+}
+
+#[test]
+fn test_layers_override_in_order() {
+    let path = write_config(
+        "layers",
+        r#"
+        [server]
+        bind = "127.0.0.1:4000"
+        [database]
+        max_connections = 8
+        min_connections = 2
+        "#,
+    );
+    let mut env = required();
+    env.extend(vars(&[
+        ("SONGBIRD_DATABASE__MAX_CONNECTIONS", "12"),
+        ("SONGBIRD_SERVER__BIND", "127.0.0.1:5000"),
+    ]));
+
+    let config = Config::load_from(
+        &cli(&["--config", path.to_str().unwrap(), "--bind", "127.0.0.1:6000"]),
+        env,
+    )
+    .unwrap();
+
+    assert_eq!(config.database.min_connections, 2);
+    assert_eq!(config.database.max_connections, 12);
+    assert_eq!(config.server.bind.to_string(), "127.0.0.1:6000");
+}
+
+#[test]
+fn test_prefixed_env_wins_over_legacy_env() {
+    let mut env = required();
+    env.push(("SONGBIRD_AUTH__JWT_SECRET".to_string(), "newer".to_string()));
+
+    let config = Config::load_from(&cli(&[]), env).unwrap();
+
+    assert_eq!(config.auth.jwt_secret, "newer");
+}
+
+#[test]
+fn test_numeric_strings_stay_strings() {
+    let config = Config::load_from(&cli(&["--set", "auth.jwt_secret=12345"]), required()).unwrap();
+
+    assert_eq!(config.auth.jwt_secret, "12345");
+}
+
+#[test]
+fn test_set_parses_typed_values() {
+    let config = Config::load_from(
+        &cli(&[
+            "--set",
+            "rate_limit.enabled=false",
+            "--set",
+            "rate_limit.per_ip.refill_per_second=2.5",
+            "--set",
+            "cors.allowed_origins=https://a.example.com, https://b.example.com",
+            "--log-format",
+            "json",
+        ]),
+        required(),
+    )
+    .unwrap();
+
+    assert!(!config.rate_limit.enabled);
+    assert_eq!(config.rate_limit.per_ip.refill_per_second, 2.5);
+    assert_eq!(
+        config.cors.allowed_origins,
+        ["https://a.example.com", "https://b.example.com"]
+    );
+    assert_eq!(config.log.format, LogFormat::Json);
+}
+
+#[test]
+fn test_type_errors_name_the_key() {
+    let error = Config::load_from(&cli(&["--set", "database.max_connections=lots"]), required()).unwrap_err();
+
+    assert!(error.to_string().contains("database.max_connections"), "{}", error);
+}
+
+#[test]
+fn test_unknown_keys_are_rejected() {
+    let path = write_config("unknown", "[database]\nmax_conections = 3\n");
+
+    let error = Config::load_from(&cli(&["--config", path.to_str().unwrap()]), required()).unwrap_err();
+
+    assert!(error.to_string().contains("max_conections"), "{}", error);
+}
+
+#[test]
+fn test_unknown_set_keys_are_rejected() {
+    let error = Config::load_from(&cli(&["--set", "database.max_conections=3"]), required()).unwrap_err();
+
+    assert!(error.to_string().contains("max_conections"), "{}", error);
+}
+
+/// The environment is shared with everything else on the host, so strays
+/// are skipped and reported instead of stopping startup.
+#[test]
+fn test_unknown_env_keys_are_ignored() {
+    let mut env = required();
+    env.extend(vars(&[
+        ("SONGBIRD_DATABASE__MAX_CONECTIONS", "3"),
+        ("SONGBIRD_DEPLOY_COLOR", "blue"),
+        ("SONGBIRD_DATABASE__MIN_CONNECTIONS", "2"),
+    ]));
+
+    let config = Config::load_from(&cli(&[]), env).unwrap();
+
+    assert_eq!(config.database.min_connections, 2);
+    assert_eq!(config.database.max_connections, Config::default().database.max_connections);
+    assert_eq!(config.ignored_env, ["SONGBIRD_DATABASE__MAX_CONECTIONS", "SONGBIRD_DEPLOY_COLOR"]);
+}
+
+#[test]
+fn test_env_type_errors_are_still_rejected() {
+    let mut env = required();
+    env.extend(vars(&[("SONGBIRD_DATABASE__MAX_CONNECTIONS", "lots")]));
+
+    let error = Config::load_from(&cli(&[]), env).unwrap_err();
+
+    assert!(error.to_string().contains("database.max_connections"), "{}", error);
+}
+
+#[test]
+fn test_malformed_set_is_rejected() {
+    let error = Config::load_from(&cli(&["--set", "database.url"]), required()).unwrap_err();
+
+    assert!(matches!(error, ConfigError::Parse { .. }));
+}
+
+#[test]
+fn test_validation_problems() {
+    let problems = problems(Config::load_from(
+        &cli(&[
+            "--database-url",
+            "mysql://localhost/songbird",
+            "--set",
+            "database.min_connections=10",
+            "--set",
+            "rate_limit.per_user.capacity=0",
+            "--set",
+            "rate_limit.trusted_proxies=10.0.0.0/33",
+            "--set",
+            "cors.allowed_origins=https://app.example.com/path",
+            "--set",
+            "server.tls.cert_path=/does/not/exist.pem",
+            "--set",
+            "server.tls.key_path=/does/not/exist.key",
+            "--set",
+            "server.worker_id=1024",
+            "--set",
+            "cache.ttl_secs=0",
+        ]),
+        required(),
+    ));
+
+    for key in [
+        "database.url",
+        "database.min_connections",
+        "rate_limit.per_user.capacity",
+        "rate_limit.trusted_proxies",
+        "cors.allowed_origins",
+        "server.tls.cert_path",
+        "server.tls.key_path",
+        "server.worker_id",
+        "cache.ttl_secs",
+    ] {
+        assert!(problems.iter().any(|p| p.starts_with(key)), "no problem for {}: {:?}", key, problems);
+    }
+}
+
+#[test]
+fn test_sqlite_url_is_allowed() {
+    let config = Config::load_from(&cli(&["--database-url", "sqlite:///var/lib/songbird.db"]), required()).unwrap();
+
+    assert_eq!(config.database.url, "sqlite:///var/lib/songbird.db");
+}
+
+#[test]
+fn test_trusted_proxies_are_addresses_or_ranges() {
+    let mut env = required();
+    env.extend(vars(&[("SONGBIRD_RATE_LIMIT__TRUSTED_PROXIES", r#"["10.0.0.0/8", "::1", "fd00::/8"]"#)]));
+    let config = Config::load_from(&Cli::default(), env).unwrap();
+
+    assert_eq!(config.rate_limit.trusted_proxies, ["10.0.0.0/8", "::1", "fd00::/8"]);
+}
+
+#[test]
+fn test_wildcard_origin_is_allowed() {
+    let config = Config::load_from(&cli(&["--set", "cors.allowed_origins=*"]), required()).unwrap();
+
+    assert_eq!(config.cors.allowed_origins, ["*"]);
+}
+
+#[test]
+fn test_otlp_endpoint_depends_on_feature() {
+    let result = Config::load_from(
+        &cli(&["--set", "log.otlp_endpoint=http://localhost:4318"]),
+        required(),
+    );
+
+    if cfg!(feature = "otlp") {
+        assert_eq!(result.unwrap().log.otlp_endpoint.as_deref(), Some("http://localhost:4318"));
+    } else {
+        assert!(problems(result)[0].starts_with("log.otlp_endpoint"));
+    }
+}
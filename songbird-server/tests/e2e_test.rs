@@ -0,0 +1,249 @@
+// `Backends` is for the tests that compare backends
+#[allow(dead_code)]
+mod common;
+
+use axum::http::StatusCode;
+use common::{TestApp, PASSWORD};
+use serde_json::{json, Value};
+use songbird_server::error::{AppError, ErrorCode};
+use songbird_server::models::models::{NewServer, NewServerMember};
+
+#[tokio::test]
+async fn test_each_test_gets_a_fresh_database() {
+    let app = TestApp::spawn().await;
+
+    let (database, users): (String, i64) =
+        sqlx::query_as("SELECT current_database()::text, (SELECT COUNT(*) FROM users)")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+
+    assert_eq!(database, app.database_name());
+    assert_eq!(users, 0);
+}
+
+#[tokio::test]
+async fn test_signup_login_and_server_membership() {
+    let app = TestApp::spawn().await;
+    let owner = app.sign_up("alice").await;
+    let member = app.sign_up("bob").await;
+
+    let server_id = app.create_server("Birdhouse", &owner).await;
+    let channel = app.create_channel(server_id, "general").await;
+    app.join(server_id, &owner).await;
+    app.join(server_id, &member).await;
+
+    let messages = format!("/api/v1/channels/{}/messages", channel.channel_id);
+    app.server
+        .post(&messages)
+        .authorization(&member.token)
+        .json(&json!({ "content": "Hello from bob" }))
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let listed = app.server.get(&messages).authorization(&owner.token).await.json::<Value>();
+    let types: Vec<&str> = listed["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["message_type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, ["default", "member_join", "member_join"]);
+    assert_eq!(listed["data"][0]["author"]["username"], "bob");
+
+    let permissions = app
+        .server
+        .put(&format!("/api/v1/servers/{}/members/{}/permissions", server_id, member.user_id))
+        .authorization(&owner.token)
+        .json(&json!({ "permissions": 0 }))
+        .await;
+    permissions.assert_status_ok();
+    assert_eq!(permissions.json::<Value>()["data"]["permissions"], 0);
+}
+
+#[tokio::test]
+async fn test_duplicate_username_and_email() {
+    let app = TestApp::spawn().await;
+    let alice = app.sign_up("alice").await;
+    app.sign_up("bob").await;
+
+    let same_username = app
+        .server
+        .post("/api/v1/users/create")
+        .json(&json!({ "username": "alice", "email": "someone@example.com", "password": PASSWORD }))
+        .await;
+    same_username.assert_status(StatusCode::CONFLICT);
+    assert_eq!(same_username.json::<Value>()["code"], "USERNAME_TAKEN");
+
+    let same_email = app
+        .server
+        .post("/api/v1/users/create")
+        .json(&json!({ "username": "alicia", "email": "alice@example.com", "password": PASSWORD }))
+        .await;
+    same_email.assert_status(StatusCode::CONFLICT);
+    assert_eq!(same_email.json::<Value>()["code"], "EMAIL_TAKEN");
+
+    let renamed_onto_bob = app
+        .server
+        .put(&format!("/api/v1/users/{}", alice.user_id))
+        .authorization(&alice.token)
+        .json(&json!({ "email": "bob@example.com", "current_password": PASSWORD }))
+        .await;
+    renamed_onto_bob.assert_status(StatusCode::CONFLICT);
+    assert_eq!(renamed_onto_bob.json::<Value>()["code"], "EMAIL_TAKEN");
+}
+
+#[tokio::test]
+async fn test_duplicate_server_name_and_unknown_owner() {
+    let app = TestApp::spawn().await;
+    let owner = app.sign_up("alice").await;
+    app.create_server("Birdhouse", &owner).await;
+
+    let duplicate = app
+        .server
+        .post("/api/v1/servers")
+        .authorization(&owner.token)
+        .json(&json!({ "name": "Birdhouse", "description": "" }))
+        .await;
+    duplicate.assert_status(StatusCode::CONFLICT);
+    assert_eq!(duplicate.json::<Value>()["code"], "SERVER_NAME_TAKEN");
+
+    // The API takes the owner from the session; the foreign key still backs it
+    let unknown_owner = app
+        .repositories
+        .server_repository
+        .create(NewServer {
+            server_name: "Nest".to_string(),
+            owner_user_id: owner.user_id + 1000,
+            icon_url: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(AppError::from(unknown_owner).code(), ErrorCode::InvalidReference);
+}
+
+#[tokio::test]
+async fn test_joining_twice_rolls_back() {
+    let app = TestApp::spawn().await;
+    let owner = app.sign_up("alice").await;
+    let server_id = app.create_server("Birdhouse", &owner).await;
+    let channel = app.create_channel(server_id, "general").await;
+    app.join(server_id, &owner).await;
+
+    let rejoined = app
+        .repositories
+        .server_member_repository
+        .create(NewServerMember {
+            server_id,
+            user_id: owner.user_id,
+            nickname: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(
+        rejoined.as_database_error().and_then(|e| e.constraint()),
+        Some("server_members_pkey")
+    );
+
+    // The join message is written in the same transaction as the member
+    let join_messages: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM messages WHERE channel_id = $1")
+        .bind(channel.channel_id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(join_messages, 1);
+}
+
+#[tokio::test]
+async fn test_channel_is_members_only() {
+    let app = TestApp::spawn().await;
+    let owner = app.sign_up("alice").await;
+    let outsider = app.sign_up("mallory").await;
+    let server_id = app.create_server("Birdhouse", &owner).await;
+    let channel = app.create_channel(server_id, "general").await;
+    app.join(server_id, &owner).await;
+
+    app.server
+        .post(&format!("/api/v1/channels/{}/messages", channel.channel_id))
+        .authorization(&outsider.token)
+        .json(&json!({ "content": "Let me in" }))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+async fn test_deleting_owner_deletes_their_servers() {
+    let app = TestApp::spawn().await;
+    let owner = app.sign_up("alice").await;
+    let server_id = app.create_server("Birdhouse", &owner).await;
+
+    app.server
+        .delete(&format!("/api/v1/users/{}", owner.user_id))
+        .authorization(&owner.token)
+        .await
+        .assert_status_ok();
+
+    app.server
+        .get(&format!("/api/v1/servers/{}", server_id))
+        .await
+        .assert_status_not_found();
+}
+
+/// Search through the route, which only members of the server may use.
+/// `search_test.rs` checks what it matches on every backend.
+#[tokio::test]
+async fn test_search_matches_whole_words_in_one_channel() {
+    let app = TestApp::spawn().await;
+    let owner = app.sign_up("alice").await;
+    let outsider = app.sign_up("mallory").await;
+    let server_id = app.create_server("Birdhouse", &owner).await;
+    let channel = app.create_channel(server_id, "general").await;
+    let elsewhere = app.create_channel(server_id, "random").await;
+    app.join(server_id, &owner).await;
+    let (app, owner) = (&app, &owner);
+    let post = |channel_id: i64, content: &'static str| async move {
+        let response = app
+            .server
+            .post(&format!("/api/v1/channels/{}/messages", channel_id))
+            .authorization(&owner.token)
+            .json(&json!({ "content": content }))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        response.json::<Value>()["data"]["message_id"].as_str().unwrap().to_string()
+    };
+    let seeds = post(channel.channel_id, "Sunflower seeds are in").await;
+    let feeder = post(channel.channel_id, "The feeder needs seeds").await;
+    post(elsewhere.channel_id, "More seeds here").await;
+    let search = &format!("/api/v1/channels/{}/messages/search", channel.channel_id);
+    let found = |query: &'static str| async move {
+        let response = app.server.get(search).add_query_param("q", query).authorization(&owner.token).await;
+        response.assert_status_ok();
+        response.json::<Value>()["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["message_id"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(found("SEEDS").await, [feeder.clone(), seeds.clone()]);
+    assert_eq!(found("sunflower seeds").await, [seeds]);
+    assert_eq!(found("seeds | feeder:*").await, [feeder]);
+    assert!(found("sunflow:*").await.is_empty());
+
+    let limited = app
+        .server
+        .get(search)
+        .add_query_param("q", "seeds")
+        .add_query_param("limit", 1)
+        .authorization(&owner.token)
+        .await;
+    assert_eq!(limited.json::<Value>()["data"].as_array().unwrap().len(), 1);
+
+    app.server
+        .get(search)
+        .add_query_param("q", "seeds")
+        .authorization(&outsider.token)
+        .await
+        .assert_status_forbidden();
+}
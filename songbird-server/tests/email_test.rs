@@ -0,0 +1,116 @@
+use songbird_server::auth::{EmailTokenPurpose, SessionKeys};
+use songbird_server::mailer::{Email, LogMailer, Mailer};
+
+#[tokio::test]
+async fn test_log_mailer_writes_each_email_to_a_file() {
+    let dir = std::env::temp_dir().join(format!("songbird-mail-{}", std::process::id()));
+    let mailer = LogMailer::new(Some(dir.clone()));
+
+    for subject in ["First", "Second"] {
+        mailer
+            .send(Email {
+                to: "alice@example.com".to_string(),
+                subject: subject.to_string(),
+                body: "Hello".to_string(),
+            })
+            .await
+            .unwrap();
+    }
+
+    let mut files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    files.sort();
+    assert_eq!(files.len(), 2);
+    let contents = std::fs::read_to_string(&files[1]).unwrap();
+    assert!(contents.contains("To: alice@example.com"));
+    assert!(contents.contains("Subject: Second"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_log_mailer_keeps_earlier_files_after_a_restart() {
+    let dir = std::env::temp_dir().join(format!("songbird-mail-restart-{}", std::process::id()));
+    let email = Email {
+        to: "alice@example.com".to_string(),
+        subject: "Hello".to_string(),
+        body: "Hello".to_string(),
+    };
+
+    LogMailer::new(Some(dir.clone())).send(email.clone()).await.unwrap();
+    LogMailer::new(Some(dir.clone())).send(email).await.unwrap();
+
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_log_mailer_keeps_the_address_out_of_the_path() {
+    let root = std::env::temp_dir().join(format!("songbird-mail-path-{}", std::process::id()));
+    let dir = root.join("a").join("mail");
+    let mailer = LogMailer::new(Some(dir.clone()));
+
+    mailer
+        .send(Email {
+            to: "../../escaped/x@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Hello".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(files.len(), 1);
+    let contents = std::fs::read_to_string(dir.join(&files[0])).unwrap();
+    assert!(contents.contains("To: ../../escaped/x@example.com"));
+    assert!(!root.join("escaped").exists());
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_email_token_round_trip() {
+    let keys = SessionKeys::new(b"test-secret");
+
+    let (token, issued) = keys
+        .issue_email_token(7, EmailTokenPurpose::ResetPassword, "alice@example.com")
+        .unwrap();
+    let claims = keys.verify_email_token(&token).unwrap();
+
+    assert_eq!(claims.sub, 7);
+    assert_eq!(claims.jti, issued.jti);
+    assert_eq!(claims.purpose, EmailTokenPurpose::ResetPassword);
+    assert_eq!(claims.email, "alice@example.com");
+}
+
+#[test]
+fn test_email_tokens_and_sessions_are_not_interchangeable() {
+    let keys = SessionKeys::new(b"test-secret");
+
+    let session = keys.issue(7).unwrap();
+    let (email_token, _) = keys
+        .issue_email_token(7, EmailTokenPurpose::VerifyEmail, "alice@example.com")
+        .unwrap();
+
+    assert!(keys.verify_email_token(&session).is_err());
+    assert!(keys.verify(&email_token).is_err());
+    assert!(keys.verify_mfa_ticket(&email_token).is_err());
+}
+
+#[test]
+fn test_tokens_from_another_secret_are_rejected() {
+    let keys = SessionKeys::new(b"test-secret");
+    let other = SessionKeys::new(b"other-secret");
+
+    let (token, _) = other
+        .issue_email_token(7, EmailTokenPurpose::VerifyEmail, "alice@example.com")
+        .unwrap();
+
+    assert!(keys.verify_email_token(&token).is_err());
+}
@@ -0,0 +1,189 @@
+// Only the database half of the harness is used here
+#[allow(dead_code)]
+mod common;
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use common::fixtures::{create_channel, create_server, create_user, new_member, new_server, new_user};
+use common::Backends;
+use songbird_server::error::{AppError, ErrorCode};
+use songbird_server::models::models::NewUser;
+use songbird_server::services::ChannelAccessError;
+use std::time::Duration;
+
+/// The constraint the database named and the code and status the API
+/// answers with.
+fn violation<T: std::fmt::Debug>(result: Result<T, sqlx::Error>) -> (Option<String>, ErrorCode, StatusCode) {
+    let error = result.unwrap_err();
+    let constraint = error
+        .as_database_error()
+        .and_then(|db_error| db_error.constraint())
+        .map(str::to_string);
+    let error = AppError::from(error);
+    let code = error.code();
+
+    (constraint, code, error.into_response().status())
+}
+
+#[test]
+fn test_error_codes_serialize_as_screaming_snake_case() {
+    assert_eq!(serde_json::to_value(ErrorCode::UsernameTaken).unwrap(), "USERNAME_TAKEN");
+    assert_eq!(serde_json::to_value(ErrorCode::InvalidMfaCode).unwrap(), "INVALID_MFA_CODE");
+}
+
+#[test]
+fn test_error_code_statuses() {
+    assert_eq!(ErrorCode::UsernameTaken.status(), StatusCode::CONFLICT);
+    assert_eq!(ErrorCode::UnknownChannel.status(), StatusCode::NOT_FOUND);
+    assert_eq!(ErrorCode::InvalidCredentials.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(ErrorCode::ValidationFailed.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(ErrorCode::InternalError.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(ErrorCode::ServiceUnavailable.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[test]
+fn test_channel_access_errors_map_to_codes() {
+    assert_eq!(AppError::from(ChannelAccessError::NotFound).code(), ErrorCode::UnknownChannel);
+    assert_eq!(AppError::from(ChannelAccessError::Forbidden).code(), ErrorCode::Forbidden);
+}
+
+#[test]
+fn test_unexpected_database_errors_are_internal() {
+    let error = AppError::from(sqlx::Error::RowNotFound);
+
+    assert_eq!(error.code(), ErrorCode::InternalError);
+    assert_eq!(error.into_response().status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+fn test_rate_limited_response_rounds_retry_after_up() {
+    let response = AppError::rate_limited(Duration::from_millis(1500), "Slow down").into_response();
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()["retry-after"], "2");
+}
+
+#[tokio::test]
+async fn test_duplicate_username_is_username_taken() {
+    let backends = Backends::create().await;
+
+    for (backend, repositories) in backends.all() {
+        create_user(repositories, "alice").await;
+        let duplicate = repositories
+            .user_repository
+            .create(NewUser {
+                email: "other@example.com".to_string(),
+                ..new_user("alice")
+            })
+            .await;
+
+        assert_eq!(
+            violation(duplicate),
+            (Some("users_username_key".to_string()), ErrorCode::UsernameTaken, StatusCode::CONFLICT),
+            "{}",
+            backend
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_duplicate_email_is_email_taken() {
+    let backends = Backends::create().await;
+
+    for (backend, repositories) in backends.all() {
+        create_user(repositories, "alice").await;
+        let duplicate = repositories
+            .user_repository
+            .create(NewUser {
+                email: "alice@example.com".to_string(),
+                ..new_user("bob")
+            })
+            .await;
+
+        assert_eq!(
+            violation(duplicate),
+            (Some("users_email_key".to_string()), ErrorCode::EmailTaken, StatusCode::CONFLICT),
+            "{}",
+            backend
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_duplicate_server_name_is_server_name_taken() {
+    let backends = Backends::create().await;
+
+    for (backend, repositories) in backends.all() {
+        let alice = create_user(repositories, "alice").await;
+        create_server(repositories, "Birdhouse", alice.user_id).await;
+        let duplicate = repositories
+            .server_repository
+            .create(new_server("Birdhouse", alice.user_id))
+            .await;
+
+        assert_eq!(
+            violation(duplicate),
+            (Some("servers_server_name_key".to_string()), ErrorCode::ServerNameTaken, StatusCode::CONFLICT),
+            "{}",
+            backend
+        );
+    }
+}
+
+/// SQLite does not say which foreign key failed, so only the code is
+/// compared.
+#[tokio::test]
+async fn test_missing_reference_is_invalid_reference() {
+    let backends = Backends::create().await;
+
+    for (backend, repositories) in backends.all() {
+        let orphan = repositories.server_repository.create(new_server("Birdhouse", 42)).await;
+        let (_, code, status) = violation(orphan);
+
+        assert_eq!((code, status), (ErrorCode::InvalidReference, StatusCode::BAD_REQUEST), "{}", backend);
+    }
+}
+
+/// Unique constraints without a code of their own are a plain conflict.
+#[tokio::test]
+async fn test_other_duplicates_are_conflicts() {
+    let backends = Backends::create().await;
+
+    for (backend, repositories) in backends.all() {
+        let alice = create_user(repositories, "alice").await;
+        let server = create_server(repositories, "Birdhouse", alice.user_id).await;
+        let member = || new_member(server.server_id, alice.user_id);
+        repositories.server_member_repository.create(member()).await.unwrap();
+        let duplicate = repositories.server_member_repository.create(member()).await;
+
+        assert_eq!(
+            violation(duplicate),
+            (Some("server_members_pkey".to_string()), ErrorCode::Conflict, StatusCode::CONFLICT),
+            "{}",
+            backend
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_check_violation_is_invalid_request() {
+    let backends = Backends::create().await;
+
+    for (backend, repositories) in backends.all() {
+        let alice = create_user(repositories, "alice").await;
+        let server = create_server(repositories, "Birdhouse", alice.user_id).await;
+        let channel = create_channel(repositories, server.server_id, "general").await;
+        let invalid = repositories.channel_repository.update_rate_limit(channel.channel_id, -1).await;
+
+        assert_eq!(
+            violation(invalid),
+            (
+                Some("channels_rate_limit_per_user_check".to_string()),
+                ErrorCode::InvalidRequest,
+                StatusCode::BAD_REQUEST
+            ),
+            "{}",
+            backend
+        );
+    }
+}
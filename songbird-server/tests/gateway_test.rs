@@ -0,0 +1,104 @@
+use axum_test::{TestServer, TestWebSocket};
+use serde_json::{json, Value};
+use songbird_server::config::{Cli, Config};
+use songbird_server::repositories::{MemoryDatabase, Repositories, UserRepository};
+use songbird_server::shutdown::Shutdown;
+use songbird_server::{build_state, create_router};
+
+/// The app on a real socket, which the WebSocket upgrade needs, around one
+/// in-memory database.
+fn server() -> (TestServer, MemoryDatabase) {
+    let vars = vec![
+        ("DATABASE_URL".to_string(), "postgres://localhost/songbird_unused".to_string()),
+        ("JWT_SECRET".to_string(), "gateway-test-secret".to_string()),
+        ("MAIL_BACKEND".to_string(), "log".to_string()),
+    ];
+    let config = Config::load_from(&Cli::default(), vars).unwrap();
+
+    let database = MemoryDatabase::new();
+    let state = build_state(&config, Repositories::in_memory(database.clone()), Shutdown::new()).unwrap();
+    let server = TestServer::builder()
+        .http_transport()
+        .build(create_router(state, &config))
+        .unwrap();
+
+    (server, database)
+}
+
+/// Signs up and logs in; returns the user ID and a session token.
+async fn sign_up(server: &TestServer, username: &str) -> (i64, String) {
+    let created = server
+        .post("/api/v1/users/create")
+        .json(&json!({
+            "username": username,
+            "email": format!("{}@example.com", username),
+            "password": "correct horse battery",
+        }))
+        .await;
+    created.assert_status(axum::http::StatusCode::CREATED);
+    let user_id: i64 = created.json::<Value>()["data"]["user_id"].as_str().unwrap().parse().unwrap();
+
+    let login = server
+        .post("/api/v1/login")
+        .json(&json!({ "username": username, "password": "correct horse battery" }))
+        .await;
+    login.assert_status_ok();
+    let token = login.json::<Value>()["data"]["token"].as_str().unwrap().to_string();
+
+    (user_id, token)
+}
+
+/// A gateway session past HELLO, IDENTIFY and READY.
+async fn identify(server: &TestServer, token: &str) -> TestWebSocket {
+    let mut socket = server.get_websocket("/api/v1/gateway").await.into_websocket().await;
+    assert_eq!(socket.receive_json::<Value>().await["op"], 10);
+
+    socket.send_json(&json!({ "op": 2, "d": { "token": token } })).await;
+    assert_eq!(socket.receive_json::<Value>().await["t"], "READY");
+
+    socket
+}
+
+/// The opcode of the next frame that is not a dispatch.
+async fn next_opcode(socket: &mut TestWebSocket) -> u64 {
+    loop {
+        let frame: Value = socket.receive_json().await;
+        if frame["op"] != 0 {
+            return frame["op"].as_u64().unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_heartbeat_is_acknowledged() {
+    let (server, _database) = server();
+    let (_, token) = sign_up(&server, "alice").await;
+    let mut socket = identify(&server, &token).await;
+
+    socket.send_json(&json!({ "op": 1 })).await;
+    assert_eq!(next_opcode(&mut socket).await, 11);
+}
+
+#[tokio::test]
+async fn test_heartbeat_ends_a_revoked_session() {
+    let (server, database) = server();
+    let (user_id, token) = sign_up(&server, "alice").await;
+    let mut socket = identify(&server, &token).await;
+
+    UserRepository::revoke_sessions(&database, user_id).await.unwrap();
+
+    socket.send_json(&json!({ "op": 1 })).await;
+    assert_eq!(next_opcode(&mut socket).await, 9);
+}
+
+#[tokio::test]
+async fn test_heartbeat_ends_the_session_of_a_disabled_account() {
+    let (server, database) = server();
+    let (user_id, token) = sign_up(&server, "alice").await;
+    let mut socket = identify(&server, &token).await;
+
+    UserRepository::set_disabled(&database, user_id, true).await.unwrap();
+
+    socket.send_json(&json!({ "op": 1 })).await;
+    assert_eq!(next_opcode(&mut socket).await, 9);
+}
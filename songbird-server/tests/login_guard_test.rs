@@ -0,0 +1,49 @@
+use songbird_server::services::login_guard::{
+    backoff, LoginGuard, ACCOUNT_FREE_ATTEMPTS, IP_FREE_ATTEMPTS, MAX_BACKOFF,
+};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+#[test]
+fn test_backoff_doubles_after_free_attempts() {
+    assert_eq!(backoff(0, 3), Duration::ZERO);
+    assert_eq!(backoff(2, 3), Duration::ZERO);
+    assert_eq!(backoff(3, 3), Duration::from_secs(1));
+    assert_eq!(backoff(4, 3), Duration::from_secs(2));
+    assert_eq!(backoff(6, 3), Duration::from_secs(8));
+}
+
+#[test]
+fn test_backoff_is_capped() {
+    assert_eq!(backoff(100, 3), MAX_BACKOFF);
+    assert_eq!(backoff(u32::MAX, 0), MAX_BACKOFF);
+}
+
+#[test]
+fn test_account_is_locked_after_free_attempts() {
+    let guard = LoginGuard::new();
+    for _ in 0..ACCOUNT_FREE_ATTEMPTS - 1 {
+        guard.record_failure("alice", None);
+    }
+    assert!(guard.check("alice", None).is_ok());
+
+    guard.record_failure("alice", None);
+
+    assert!(guard.check("alice", None).is_err());
+    assert!(guard.check("bob", None).is_ok());
+}
+
+#[test]
+fn test_success_clears_the_account_but_not_the_address() {
+    let guard = LoginGuard::new();
+    for i in 0..IP_FREE_ATTEMPTS {
+        guard.record_failure(&format!("user{}", i % 2), Some(IP));
+    }
+
+    guard.record_success("user0");
+
+    assert!(guard.check("someone-else", Some(IP)).is_err());
+    assert!(guard.check("someone-else", None).is_ok());
+}
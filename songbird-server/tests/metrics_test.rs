@@ -0,0 +1,70 @@
+use songbird_server::gateway::events::{DispatchEvent, TypingStopEvent};
+use songbird_server::gateway::GatewayHub;
+use songbird_server::metrics::Metrics;
+
+fn line<'a>(rendered: &'a str, prefix: &str) -> Option<&'a str> {
+    rendered.lines().find(|line| line.starts_with(prefix))
+}
+
+#[test]
+fn test_requests_are_counted_by_route_and_status() {
+    let metrics = Metrics::new();
+
+    metrics.record_request("GET", "/api/users/{user_id}", 200, 0.01);
+    metrics.record_request("GET", "/api/users/{user_id}", 200, 0.02);
+    metrics.record_request("GET", "/api/users/{user_id}", 404, 0.01);
+
+    let rendered = metrics.render();
+    assert_eq!(
+        line(&rendered, r#"songbird_http_requests_total{method="GET",route="/api/users/{user_id}",status="200"}"#),
+        Some(r#"songbird_http_requests_total{method="GET",route="/api/users/{user_id}",status="200"} 2"#)
+    );
+    assert!(line(&rendered, r#"songbird_http_request_duration_seconds_count{method="GET",route="/api/users/{user_id}"} 3"#).is_some());
+}
+
+#[test]
+fn test_render_ends_with_eof_marker() {
+    let rendered = Metrics::new().render();
+
+    assert!(rendered.ends_with("# EOF\n"));
+}
+
+#[test]
+fn test_gateway_sessions_are_tracked() {
+    let metrics = Metrics::new();
+    let hub = GatewayHub::new(metrics.clone());
+
+    let (first, _first_rx) = hub.register(1);
+    let (_second, _second_rx) = hub.register(2);
+    assert!(line(&metrics.render(), "songbird_gateway_connections 2").is_some());
+
+    hub.unregister(first);
+    hub.unregister(first);
+    assert!(line(&metrics.render(), "songbird_gateway_connections 1").is_some());
+}
+
+#[test]
+fn test_dispatches_are_counted_per_delivered_session() {
+    let metrics = Metrics::new();
+    let hub = GatewayHub::new(metrics.clone());
+    let (_a, _a_rx) = hub.register(1);
+    let (_b, _b_rx) = hub.register(1);
+    let (_c, _c_rx) = hub.register(2);
+
+    let event = DispatchEvent::TypingStop(TypingStopEvent {
+        channel_id: 1,
+        user_id: 3,
+    });
+    hub.dispatch_to_users([1, 2, 42], &event);
+
+    assert!(line(&metrics.render(), r#"songbird_gateway_events_total{event="TYPING_STOP"} 3"#).is_some());
+}
+
+#[test]
+fn test_messages_created() {
+    let metrics = Metrics::new();
+
+    metrics.message_created();
+
+    assert!(line(&metrics.render(), "songbird_messages_created_total 1").is_some());
+}
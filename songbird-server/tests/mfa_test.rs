@@ -0,0 +1,45 @@
+use songbird_server::mfa::{
+    code_at, generate_recovery_codes, hash_recovery_code, is_totp_code, step_at, verify_code,
+    RECOVERY_CODE_COUNT,
+};
+
+// The RFC 6238 SHA-1 test secret, "12345678901234567890", in base32.
+const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+#[test]
+fn test_codes_match_rfc_6238_vectors() {
+    assert_eq!(code_at(RFC_SECRET, step_at(59)).as_deref(), Some("287082"));
+    assert_eq!(code_at(RFC_SECRET, step_at(1111111109)).as_deref(), Some("081804"));
+    assert_eq!(code_at(RFC_SECRET, step_at(2000000000)).as_deref(), Some("279037"));
+}
+
+#[test]
+fn test_verify_accepts_one_step_of_drift() {
+    let now = 1111111109;
+    let previous = code_at(RFC_SECRET, step_at(now) - 1).unwrap();
+    let too_old = code_at(RFC_SECRET, step_at(now) - 2).unwrap();
+
+    assert_eq!(verify_code(RFC_SECRET, &previous, now), Some(step_at(now) - 1));
+    assert_eq!(verify_code(RFC_SECRET, &too_old, now), None);
+}
+
+#[test]
+fn test_verify_rejects_malformed_codes() {
+    assert_eq!(verify_code(RFC_SECRET, "28708", 59), None);
+    assert_eq!(verify_code(RFC_SECRET, "abcdef", 59), None);
+    assert_eq!(verify_code("not base32!", "287082", 59), None);
+}
+
+#[test]
+fn test_recovery_codes_are_distinct_from_totp_codes() {
+    let codes = generate_recovery_codes();
+
+    assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+    assert!(codes.iter().all(|code| !is_totp_code(code)));
+}
+
+#[test]
+fn test_recovery_code_hash_ignores_case_and_dash() {
+    assert_eq!(hash_recovery_code("abcde-fghjk"), hash_recovery_code("ABCDEFGHJK"));
+    assert_ne!(hash_recovery_code("abcde-fghjk"), hash_recovery_code("abcde-fghjm"));
+}
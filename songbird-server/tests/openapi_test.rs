@@ -0,0 +1,143 @@
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use songbird_server::config::{Cli, Config};
+use songbird_server::openapi::ApiDoc;
+use songbird_server::repositories::{MemoryDatabase, Repositories};
+use songbird_server::shutdown::Shutdown;
+use songbird_server::{build_state, create_router};
+use std::path::PathBuf;
+use tower::ServiceExt;
+use utoipa::OpenApi;
+
+fn app() -> Router {
+    let vars = vec![
+        ("DATABASE_URL".to_string(), "postgres://localhost/songbird_unused".to_string()),
+        ("JWT_SECRET".to_string(), "openapi-test-secret".to_string()),
+        ("MAIL_BACKEND".to_string(), "log".to_string()),
+        // Every operation is requested once; none of them should be throttled
+        ("SONGBIRD_RATE_LIMIT__ENABLED".to_string(), "false".to_string()),
+    ];
+    let config = Config::load_from(&Cli::default(), vars).unwrap();
+
+    let state = build_state(&config, Repositories::in_memory(MemoryDatabase::new()), Shutdown::new()).unwrap();
+    create_router(state, &config)
+}
+
+fn generated() -> String {
+    let mut spec = ApiDoc::openapi().to_pretty_json().unwrap();
+    spec.push('\n');
+    spec
+}
+
+/// Every documented operation as `(method, path template)`.
+fn documented_operations() -> Vec<(Method, String)> {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let mut operations = Vec::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            operations.push((method.to_uppercase().parse().unwrap(), path.clone()));
+        }
+    }
+    operations
+}
+
+/// Run with `UPDATE_OPENAPI=1` to rewrite the committed copy after changing
+/// a handler or DTO.
+#[test]
+fn test_committed_spec_is_current() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("openapi.json");
+    let spec = generated();
+
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(&path, &spec).unwrap();
+        return;
+    }
+
+    let committed = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        committed == spec,
+        "openapi.json is out of date; regenerate it with `UPDATE_OPENAPI=1 cargo test --test openapi_test`"
+    );
+}
+
+#[tokio::test]
+async fn test_spec_is_served() {
+    let response = app()
+        .oneshot(Request::get("/api/openapi.json").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let served: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(served, serde_json::to_value(ApiDoc::openapi()).unwrap());
+}
+
+/// An unmatched path is an empty 404 and an unmatched method a 405; any
+/// other answer means a handler ran.
+#[tokio::test]
+async fn test_documented_operations_are_routed() {
+    let app = app();
+
+    for (method, template) in documented_operations() {
+        let uri = template
+            .split('/')
+            .map(|segment| if segment.starts_with('{') { "1" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method(method.clone()).uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{} {} is not routed", method, template);
+        assert!(
+            status != StatusCode::NOT_FOUND || !bytes.is_empty(),
+            "{} {} is not routed",
+            method,
+            template
+        );
+    }
+}
+
+/// The other direction: scans the router for `.route(path, method(...))`
+/// calls and checks each one is documented. Routes in the v1 tree are
+/// relative to `/api/v1`.
+#[test]
+fn test_routes_are_documented() {
+    let source: String = include_str!("../src/router.rs")
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let documented = documented_operations();
+
+    let mut routes = 0;
+    for call in source.split(".route(").skip(1) {
+        let call = call.trim_start();
+        let Some(rest) = call.strip_prefix('"') else {
+            continue;
+        };
+        let (path, rest) = rest.split_once('"').unwrap();
+        let method = rest.trim_start_matches([',', ' ']).split('(').next().unwrap();
+        let method: Method = method.to_uppercase().parse().unwrap();
+
+        assert!(
+            documented
+                .iter()
+                .any(|(m, p)| *m == method && (p == path || *p == format!("/api/v1{}", path))),
+            "{} {} is routed but not documented",
+            method,
+            path
+        );
+        routes += 1;
+    }
+
+    assert!(routes > 30, "only found {} routes in router.rs", routes);
+}
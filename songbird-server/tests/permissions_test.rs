@@ -0,0 +1,33 @@
+use songbird_server::models::permissions::Permissions;
+
+#[test]
+fn test_all_contains_every_permission() {
+    for permission in [
+        Permissions::MANAGE_MESSAGES,
+        Permissions::MANAGE_CHANNELS,
+        Permissions::KICK_MEMBERS,
+        Permissions::BAN_MEMBERS,
+        Permissions::MANAGE_SERVER,
+    ] {
+        assert!(Permissions::ALL.contains(permission));
+        assert!(!Permissions::NONE.contains(permission));
+    }
+}
+
+#[test]
+fn test_combined_permissions() {
+    let permissions = Permissions::MANAGE_MESSAGES | Permissions::KICK_MEMBERS;
+
+    assert!(permissions.contains(Permissions::MANAGE_MESSAGES));
+    assert!(permissions.contains(Permissions::KICK_MEMBERS));
+    assert!(!permissions.contains(Permissions::MANAGE_CHANNELS));
+    assert!(permissions.intersects(Permissions::MANAGE_CHANNELS | Permissions::KICK_MEMBERS));
+}
+
+#[test]
+fn test_unknown_bits_are_dropped() {
+    let permissions = Permissions::from_bits_truncate(i64::MAX);
+
+    assert_eq!(permissions, Permissions::ALL);
+    assert_eq!(Permissions::from_bits_truncate(1 << 40), Permissions::NONE);
+}
@@ -0,0 +1,74 @@
+use chrono::{Duration, Utc};
+use songbird_server::models::presence::{CustomStatus, PresenceStatus};
+
+#[test]
+fn test_presence_without_sessions_is_offline() {
+    assert_eq!(
+        PresenceStatus::aggregate(PresenceStatus::Online, &[]),
+        PresenceStatus::Offline
+    );
+    assert_eq!(
+        PresenceStatus::aggregate(PresenceStatus::Dnd, &[]),
+        PresenceStatus::Offline
+    );
+}
+
+#[test]
+fn test_presence_is_idle_only_when_every_session_is_afk() {
+    assert_eq!(
+        PresenceStatus::aggregate(PresenceStatus::Online, &[true, false]),
+        PresenceStatus::Online
+    );
+    assert_eq!(
+        PresenceStatus::aggregate(PresenceStatus::Online, &[true, true]),
+        PresenceStatus::Idle
+    );
+}
+
+#[test]
+fn test_invisible_presence_appears_offline() {
+    assert_eq!(
+        PresenceStatus::aggregate(PresenceStatus::Invisible, &[false]),
+        PresenceStatus::Offline
+    );
+}
+
+#[test]
+fn test_dnd_presence_ignores_afk_sessions() {
+    assert_eq!(
+        PresenceStatus::aggregate(PresenceStatus::Dnd, &[true]),
+        PresenceStatus::Dnd
+    );
+}
+
+#[test]
+fn test_presence_status_round_trips_through_strings() {
+    for status in [
+        PresenceStatus::Online,
+        PresenceStatus::Idle,
+        PresenceStatus::Dnd,
+        PresenceStatus::Invisible,
+        PresenceStatus::Offline,
+    ] {
+        assert_eq!(status.as_str().parse::<PresenceStatus>(), Ok(status));
+    }
+    assert!("away".parse::<PresenceStatus>().is_err());
+}
+
+#[test]
+fn test_expired_custom_status_is_dropped() {
+    let expired = CustomStatus::from_columns(
+        Some("In a meeting".to_string()),
+        None,
+        Some(Utc::now() - Duration::minutes(1)),
+    );
+    let active = CustomStatus::from_columns(
+        Some("In a meeting".to_string()),
+        None,
+        Some(Utc::now() + Duration::minutes(1)),
+    );
+
+    assert_eq!(expired, None);
+    assert!(active.is_some());
+    assert_eq!(CustomStatus::from_columns(None, None, None), None);
+}
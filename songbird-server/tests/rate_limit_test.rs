@@ -0,0 +1,127 @@
+use axum::extract::ConnectInfo;
+use axum::http::{Extensions, HeaderMap};
+use songbird_server::rate_limit::{BucketConfig, TokenBucket, TrustedProxies};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+const CONFIG: BucketConfig = BucketConfig {
+    capacity: 3,
+    refill_per_second: 1.0,
+};
+
+#[test]
+fn test_bucket_allows_a_full_burst() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(CONFIG, now);
+
+    for expected_remaining in [2, 1, 0] {
+        let status = bucket.try_acquire(CONFIG, now);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, expected_remaining);
+        assert_eq!(status.limit, 3);
+    }
+}
+
+#[test]
+fn test_empty_bucket_reports_retry_after() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(CONFIG, now);
+    for _ in 0..3 {
+        bucket.try_acquire(CONFIG, now);
+    }
+
+    let status = bucket.try_acquire(CONFIG, now);
+
+    assert!(!status.allowed);
+    assert_eq!(status.retry_after, Duration::from_secs(1));
+    assert_eq!(status.reset_after, Duration::from_secs(3));
+}
+
+#[test]
+fn test_bucket_refills_over_time() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(CONFIG, now);
+    for _ in 0..3 {
+        bucket.try_acquire(CONFIG, now);
+    }
+
+    let status = bucket.try_acquire(CONFIG, now + Duration::from_millis(1500));
+
+    assert!(status.allowed);
+    assert_eq!(status.remaining, 0);
+}
+
+#[test]
+fn test_bucket_never_exceeds_capacity() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(CONFIG, now);
+
+    let status = bucket.try_acquire(CONFIG, now + Duration::from_secs(60));
+
+    assert_eq!(status.remaining, 2);
+}
+
+/// The client address of a request from `peer` with `headers`, behind
+/// proxies in 10.0.0.0/8 and at ::1.
+fn client_ip(peer: &str, headers: &[(&'static str, &str)]) -> Option<IpAddr> {
+    let proxies = TrustedProxies::new(&["10.0.0.0/8".to_string(), "::1".to_string()]);
+    let mut extensions = Extensions::new();
+    extensions.insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        header_map.append(*name, value.parse().unwrap());
+    }
+
+    proxies.client_ip(&extensions, &header_map)
+}
+
+fn ip(ip: &str) -> Option<IpAddr> {
+    Some(ip.parse().unwrap())
+}
+
+#[test]
+fn test_forwarded_addresses_from_anyone_else_are_ignored() {
+    let headers = [("x-forwarded-for", "203.0.113.7"), ("forwarded", "for=203.0.113.7")];
+
+    assert_eq!(client_ip("198.51.100.1:5000", &headers), ip("198.51.100.1"));
+    assert_eq!(client_ip("198.51.100.1:5000", &[]), ip("198.51.100.1"));
+    assert_eq!(client_ip("10.0.0.1:5000", &[]), ip("10.0.0.1"));
+}
+
+#[test]
+fn test_client_is_the_first_hop_that_is_not_a_trusted_proxy() {
+    // The client can put anything on the left; only the hops our proxies
+    // added count
+    let headers = [("x-forwarded-for", "192.0.2.99, 203.0.113.7, 10.0.0.2")];
+
+    assert_eq!(client_ip("10.0.0.1:5000", &headers), ip("203.0.113.7"));
+    assert_eq!(client_ip("[::1]:5000", &[("x-forwarded-for", "10.0.0.3")]), ip("10.0.0.3"));
+    assert_eq!(client_ip("[::ffff:10.0.0.1]:5000", &headers), ip("203.0.113.7"));
+}
+
+#[test]
+fn test_forwarded_takes_precedence_over_x_forwarded_for() {
+    let headers = [
+        ("x-forwarded-for", "192.0.2.99"),
+        ("forwarded", r#"for="[2001:db8::1]:4711";proto=https"#),
+        ("forwarded", "for=10.0.0.2:80"),
+    ];
+
+    assert_eq!(client_ip("10.0.0.1:5000", &headers), ip("2001:db8::1"));
+}
+
+#[test]
+fn test_hop_that_is_not_an_address_ends_at_the_proxy() {
+    assert_eq!(client_ip("10.0.0.1:5000", &[("forwarded", "for=unknown, for=10.0.0.2")]), ip("10.0.0.2"));
+    assert_eq!(client_ip("10.0.0.1:5000", &[("x-forwarded-for", "_hidden")]), ip("10.0.0.1"));
+}
+
+#[test]
+fn test_no_connect_info_means_no_address() {
+    let proxies = TrustedProxies::new(&["10.0.0.0/8".to_string()]);
+    let headers: HeaderMap = [(axum::http::header::FORWARDED, "for=203.0.113.7".parse().unwrap())]
+        .into_iter()
+        .collect();
+
+    assert_eq!(proxies.client_ip(&Extensions::new(), &headers), None);
+}
@@ -0,0 +1,112 @@
+// Only the fixtures are used here
+#[allow(dead_code)]
+mod common;
+
+use common::fixtures::{create_joined_server, create_user, new_member, ready_service};
+use rand::distr::{Alphanumeric, SampleString};
+use songbird_server::config::DatabaseConfig;
+use songbird_server::database::connect;
+use songbird_server::models::models::NewServerMember;
+use songbird_server::repositories::{MemoryDatabase, Repositories};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Statements run so far, counted from the event sqlx logs for each one.
+static QUERIES: AtomicUsize = AtomicUsize::new(0);
+
+struct CountQueries;
+
+impl<S: tracing::Subscriber> Layer<S> for CountQueries {
+    fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+        if event.metadata().target() == "sqlx::query" {
+            QUERIES.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// A SQLite file of its own, removed along with the value.
+struct TempDatabase(PathBuf);
+
+impl Drop for TempDatabase {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.0.display(), suffix));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_ready_holds_servers_with_their_channels_and_dms() {
+    let repositories = Repositories::in_memory(MemoryDatabase::new());
+    let alice = create_user(&repositories, "alice").await;
+    let bob = create_user(&repositories, "bob").await;
+
+    let birdhouse = create_joined_server(&repositories, "Birdhouse", alice.user_id, &["random", "general"]).await;
+    let nest = create_joined_server(&repositories, "Nest", bob.user_id, &["chirps"]).await;
+    repositories
+        .server_member_repository
+        .create(NewServerMember {
+            nickname: Some("Al".to_string()),
+            ..new_member(nest, alice.user_id)
+        })
+        .await
+        .unwrap();
+    create_joined_server(&repositories, "Elsewhere", bob.user_id, &["private"]).await;
+    let dm = repositories
+        .direct_message_repository
+        .find_or_create_dm_channel(alice.user_id, bob.user_id)
+        .await
+        .unwrap();
+
+    let ready = ready_service(&repositories).build(7, alice.clone()).await.unwrap();
+
+    assert_eq!(ready.session_id, 7);
+    assert_eq!(ready.user.user_id, alice.user_id);
+    let servers: Vec<(i64, Vec<&str>)> = ready
+        .servers
+        .iter()
+        .map(|ready| (ready.server.server_id, ready.channels.iter().map(|c| c.name.as_str()).collect()))
+        .collect();
+    assert_eq!(servers, vec![(birdhouse, vec!["general", "random"]), (nest, vec!["chirps"])]);
+    assert!(ready.servers.iter().all(|ready| ready.member.user_id == alice.user_id));
+    assert_eq!(ready.servers[1].member.nickname.as_deref(), Some("Al"));
+    let private_channels: Vec<i64> = ready.private_channels.iter().map(|c| c.channel_id).collect();
+    assert_eq!(private_channels, vec![dm.channel_id]);
+}
+
+#[tokio::test]
+async fn test_ready_takes_the_same_queries_for_any_number_of_servers() {
+    let _ = tracing_subscriber::registry().with(CountQueries).try_init();
+
+    let suffix = Alphanumeric.sample_string(&mut rand::rng(), 12);
+    let database = TempDatabase(std::env::temp_dir().join(format!("songbird_ready_{}.db", suffix)));
+    // One connection, so opening another halfway does not add to the count
+    let config = DatabaseConfig {
+        url: format!("sqlite://{}", database.0.display()),
+        max_connections: 1,
+        ..DatabaseConfig::default()
+    };
+    let repositories = connect(&config).await.unwrap().repositories();
+    let ready = ready_service(&repositories);
+
+    let mut counts = Vec::new();
+    for servers in [1, 25] {
+        let user = create_user(&repositories, &format!("user{}", servers)).await;
+        for i in 0..servers {
+            let name = format!("server{}-{}", servers, i);
+            create_joined_server(&repositories, &name, user.user_id, &["general", "random"]).await;
+        }
+
+        let before = QUERIES.load(Ordering::SeqCst);
+        let payload = ready.build(1, user).await.unwrap();
+        counts.push(QUERIES.load(Ordering::SeqCst) - before);
+
+        assert_eq!(payload.servers.len(), servers);
+        assert!(payload.servers.iter().all(|server| server.channels.len() == 2));
+    }
+
+    assert_eq!(counts, vec![4, 4]);
+}
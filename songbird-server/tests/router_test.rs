@@ -0,0 +1,261 @@
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use serde_json::json;
+use songbird_server::config::{Cli, Config};
+use songbird_server::repositories::{MemoryDatabase, Repositories};
+use songbird_server::services::login_guard::IP_FREE_ATTEMPTS;
+use songbird_server::shutdown::Shutdown;
+use songbird_server::{build_state, create_router};
+use std::net::SocketAddr;
+use tower::ServiceExt;
+
+/// The whole app, in process, on the in-memory backend.
+fn app(overrides: &[(&str, &str)]) -> Router {
+    let mut vars = vec![
+        ("DATABASE_URL".to_string(), "postgres://localhost/songbird_unused".to_string()),
+        ("JWT_SECRET".to_string(), "router-test-secret".to_string()),
+        ("MAIL_BACKEND".to_string(), "log".to_string()),
+    ];
+    vars.extend(
+        overrides
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string())),
+    );
+    let config = Config::load_from(&Cli::default(), vars).unwrap();
+
+    let state = build_state(&config, Repositories::in_memory(MemoryDatabase::new()), Shutdown::new()).unwrap();
+    create_router(state, &config)
+}
+
+async fn json(response: axum::response::Response) -> serde_json::Value {
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn test_healthz() {
+    let response = app(&[])
+        .oneshot(Request::get("/healthz").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(json(response).await["data"], "ok");
+}
+
+#[tokio::test]
+async fn test_request_id_is_generated_or_echoed() {
+    let generated = app(&[])
+        .oneshot(Request::get("/healthz").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert!(generated.headers().contains_key("x-request-id"));
+
+    let echoed = app(&[])
+        .oneshot(
+            Request::get("/healthz")
+                .header("x-request-id", "from-the-proxy")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(echoed.headers()["x-request-id"], "from-the-proxy");
+}
+
+#[tokio::test]
+async fn test_protected_route_requires_a_session() {
+    let response = app(&[])
+        .oneshot(
+            Request::delete("/api/users/1/custom_status")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(json(response).await["code"], "UNAUTHORIZED");
+}
+
+#[tokio::test]
+async fn test_invalid_body_is_rejected_before_the_database() {
+    let response = app(&[])
+        .oneshot(
+            Request::post("/api/users/create")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"username":"x","email":"nope","password":"short"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = json(response).await;
+    assert_eq!(body["code"], "VALIDATION_FAILED");
+    for field in ["username", "email", "password"] {
+        assert!(body["data"]["fields"].get(field).is_some(), "no error for {}", field);
+    }
+}
+
+#[tokio::test]
+async fn test_body_limit() {
+    let response = app(&[("SONGBIRD_LIMITS__MAX_BODY_BYTES", "16")])
+        .oneshot(
+            Request::post("/api/login")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"username":"someone","password":"something"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_cors_preflight() {
+    let response = app(&[("SONGBIRD_CORS__ALLOWED_ORIGINS", "https://app.example.com")])
+        .oneshot(
+            Request::builder()
+                .method(Method::OPTIONS)
+                .uri("/api/login")
+                .header(header::ORIGIN, "https://app.example.com")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+        "https://app.example.com"
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_route_is_counted() {
+    let app = app(&[]);
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/nowhere").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let metrics = app
+        .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let bytes = metrics.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(text.contains(r#"songbird_http_requests_total{method="GET",route="unmatched",status="404"} 1"#));
+}
+
+#[tokio::test]
+async fn test_v1_routes_are_not_deprecated() {
+    let response = app(&[])
+        .oneshot(Request::get("/api/v1/users").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(!response.headers().contains_key("deprecation"));
+    assert!(!response.headers().contains_key("sunset"));
+}
+
+#[tokio::test]
+async fn test_unversioned_routes_are_a_deprecated_alias_for_v1() {
+    let response = app(&[])
+        .oneshot(Request::get("/api/users/7").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // Served by the same handler as v1, errors included
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["deprecation"], "@1792368000");
+    assert_eq!(response.headers()["sunset"], "Mon, 19 Apr 2027 00:00:00 GMT");
+    assert_eq!(
+        response.headers()[header::LINK],
+        r#"</api/v1/users/7>; rel="successor-version""#
+    );
+    assert_eq!(json(response).await["code"], "UNKNOWN_USER");
+}
+
+#[tokio::test]
+async fn test_cors_exposes_deprecation_headers() {
+    let response = app(&[("SONGBIRD_CORS__ALLOWED_ORIGINS", "https://app.example.com")])
+        .oneshot(
+            Request::get("/api/users")
+                .header(header::ORIGIN, "https://app.example.com")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let exposed = response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS].to_str().unwrap();
+    for name in ["deprecation", "sunset", "link"] {
+        assert!(exposed.contains(name), "{} is not exposed: {}", name, exposed);
+    }
+}
+
+/// `request`, arriving from `peer` with `client` in `X-Forwarded-For`.
+fn forwarded(request: axum::http::request::Builder, peer: &str, client: &str, body: Body) -> Request<Body> {
+    let mut request = request
+        .header("x-forwarded-for", client)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+    request
+}
+
+#[tokio::test]
+async fn test_rate_limit_keys_on_the_forwarded_client_behind_a_trusted_proxy() {
+    let app = app(&[
+        ("SONGBIRD_RATE_LIMIT__PER_IP__CAPACITY", "1"),
+        ("SONGBIRD_RATE_LIMIT__TRUSTED_PROXIES", "127.0.0.1"),
+    ]);
+    let status = |peer: &'static str, client: &'static str| {
+        let request = forwarded(Request::get("/api/v1/users"), peer, client, Body::empty());
+        let app = app.clone();
+        async move { app.oneshot(request).await.unwrap().status() }
+    };
+
+    assert_eq!(status("127.0.0.1:5000", "203.0.113.7").await, StatusCode::OK);
+    assert_eq!(status("127.0.0.1:5000", "203.0.113.8").await, StatusCode::OK);
+    assert_eq!(status("127.0.0.1:5000", "203.0.113.7").await, StatusCode::TOO_MANY_REQUESTS);
+
+    // Anyone else is limited by their own address, whatever they forward
+    assert_eq!(status("198.51.100.1:5000", "203.0.113.9").await, StatusCode::OK);
+    assert_eq!(status("198.51.100.1:5000", "203.0.113.10").await, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_login_backoff_keys_on_the_forwarded_client_behind_a_trusted_proxy() {
+    let app = app(&[
+        ("SONGBIRD_RATE_LIMIT__ENABLED", "false"),
+        ("SONGBIRD_RATE_LIMIT__TRUSTED_PROXIES", "127.0.0.1"),
+    ]);
+    // A different account every time, so only the address backs off
+    let login = |client: &'static str, attempt: u32| {
+        let body = json!({ "username": format!("nobody{}", attempt), "password": "wrong password" });
+        let request = forwarded(Request::post("/api/v1/login"), "127.0.0.1:5000", client, body.to_string().into());
+        let app = app.clone();
+        async move { app.oneshot(request).await.unwrap().status() }
+    };
+
+    for attempt in 0..IP_FREE_ATTEMPTS {
+        assert_eq!(login("203.0.113.7", attempt).await, StatusCode::UNAUTHORIZED);
+    }
+    assert_eq!(login("203.0.113.7", IP_FREE_ATTEMPTS).await, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(login("203.0.113.8", IP_FREE_ATTEMPTS).await, StatusCode::UNAUTHORIZED);
+}
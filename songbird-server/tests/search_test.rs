@@ -0,0 +1,88 @@
+// Only the database half of the harness is used here
+#[allow(dead_code)]
+mod common;
+
+use common::fixtures::{create_channel, create_joined_server, create_message, create_user};
+use common::Backends;
+use songbird_server::repositories::Repositories;
+
+const CONTENTS: [&str; 5] = [
+    "Sunflower seeds are in",
+    "The feeder needs seeds",
+    "Mail alice@example.com about the feeder-cam",
+    "See https://example.com/birds for v2.5 notes",
+    "snake_case and don't",
+];
+
+/// A channel holding `CONTENTS` in order, and a second channel with a
+/// message that matches nearly everything; returns the first channel.
+async fn seed(repositories: &Repositories) -> i64 {
+    let alice = create_user(repositories, "alice").await.user_id;
+    let server_id = create_joined_server(repositories, "Birdhouse", alice, &[]).await;
+    let channel_id = create_channel(repositories, server_id, "general").await.channel_id;
+    let elsewhere = create_channel(repositories, server_id, "random").await.channel_id;
+    for content in CONTENTS {
+        create_message(repositories, channel_id, alice, content).await;
+    }
+    create_message(repositories, elsewhere, alice, &CONTENTS.join(" ")).await;
+    channel_id
+}
+
+/// The contents found for `query`, newest first.
+async fn search(repositories: &Repositories, channel_id: i64, query: &str) -> Vec<String> {
+    repositories
+        .message_repository
+        .search_with_authors(channel_id, query, 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|message| message.content)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_backends_find_the_same_messages() {
+    let backends = Backends::create().await;
+    let [sunflower, feeder, mail, link, snake] = CONTENTS;
+    let expected: [(&str, Vec<&str>); 14] = [
+        ("SEEDS", vec![feeder, sunflower]),
+        ("sunflower seeds", vec![sunflower]),
+        ("seeds | feeder:*", vec![feeder]),
+        ("\"feeder\" OR", vec![]),
+        ("sunflow", vec![]),
+        ("feeder", vec![mail, feeder]),
+        ("alice", vec![mail]),
+        ("example com", vec![link, mail]),
+        ("cam", vec![mail]),
+        ("birds", vec![link]),
+        ("5", vec![link]),
+        ("snake", vec![snake]),
+        ("don't", vec![snake]),
+        ("", vec![]),
+    ];
+
+    for (backend, repositories) in backends.all() {
+        let channel_id = seed(repositories).await;
+        for (query, contents) in &expected {
+            assert_eq!(&search(repositories, channel_id, query).await, contents, "{} on {}", query, backend);
+        }
+    }
+}
+
+/// Non-ASCII letters are part of words on SQLite and in memory; Postgres
+/// depends on `LC_CTYPE`, as the `search_with_authors` doc explains.
+#[tokio::test]
+async fn test_diacritics_are_kept() {
+    let backends = Backends::create().await;
+
+    for (backend, repositories) in [("sqlite", &backends.sqlite.repositories), ("memory", &backends.memory)] {
+        let alice = create_user(repositories, "alice").await.user_id;
+        let server_id = create_joined_server(repositories, "Birdhouse", alice, &[]).await;
+        let channel_id = create_channel(repositories, server_id, "general").await.channel_id;
+        create_message(repositories, channel_id, alice, "Café au lait").await;
+
+        assert_eq!(search(repositories, channel_id, "CAFÉ").await, ["Café au lait"], "on {}", backend);
+        assert!(search(repositories, channel_id, "cafe").await.is_empty(), "on {}", backend);
+        assert!(search(repositories, channel_id, "caf").await.is_empty(), "on {}", backend);
+    }
+}
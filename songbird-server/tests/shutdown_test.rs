@@ -0,0 +1,57 @@
+use songbird_server::shutdown::Shutdown;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_triggered_resolves_after_trigger() {
+    let shutdown = Shutdown::new();
+    assert!(!shutdown.is_triggered());
+
+    shutdown.trigger();
+
+    assert!(shutdown.is_triggered());
+    tokio::time::timeout(Duration::from_secs(1), shutdown.triggered())
+        .await
+        .expect("triggered should resolve once triggered");
+}
+
+#[tokio::test]
+async fn test_drain_waits_for_spawned_tasks() {
+    let shutdown = Shutdown::new();
+    let finished = Arc::new(AtomicBool::new(false));
+
+    let flag = finished.clone();
+    shutdown.spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        flag.store(true, Ordering::SeqCst);
+    });
+
+    assert!(shutdown.drain(Duration::from_secs(5)).await);
+    assert!(finished.load(Ordering::SeqCst));
+    assert_eq!(shutdown.pending_tasks(), 0);
+}
+
+#[tokio::test]
+async fn test_drain_gives_up_after_timeout() {
+    let shutdown = Shutdown::new();
+    shutdown.spawn(std::future::pending::<()>());
+
+    assert!(!shutdown.drain(Duration::from_millis(50)).await);
+    assert_eq!(shutdown.pending_tasks(), 1);
+}
+
+#[tokio::test]
+async fn test_tracked_futures_hold_up_drain() {
+    let shutdown = Shutdown::new();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+    let session = tokio::spawn(shutdown.track(async move {
+        let _ = rx.await;
+    }));
+
+    assert!(!shutdown.drain(Duration::from_millis(50)).await);
+    tx.send(()).unwrap();
+    session.await.unwrap();
+    assert!(shutdown.drain(Duration::from_secs(1)).await);
+}
@@ -0,0 +1,93 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use songbird_server::snowflake::{self, lower_bound, next_id, timestamp, worker_id};
+use std::collections::HashSet;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Ids {
+    #[serde(with = "songbird_server::snowflake::string")]
+    id: i64,
+    #[serde(default, with = "songbird_server::snowflake::option_string")]
+    parent_id: Option<i64>,
+}
+
+#[test]
+fn test_ids_increase_and_never_repeat_across_threads() {
+    let threads: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(|| (0..10_000).map(|_| next_id()).collect::<Vec<_>>()))
+        .collect();
+
+    let mut seen = HashSet::new();
+    for thread in threads {
+        let ids = thread.join().unwrap();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        seen.extend(ids);
+    }
+    assert_eq!(seen.len(), 40_000);
+}
+
+#[test]
+fn test_id_carries_its_creation_time() {
+    let before = Utc::now();
+    let id = next_id();
+
+    // Only ahead of the clock when thousands are made in one millisecond
+    let made_at = timestamp(id);
+    assert!(made_at >= before - Duration::milliseconds(1));
+    assert!(made_at <= Utc::now() + Duration::seconds(1));
+}
+
+#[test]
+fn test_lower_bound_separates_older_and_newer_ids() {
+    let older = next_id();
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let cursor = lower_bound(Utc::now());
+    let newer = next_id();
+
+    assert!(older < cursor);
+    assert!(cursor <= newer);
+    assert_eq!(lower_bound(Utc::now() - Duration::days(365 * 100)), 0);
+}
+
+#[test]
+fn test_worker_id_is_stamped_into_ids() {
+    snowflake::set_worker_id(snowflake::MAX_WORKER_ID);
+    let id = next_id();
+    snowflake::set_worker_id(0);
+
+    assert_eq!(worker_id(id), snowflake::MAX_WORKER_ID);
+    assert_eq!(worker_id(next_id()), 0);
+}
+
+#[test]
+fn test_ids_are_written_as_strings() {
+    let ids = Ids {
+        id: 9_007_199_254_740_993,
+        parent_id: Some(1),
+    };
+
+    assert_eq!(
+        serde_json::to_value(&ids).unwrap(),
+        json!({ "id": "9007199254740993", "parent_id": "1" })
+    );
+    assert_eq!(serde_json::to_value(Ids { id: 1, parent_id: None }).unwrap()["parent_id"], json!(null));
+}
+
+#[test]
+fn test_ids_are_read_from_strings_or_numbers() {
+    let expected = Ids {
+        id: 9_007_199_254_740_993,
+        parent_id: Some(1),
+    };
+
+    let from_strings: Ids = serde_json::from_value(json!({ "id": "9007199254740993", "parent_id": "1" })).unwrap();
+    let from_numbers: Ids = serde_json::from_value(json!({ "id": 9_007_199_254_740_993_i64, "parent_id": 1 })).unwrap();
+    let missing: Ids = serde_json::from_value(json!({ "id": "1" })).unwrap();
+
+    assert_eq!(from_strings, expected);
+    assert_eq!(from_numbers, expected);
+    assert_eq!(missing.parent_id, None);
+    assert!(serde_json::from_value::<Ids>(json!({ "id": "12abc" })).is_err());
+    assert!(serde_json::from_value::<Ids>(json!({ "id": -1.5 })).is_err());
+}
@@ -0,0 +1,439 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chrono::{Duration, TimeZone, Utc};
+use rand::distr::{Alphanumeric, SampleString};
+use serde_json::{json, Value};
+use songbird_server::config::{Cli, Config};
+use songbird_server::database::{connect, Database, SQLITE_MIGRATOR};
+use songbird_server::models::models::{MessageType, NewChannel, NewMessage, NewServer, NewServerMember, NewUser};
+use songbird_server::repositories::{PinOutcome, Repositories};
+use songbird_server::shutdown::Shutdown;
+use songbird_server::snowflake;
+use songbird_server::{build_state, create_router};
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use std::path::PathBuf;
+
+/// The app on a SQLite file of its own, removed along with the value.
+struct SqliteApp {
+    server: TestServer,
+    repositories: Repositories,
+    path: PathBuf,
+}
+
+impl SqliteApp {
+    async fn spawn() -> Self {
+        let suffix = Alphanumeric.sample_string(&mut rand::rng(), 12);
+        let path = std::env::temp_dir().join(format!("songbird_test_{}.db", suffix));
+
+        let vars = vec![
+            ("DATABASE_URL".to_string(), format!("sqlite://{}", path.display())),
+            ("JWT_SECRET".to_string(), "sqlite-test-secret".to_string()),
+            ("MAIL_BACKEND".to_string(), "log".to_string()),
+        ];
+        let config = Config::load_from(&Cli::default(), vars).unwrap();
+
+        let database = connect(&config.database).await.unwrap();
+        assert!(matches!(database, Database::Sqlite(_)));
+        let repositories = database.repositories();
+        let state = build_state(&config, repositories.clone(), Shutdown::new()).unwrap();
+        let server = TestServer::new(create_router(state, &config)).unwrap();
+
+        Self {
+            server,
+            repositories,
+            path,
+        }
+    }
+
+    async fn create_user(&self, username: &str) -> i64 {
+        self.repositories
+            .user_repository
+            .create(NewUser {
+                username: username.to_string(),
+                email: format!("{}@example.com", username),
+                password_hash: "not a real hash".to_string(),
+                avatar_url: None,
+                status: "offline".to_string(),
+            })
+            .await
+            .unwrap()
+            .user_id
+    }
+
+    async fn create_server(&self, name: &str, owner_user_id: i64) -> i64 {
+        self.repositories
+            .server_repository
+            .create(NewServer {
+                server_name: name.to_string(),
+                owner_user_id,
+                icon_url: None,
+            })
+            .await
+            .unwrap()
+            .server_id
+    }
+
+    async fn create_channel(&self, server_id: i64, name: &str) -> i64 {
+        self.repositories
+            .channel_repository
+            .create(NewChannel {
+                server_id: Some(server_id),
+                name: name.to_string(),
+                channel_type: "text".to_string(),
+            })
+            .await
+            .unwrap()
+            .channel_id
+    }
+
+    async fn post(&self, channel_id: i64, author_user_id: i64, content: &str) -> i64 {
+        self.repositories
+            .message_repository
+            .create(NewMessage {
+                channel_id,
+                author_user_id,
+                content: content.to_string(),
+                message_type: MessageType::Default,
+                referenced_message_id: None,
+            })
+            .await
+            .unwrap()
+            .message_id
+    }
+}
+
+impl Drop for SqliteApp {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.path.display(), suffix));
+        }
+    }
+}
+
+fn constraint(error: &sqlx::Error) -> Option<String> {
+    error.as_database_error()?.constraint().map(str::to_string)
+}
+
+#[tokio::test]
+async fn test_new_database_is_migrated() {
+    let app = SqliteApp::spawn().await;
+
+    let response = app.server.get("/readyz").await;
+
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["data"]["schema_version"], 12);
+}
+
+#[tokio::test]
+async fn test_signup_conflicts_match_postgres() {
+    let app = SqliteApp::spawn().await;
+    let signup = |username: &str, email: &str| {
+        app.server
+            .post("/api/v1/users/create")
+            .json(&json!({ "username": username, "email": email, "password": "correct horse battery" }))
+    };
+
+    signup("alice", "alice@example.com").await.assert_status(StatusCode::CREATED);
+
+    let same_username = signup("alice", "other@example.com").await;
+    same_username.assert_status(StatusCode::CONFLICT);
+    assert_eq!(same_username.json::<Value>()["code"], "USERNAME_TAKEN");
+
+    let same_email = signup("alicia", "alice@example.com").await;
+    same_email.assert_status(StatusCode::CONFLICT);
+    assert_eq!(same_email.json::<Value>()["code"], "EMAIL_TAKEN");
+}
+
+#[tokio::test]
+async fn test_constraint_names_match_postgres() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let server_id = app.create_server("Birdhouse", alice).await;
+    let channel_id = app.create_channel(server_id, "general").await;
+
+    let duplicate_server = app
+        .repositories
+        .server_repository
+        .create(NewServer {
+            server_name: "Birdhouse".to_string(),
+            owner_user_id: alice,
+            icon_url: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(constraint(&duplicate_server).as_deref(), Some("servers_server_name_key"));
+
+    let unknown_owner = app
+        .repositories
+        .server_repository
+        .create(NewServer {
+            server_name: "Nest".to_string(),
+            owner_user_id: 999,
+            icon_url: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(unknown_owner.as_database_error().unwrap().code().as_deref(), Some("23503"));
+
+    let slowmode = app
+        .repositories
+        .channel_repository
+        .update_rate_limit(channel_id, 100_000)
+        .await
+        .unwrap_err();
+    assert_eq!(constraint(&slowmode).as_deref(), Some("channels_rate_limit_per_user_check"));
+}
+
+#[tokio::test]
+async fn test_joining_posts_in_the_oldest_text_channel_and_rolls_back_on_conflict() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let server_id = app.create_server("Birdhouse", alice).await;
+    let general = app.create_channel(server_id, "general").await;
+    app.create_channel(server_id, "announcements").await;
+    let member = || NewServerMember {
+        server_id,
+        user_id: alice,
+        nickname: None,
+    };
+
+    let (_, message) = app.repositories.server_member_repository.create(member()).await.unwrap();
+    let message = message.unwrap();
+    assert_eq!(message.channel_id, general);
+    assert_eq!(message.message_type, MessageType::MemberJoin);
+
+    let error = app.repositories.server_member_repository.create(member()).await.unwrap_err();
+    assert_eq!(constraint(&error).as_deref(), Some("server_members_pkey"));
+    assert_eq!(app.repositories.message_repository.count_by_channel(general).await.unwrap(), 1);
+
+    let (left, message) = app.repositories.server_member_repository.delete(server_id, alice).await.unwrap();
+    assert!(left);
+    assert_eq!(message.unwrap().message_type, MessageType::MemberLeave);
+}
+
+#[tokio::test]
+async fn test_rename_posts_a_message_only_when_the_name_changes() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let server_id = app.create_server("Birdhouse", alice).await;
+    let channel_id = app.create_channel(server_id, "general").await;
+    let channels = &app.repositories.channel_repository;
+
+    let (channel, message) = channels.update(channel_id, "lobby".to_string(), alice).await.unwrap();
+    assert_eq!(channel.name, "lobby");
+    assert!(channel.updated_at.is_some());
+    let message = message.unwrap();
+    assert_eq!(message.message_type, MessageType::ChannelRename);
+    assert_eq!(message.content, "lobby");
+
+    let (_, message) = channels.update(channel_id, "lobby".to_string(), alice).await.unwrap();
+    assert!(message.is_none());
+
+    let missing = channels.update(999, "lobby".to_string(), alice).await.unwrap_err();
+    assert!(matches!(missing, sqlx::Error::RowNotFound));
+}
+
+#[tokio::test]
+async fn test_pins_respect_the_cap() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let server_id = app.create_server("Birdhouse", alice).await;
+    let channel_id = app.create_channel(server_id, "general").await;
+    let first = app.post(channel_id, alice, "first").await;
+    let second = app.post(channel_id, alice, "second").await;
+    let messages = &app.repositories.message_repository;
+
+    assert_eq!(messages.pin(channel_id, first, alice, 1).await.unwrap(), PinOutcome::Pinned);
+    assert_eq!(messages.pin(channel_id, first, alice, 1).await.unwrap(), PinOutcome::AlreadyPinned);
+    assert_eq!(messages.pin(channel_id, second, alice, 1).await.unwrap(), PinOutcome::LimitReached);
+
+    let pinned = messages.find_pinned_with_authors(channel_id).await.unwrap();
+    assert_eq!(pinned.len(), 1);
+    assert_eq!(pinned[0].author.username, "alice");
+
+    assert!(messages.unpin(channel_id, first).await.unwrap());
+    assert_eq!(messages.pin(channel_id, second, alice, 1).await.unwrap(), PinOutcome::Pinned);
+}
+
+#[tokio::test]
+async fn test_messages_list_newest_first() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let server_id = app.create_server("Birdhouse", alice).await;
+    let channel_id = app.create_channel(server_id, "general").await;
+    for content in ["one", "two", "three"] {
+        app.post(channel_id, alice, content).await;
+    }
+
+    let messages = &app.repositories.message_repository;
+
+    let page = messages.find_by_channel_with_authors(channel_id, None, 2).await.unwrap();
+    let contents: Vec<&str> = page.iter().map(|message| message.content.as_str()).collect();
+    assert_eq!(contents, ["three", "two"]);
+
+    let before = page.last().unwrap().message_id;
+    let page = messages.find_by_channel_with_authors(channel_id, Some(before), 2).await.unwrap();
+    let contents: Vec<&str> = page.iter().map(|message| message.content.as_str()).collect();
+    assert_eq!(contents, ["one"]);
+}
+
+#[tokio::test]
+async fn test_search_follows_edits_and_deletes() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let server_id = app.create_server("Birdhouse", alice).await;
+    let channel_id = app.create_channel(server_id, "general").await;
+    let elsewhere = app.create_channel(server_id, "random").await;
+    let seeds = app.post(channel_id, alice, "Sunflower seeds are in").await;
+    let feeder = app.post(channel_id, alice, "The feeder needs seeds").await;
+    app.post(elsewhere, alice, "More seeds here").await;
+    let messages = &app.repositories.message_repository;
+
+    let found = messages.search_with_authors(channel_id, "SEEDS", 10).await.unwrap();
+    let ids: Vec<i64> = found.iter().map(|message| message.message_id).collect();
+    assert_eq!(ids, [feeder, seeds]);
+    assert_eq!(found[0].author.username, "alice");
+
+    // Every word has to match, and FTS5 syntax is ignored
+    let found = messages.search_with_authors(channel_id, "sunflower seeds", 10).await.unwrap();
+    assert_eq!(found.len(), 1);
+    let found = messages.search_with_authors(channel_id, "feeder* seeds", 10).await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(messages.search_with_authors(channel_id, "sunflow*", 10).await.unwrap().is_empty());
+    assert!(messages.search_with_authors(channel_id, "\"*", 10).await.unwrap().is_empty());
+
+    messages.update_content(seeds, "Sunflower hearts are in".to_string()).await.unwrap();
+    messages.delete(feeder).await.unwrap();
+    assert!(messages.search_with_authors(channel_id, "seeds", 10).await.unwrap().is_empty());
+    let found = messages.search_with_authors(channel_id, "hearts", 10).await.unwrap();
+    assert_eq!(found[0].message_id, seeds);
+}
+
+/// Rows from before Snowflake IDs are renumbered from their `created_at`,
+/// keeping the old ID in the low bits, and references follow them.
+#[tokio::test]
+async fn test_legacy_ids_are_renumbered() {
+    let suffix = Alphanumeric.sample_string(&mut rand::rng(), 12);
+    let path = std::env::temp_dir().join(format!("songbird_legacy_{}.db", suffix));
+    let options = SqliteConnectOptions::new().filename(&path).create_if_missing(true).foreign_keys(true);
+    let pool = SqlitePoolOptions::new().max_connections(1).connect_with(options).await.unwrap();
+
+    let before_snowflakes = Migrator {
+        migrations: SQLITE_MIGRATOR.migrations.iter().filter(|m| m.version < 10).cloned().collect(),
+        ..Migrator::DEFAULT
+    };
+    before_snowflakes.run(&pool).await.unwrap();
+
+    let created_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    for statement in [
+        "INSERT INTO users (user_id, username, email, password_hash, created_at) VALUES (7, 'alice', 'a@example.com', '', ?)",
+        "INSERT INTO servers (server_id, server_name, owner_user_id, created_at) VALUES (3, 'Birdhouse', 7, ?)",
+        "INSERT INTO channels (channel_id, server_id, name, type, created_at) VALUES (5, 3, 'general', 'text', ?)",
+        "INSERT INTO messages (message_id, channel_id, author_user_id, content, created_at) VALUES (9, 5, 7, 'hi', ?)",
+    ] {
+        sqlx::query(statement).bind(created_at).execute(&pool).await.unwrap();
+    }
+
+    SQLITE_MIGRATOR.run(&pool).await.unwrap();
+
+    let (user_id, owner_user_id): (i64, i64) = sqlx::query_as("SELECT u.user_id, s.owner_user_id FROM users u, servers s")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let (message_id, channel_id, author_user_id): (i64, i64, i64) =
+        sqlx::query_as("SELECT message_id, channel_id, author_user_id FROM messages")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    let server_channel: i64 = sqlx::query_scalar("SELECT channel_id FROM channels").fetch_one(&pool).await.unwrap();
+    let indexed: i64 = sqlx::query_scalar("SELECT rowid FROM messages_fts WHERE messages_fts MATCH 'hi'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(snowflake::timestamp(user_id), created_at);
+    assert_eq!(user_id & 0x3f_ffff, 7);
+    assert_eq!(message_id & 0x3f_ffff, 9);
+    assert_eq!(owner_user_id, user_id);
+    assert_eq!(author_user_id, user_id);
+    assert_eq!(channel_id, server_channel);
+    assert_eq!(indexed, message_id);
+    assert!(snowflake::next_id() > message_id);
+}
+
+#[tokio::test]
+async fn test_email_tokens_are_single_use_and_superseded() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let tokens = &app.repositories.email_token_repository;
+    let expires_at = Utc::now() + Duration::hours(1);
+
+    tokens.create("first", alice, "verify_email", "alice@example.com", expires_at).await.unwrap();
+    tokens.create("second", alice, "verify_email", "alice@example.com", expires_at).await.unwrap();
+    tokens.create("stale", alice, "reset_password", "alice@example.com", Utc::now() - Duration::seconds(1)).await.unwrap();
+
+    assert_eq!(tokens.consume("first", "verify_email").await.unwrap(), None);
+    assert_eq!(
+        tokens.consume("second", "verify_email").await.unwrap(),
+        Some((alice, "alice@example.com".to_string()))
+    );
+    assert_eq!(tokens.consume("second", "verify_email").await.unwrap(), None);
+    assert_eq!(tokens.consume("stale", "reset_password").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_mfa_enrollment_and_recovery_codes() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let mfa = &app.repositories.mfa_repository;
+    let codes = vec!["hash-one".to_string(), "hash-two".to_string()];
+
+    assert!(mfa.begin_enrollment(alice, "FIRSTSECRET").await.unwrap());
+    assert!(mfa.begin_enrollment(alice, "SECONDSECRET").await.unwrap());
+    assert!(mfa.confirm_enrollment(alice, 10, &codes).await.unwrap());
+    assert!(!mfa.begin_enrollment(alice, "THIRDSECRET").await.unwrap());
+    assert_eq!(mfa.find_totp(alice).await.unwrap().unwrap().secret, "SECONDSECRET");
+    assert!(mfa.is_enabled(alice).await.unwrap());
+
+    assert!(!mfa.consume_step(alice, 10).await.unwrap());
+    assert!(mfa.consume_step(alice, 11).await.unwrap());
+    assert!(mfa.consume_recovery_code(alice, "hash-one").await.unwrap());
+    assert!(!mfa.consume_recovery_code(alice, "hash-one").await.unwrap());
+
+    assert!(mfa.disable(alice).await.unwrap());
+    assert!(!mfa.is_enabled(alice).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_deleting_a_user_cascades() {
+    let app = SqliteApp::spawn().await;
+    let alice = app.create_user("alice").await;
+    let bob = app.create_user("bob").await;
+    let server_id = app.create_server("Birdhouse", alice).await;
+    let dm = app
+        .repositories
+        .direct_message_repository
+        .find_or_create_dm_channel(alice, bob)
+        .await
+        .unwrap();
+    let again = app
+        .repositories
+        .direct_message_repository
+        .find_or_create_dm_channel(alice, bob)
+        .await
+        .unwrap();
+    assert_eq!(again.channel_id, dm.channel_id);
+    app.post(dm.channel_id, alice, "hi bob").await;
+
+    assert!(app.repositories.user_repository.delete(alice).await.unwrap());
+
+    assert!(app.repositories.server_repository.find_by_id(server_id).await.unwrap().is_none());
+    assert_eq!(app.repositories.message_repository.count_by_user(alice).await.unwrap(), 0);
+    assert_eq!(
+        app.repositories.channel_repository.find_direct_message_member_ids(dm.channel_id).await.unwrap(),
+        [bob]
+    );
+}
@@ -0,0 +1,174 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use serde_json::{json, Value};
+use songbird_server::config::{Cli, Config};
+use songbird_server::models::models::NewChannel;
+use songbird_server::repositories::{MemoryDatabase, Repositories};
+use songbird_server::shutdown::Shutdown;
+use songbird_server::{build_state, create_router, AppState};
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// The app around one in-memory database, and its state for listening in
+/// on what the gateway sends.
+fn server() -> (TestServer, AppState) {
+    let vars = vec![
+        ("DATABASE_URL".to_string(), "postgres://localhost/songbird_unused".to_string()),
+        ("JWT_SECRET".to_string(), "system-message-test-secret".to_string()),
+        ("MAIL_BACKEND".to_string(), "log".to_string()),
+    ];
+    let config = Config::load_from(&Cli::default(), vars).unwrap();
+
+    let state = build_state(&config, Repositories::in_memory(MemoryDatabase::new()), Shutdown::new()).unwrap();
+    let server = TestServer::new(create_router(state.clone(), &config)).unwrap();
+
+    (server, state)
+}
+
+/// Signs up and logs in; returns the user ID and a bearer token.
+async fn sign_up(server: &TestServer, username: &str) -> (i64, String) {
+    let created = server
+        .post("/api/v1/users/create")
+        .json(&json!({
+            "username": username,
+            "email": format!("{}@example.com", username),
+            "password": "correct horse battery",
+        }))
+        .await;
+    created.assert_status(StatusCode::CREATED);
+    let user_id: i64 = created.json::<Value>()["data"]["user_id"].as_str().unwrap().parse().unwrap();
+
+    let login = server
+        .post("/api/v1/login")
+        .json(&json!({ "username": username, "password": "correct horse battery" }))
+        .await;
+    login.assert_status_ok();
+    let token = login.json::<Value>()["data"]["token"].as_str().unwrap().to_string();
+
+    (user_id, format!("Bearer {}", token))
+}
+
+/// A server owned by whoever `owner_token` belongs to, with one text
+/// channel, which is therefore its system channel. Nobody has joined yet.
+async fn create_server(server: &TestServer, state: &AppState, owner_token: &str) -> (i64, i64) {
+    let response = server
+        .post("/api/v1/servers")
+        .authorization(owner_token)
+        .json(&json!({ "name": "Garden", "description": "" }))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let server_id: i64 = response.json::<Value>()["data"]["server_id"].as_str().unwrap().parse().unwrap();
+
+    // No route creates channels yet
+    let channel = state
+        .channel_repository
+        .create(NewChannel {
+            server_id: Some(server_id),
+            name: "general".to_string(),
+            channel_type: "text".to_string(),
+        })
+        .await
+        .unwrap();
+
+    (server_id, channel.channel_id)
+}
+
+async fn join(server: &TestServer, server_id: i64, token: &str) {
+    server
+        .post(&format!("/api/v1/servers/{}/members", server_id))
+        .authorization(token)
+        .await
+        .assert_status(StatusCode::CREATED);
+}
+
+/// The next MESSAGE_CREATE payload sent to the session, skipping anything
+/// else the session was sent.
+fn next_message(frames: &mut UnboundedReceiver<Arc<str>>) -> Value {
+    while let Ok(frame) = frames.try_recv() {
+        let frame: Value = serde_json::from_str(&frame).unwrap();
+        if frame["t"] == "MESSAGE_CREATE" {
+            return frame["d"].clone();
+        }
+    }
+    panic!("no MESSAGE_CREATE was sent");
+}
+
+#[tokio::test]
+async fn test_join_sends_member_join_to_the_channel() {
+    let (server, state) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+    let (bob, bob_token) = sign_up(&server, "bob").await;
+    let (server_id, channel_id) = create_server(&server, &state, &alice_token).await;
+    join(&server, server_id, &alice_token).await;
+
+    let (_, mut frames) = state.gateway.register(alice);
+    join(&server, server_id, &bob_token).await;
+
+    let message = next_message(&mut frames);
+    assert_eq!(message["message_type"], "member_join");
+    assert_eq!(message["channel_id"], channel_id.to_string());
+    assert_eq!(message["author"]["user_id"], bob.to_string());
+}
+
+#[tokio::test]
+async fn test_leave_sends_member_leave_to_the_channel() {
+    let (server, state) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+    let (bob, bob_token) = sign_up(&server, "bob").await;
+    let (server_id, channel_id) = create_server(&server, &state, &alice_token).await;
+    join(&server, server_id, &alice_token).await;
+    join(&server, server_id, &bob_token).await;
+
+    let (_, mut frames) = state.gateway.register(alice);
+    server
+        .delete(&format!("/api/v1/servers/{}/members/{}", server_id, bob))
+        .authorization(&bob_token)
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    let message = next_message(&mut frames);
+    assert_eq!(message["message_type"], "member_leave");
+    assert_eq!(message["channel_id"], channel_id.to_string());
+    assert_eq!(message["author"]["user_id"], bob.to_string());
+}
+
+#[tokio::test]
+async fn test_rename_sends_channel_rename_to_the_channel() {
+    let (server, state) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+    let (bob, bob_token) = sign_up(&server, "bob").await;
+    let (server_id, channel_id) = create_server(&server, &state, &alice_token).await;
+    join(&server, server_id, &alice_token).await;
+    join(&server, server_id, &bob_token).await;
+
+    let (_, mut frames) = state.gateway.register(bob);
+    let renamed = server
+        .put(&format!("/api/v1/channels/{}", channel_id))
+        .authorization(&alice_token)
+        .json(&json!({ "name": "lobby" }))
+        .await;
+    assert_eq!(renamed.json::<Value>()["data"]["name"], "lobby");
+
+    let message = next_message(&mut frames);
+    assert_eq!(message["message_type"], "channel_rename");
+    assert_eq!(message["channel_id"], channel_id.to_string());
+    assert_eq!(message["author"]["user_id"], alice.to_string());
+}
+
+#[tokio::test]
+async fn test_rename_to_the_same_name_sends_nothing() {
+    let (server, state) = server();
+    let (alice, alice_token) = sign_up(&server, "alice").await;
+    let (server_id, channel_id) = create_server(&server, &state, &alice_token).await;
+    join(&server, server_id, &alice_token).await;
+
+    let (_, mut frames) = state.gateway.register(alice);
+    server
+        .put(&format!("/api/v1/channels/{}", channel_id))
+        .authorization(&alice_token)
+        .json(&json!({ "name": "general" }))
+        .await
+        .assert_status_ok();
+
+    assert!(frames.try_recv().is_err());
+}
@@ -1,4 +1,3 @@
-use serde_json;
 use songbird_server::handlers::user_handlers::{CreateUserRequest, UpdateUserRequest};
 
 #[test]
@@ -27,6 +26,8 @@ fn test_update_user_request_serialization() {
         password: Some("newpassword".to_string()),
         avatar_url: Some("https://example.com/new-avatar.jpg".to_string()),
         status: Some("away".to_string()),
+        current_password: None,
+        mfa_code: None,
     };
 
     let json = serde_json::to_string(&request).unwrap();
@@ -47,6 +48,8 @@ fn test_partial_update_user_request_serialization() {
         password: None,
         avatar_url: None,
         status: Some("away".to_string()),
+        current_password: None,
+        mfa_code: None,
     };
 
     let json = serde_json::to_string(&request).unwrap();
@@ -0,0 +1,57 @@
+use songbird_server::handlers::user_handlers::CreateUserRequest;
+use songbird_server::validation::{
+    validate_http_url, validate_not_blank, validate_password, validate_username, ValidationErrorResponse,
+};
+use validator::Validate;
+
+#[test]
+fn test_username_rules() {
+    assert!(validate_username("alice").is_ok());
+    assert!(validate_username("a.b-c_9").is_ok());
+    assert!(validate_username("a").is_err());
+    assert!(validate_username(&"a".repeat(33)).is_err());
+    assert!(validate_username("two words").is_err());
+    assert!(validate_username("émile").is_err());
+}
+
+#[test]
+fn test_password_strength() {
+    assert!(validate_password("correct1horse").is_ok());
+    assert!(validate_password("short1").is_err());
+    assert!(validate_password("onlyletters").is_err());
+    assert!(validate_password("1234567890").is_err());
+    assert!(validate_password(&format!("a{}", "1".repeat(128))).is_err());
+}
+
+#[test]
+fn test_http_url() {
+    assert!(validate_http_url("https://cdn.example.com/a.png").is_ok());
+    assert!(validate_http_url("http://localhost:8080").is_ok());
+    assert!(validate_http_url("ftp://example.com/a.png").is_err());
+    assert!(validate_http_url("javascript:alert(1)").is_err());
+    assert!(validate_http_url("https:///a.png").is_err());
+    assert!(validate_http_url("https://example.com/a b.png").is_err());
+}
+
+#[test]
+fn test_not_blank() {
+    assert!(validate_not_blank("hi").is_ok());
+    assert!(validate_not_blank(" \n\t").is_err());
+}
+
+#[test]
+fn test_errors_are_grouped_by_field() {
+    let request = CreateUserRequest {
+        username: "a b".to_string(),
+        email: "not-an-email".to_string(),
+        password: "correct1horse".to_string(),
+        avatar_url: None,
+    };
+
+    let errors = request.validate().unwrap_err();
+    let response = ValidationErrorResponse::from(&errors);
+
+    assert_eq!(response.fields.len(), 2);
+    assert_eq!(response.fields["username"][0].code, "charset");
+    assert_eq!(response.fields["email"][0].code, "email");
+}